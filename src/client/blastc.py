@@ -34,10 +34,19 @@ class Console(cmd.Cmd):
             print t
 
     def do_show(self, line):
-        if line.strip() != 'tables':
-            print 'only support show tables'
+        if line.strip() not in ('tables', 'pool'):
+            print 'only support show tables and show pool'
         else:
-            self.socket.sendall('show tables\n')
+            self.socket.sendall('show %s\n' % line.strip())
+            data = self.get_remote_data()
+            print data
+
+    def do_resize(self, line):
+        args = line.split()
+        if len(args) != 2 or args[0] != 'pool':
+            print 'usage: resize pool <page number>'
+        else:
+            self.socket.sendall('resize pool %s\n' % args[1])
             data = self.get_remote_data()
             print data
 
@@ -13,50 +13,46 @@ def handle_query(response):
         raise SqlError(response[4:])
     json_str = struct.unpack('%ds' % json_len, response[4:4+json_len])[0]
     tuple_desc = json.loads(json_str)
-    tuple_len = sum(map(attr_len, tuple_desc))
     payload_start = 4 + json_len
-    tuple_sum = int(len(response) - payload_start) / int(tuple_len)
-    payload_end = tuple_len * tuple_sum + payload_start
-    assert len(response) == payload_end + 2
+    payload_end = len(response) - 2
     assert response[payload_end:] == '\r\n'
-    
-    gaps = [0] + map(get_gap, tuple_desc)[:-1]
-    offset_list = map(lambda i: sum(gaps[:i]), range(1, len(gaps)+1))
-    
-    # payload
+
+    # payload, every tuple starts with a null bitmap,
+    # Varchar and Text are u32 length followed by the content
+    bitmap_len = (len(tuple_desc) + 7) / 8
     result = []
-    for i in range(payload_start, payload_end, tuple_len):
-        index_list = map(lambda o: o + i, offset_list)
-        value_list = map(lambda (a, j): get_value(a, response, j), zip(tuple_desc, index_list))
+    i = payload_start
+    while i < payload_end:
+        tuple_start = i
+        i += bitmap_len
+        value_list = []
+        for k, attr_type in enumerate(tuple_desc):
+            value, i = get_value(attr_type, response, i)
+            if is_null(response, tuple_start, k):
+                value = None
+            value_list.append(value)
         result.append(tuple(value_list))
+    assert i == payload_end
 
     return (tuple_desc, result)
 
 
-def attr_len(attr):
-    if attr['type'] in ("Int", "Float"):
-        return 4
-    else:
-        assert attr['type'] == 'Char'
-        return int(attr['len'])
+def is_null(data, tuple_start, attr_index):
+    return ord(data[tuple_start + attr_index / 8]) & (1 << (attr_index % 8)) != 0
 
 
 def get_value(attr_type, data, index):
+    # return the value and the index of the next value
     if attr_type['type'] == 'Int':
-        return struct.unpack('<I', data[index:index+4])[0]
+        return struct.unpack('<I', data[index:index+4])[0], index + 4
     elif attr_type['type'] == 'Float':
-        return struct.unpack('<f', data[index:index+4])[0]
+        return struct.unpack('<f', data[index:index+4])[0], index + 4
     elif attr_type['type'] == 'Char':
         str_len = int(attr_type['len'])
-        return struct.unpack('%ds' % str_len, data[index:index+str_len])[0].rstrip('\0')
-    raise Exception('invalid type %s' % attr_type['type'])
-
-
-def get_gap(attr_type):
-    if attr_type['type'] in ('Int', 'Float'):
-        return 4
-    elif attr_type['type'] == 'Char':
-        return int(attr_type['len'])
+        return struct.unpack('%ds' % str_len, data[index:index+str_len])[0].rstrip('\0'), index + str_len
+    elif attr_type['type'] in ('Varchar', 'Text'):
+        str_len = struct.unpack('<I', data[index:index+4])[0]
+        return data[index+4:index+4+str_len], index + 4 + str_len
     raise Exception('invalid type %s' % attr_type['type'])
 
 
@@ -69,4 +65,8 @@ def repr_attr_type(attr_type):
         return attr_type['type']
     elif attr_type['type'] == 'Char':
         return 'Char(%s)' % attr_type['len']
+    elif attr_type['type'] == 'Varchar':
+        return 'Varchar(%s)' % attr_type['len']
+    elif attr_type['type'] == 'Text':
+        return 'Text'
     raise Exception('invalid type')
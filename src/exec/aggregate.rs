@@ -0,0 +1,283 @@
+use std::boxed::Box;
+use std::option::Option;
+use std::cmp::Ordering;
+use std::mem::replace;
+use std::collections::HashMap;
+use ::store::tuple::{Row, TupleValue, cmp_tuple_value};
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::ExecError;
+use super::spill::encode_value;
+
+
+// Aggregation of the rows grouped by one attribute, or of all the rows as a single group.
+// An aggregated row is the group by attribute followed by the results of the aggregate functions.
+// Null values are skipped by all the functions except count(*),
+// sum, avg, min and max are null if a group has no other value.
+// The sum of int is an int and wraps around on overflow, avg is always a float.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggreFunc {
+    Count,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggreFunc {
+    pub fn from_name(name : &str) -> Option<AggreFunc> {
+        match name {
+            "count" => Some(AggreFunc::Count),
+            "sum" => Some(AggreFunc::Sum),
+            "avg" => Some(AggreFunc::Avg),
+            "min" => Some(AggreFunc::Min),
+            "max" => Some(AggreFunc::Max),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AggreDesc {
+    pub func : AggreFunc,
+    pub index : Option<usize>,  // None for count(*)
+}
+
+#[derive(Debug, Clone)]
+struct AggreState {
+    count : usize,
+    int_sum : i64,
+    float_sum : f64,
+    is_float : bool,
+    extreme : TupleValue,  // min or max
+}
+
+impl AggreState {
+    fn new() -> AggreState {
+        AggreState{
+            count : 0,
+            int_sum : 0,
+            float_sum : 0.0,
+            is_float : false,
+            extreme : TupleValue::Null,
+        }
+    }
+    fn update(&mut self, desc : &AggreDesc, row : &Row) {
+        let value = match desc.index {
+            Some(i) => &row[i],
+            None => {
+                self.count += 1;
+                return;
+            }
+        };
+        match value {
+            &TupleValue::Null => return,
+            &TupleValue::Int(n) => self.int_sum += n as i64,
+            &TupleValue::Float(f) => {
+                self.float_sum += f as f64;
+                self.is_float = true;
+            }
+            &TupleValue::Char(..) => (),
+        }
+        self.count += 1;
+        let replace_extreme = match (desc.func, cmp_tuple_value(value, &self.extreme)) {
+            _ if self.count == 1 => true,
+            (AggreFunc::Min, Ordering::Less) | (AggreFunc::Max, Ordering::Greater) => true,
+            _ => false,
+        };
+        if replace_extreme {
+            self.extreme = value.clone();
+        }
+    }
+    fn get_result(&self, func : AggreFunc) -> TupleValue {
+        if self.count == 0 && func != AggreFunc::Count {
+            return TupleValue::Null;
+        }
+        match func {
+            AggreFunc::Count => TupleValue::Int(self.count as i32),
+            AggreFunc::Sum if self.is_float => TupleValue::Float(self.float_sum as f32),
+            AggreFunc::Sum => TupleValue::Int(self.int_sum as i32),
+            AggreFunc::Avg =>
+                TupleValue::Float(((self.float_sum + self.int_sum as f64) / self.count as f64) as f32),
+            AggreFunc::Min | AggreFunc::Max => self.extreme.clone(),
+        }
+    }
+}
+
+fn gen_aggre_row(group_key : Option<TupleValue>, aggre_list : &Vec<AggreDesc>, state_list : &Vec<AggreState>)
+        -> Row {
+    let mut row = Vec::new();
+    if let Some(key) = group_key {
+        row.push(key);
+    }
+    for (desc, state) in aggre_list.iter().zip(state_list.iter()) {
+        row.push(state.get_result(desc.func));
+    }
+    row
+}
+
+
+// Keeps a state for every group in a hash table, the input can be in any order.
+// The groups are returned in the order they first appear.
+#[derive(Debug)]
+pub struct HashAggregate {
+    data_source : ExecIterRef,
+    group_index : Option<usize>,
+    aggre_list : Vec<AggreDesc>,
+    aggregated : bool,
+    group_list : Vec<(Option<TupleValue>, Vec<AggreState>)>,  // in reverse order after aggregated
+    finished : bool,
+}
+
+impl HashAggregate {
+    pub fn new(group_index : Option<usize>, aggre_list : Vec<AggreDesc>, inner_iter : ExecIterRef)
+            -> ExecIterRef {
+        Box::new(HashAggregate{
+            data_source : inner_iter,
+            group_index : group_index,
+            aggre_list : aggre_list,
+            aggregated : false,
+            group_list : Vec::new(),
+            finished : false,
+        })
+    }
+    fn aggregate(&mut self) {
+        let mut group_map : HashMap<Vec<u8>, usize> = HashMap::new();
+        if self.group_index.is_none() {
+            // a single group even if there is no row
+            self.group_list.push((None, vec![AggreState::new(); self.aggre_list.len()]));
+        }
+        while let Some(row) = self.data_source.get_next() {
+            let i = match self.group_index {
+                Some(group_index) => {
+                    let mut hash_key = Vec::new();
+                    encode_value(&mut hash_key, &row[group_index]);
+                    let group_list = &mut self.group_list;
+                    let state_num = self.aggre_list.len();
+                    *group_map.entry(hash_key).or_insert_with(|| {
+                        group_list.push((Some(row[group_index].clone()), vec![AggreState::new(); state_num]));
+                        group_list.len() - 1
+                    })
+                }
+                None => 0,
+            };
+            for (desc, state) in self.aggre_list.iter().zip(self.group_list[i].1.iter_mut()) {
+                state.update(desc, &row);
+            }
+        }
+        self.group_list.reverse();
+    }
+}
+
+impl ExecIter for HashAggregate {
+    fn open(&mut self) {
+        self.data_source.open();
+    }
+    fn close(&mut self) {
+        self.data_source.close();
+        self.group_list.clear();
+        self.finished = true;
+    }
+    fn explain(&self) -> String {
+        format!("hash aggregate {:?} group by {:?} from source {:?}",
+            self.aggre_list, self.group_index, self.data_source)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        if self.finished {
+            return None;
+        }
+        if !self.aggregated {
+            self.aggregated = true;
+            self.aggregate();
+            if self.data_source.get_error().is_some() {
+                self.close();
+                return None;
+            }
+        }
+        match self.group_list.pop() {
+            Some((group_key, state_list)) => Some(gen_aggre_row(group_key, &self.aggre_list, &state_list)),
+            None => {
+                self.close();
+                None
+            }
+        }
+    }
+    fn get_error(&self) -> Option<ExecError> { self.data_source.get_error() }
+}
+
+
+// Aggregates the input sorted by the group by attribute, one group at a time.
+#[derive(Debug)]
+pub struct SortAggregate {
+    data_source : ExecIterRef,
+    group_index : usize,
+    aggre_list : Vec<AggreDesc>,
+    group_key : Option<TupleValue>,  // None before the first row
+    state_list : Vec<AggreState>,
+    finished : bool,
+}
+
+impl SortAggregate {
+    pub fn new(group_index : usize, aggre_list : Vec<AggreDesc>, inner_iter : ExecIterRef) -> ExecIterRef {
+        let state_num = aggre_list.len();
+        Box::new(SortAggregate{
+            data_source : inner_iter,
+            group_index : group_index,
+            aggre_list : aggre_list,
+            group_key : None,
+            state_list : vec![AggreState::new(); state_num],
+            finished : false,
+        })
+    }
+    fn finish_group(&mut self, next_key : Option<TupleValue>) -> Option<Row> {
+        let state_list = replace(&mut self.state_list, vec![AggreState::new(); self.aggre_list.len()]);
+        match replace(&mut self.group_key, next_key) {
+            Some(key) => Some(gen_aggre_row(Some(key), &self.aggre_list, &state_list)),
+            None => None,
+        }
+    }
+}
+
+impl ExecIter for SortAggregate {
+    fn open(&mut self) {
+        self.data_source.open();
+    }
+    fn close(&mut self) {
+        self.data_source.close();
+        self.finished = true;
+    }
+    fn explain(&self) -> String {
+        format!("sort aggregate {:?} group by {:?} from source {:?}",
+            self.aggre_list, self.group_index, self.data_source)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        if self.finished {
+            return None;
+        }
+        while let Some(row) = self.data_source.get_next() {
+            let same_group = match self.group_key {
+                Some(ref key) => cmp_tuple_value(key, &row[self.group_index]) == Ordering::Equal,
+                None => false,
+            };
+            let result = if same_group {
+                None
+            } else {
+                let next_key = row[self.group_index].clone();
+                self.finish_group(Some(next_key))
+            };
+            for (desc, state) in self.aggre_list.iter().zip(self.state_list.iter_mut()) {
+                state.update(desc, &row);
+            }
+            if result.is_some() {
+                return result;
+            }
+        }
+        let result = if self.data_source.get_error().is_some() {
+            None
+        } else {
+            self.finish_group(None)
+        };
+        self.close();
+        result
+    }
+    fn get_error(&self) -> Option<ExecError> { self.data_source.get_error() }
+}
@@ -0,0 +1,89 @@
+use std::boxed::Box;
+use std::option::Option;
+use ::store::table::TableManagerRef;
+use ::store::tuple::{Row, TupleValue, gen_value_list};
+use ::parser::{AlterStatement, AlterAction};
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::{ExecError, ExecErrorType};
+
+
+#[derive(Debug)]
+pub struct AlterTable {
+    stmt : AlterStatement,
+    finished : bool,
+    error : Option<ExecError>,
+    table_manager : TableManagerRef,
+}
+
+impl AlterTable {
+    pub fn new(stmt : AlterStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(AlterTable{
+            finished : false,
+            error : None,
+            stmt : stmt,
+            table_manager : table_manager.clone(),
+        })
+    }
+}
+
+impl ExecIter for AlterTable {
+    fn open(&mut self) {}
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        // the existing tuples are rewritten even if only a name changes,
+        // since the table files are checked against the schema
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        let table_ref = self.table_manager.borrow().get_table(&self.stmt.table).unwrap();
+        let mut table = table_ref.borrow().clone();
+        let result = match self.stmt.action {
+            AlterAction::AddColumn{ref decl, ref default} => {
+                let attr = decl.gen_attr();
+                let value = match default {
+                    &Some(ref value) => gen_value_list(&vec![value.clone()], &vec![attr.attr_type]).pop().unwrap(),
+                    &None => TupleValue::Null,
+                };
+                table.attr_list.push(attr);
+                self.table_manager.borrow_mut().alter_table(&self.stmt.table, table,
+                    &|mut value_list| { value_list.push(value.clone()); value_list })
+            }
+            AlterAction::DropColumn{ref attr} => {
+                let attr_index = table.get_attr_index(attr).unwrap();
+                table.attr_list.remove(attr_index);
+                self.table_manager.borrow_mut().alter_table(&self.stmt.table, table,
+                    &|mut value_list| { value_list.remove(attr_index); value_list })
+            }
+            AlterAction::RenameColumn{ref from, ref to} => {
+                let attr_index = table.get_attr_index(from).unwrap();
+                table.attr_list[attr_index].name = to.clone();
+                for index in table.index_list.iter_mut() {
+                    for attr in index.attr_list.iter_mut().filter(|a| *a == from) {
+                        *attr = to.clone();
+                    }
+                }
+                self.table_manager.borrow_mut().alter_table(&self.stmt.table, table, &|value_list| value_list)
+            }
+            AlterAction::RenameTable{ref to} => {
+                table.name = to.clone();
+                self.table_manager.borrow_mut().alter_table(&self.stmt.table, table, &|value_list| value_list)
+            }
+        };
+        match result {
+            Ok(true) => (),
+            Ok(false) => {
+                self.error = Some(ExecError{
+                    error_type : ExecErrorType::PageInUse,
+                    error_msg : format!("pages of table {} are in use", self.stmt.table),
+                });
+            }
+            Err(err) => self.error = Some(ExecError::from(err)),
+        }
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+}
@@ -1,10 +1,8 @@
 use std::boxed::Box;
 use std::option::Option;
 use std::collections::HashMap;
-use std::ptr::write;
-use ::utils::pointer::write_string;
-use ::store::table::{AttrType, TableManagerRef};
-use ::store::tuple::{TupleData, TupleValue, TupleDesc};
+use ::store::table::TableManagerRef;
+use ::store::tuple::{Row, TupleValue};
 use ::parser::{
     InsertStatement,
 };
@@ -17,6 +15,7 @@ pub struct Insert {
     stmt : InsertStatement,
     table_manager : TableManagerRef,
     finished : bool,
+    error : Option<ExecError>,
 }
 
 impl Insert {
@@ -25,6 +24,7 @@ impl Insert {
             finished : false,
             stmt : stmt,
             table_manager : table_manager.clone(),
+            error : None,
         })
     }
 }
@@ -37,15 +37,18 @@ impl ExecIter for Insert {
     fn explain(&self) -> String {
         format!("{}", self.stmt)
     }
-    fn get_next(&mut self) -> Option<TupleData> {
+    fn get_next(&mut self) -> Option<Row> {
         if self.finished {
             return None;
         }
-        self.table_manager.borrow_mut().insert(&self.stmt.table, &self.stmt.value_list);
+        let result = self.table_manager.borrow_mut().insert(&self.stmt.table, &self.stmt.value_list);
+        if let Err(err) = result {
+            self.error = Some(ExecError::from(err));
+        }
         self.close();
         None
     }
-    fn get_error(&self) -> Option<ExecError> { None }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
 }
 
 
@@ -55,6 +58,7 @@ pub struct Delete {
     data_source : ExecIterRef,
     table_manager : TableManagerRef,
     finished : bool,
+    error : Option<ExecError>,
 }
 
 impl Delete {
@@ -64,6 +68,7 @@ impl Delete {
             data_source : data_source,
             table_manager : table_manager.clone(),
             finished : false,
+            error : None,
         })
     }
 }
@@ -80,21 +85,28 @@ impl ExecIter for Delete {
     fn explain(&self) -> String {
         format!("delete tuple from source: {:?}", self.data_source)
     }
-    fn get_next(&mut self) -> Option<TupleData> {
+    fn get_next(&mut self) -> Option<Row> {
         if self.finished {
             return None;
         }
-        let tuple_data = match self.data_source.get_next() {
-            Some(tuple_data) => tuple_data,
+        let row = match self.data_source.get_next() {
+            Some(row) => row,
             None => {
+                self.error = self.data_source.get_error();
                 self.close();
                 return None;
             }
         };
-        self.table_manager.borrow_mut().file_manager.delete(&self.table, tuple_data[0]);
-        Some(tuple_data)  // only to indicate not finished, the data inside is only for tests
+        let record_id = self.data_source.get_record_id().unwrap();
+        let result = self.table_manager.borrow_mut().file_manager.delete(&self.table, &record_id);
+        if let Err(err) = result {
+            self.error = Some(ExecError::from(err));
+            self.close();
+            return None;
+        }
+        Some(row)  // only to indicate not finished, the data inside is only for tests
     }
-    fn get_error(&self) -> Option<ExecError> { None }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
 }
 
 
@@ -105,23 +117,22 @@ pub struct Update {
     table_manager : TableManagerRef,
     finished : bool,
     set_values : HashMap<usize, TupleValue>,
-    tuple_desc : TupleDesc,
+    error : Option<ExecError>,
 }
 
 impl Update {
     pub fn new(
             table : &String,
-            tuple_desc : TupleDesc,
             set_values : HashMap<usize, TupleValue>,
             data_source : ExecIterRef,
             table_manager : &TableManagerRef) -> ExecIterRef {
         Box::new(Update{
             table : table.clone(),
-            tuple_desc : tuple_desc,
             data_source : data_source,
             table_manager : table_manager.clone(),
             finished : false,
             set_values : set_values,
+            error : None,
         })
     }
 }
@@ -138,47 +149,58 @@ impl ExecIter for Update {
     fn explain(&self) -> String {
         format!("update tuple from source: {:?}, set {:?}", self.data_source, self.set_values)
     }
-    fn get_next(&mut self) -> Option<TupleData> {
+    fn get_next(&mut self) -> Option<Row> {
         if self.finished {
             return None;
         }
-        let tuple_data = match self.data_source.get_next() {
-            Some(tuple_data) => tuple_data,
+        let mut row = match self.data_source.get_next() {
+            Some(row) => row,
             None => {
+                self.error = self.data_source.get_error();
                 self.close();
                 return None;
             }
         };
-        for (i, v) in self.set_values.iter() {
-            let p = tuple_data[*i];
-            unsafe {
-                match v {
-                    &TupleValue::Int(num) => write::<i32>(p as *mut i32, num),
-                    &TupleValue::Float(num) => write::<f32>(p as *mut f32, num),
-                    &TupleValue::Char(ref s) => {
-                        let len = extract!(self.tuple_desc.attr_desc[*i], AttrType::Char{len}, len);
-                        write_string(p, s, len);
-                    }
+        let record_id = self.data_source.get_record_id().unwrap();
+        let result = self.table_manager.borrow_mut().file_manager.update(
+            &self.table, &record_id, &self.set_values);
+        let error = match result {
+            Ok(None) => {
+                // the row is a copy, apply the new values to it as well
+                for (index, value) in &self.set_values {
+                    row[*index] = value.clone();
                 }
+                return Some(row);
             }
-        }
-        Some(tuple_data)
+            Ok(Some(index)) => ExecError{
+                error_type : ExecErrorType::UniqueKeyExist,
+                error_msg : format!("unique key already exist in index {}", index),
+            },
+            Err(err) => ExecError::from(err),
+        };
+        self.error = Some(error);
+        self.close();
+        None
     }
-    fn get_error(&self) -> Option<ExecError> { None }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
 }
 
 #[derive(Debug)]
 pub struct CheckAndInsert {
     filter_plan : ExecIterRef,
+    unique_check_list : Vec<(String, ExecIterRef)>,  // index name and plan to find the same key
     insert_plan : ExecIterRef,
     error : Option<ExecError>,
     finished : bool,
 }
 
 impl CheckAndInsert {
-    pub fn new(filter_plan : ExecIterRef, insert_plan : ExecIterRef) -> ExecIterRef {
+    pub fn new(filter_plan : ExecIterRef,
+            unique_check_list : Vec<(String, ExecIterRef)>,
+            insert_plan : ExecIterRef) -> ExecIterRef {
         Box::new(CheckAndInsert{
             filter_plan : filter_plan,
+            unique_check_list : unique_check_list,
             insert_plan : insert_plan,
             error : None,
             finished : false,
@@ -190,6 +212,9 @@ impl ExecIter for CheckAndInsert {
     fn open(&mut self) {
         assert!(!self.finished);
         self.filter_plan.open();
+        for &mut (_, ref mut check) in self.unique_check_list.iter_mut() {
+            check.open();
+        }
         self.insert_plan.open();
     }
     fn close(&mut self) {
@@ -198,26 +223,58 @@ impl ExecIter for CheckAndInsert {
         }
         self.finished = true;
         self.filter_plan.close();
+        for &mut (_, ref mut check) in self.unique_check_list.iter_mut() {
+            check.close();
+        }
         self.insert_plan.close();
     }
     fn explain(&self) -> String {
-        format!("check if primary key exist {:?}, then insert {:?}",
-            self.filter_plan, self.insert_plan)
+        format!("check if primary key exist {:?}, check unique keys {:?}, then insert {:?}",
+            self.filter_plan, self.unique_check_list, self.insert_plan)
     }
-    fn get_next(&mut self) -> Option<TupleData> {
+    fn get_next(&mut self) -> Option<Row> {
         if self.finished {
             return None;
         }
-        match self.filter_plan.get_next() {
-            Some(..) => {
+        if let Some(..) = self.filter_plan.get_next() {
+            self.error = Some(ExecError{
+                error_type : ExecErrorType::PrimaryKeyExist,
+                error_msg : format!("primary key already exist"),
+            });
+            self.close();
+            return None;
+        }
+        if let Some(err) = self.filter_plan.get_error() {
+            self.error = Some(err);
+            self.close();
+            return None;
+        }
+        let mut exist_index = None;
+        for &mut (ref index, ref mut check) in self.unique_check_list.iter_mut() {
+            if let Some(..) = check.get_next() {
+                exist_index = Some(index.clone());
+                break;
+            }
+            if let Some(err) = check.get_error() {
+                self.error = Some(err);
+                break;
+            }
+        }
+        if self.error.is_some() {
+            self.close();
+            return None;
+        }
+        match exist_index {
+            Some(index) => {
                 self.error = Some(ExecError{
-                    error_type : ExecErrorType::PrimaryKeyExist,
-                    error_msg : format!("primary key already exist"),
+                    error_type : ExecErrorType::UniqueKeyExist,
+                    error_msg : format!("unique key already exist in index {}", index),
                 });
                 self.close();
             }
             None => {
                 self.insert_plan.get_next();
+                self.error = self.insert_plan.get_error();
             }
         };
         None
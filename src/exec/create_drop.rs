@@ -1,11 +1,10 @@
 use std::boxed::Box;
 use std::option::Option;
-use ::store::table::{Table, Attr, AttrType, TableManagerRef};
-use ::store::tuple::TupleData;
-use ::parser::{CreateStatement, DropStatement};
-use ::parser;
+use ::store::table::{Table, IndexDesc, TableManagerRef};
+use ::store::tuple::Row;
+use ::parser::{CreateStatement, DropStatement, CreateIndexStatement, DropIndexStatement};
 use super::iter::{ExecIter, ExecIterRef};
-use super::error::ExecError;
+use super::error::{ExecError, ExecErrorType};
 
 
 #[derive(Debug)]
@@ -31,32 +30,24 @@ impl ExecIter for CreateTable {
     fn explain(&self) -> String {
         format!("{}", self.stmt)
     }
-    fn get_next(&mut self) -> Option<TupleData> {
+    fn get_next(&mut self) -> Option<Row> {
         if self.finished {
             return None;
         }
-        let mut attr_list = Vec::new();
-        for attr in &self.stmt.decl_list {
-            attr_list.push(Attr{
-                name : attr.name.clone(),
-                attr_type : match attr.attr_type {
-                    parser::create_drop::AttrType::Int => AttrType::Int,
-                    parser::create_drop::AttrType::Float => AttrType::Float,
-                    parser::create_drop::AttrType::Char{ref len} =>
-                        AttrType::Char{len : len.parse::<usize>().unwrap()},
-                },
-                primary : attr.primary,
-                nullable : attr.nullable,
-            });
-        }
+        let attr_list = self.stmt.decl_list.iter().map(|d| d.gen_attr()).collect();
+        let mut manager = self.table_manager.borrow_mut();
+        let compression = match self.stmt.get_option("compression") {
+            Some(ref c) if c == "none" => None,
+            Some(c) => Some(c),
+            None => manager.get_default_compression(),
+        };
         let table = Table{
             name : self.stmt.table.clone(),
             attr_list : attr_list,
+            index_list : Vec::new(),
+            compression : compression,
         };
-        {
-            let mut manager = self.table_manager.borrow_mut();
-            manager.add_table(table);
-        }
+        manager.add_table(table);
         self.finished = true;
         None
     }
@@ -87,7 +78,7 @@ impl ExecIter for DropTable {
     fn explain(&self) -> String {
         format!("{}", self.stmt)
     }
-    fn get_next(&mut self) -> Option<TupleData> {
+    fn get_next(&mut self) -> Option<Row> {
         if self.finished {
             return None;
         }
@@ -100,3 +91,91 @@ impl ExecIter for DropTable {
     }
     fn get_error(&self) -> Option<ExecError> { None }
 }
+
+
+#[derive(Debug)]
+pub struct CreateIndex {
+    stmt : CreateIndexStatement,
+    finished : bool,
+    error : Option<ExecError>,
+    table_manager : TableManagerRef,
+}
+
+impl CreateIndex {
+    pub fn new(stmt : CreateIndexStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(CreateIndex{
+            finished : false,
+            error : None,
+            stmt : stmt,
+            table_manager : table_manager.clone(),
+        })
+    }
+}
+
+impl ExecIter for CreateIndex {
+    fn open(&mut self) {}
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        if self.finished {
+            return None;
+        }
+        let index = IndexDesc{
+            name : self.stmt.index.clone(),
+            attr_list : self.stmt.attr_list.clone(),
+            unique : self.stmt.unique,
+        };
+        let result = self.table_manager.borrow_mut().create_index(&self.stmt.table, index);
+        match result {
+            Ok(true) => (),
+            Ok(false) => {
+                self.error = Some(ExecError{
+                    error_type : ExecErrorType::UniqueKeyExist,
+                    error_msg : format!("duplicate key found when creating unique index {}",
+                        self.stmt.index),
+                });
+            }
+            Err(err) => self.error = Some(ExecError::from(err)),
+        }
+        self.finished = true;
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+}
+
+
+#[derive(Debug)]
+pub struct DropIndex {
+    stmt : DropIndexStatement,
+    finished : bool,
+    table_manager : TableManagerRef,
+}
+
+impl DropIndex {
+    pub fn new(stmt : DropIndexStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(DropIndex{
+            finished : false,
+            stmt : stmt,
+            table_manager : table_manager.clone(),
+        })
+    }
+}
+
+impl ExecIter for DropIndex {
+    fn open(&mut self) {}
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        if self.finished {
+            return None;
+        }
+        self.table_manager.borrow_mut().drop_index(&self.stmt.table, &self.stmt.index);
+        self.finished = true;
+        None
+    }
+    fn get_error(&self) -> Option<ExecError> { None }
+}
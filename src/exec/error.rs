@@ -1,6 +1,14 @@
+use ::store::error::{StorageError, StorageErrorType};
+
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum ExecErrorType {
     PrimaryKeyExist,
+    UniqueKeyExist,
+    PageInUse,
+    PageCorrupted,
+    PoolExhausted,
+    IoError,
 }
 
 #[derive(Debug, Clone)]
@@ -8,3 +16,17 @@ pub struct ExecError {
     pub error_type : ExecErrorType,
     pub error_msg : String,
 }
+
+impl From<StorageError> for ExecError {
+    fn from(err : StorageError) -> ExecError {
+        let error_type = match err.error_type {
+            StorageErrorType::PageCorrupted => ExecErrorType::PageCorrupted,
+            StorageErrorType::PoolExhausted => ExecErrorType::PoolExhausted,
+            StorageErrorType::IoError => ExecErrorType::IoError,
+        };
+        ExecError{
+            error_type : error_type,
+            error_msg : err.error_msg,
+        }
+    }
+}
@@ -1,4 +1,3 @@
-use std::ptr::read;
 use std::collections::HashMap;
 use ::parser::condition::{
     ConditionExpr,
@@ -11,30 +10,50 @@ use ::parser::condition::{
 };
 use ::parser::common::{ValueExpr, ValueType};
 use ::parser::attribute::AttributeExpr;
-use ::store::buffer::DataPtr;
-use ::store::table::AttrType;
-use ::utils::pointer::read_string;
+use ::store::tuple::{TupleValue, Row};
+use ::store::table::IndexMap;
 
 
-pub type PtrMap = HashMap<(String, String), (DataPtr, AttrType)>;
+pub type ValueMap = HashMap<(String, String), TupleValue>;
+
+pub fn gen_value_map(index_map : &IndexMap, row : &Row) -> ValueMap {
+    let mut value_map = ValueMap::new();
+    for (k, index) in index_map {
+        value_map.insert(k.clone(), row[*index].clone());
+    }
+    value_map
+}
+
+pub fn gen_aggre_key(func : &String, table : &Option<String>, attr : &String) -> (String, String) {
+    // key of the result of an aggregate function in the aggregated rows,
+    // the table is empty for count(*)
+    (table.clone().unwrap_or(String::new()), format!("{}({})", func, attr))
+}
 
 
-pub fn eval_cond(condition : &ConditionExpr, ptr_map : &PtrMap) -> bool {
+pub fn eval_cond(condition : &ConditionExpr, value_map : &ValueMap) -> bool {
+    eval_cond_value(condition, value_map) == Some(true)
+}
+
+pub fn eval_cond_value(condition : &ConditionExpr, value_map : &ValueMap) -> Option<bool> {
+    // None for unknown, which is the result of comparing with null
     match condition {
-        &ConditionExpr::NotExpr{ ref operant } => !eval_cond(operant, ptr_map),
+        &ConditionExpr::NotExpr{ ref operant } => eval_cond_value(operant, value_map).map(|b| !b),
         &ConditionExpr::CmpExpr{ ref lhs, ref rhs, op } =>
-            eval_cmp_operant(lhs, rhs, op, ptr_map),
+            eval_cmp_operant(lhs, rhs, op, value_map),
         &ConditionExpr::LogicExpr{ ref lhs, ref rhs, op } =>
-            eval_logic_op(lhs, rhs, op, ptr_map),
+            eval_logic_op(lhs, rhs, op, value_map),
     }
 }
 
-pub fn eval_logic_op(lhs : &CondRef, rhs : &CondRef, op : LogicOp, ptr_map : &PtrMap) -> bool {
-    let lresult = eval_cond(&**lhs, ptr_map);
-    let rresult = eval_cond(&**rhs, ptr_map);
-    match op {
-        LogicOp::Or => lresult || rresult,
-        LogicOp::And => lresult && rresult,
+pub fn eval_logic_op(lhs : &CondRef, rhs : &CondRef, op : LogicOp, value_map : &ValueMap) -> Option<bool> {
+    let lresult = eval_cond_value(&**lhs, value_map);
+    let rresult = eval_cond_value(&**rhs, value_map);
+    match (op, lresult, rresult) {
+        (LogicOp::Or, Some(true), _) | (LogicOp::Or, _, Some(true)) => Some(true),
+        (LogicOp::And, Some(false), _) | (LogicOp::And, _, Some(false)) => Some(false),
+        (_, Some(l), Some(r)) => Some(l && r),  // both true for and, both false for or
+        _ => None,
     }
 }
 
@@ -42,92 +61,110 @@ pub fn eval_cmp_operant(
         lhs : &CmpOperantExpr,
         rhs : &CmpOperantExpr,
         op : CmpOp,
-        ptr_map : &PtrMap) -> bool {
+        value_map : &ValueMap) -> Option<bool> {
+    match op {
+        CmpOp::Is => return Some(eval_is_null(lhs, value_map)),
+        CmpOp::IsNot => return Some(!eval_is_null(lhs, value_map)),
+        _ => (),
+    }
     match (lhs, rhs) {
         (&CmpOperantExpr::Value(ref l), &CmpOperantExpr::Value(ref r)) => {
             let lvalue = eval_str(l);
             let rvalue = eval_str(r);
-            eval_str_cmp(&lvalue, &rvalue, op)
+            Some(eval_str_cmp(&lvalue, &rvalue, op))
         }
         (&CmpOperantExpr::Value(ref l), &CmpOperantExpr::Arith(ref r)) => {
             let lvalue = eval_str(l);
-            let rvalue = eval_str_attr(r, ptr_map);
-            eval_str_cmp(&lvalue, &rvalue, op)
+            let rvalue = try_opt!(eval_str_attr(r, value_map));
+            Some(eval_str_cmp(&lvalue, &rvalue, op))
         }
         (&CmpOperantExpr::Arith(ref l), &CmpOperantExpr::Value(ref r)) => {
-            let lvalue = eval_str_attr(l, ptr_map);
+            let lvalue = try_opt!(eval_str_attr(l, value_map));
             let rvalue = eval_str(r);
-            eval_str_cmp(&lvalue, &rvalue, op)
+            Some(eval_str_cmp(&lvalue, &rvalue, op))
         }
         (&CmpOperantExpr::Arith(ref l), &CmpOperantExpr::Arith(ref r)) => {
-            let lvalue = eval_arith(l, ptr_map);
-            let rvalue = eval_arith(r, ptr_map);
-            match op {
+            let lvalue = try_opt!(eval_arith(l, value_map));
+            let rvalue = try_opt!(eval_arith(r, value_map));
+            Some(match op {
                 CmpOp::LT => lvalue < rvalue,
                 CmpOp::GT => lvalue > rvalue,
                 CmpOp::LE => lvalue <= rvalue,
                 CmpOp::GE => lvalue >= rvalue,
                 CmpOp::EQ => lvalue == rvalue,
                 CmpOp::NE => lvalue != rvalue,
-                CmpOp::Is => unimplemented!(),
-                CmpOp::IsNot => unimplemented!(),
-            }
+                CmpOp::Is | CmpOp::IsNot => unreachable!(),
+            })
         }
     }
 }
 
+pub fn eval_is_null(operant : &CmpOperantExpr, value_map : &ValueMap) -> bool {
+    match operant {
+        &CmpOperantExpr::Value(ref v) => v.value_type == ValueType::Null,
+        &CmpOperantExpr::Arith(ArithExpr::Attr(ref attr_expr)) =>
+            is_match!(get_attr_value(attr_expr, value_map), &TupleValue::Null),
+        &CmpOperantExpr::Arith(ref arith) => eval_arith(arith, value_map).is_none(),
+    }
+}
+
 pub fn eval_str_cmp(lvalue : &String, rvalue : &String, op : CmpOp) -> bool {
     match op {
         CmpOp::LT | CmpOp::GT| CmpOp::LE| CmpOp::GE =>
             panic!("invalid operationo for string"),
         CmpOp::EQ => lvalue == rvalue,
         CmpOp::NE => lvalue != rvalue,
-        CmpOp::Is => unimplemented!(),
-        CmpOp::IsNot => unimplemented!(),
+        CmpOp::Is | CmpOp::IsNot => panic!("invalid operation for string"),
     }
 }
 
-pub fn eval_str_attr(expr : &ArithExpr, ptr_map : &PtrMap) -> String {
+fn get_attr_value<'a>(attr_expr : &AttributeExpr, value_map : &'a ValueMap) -> &'a TupleValue {
+    let key = match attr_expr {
+        &AttributeExpr::TableAttr{ref table, ref attr} => {
+            assert!(table.is_some());
+            (table.clone().unwrap(), attr.clone())
+        }
+        &AttributeExpr::AggreFuncCall{ref func, ref table, ref attr} => gen_aggre_key(func, table, attr),
+    };
+    value_map.get(&key).unwrap()
+}
+
+pub fn eval_str_attr(expr : &ArithExpr, value_map : &ValueMap) -> Option<String> {
+    // None if the attribute is null
     match expr {
         &ArithExpr::Attr( ref attr_expr ) => {
-            let (table, attr) = match attr_expr {
-                &AttributeExpr::TableAttr{ref table, ref attr} => (table.clone(), attr.clone()),
-                &AttributeExpr::AggreFuncCall{ref table, ref attr, ..} => (table.clone(), attr.clone()),
-            };
-            assert!(table.is_some());
-            let (p, t) = ptr_map.get(&(table.unwrap(), attr)).unwrap().clone();
-            let len = extract!(t, AttrType::Char{len}, len);
-            unsafe{ read_string(p, len) }
+            match get_attr_value(attr_expr, value_map) {
+                &TupleValue::Null => None,
+                &TupleValue::Char(ref s) => Some(s.clone()),
+                v => panic!("invalid value {:?}", v),
+            }
         }
         _ => panic!("expected attribute, found {:?}", expr),
     }
 }
 
-pub fn eval_arith(expr : &ArithExpr, ptr_map : &PtrMap) -> f32 {
+pub fn eval_arith(expr : &ArithExpr, value_map : &ValueMap) -> Option<f32> {
+    // None if any attribute is null
     match expr {
         &ArithExpr::BinaryExpr{ ref lhs, ref rhs, op } => {
-            let l = eval_arith(lhs, ptr_map);
-            let r = eval_arith(rhs, ptr_map);
-            match op {
+            let l = try_opt!(eval_arith(lhs, value_map));
+            let r = try_opt!(eval_arith(rhs, value_map));
+            Some(match op {
                 ArithOp::Add => l + r,
                 ArithOp::Sub => l - r,
                 ArithOp::Mul => l * r,
                 ArithOp::Div => l / r,
                 ArithOp::Mod => l % r,
-            }
+            })
         }
-        &ArithExpr::MinusExpr{ ref operant } => - eval_arith(operant, ptr_map),
-        &ArithExpr::Value(ref v) => eval_num(v),
+        &ArithExpr::MinusExpr{ ref operant } => eval_arith(operant, value_map).map(|n| -n),
+        &ArithExpr::Value(ref v) => Some(eval_num(v)),
         &ArithExpr::Attr( ref attr_expr ) => {
-            let (table, attr) = match attr_expr {
-                &AttributeExpr::TableAttr{ref table, ref attr} => (table.clone(), attr.clone()),
-                &AttributeExpr::AggreFuncCall{ref table, ref attr, ..} => (table.clone(), attr.clone()),
-            };
-            let (p, t) = ptr_map.get(&(table.unwrap(), attr)).unwrap().clone();
-            match t {
-                AttrType::Int => unsafe{ read::<i32>(p as *const i32) as f32 },
-                AttrType::Float => unsafe{ read::<f32>(p as *const f32) },
-                _ => panic!("invalid type {:?}", t),
+            match get_attr_value(attr_expr, value_map) {
+                &TupleValue::Null => None,
+                &TupleValue::Int(n) => Some(n as f32),
+                &TupleValue::Float(n) => Some(n),
+                v => panic!("invalid value {:?}", v),
             }
         }
     }
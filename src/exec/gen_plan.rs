@@ -1,10 +1,13 @@
 use std::vec::Vec;
 use std::boxed::Box;
 use std::collections::HashMap;
+use std::mem::replace;
 use ::parser::common::{Statement, ValueExpr, ValueType};
-use ::parser::select::{Relation, SelectExpr};
+use ::parser::select::{Relation, SelectExpr, OrderBy, JoinType, JoinConstraint};
+use ::parser::sem_check::has_aggre_func;
+use ::parser::alter::AlterAction;
 use ::parser::attribute::AttributeExpr;
-use ::parser::condition::gen_check_primary_key_condition_expr;
+use ::parser::condition::{ConditionExpr, CmpOperantExpr, ArithExpr, CmpOp, LogicOp};
 use ::parser::{
     SelectStatement,
     InsertStatement,
@@ -12,13 +15,26 @@ use ::parser::{
     DeleteStatement,
     CreateStatement,
     DropStatement,
+    CreateIndexStatement,
+    DropIndexStatement,
+    VacuumStatement,
+    AlterStatement,
 };
-use ::store::table::{TableSet, TableManagerRef, TableRef};
-use ::store::tuple::TupleValue; 
+use ::store::table::{TableSet, TableManagerRef, TableRef, AttrType, Attr, IndexMap};
+use ::store::tuple::{TupleValue, TupleDesc};
+use ::utils::array::projection;
+use ::store::index::PRIMARY_INDEX;
+use ::store::btree::KeyRange;
 use super::iter::ExecIterRef;
-use super::create_drop::{CreateTable, DropTable};
+use super::create_drop::{CreateTable, DropTable, CreateIndex, DropIndex};
 use super::change::{Insert, CheckAndInsert, Update, Delete};
-use super::query::{FileScan, Filter, Projection};
+use super::query::{FileScan, IndexScan, Filter, Projection};
+use super::vacuum::Vacuum;
+use super::alter::AlterTable;
+use super::sort::{Sort, SortKeyList};
+use super::join::{NestedLoopJoin, HashJoin};
+use super::aggregate::{HashAggregate, SortAggregate, AggreFunc, AggreDesc};
+use super::evaluate::gen_aggre_key;
 
 
 pub fn gen_plan(stmt : Statement, table_manager : &TableManagerRef)
@@ -26,6 +42,10 @@ pub fn gen_plan(stmt : Statement, table_manager : &TableManagerRef)
     match stmt {
         Statement::Create(create) => gen_create_plan(create, table_manager),
         Statement::Drop(drop) => gen_drop_plan(drop, table_manager),
+        Statement::CreateIndex(create) => gen_create_index_plan(create, table_manager),
+        Statement::DropIndex(drop) => gen_drop_index_plan(drop, table_manager),
+        Statement::Vacuum(vacuum) => gen_vacuum_plan(vacuum, table_manager),
+        Statement::Alter(alter) => gen_alter_plan(alter, table_manager),
         Statement::Insert(insert) => gen_insert_plan(insert, table_manager),
         Statement::Update(update) => gen_update_plan(update, table_manager),
         Statement::Delete(delete) => gen_delete_plan(delete, table_manager),
@@ -41,59 +61,540 @@ pub fn gen_drop_plan(stmt : DropStatement, table_manager : &TableManagerRef) ->
     DropTable::new(stmt, table_manager)
 }
 
+pub fn gen_create_index_plan(stmt : CreateIndexStatement, table_manager : &TableManagerRef)
+        -> ExecIterRef {
+    CreateIndex::new(stmt, table_manager)
+}
+
+pub fn gen_drop_index_plan(stmt : DropIndexStatement, table_manager : &TableManagerRef)
+        -> ExecIterRef {
+    DropIndex::new(stmt, table_manager)
+}
+
+pub fn gen_vacuum_plan(stmt : VacuumStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    Vacuum::new(stmt, table_manager)
+}
+
+pub fn gen_alter_plan(stmt : AlterStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+    AlterTable::new(stmt, table_manager)
+}
+
 pub fn gen_select_plan(stmt : SelectStatement, table_manager : &TableManagerRef) -> ExecIterRef {
-    // join and sub query not supported now
-    let table_name = extract!(&stmt.relation_list[0], &Relation::TableName(ref name), name.clone());
-    let table = table_manager.borrow().get_table(&table_name).unwrap();
-    let mut query = FileScan::new(&table_name, table_manager);
-    let (attr_index, proj_attr_list) = gen_select_proj_info(&stmt, &table);
-    let need_proj = is_match!(stmt.select_expr, SelectExpr::AttrList(..));
-    if let Some(cond) = stmt.where_condition {
-        query = Filter::new(Box::new(cond),
-            table.borrow().gen_index_map(),
-            table.borrow().gen_tuple_desc(), query);
+    // sub query not supported now
+    let table_list = get_select_table_list(&stmt, table_manager);
+    let (attr_index, proj_attr_list) = gen_select_proj_info(&stmt, &table_list);
+    let merge_list = gen_select_merge_list(&stmt, &table_list);
+    let need_proj = is_match!(stmt.select_expr, SelectExpr::AttrList(..)) || !merge_list.is_empty();
+    let aggre_info = if is_aggre_select(&stmt) { Some(gen_aggre_info(&stmt, &table_list)) } else { None };
+    let mut query = gen_join_plan(stmt.relation_list, stmt.where_condition, table_manager);
+    if let Some(aggre_info) = aggre_info {
+        let aggre_list = aggre_info.aggre_list.clone();
+        // sort by the group by attribute for ORDER BY anyway, then aggregate the sorted groups
+        let sort_group = match (aggre_info.group_index, &stmt.order_by) {
+            (Some(group_index), &Some(ref order_by)) if order_by.key_list.len() == 1
+                    && get_attr_key(&order_by.key_list[0].attr) == aggre_info.attr_list[0] =>
+                Some((group_index, order_by.key_list[0].desc)),
+            _ => None,
+        };
+        query = match sort_group {
+            Some((group_index, desc)) => {
+                query = Sort::new(vec![(group_index, desc)], query, table_manager);
+                SortAggregate::new(group_index, aggre_list, query)
+            }
+            None => HashAggregate::new(aggre_info.group_index, aggre_list, query),
+        };
+        let having_condition = stmt.groupby_having.and_then(|g| g.having_condition);
+        if let Some(cond) = having_condition {
+            query = Filter::new(Box::new(cond), aggre_info.index_map.clone(),
+                TupleDesc::new(&aggre_info.gen_attr_list()), query);
+        }
+        if let (&Some(ref order_by), None) = (&stmt.order_by, sort_group) {
+            query = Sort::new(gen_sort_key_list(order_by, &aggre_info.index_map), query, table_manager);
+        }
+    } else if let Some(ref order_by) = stmt.order_by {
+        let index_map = gen_join_index_map(&table_list);
+        query = Sort::new(gen_sort_key_list(order_by, &index_map), query, table_manager);
     }
     if need_proj {
-        query = Projection::new(attr_index, proj_attr_list, query);
+        query = Projection::with_merge_list(attr_index, proj_attr_list, merge_list, query);
     }
     query
 }
 
-pub fn gen_select_proj_info(
-        stmt : &SelectStatement, table : &TableRef) -> (Vec<usize>, Vec<(String, String)>) {
-    let table = table.borrow();
-    let mut proj_attr_index = Vec::new();
-    let mut proj_attr_list = Vec::new();
-    let mut table_and_attr_list = match stmt.select_expr {
-        SelectExpr::AttrList(ref l) => {
-            let mut table_and_attr_list = Vec::new();
-            for attr in l {
-                let table_and_attr = extract!(attr, &AttributeExpr::TableAttr{ref table, ref attr},
-                    (table.clone().unwrap(), attr.clone()));
-                table_and_attr_list.push(table_and_attr);
+pub fn get_select_table_list(stmt : &SelectStatement, table_manager : &TableManagerRef) -> Vec<TableRef> {
+    // in the order of the rows of the tables in the joined rows
+    let mut name_list = Vec::new();
+    for relation in stmt.relation_list.iter() {
+        collect_relation_table(relation, &mut name_list);
+    }
+    name_list.iter().map(|name| table_manager.borrow().get_table(name).unwrap()).collect()
+}
+
+fn collect_relation_table(relation : &Relation, name_list : &mut Vec<String>) {
+    match relation {
+        &Relation::TableName(ref name) => name_list.push(name.clone()),
+        &Relation::Select(ref sub_select) => name_list.extend(gen_select_table_set_helper(sub_select)),
+        &Relation::Join{ref lhs, ref rhs, ..} => {
+            collect_relation_table(lhs, name_list);
+            collect_relation_table(rhs, name_list);
+        }
+    }
+}
+
+pub fn gen_join_index_map(table_list : &Vec<TableRef>) -> IndexMap {
+    // the rows of the tables are concatenated in order
+    let mut index_map = IndexMap::new();
+    let mut offset = 0;
+    for table in table_list.iter() {
+        let table = table.borrow();
+        for (key, i) in table.gen_index_map() {
+            index_map.insert(key, offset + i);
+        }
+        offset += table.attr_list.len();
+    }
+    index_map
+}
+
+pub fn gen_join_attr_list(table_list : &Vec<TableRef>) -> Vec<Attr> {
+    table_list.iter().flat_map(|table| table.borrow().attr_list.clone()).collect()
+}
+
+pub fn gen_join_plan(relation_list : Vec<Relation>, cond : Option<ConditionExpr>,
+        table_manager : &TableManagerRef) -> ExecIterRef {
+    // join the relations from left to right,
+    // each conjunct of the condition is checked as soon as all its tables are joined,
+    // except on the tables padded by outer joins, which is checked after the outer joins
+    let mut cond_list = Vec::new();
+    if let Some(cond) = cond {
+        split_conjunction(cond, &mut cond_list);
+    }
+    let mut joined_table_list : Vec<TableRef> = Vec::new();
+    let mut query : Option<ExecIterRef> = None;
+    for relation in relation_list.into_iter() {
+        let (plan, table_list) = gen_relation_plan(relation, &mut cond_list, false, table_manager);
+        query = Some(match query {
+            None => plan,
+            Some(joined) => gen_join(joined, plan, JoinType::Inner,
+                &joined_table_list, &table_list, &mut cond_list, table_manager),
+        });
+        joined_table_list.extend(table_list);
+    }
+    let mut query = query.unwrap();
+    if let Some(cond) = take_conjunction(&mut cond_list, &joined_table_list) {
+        query = Filter::new(Box::new(cond), gen_join_index_map(&joined_table_list),
+            TupleDesc::new(&gen_join_attr_list(&joined_table_list)), query);
+    }
+    assert!(cond_list.is_empty());
+    query
+}
+
+fn gen_relation_plan(relation : Relation, cond_list : &mut Vec<ConditionExpr>, padded : bool,
+        table_manager : &TableManagerRef) -> (ExecIterRef, Vec<TableRef>) {
+    // the conjuncts of cond_list are not taken if the relation may be padded by outer joins
+    match relation {
+        Relation::TableName(name) => {
+            let table = table_manager.borrow().get_table(&name).unwrap();
+            let local_cond = if padded { None } else { take_conjunction(cond_list, &vec![table.clone()]) };
+            let mut scan = gen_scan_plan(&table, &local_cond, table_manager);
+            if let Some(cond) = local_cond {
+                scan = Filter::new(Box::new(cond),
+                    table.borrow().gen_index_map(),
+                    table.borrow().gen_tuple_desc(), scan);
+            }
+            (scan, vec![table])
+        }
+        Relation::Select(..) => panic!("sub query not supported"),
+        Relation::Join{lhs, rhs, join_type, constraint} => {
+            let lhs_padded = padded || join_type == JoinType::Right || join_type == JoinType::Full;
+            let rhs_padded = padded || join_type == JoinType::Left || join_type == JoinType::Full;
+            let (lhs_plan, lhs_table_list) = gen_relation_plan(*lhs, cond_list, lhs_padded, table_manager);
+            let (rhs_plan, rhs_table_list) = gen_relation_plan(*rhs, cond_list, rhs_padded, table_manager);
+            let mut join_cond_list = Vec::new();
+            match constraint {
+                JoinConstraint::On(cond) => split_conjunction(cond, &mut join_cond_list),
+                JoinConstraint::Using(attr_list) => for attr in attr_list.iter() {
+                    join_cond_list.push(gen_using_cond(attr, &lhs_table_list, &rhs_table_list));
+                },
+            }
+            let mut table_list = lhs_table_list.clone();
+            table_list.extend(rhs_table_list.iter().cloned());
+            // the conjuncts of cond_list are the same as the join condition for inner join
+            if join_type == JoinType::Inner && !padded {
+                if let Some(cond) = take_conjunction(cond_list, &table_list) {
+                    split_conjunction(cond, &mut join_cond_list);
+                }
+            }
+            let plan = gen_join(lhs_plan, rhs_plan, join_type,
+                &lhs_table_list, &rhs_table_list, &mut join_cond_list, table_manager);
+            assert!(join_cond_list.is_empty());
+            (plan, table_list)
+        }
+    }
+}
+
+fn gen_join(lhs : ExecIterRef, rhs : ExecIterRef, join_type : JoinType,
+        lhs_table_list : &Vec<TableRef>, rhs_table_list : &Vec<TableRef>,
+        cond_list : &mut Vec<ConditionExpr>, table_manager : &TableManagerRef) -> ExecIterRef {
+    // the conjuncts of cond_list only using the tables of both sides are checked in the join,
+    // a hash join is used if there is any equality between the two sides
+    let (left_key, right_key) = take_equi_key(cond_list, lhs_table_list, rhs_table_list);
+    let mut table_list = lhs_table_list.clone();
+    table_list.extend(rhs_table_list.iter().cloned());
+    let join_cond = take_conjunction(cond_list, &table_list).map(Box::new);
+    let index_map = gen_join_index_map(&table_list);
+    let left_width = gen_join_attr_list(lhs_table_list).len();
+    if left_key.is_empty() {
+        NestedLoopJoin::new(lhs, rhs, join_type, join_cond, index_map, left_width, table_manager)
+    } else {
+        HashJoin::new(lhs, rhs, join_type, left_key, right_key, join_cond, index_map, left_width, table_manager)
+    }
+}
+
+fn gen_using_cond(attr : &String, lhs_table_list : &Vec<TableRef>, rhs_table_list : &Vec<TableRef>) -> ConditionExpr {
+    let gen_operant = |table_list| CmpOperantExpr::Arith(ArithExpr::Attr(AttributeExpr::TableAttr{
+        table : Some(find_attr_table(attr, table_list)),
+        attr : attr.clone(),
+    }));
+    ConditionExpr::CmpExpr{
+        lhs : gen_operant(lhs_table_list),
+        rhs : gen_operant(rhs_table_list),
+        op : CmpOp::EQ,
+    }
+}
+
+fn find_attr_table(attr : &String, table_list : &Vec<TableRef>) -> String {
+    // the attribute is in only one table of each side of USING joins, which is checked by sem_check
+    table_list.iter().map(|table| table.borrow()).find(|table| {
+        table.attr_list.iter().any(|a| a.name == *attr)
+    }).map(|table| table.name.clone()).unwrap()
+}
+
+// the key of a selected column, and the key of the column used when it's null
+type SelectColumn = ((String, String), Option<(String, String)>);
+
+struct UsingKey {
+    lhs_key : (String, String),
+    rhs_key : (String, String),
+    right : bool,
+}
+
+impl UsingKey {
+    fn gen_column(&self) -> SelectColumn {
+        // the merged column is the one of the side not padded by the join,
+        // the other one is used when it's null
+        if self.right {
+            (self.rhs_key.clone(), Some(self.lhs_key.clone()))
+        } else {
+            (self.lhs_key.clone(), Some(self.rhs_key.clone()))
+        }
+    }
+}
+
+fn collect_using_key(relation : &Relation, table_list : &Vec<TableRef>,
+        key_list : &mut Vec<UsingKey>) {
+    if let &Relation::Join{ref lhs, ref rhs, join_type, ref constraint} = relation {
+        collect_using_key(lhs, table_list, key_list);
+        collect_using_key(rhs, table_list, key_list);
+        if let &JoinConstraint::Using(ref attr_list) = constraint {
+            let gen_side_table_list = |side : &Relation| {
+                let mut name_list = Vec::new();
+                collect_relation_table(side, &mut name_list);
+                table_list.iter().filter(|t| name_list.contains(&t.borrow().name)).cloned().collect()
+            };
+            let lhs_table_list = gen_side_table_list(lhs);
+            let rhs_table_list = gen_side_table_list(rhs);
+            for attr in attr_list.iter() {
+                let lhs_key = (find_attr_table(attr, &lhs_table_list), attr.clone());
+                let rhs_key = (find_attr_table(attr, &rhs_table_list), attr.clone());
+                key_list.push(UsingKey{
+                    lhs_key : lhs_key,
+                    rhs_key : rhs_key,
+                    right : join_type == JoinType::Right,
+                });
             }
-            table_and_attr_list
         }
-        SelectExpr::AllAttribute => {
-            table.get_attr_name_list().iter().map(|a| (table.name.clone(), a.clone())).collect()
+    }
+}
+
+fn split_conjunction(cond : ConditionExpr, cond_list : &mut Vec<ConditionExpr>) {
+    match cond {
+        ConditionExpr::LogicExpr{lhs, rhs, op : LogicOp::And} => {
+            split_conjunction(*lhs, cond_list);
+            split_conjunction(*rhs, cond_list);
+        }
+        cond => cond_list.push(cond),
+    }
+}
+
+fn take_conjunction(cond_list : &mut Vec<ConditionExpr>, table_list : &Vec<TableRef>) -> Option<ConditionExpr> {
+    // remove the conjuncts only using the tables and combine them
+    let name_list : Vec<String> = table_list.iter().map(|t| t.borrow().name.clone()).collect();
+    let mut result = None;
+    for cond in replace(cond_list, Vec::new()) {
+        let mut attr_list = Vec::new();
+        collect_cond_attr(&cond, &mut attr_list);
+        let covered = attr_list.iter().all(|attr| name_list.contains(&get_attr_key(attr).0));
+        if !covered {
+            cond_list.push(cond);
+            continue;
+        }
+        result = Some(match result {
+            Some(lhs) => ConditionExpr::LogicExpr{ lhs : Box::new(lhs), rhs : Box::new(cond), op : LogicOp::And },
+            None => cond,
+        });
+    }
+    result
+}
+
+fn take_equi_key(cond_list : &mut Vec<ConditionExpr>, left_table_list : &Vec<TableRef>,
+        right_table_list : &Vec<TableRef>) -> (Vec<usize>, Vec<usize>) {
+    // remove the equalities between an attribute of the left tables and one of the right tables,
+    // and return the indexes of them in the left rows and the right rows
+    let left_index_map = gen_join_index_map(left_table_list);
+    let left_attr_list = gen_join_attr_list(left_table_list);
+    let right_index_map = gen_join_index_map(right_table_list);
+    let right_attr_list = gen_join_attr_list(right_table_list);
+    let mut left_key = Vec::new();
+    let mut right_key = Vec::new();
+    for cond in replace(cond_list, Vec::new()) {
+        let key_pair = match get_equi_attr(&cond) {
+            Some((lhs, rhs)) => {
+                let (lhs, rhs) = (get_attr_key(lhs), get_attr_key(rhs));
+                match (left_index_map.get(&lhs), right_index_map.get(&rhs),
+                        left_index_map.get(&rhs), right_index_map.get(&lhs)) {
+                    (Some(&l), Some(&r), _, _) | (_, _, Some(&l), Some(&r)) => Some((l, r)),
+                    _ => None,
+                }
+            }
+            None => None,
+        };
+        match key_pair {
+            Some((l, r)) if is_hash_key_type(left_attr_list[l].attr_type, right_attr_list[r].attr_type) => {
+                left_key.push(l);
+                right_key.push(r);
+            }
+            _ => cond_list.push(cond),
         }
+    }
+    (left_key, right_key)
+}
+
+fn get_equi_attr(cond : &ConditionExpr) -> Option<(&AttributeExpr, &AttributeExpr)> {
+    match cond {
+        &ConditionExpr::CmpExpr{
+            lhs : CmpOperantExpr::Arith(ArithExpr::Attr(ref lhs)),
+            rhs : CmpOperantExpr::Arith(ArithExpr::Attr(ref rhs)),
+            op : CmpOp::EQ,
+        } => Some((lhs, rhs)),
+        _ => None,
+    }
+}
+
+fn is_hash_key_type(lhs : AttrType, rhs : AttrType) -> bool {
+    // int and float are compared as float but hashed differently
+    match (lhs, rhs) {
+        (AttrType::Int, AttrType::Int) | (AttrType::Float, AttrType::Float) => true,
+        (AttrType::Int, _) | (AttrType::Float, _) | (_, AttrType::Int) | (_, AttrType::Float) => false,
+        _ => true,
+    }
+}
+
+pub fn is_aggre_select(stmt : &SelectStatement) -> bool {
+    stmt.groupby_having.is_some() || has_aggre_func(&stmt.select_expr)
+}
+
+// Layout of the aggregated rows, the group by attribute followed by the aggregate functions.
+#[derive(Debug)]
+pub struct AggreInfo {
+    pub group_index : Option<usize>,  // index in the rows of the table
+    pub aggre_list : Vec<AggreDesc>,
+    pub attr_list : Vec<(String, String)>,  // the keys of the attributes in the aggregated rows
+    pub attr_desc : Vec<AttrType>,
+    pub index_map : IndexMap,
+}
+
+impl AggreInfo {
+    fn gen_attr_list(&self) -> Vec<Attr> {
+        self.attr_list.iter().zip(self.attr_desc.iter()).map(|(&(_, ref attr), attr_type)| Attr{
+            name : attr.clone(),
+            attr_type : attr_type.clone(),
+            primary : false,
+            nullable : true,
+        }).collect()
+    }
+}
+
+pub fn gen_aggre_info(stmt : &SelectStatement, table_list : &Vec<TableRef>) -> AggreInfo {
+    let table_index_map = gen_join_index_map(table_list);
+    let table_attr_list = gen_join_attr_list(table_list);
+    let mut attr_list = Vec::new();
+    let mut attr_desc = Vec::new();
+    let group_index = stmt.groupby_having.as_ref().map(|g| {
+        let key = get_attr_key(&g.attr);
+        let i = *table_index_map.get(&key).unwrap();
+        attr_list.push(key);
+        attr_desc.push(table_attr_list[i].attr_type);
+        i
+    });
+    let mut aggre_expr_list = Vec::new();
+    if let SelectExpr::AttrList(ref l) = stmt.select_expr {
+        aggre_expr_list.extend(l.iter());
+    }
+    if let Some(ref cond) = stmt.groupby_having.as_ref().and_then(|g| g.having_condition.as_ref()) {
+        collect_cond_attr(cond, &mut aggre_expr_list);
+    }
+    if let Some(ref order_by) = stmt.order_by {
+        aggre_expr_list.extend(order_by.key_list.iter().map(|k| &k.attr));
+    }
+    let mut aggre_list = Vec::new();
+    for attr_expr in aggre_expr_list {
+        let (func, attr_table, attr) = match attr_expr {
+            &AttributeExpr::AggreFuncCall{ref func, ref table, ref attr} => (func, table, attr),
+            _ => continue,
+        };
+        let key = gen_aggre_key(func, attr_table, attr);
+        if attr_list.contains(&key) {
+            continue;
+        }
+        attr_list.push(key);
+        let index = attr_table.as_ref().map(|t| *table_index_map.get(&(t.clone(), attr.clone())).unwrap());
+        let func = AggreFunc::from_name(func).unwrap();
+        let attr_type = match (func, index) {
+            (AggreFunc::Count, _) => AttrType::Int,
+            (AggreFunc::Avg, _) => AttrType::Float,
+            (_, Some(i)) => table_attr_list[i].attr_type,
+            (_, None) => unreachable!(),  // only count(*)
+        };
+        aggre_list.push(AggreDesc{ func : func, index : index });
+        attr_desc.push(attr_type);
+    }
+    let index_map = attr_list.iter().enumerate().map(|(i, key)| (key.clone(), i)).collect();
+    AggreInfo{
+        group_index : group_index,
+        aggre_list : aggre_list,
+        attr_list : attr_list,
+        attr_desc : attr_desc,
+        index_map : index_map,
+    }
+}
+
+fn collect_cond_attr<'a>(cond : &'a ConditionExpr, attr_list : &mut Vec<&'a AttributeExpr>) {
+    match cond {
+        &ConditionExpr::LogicExpr{ref lhs, ref rhs, ..} => {
+            collect_cond_attr(lhs, attr_list);
+            collect_cond_attr(rhs, attr_list);
+        }
+        &ConditionExpr::NotExpr{ref operant} => collect_cond_attr(operant, attr_list),
+        &ConditionExpr::CmpExpr{ref lhs, ref rhs, ..} => {
+            for operant in [lhs, rhs].iter() {
+                if let &&CmpOperantExpr::Arith(ref arith) = operant {
+                    collect_arith_attr(arith, attr_list);
+                }
+            }
+        }
+    }
+}
+
+fn collect_arith_attr<'a>(arith : &'a ArithExpr, attr_list : &mut Vec<&'a AttributeExpr>) {
+    match arith {
+        &ArithExpr::BinaryExpr{ref lhs, ref rhs, ..} => {
+            collect_arith_attr(lhs, attr_list);
+            collect_arith_attr(rhs, attr_list);
+        }
+        &ArithExpr::MinusExpr{ref operant} => collect_arith_attr(operant, attr_list),
+        &ArithExpr::Attr(ref attr) => attr_list.push(attr),
+        &ArithExpr::Value(..) => (),
+    }
+}
+
+pub fn get_attr_key(attr_expr : &AttributeExpr) -> (String, String) {
+    match attr_expr {
+        &AttributeExpr::TableAttr{ref table, ref attr} => (table.clone().unwrap(), attr.clone()),
+        &AttributeExpr::AggreFuncCall{ref func, ref table, ref attr} => gen_aggre_key(func, table, attr),
+    }
+}
+
+pub fn gen_sort_key_list(order_by : &OrderBy, index_map : &IndexMap) -> SortKeyList {
+    order_by.key_list.iter().map(|key| {
+        (*index_map.get(&get_attr_key(&key.attr)).unwrap(), key.desc)
+    }).collect()
+}
+
+pub fn gen_select_proj_info(
+        stmt : &SelectStatement, table_list : &Vec<TableRef>) -> (Vec<usize>, Vec<(String, String)>) {
+    // the indexes are in the joined rows, or in the aggregated rows for aggregation
+    let mut proj_attr_index = Vec::new();
+    let mut proj_attr_list = Vec::new();
+    let index_map = if is_aggre_select(stmt) {
+        gen_aggre_info(stmt, table_list).index_map
+    } else {
+        gen_join_index_map(table_list)
     };
-    let index_map = table.gen_index_map();
-    for table_and_attr in table_and_attr_list.drain(..) {
+    for (table_and_attr, _) in gen_select_column_list(stmt, table_list) {
         proj_attr_index.push(index_map.get(&table_and_attr).unwrap().clone());
         proj_attr_list.push(table_and_attr);
     }
     (proj_attr_index, proj_attr_list)
 }
 
+pub fn gen_select_merge_list(stmt : &SelectStatement, table_list : &Vec<TableRef>) -> Vec<(usize, usize)> {
+    // the selected columns merged by USING joins, and the indexes in the joined rows of the other columns,
+    // which are used when the merged columns are null
+    let index_map = gen_join_index_map(table_list);
+    gen_select_column_list(stmt, table_list).into_iter().enumerate().filter_map(|(i, (_, other))| {
+        other.map(|key| (i, *index_map.get(&key).unwrap()))
+    }).collect()
+}
+
+fn gen_select_column_list(stmt : &SelectStatement, table_list : &Vec<TableRef>)
+        -> Vec<SelectColumn> {
+    // USING joins output a single column for each attribute,
+    // an unqualified attribute left by sem_check is merged by a full join
+    let mut using_key_list = Vec::new();
+    for relation in stmt.relation_list.iter() {
+        collect_using_key(relation, table_list, &mut using_key_list);
+    }
+    match stmt.select_expr {
+        SelectExpr::AttrList(ref l) => l.iter().map(|attr_expr| match attr_expr {
+            &AttributeExpr::TableAttr{table : None, ref attr} =>
+                using_key_list.iter().find(|k| k.lhs_key.1 == *attr).unwrap().gen_column(),
+            attr_expr => (get_attr_key(attr_expr), None),
+        }).collect(),
+        SelectExpr::AllAttribute => table_list.iter().flat_map(|table| {
+            let table = table.borrow();
+            table.get_attr_name_list().into_iter().map(|a| (table.name.clone(), a)).collect::<Vec<_>>()
+        }).filter_map(|key| {
+            // the merged column takes the place of the column of the left side
+            if let Some(using_key) = using_key_list.iter().find(|k| k.lhs_key == key) {
+                Some(using_key.gen_column())
+            } else if using_key_list.iter().any(|k| k.rhs_key == key) {
+                None
+            } else {
+                Some((key, None))
+            }
+        }).collect(),
+    }
+}
+
+pub fn gen_select_attr_desc(stmt : &SelectStatement, table_list : &Vec<TableRef>) -> Vec<AttrType> {
+    // types of the attributes returned by the select plan
+    let attr_desc = if is_aggre_select(stmt) {
+        gen_aggre_info(stmt, table_list).attr_desc
+    } else {
+        gen_join_attr_list(table_list).iter().map(|a| a.attr_type).collect()
+    };
+    let (attr_index, _) = gen_select_proj_info(stmt, table_list);
+    projection(&attr_index, attr_desc)
+}
+
 pub fn gen_proj_info(
         stmt : &Statement, table_manager : &TableManagerRef) -> (Vec<usize>, Vec<(String, String)>) {
     let mut proj_attr_index = Vec::new();
     let mut proj_attr_list = Vec::new();
-    let table = get_stmt_table(stmt, table_manager);
     if let &Statement::Select(ref select) = stmt {
-        return gen_select_proj_info(select, &table);
+        return gen_select_proj_info(select, &get_select_table_list(select, table_manager));
     } else {
+        let table = get_stmt_table(stmt, table_manager);
         let table = table.borrow();
         let table_name = table.name.clone();
         for (i, attr) in table.attr_list.iter().enumerate() {
@@ -106,21 +607,19 @@ pub fn gen_proj_info(
 
 pub fn get_stmt_table(stmt : &Statement, table_manager : &TableManagerRef) -> TableRef {
     match stmt {
-        &Statement::Create(..) | &Statement::Drop(..) => panic!("invalid state"),
+        &Statement::Create(..) | &Statement::Drop(..)
+            | &Statement::CreateIndex(..) | &Statement::DropIndex(..)
+            | &Statement::Vacuum(..) | &Statement::Alter(..) => panic!("invalid state"),
+        &Statement::Select(..) => panic!("select may have multiple tables"),
         &Statement::Insert(ref insert) => table_manager.borrow().get_table(&insert.table).unwrap(),
         &Statement::Update(ref update) => table_manager.borrow().get_table(&update.table).unwrap(),
         &Statement::Delete(ref delete) => table_manager.borrow().get_table(&delete.table).unwrap(),
-        &Statement::Select(ref select) => {
-            let table_name = extract!(
-                select.relation_list[0], Relation::TableName(ref name), name);
-            table_manager.borrow().get_table(&table_name).unwrap()
-        }
     }
 }
 
 pub fn gen_delete_plan(stmt : DeleteStatement, table_manager : &TableManagerRef) -> ExecIterRef {
     let table = table_manager.borrow().get_table(&stmt.table).unwrap();
-    let mut data_source = FileScan::new(&stmt.table, table_manager);
+    let mut data_source = gen_scan_plan(&table, &stmt.where_condition, table_manager);
     if let Some(cond) = stmt.where_condition {
         data_source = Filter::new(Box::new(cond),
             table.borrow().gen_index_map(),
@@ -132,14 +631,27 @@ pub fn gen_delete_plan(stmt : DeleteStatement, table_manager : &TableManagerRef)
 pub fn gen_insert_plan(stmt : InsertStatement, table_manager : &TableManagerRef) -> ExecIterRef {
     let table = table_manager.borrow().get_table(&stmt.table).unwrap();
     let pk_index = table.borrow().get_primary_key_index();
-    let pk = stmt.value_list[pk_index].value.parse::<i32>().unwrap();
+    let pk_type = table.borrow().get_primary_key_attr().attr_type;
+    let pk = value_expr_to_key_value(&stmt.value_list[pk_index], &pk_type).unwrap();
     let check = gen_check_primary_key_exist_plan(pk, &stmt.table, table_manager);
-    CheckAndInsert::new(check, Insert::new(stmt, table_manager))
+    let mut unique_check_list = Vec::new();
+    for index in table.borrow().index_list.iter().filter(|i| i.unique) {
+        let key : Option<Vec<TupleValue>> = index.attr_list.iter().map(|a| {
+            let i = table.borrow().get_attr_index(a).unwrap();
+            value_expr_to_key_value(&stmt.value_list[i], &table.borrow().attr_list[i].attr_type)
+        }).collect();
+        // key containing null never conflicts
+        if let Some(key) = key {
+            let check = IndexScan::new(&stmt.table, &index.name, KeyRange::equal(key), table_manager);
+            unique_check_list.push((index.name.clone(), check));
+        }
+    }
+    CheckAndInsert::new(check, unique_check_list, Insert::new(stmt, table_manager))
 }
 
 pub fn gen_update_plan(stmt : UpdateStatement, table_manager : &TableManagerRef) -> ExecIterRef {
     let table = table_manager.borrow().get_table(&stmt.table).unwrap();
-    let mut data_source = FileScan::new(&stmt.table, table_manager);
+    let mut data_source = gen_scan_plan(&table, &stmt.where_condition, table_manager);
     if let Some(cond) = stmt.where_condition {
         data_source = Filter::new(Box::new(cond),
             table.borrow().gen_index_map(),
@@ -154,8 +666,7 @@ pub fn gen_update_plan(stmt : UpdateStatement, table_manager : &TableManagerRef)
         let tuple_value = value_expr_to_tuple_value(value);
         set_values.insert(*index, tuple_value);
     }
-    let tuple_desc = table.borrow().gen_tuple_desc();
-    Update::new(&stmt.table, tuple_desc, set_values, data_source, table_manager)
+    Update::new(&stmt.table, set_values, data_source, table_manager)
 }
 
 pub fn value_expr_to_tuple_value(expr : &ValueExpr) -> TupleValue {
@@ -163,22 +674,148 @@ pub fn value_expr_to_tuple_value(expr : &ValueExpr) -> TupleValue {
         ValueType::Integer => TupleValue::Int(expr.value.parse::<i32>().unwrap()),
         ValueType::Float => TupleValue::Float(expr.value.parse::<f32>().unwrap()),
         ValueType::String => TupleValue::Char(expr.value.clone()),
-        ValueType::Null => unimplemented!(),
+        ValueType::Null => TupleValue::Null,
     }
 }
 
 pub fn gen_check_primary_key_exist_plan(
-        pk : i32,
+        pk : TupleValue,
         table_name : &String,
         table_manager : &TableManagerRef) -> ExecIterRef {
-    let table = table_manager.borrow().get_table(table_name).unwrap();
-    let pk_attr = table.borrow().get_primary_key_attr();
-    let cond = gen_check_primary_key_condition_expr(table_name, &pk_attr.name, pk);
-    let scan = FileScan::new(table_name, table_manager);
-    let filter = Filter::new(Box::new(cond),
-        table.borrow().gen_index_map(),
-        table.borrow().gen_tuple_desc(), scan);
-    filter
+    IndexScan::new(table_name, PRIMARY_INDEX, KeyRange::equal(vec![pk]), table_manager)
+}
+
+pub fn gen_scan_plan(table : &TableRef, cond : &Option<ConditionExpr>,
+        table_manager : &TableManagerRef) -> ExecIterRef {
+    // use the index matching the most attributes compared with literals in the condition,
+    // the condition should still be checked by Filter
+    let table_name = table.borrow().name.clone();
+    let cond = match cond {
+        &Some(ref cond) => cond,
+        &None => return FileScan::new(&table_name, table_manager),
+    };
+    let mut index_list = vec![
+        (PRIMARY_INDEX.to_string(), vec![table.borrow().get_primary_key_index()])];
+    for index in table.borrow().index_list.iter() {
+        let attr_index = index.attr_list.iter().map(
+            |a| table.borrow().get_attr_index(a).unwrap()).collect();
+        index_list.push((index.name.clone(), attr_index));
+    }
+    let mut pred_list = Vec::new();
+    collect_index_pred(cond, table, &mut pred_list);
+    let mut best : Option<(String, KeyRange)> = None;
+    for (name, attr_index) in index_list {
+        let range = match gen_key_range(&attr_index, &pred_list) {
+            Some(range) => range,
+            None => continue,
+        };
+        let better = match best {
+            Some((_, ref best_range)) => key_range_score(&range) > key_range_score(best_range),
+            None => true,
+        };
+        if better {
+            best = Some((name, range));
+        }
+    }
+    match best {
+        Some((name, range)) => IndexScan::new(&table_name, &name, range, table_manager),
+        None => FileScan::new(&table_name, table_manager),
+    }
+}
+
+fn key_range_score(range : &KeyRange) -> (usize, usize) {
+    (range.prefix.len(), range.lower.iter().count() + range.upper.iter().count())
+}
+
+fn gen_key_range(attr_index : &Vec<usize>, pred_list : &Vec<(usize, CmpOp, TupleValue)>)
+        -> Option<KeyRange> {
+    // match equality on the leading key attributes, then range on the next one
+    let mut range = KeyRange::equal(vec![]);
+    for i in attr_index.iter() {
+        let equal = pred_list.iter().filter(|&&(a, op, _)| a == *i && op == CmpOp::EQ).next();
+        if let Some(&(_, _, ref value)) = equal {
+            range.prefix.push(value.clone());
+            continue;
+        }
+        for &(_, op, ref value) in pred_list.iter().filter(|&&(a, _, _)| a == *i) {
+            match op {
+                CmpOp::GT => range.lower = Some((value.clone(), false)),
+                CmpOp::GE => range.lower = Some((value.clone(), true)),
+                CmpOp::LT => range.upper = Some((value.clone(), false)),
+                CmpOp::LE => range.upper = Some((value.clone(), true)),
+                _ => (),
+            }
+        }
+        break;
+    }
+    if range.prefix.is_empty() && range.lower.is_none() && range.upper.is_none() {
+        None
+    } else {
+        Some(range)
+    }
+}
+
+fn collect_index_pred(cond : &ConditionExpr, table : &TableRef,
+        pred_list : &mut Vec<(usize, CmpOp, TupleValue)>) {
+    // collect `attr op literal` in the conjunction of cond
+    match cond {
+        &ConditionExpr::LogicExpr{ref lhs, ref rhs, op : LogicOp::And} => {
+            collect_index_pred(lhs, table, pred_list);
+            collect_index_pred(rhs, table, pred_list);
+        }
+        &ConditionExpr::CmpExpr{ref lhs, ref rhs, op} => {
+            let flip_op = match op {
+                CmpOp::EQ => CmpOp::EQ,
+                CmpOp::LT => CmpOp::GT,
+                CmpOp::LE => CmpOp::GE,
+                CmpOp::GT => CmpOp::LT,
+                CmpOp::GE => CmpOp::LE,
+                _ => return,
+            };
+            let table = table.borrow();
+            for (i, attr) in table.attr_list.iter().enumerate() {
+                if is_attr_operant(lhs, &table.name, &attr.name) {
+                    if let Some(value) = operant_to_key_value(rhs, &attr.attr_type) {
+                        pred_list.push((i, op, value));
+                    }
+                } else if is_attr_operant(rhs, &table.name, &attr.name) {
+                    if let Some(value) = operant_to_key_value(lhs, &attr.attr_type) {
+                        pred_list.push((i, flip_op, value));
+                    }
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+fn is_attr_operant(operant : &CmpOperantExpr, table_name : &String, attr_name : &String) -> bool {
+    match operant {
+        &CmpOperantExpr::Arith(ArithExpr::Attr(AttributeExpr::TableAttr{ref table, ref attr})) =>
+            attr == attr_name && table.as_ref().map_or(true, |t| t == table_name),
+        _ => false,
+    }
+}
+
+fn operant_to_key_value(operant : &CmpOperantExpr, attr_type : &AttrType) -> Option<TupleValue> {
+    match operant {
+        &CmpOperantExpr::Arith(ArithExpr::Value(ref value)) => value_expr_to_key_value(value, attr_type),
+        &CmpOperantExpr::Value(ref value) => value_expr_to_key_value(value, attr_type),
+        _ => None,
+    }
+}
+
+fn value_expr_to_key_value(value : &ValueExpr, attr_type : &AttrType) -> Option<TupleValue> {
+    // None if the value can't be compared with the key
+    match (value.value_type, attr_type) {
+        (ValueType::Integer, &AttrType::Int) =>
+            Some(TupleValue::Int(value.value.parse::<i32>().unwrap())),
+        (ValueType::Integer, &AttrType::Float) | (ValueType::Float, &AttrType::Float) =>
+            Some(TupleValue::Float(value.value.parse::<f32>().unwrap())),
+        (ValueType::String, &AttrType::Char{..}) | (ValueType::String, &AttrType::Varchar{..}) =>
+            Some(TupleValue::Char(value.value.clone())),
+        _ => None,
+    }
 }
 
 pub fn gen_table_set(stmt : &Statement, table_manager : &TableManagerRef) -> TableSet {
@@ -206,7 +843,35 @@ pub fn gen_table_set(stmt : &Statement, table_manager : &TableManagerRef) -> Tab
                 table_list.push(drop.table.clone());
             }
         }
-
+        &Statement::CreateIndex(ref create) => {
+            if let Some(..) = table_manager.borrow().get_table(&create.table) {
+                table_list.push(create.table.clone());
+            }
+        }
+        &Statement::DropIndex(ref drop) => {
+            if let Some(..) = table_manager.borrow().get_table(&drop.table) {
+                table_list.push(drop.table.clone());
+            }
+        }
+        &Statement::Vacuum(ref vacuum) => {
+            if let Some(ref table) = vacuum.table {
+                if let Some(..) = table_manager.borrow().get_table(table) {
+                    table_list.push(table.clone());
+                }
+            }
+        }
+        &Statement::Alter(ref alter) => {
+            // the new name of a renamed table should not exist
+            let mut name_list = vec![alter.table.clone()];
+            if let AlterAction::RenameTable{ref to} = alter.action {
+                name_list.push(to.clone());
+            }
+            for name in name_list {
+                if let Some(..) = table_manager.borrow().get_table(&name) {
+                    table_list.push(name);
+                }
+            }
+        }
     }
     table_manager.borrow().gen_table_set(&table_list)
 }
@@ -214,11 +879,7 @@ pub fn gen_table_set(stmt : &Statement, table_manager : &TableManagerRef) -> Tab
 fn gen_select_table_set_helper(stmt : &SelectStatement) -> Vec<String> {
     let mut result = Vec::new();
     for rel in &stmt.relation_list {
-        match rel {
-            &Relation::TableName(ref name) => result.push(name.clone()),
-            &Relation::Select(ref sub_select) =>
-                result.extend_from_slice(&gen_select_table_set_helper(sub_select))
-        }
+        collect_relation_table(rel, &mut result);
     }
     result
 }
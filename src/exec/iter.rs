@@ -1,7 +1,8 @@
 use std::boxed::Box;
 use std::option::Option;
 use std::fmt::Debug;
-use ::store::tuple::TupleData;
+use ::store::tuple::Row;
+use ::store::file::RecordId;
 use super::error::ExecError;
 
 
@@ -9,9 +10,12 @@ use super::error::ExecError;
 pub trait ExecIter : Debug {
     fn open(&mut self);
     fn close(&mut self);
-    fn get_next(&mut self) -> Option<TupleData>;
+    fn get_next(&mut self) -> Option<Row>;
     fn explain(&self) -> String;
     fn get_error(&self) -> Option<ExecError>;
+    // address of the tuple returned by the last get_next,
+    // only for the scans and the iterators passing their tuples through
+    fn get_record_id(&self) -> Option<RecordId> { None }
 }
 
 pub type ExecIterRef = Box<ExecIter>;
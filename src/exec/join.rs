@@ -0,0 +1,700 @@
+use std::boxed::Box;
+use std::option::Option;
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::cmp::min;
+use ::store::table::{TableManagerRef, IndexMap};
+use ::store::tuple::{Row, TupleValue};
+use ::parser::condition::CondRef;
+use ::parser::select::JoinType;
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::ExecError;
+use super::evaluate::{eval_cond, gen_value_map};
+use super::spill::{SpillFile, get_row_size, encode_value};
+
+
+// Block nested loop join.
+// The outer rows are read in blocks of the work memory, and the inner rows are scanned once for each block.
+// The inner rows are kept in memory if they fit in the work memory, otherwise in a temporary file.
+// A joined row is the outer row followed by the inner row.
+// For outer joins, the unmatched outer rows of a block are padded after the block is joined with all the inner rows,
+// and the unmatched inner rows are padded after all the blocks, remembered by a flag for each inner row.
+#[derive(Debug)]
+pub struct NestedLoopJoin {
+    outer : ExecIterRef,
+    inner : ExecIterRef,
+    join_type : JoinType,
+    condition : Option<CondRef>,
+    index_map : IndexMap,  // of the joined rows
+    outer_width : usize,
+    table_manager : TableManagerRef,
+    memory_size : usize,
+    inner_loaded : bool,
+    inner_row_list : Vec<Row>,
+    inner_file : Option<SpillFile>,  // Some if the inner rows don't fit in memory
+    inner_pos : usize,  // number of the inner rows read since rewound
+    inner_matched : Vec<bool>,  // only for right and full join
+    curr_inner : Option<Row>,
+    block : Vec<Row>,
+    block_pos : usize,  // next outer row in block to join with curr_inner
+    block_matched : Vec<bool>,
+    unmatched_pos : usize,  // next outer row in block to pad
+    outer_done : bool,
+    finished : bool,
+    error : Option<ExecError>,
+}
+
+impl NestedLoopJoin {
+    pub fn new(outer : ExecIterRef, inner : ExecIterRef, join_type : JoinType, condition : Option<CondRef>,
+            index_map : IndexMap, outer_width : usize, table_manager : &TableManagerRef) -> ExecIterRef {
+        let memory_size = table_manager.borrow().get_work_memory_size();
+        Box::new(NestedLoopJoin{
+            outer : outer,
+            inner : inner,
+            join_type : join_type,
+            condition : condition,
+            index_map : index_map,
+            outer_width : outer_width,
+            table_manager : table_manager.clone(),
+            memory_size : memory_size,
+            inner_loaded : false,
+            inner_row_list : Vec::new(),
+            inner_file : None,
+            inner_pos : 0,
+            inner_matched : Vec::new(),
+            curr_inner : None,
+            block : Vec::new(),
+            block_pos : 0,
+            block_matched : Vec::new(),
+            unmatched_pos : 0,
+            outer_done : false,
+            finished : false,
+            error : None,
+        })
+    }
+    fn load_inner(&mut self) -> Result<(), ExecError> {
+        let mut size = 0;
+        while let Some(row) = self.inner.get_next() {
+            if let Some(ref mut file) = self.inner_file {
+                try!(file.write_row(&row));
+                continue;
+            }
+            size += get_row_size(&row);
+            self.inner_row_list.push(row);
+            if size > self.memory_size {
+                let mut file = try!(SpillFile::create(&self.table_manager, "join"));
+                for row in self.inner_row_list.drain(..) {
+                    try!(file.write_row(&row));
+                }
+                self.inner_file = Some(file);
+            }
+        }
+        if let Some(ref mut file) = self.inner_file {
+            try!(file.finish_write());
+        }
+        Ok(())
+    }
+    fn get_inner_num(&self) -> usize {
+        match self.inner_file {
+            Some(ref file) => file.get_row_num(),
+            None => self.inner_row_list.len(),
+        }
+    }
+    fn load_block(&mut self) {
+        self.block.clear();
+        let mut size = 0;
+        while size <= self.memory_size {
+            match self.outer.get_next() {
+                Some(row) => {
+                    size += get_row_size(&row);
+                    self.block.push(row);
+                }
+                None => break,
+            }
+        }
+        self.block_matched = vec![false; self.block.len()];
+        self.unmatched_pos = 0;
+        self.outer_done = self.block.is_empty();
+    }
+    fn next_inner(&mut self) -> Result<Option<Row>, ExecError> {
+        let row = match self.inner_file {
+            Some(ref mut file) => try!(file.read_row()),
+            None => self.inner_row_list.get(self.inner_pos).cloned(),
+        };
+        if row.is_some() {
+            self.inner_pos += 1;
+        }
+        Ok(row)
+    }
+    fn rewind_inner(&mut self) {
+        self.inner_pos = 0;
+        if let Some(ref mut file) = self.inner_file {
+            file.rewind();
+        }
+    }
+    fn next_unmatched_outer(&mut self) -> Option<Row> {
+        if !keep_left(self.join_type) {
+            return None;
+        }
+        while self.unmatched_pos < self.block.len() {
+            let i = self.unmatched_pos;
+            self.unmatched_pos += 1;
+            if !self.block_matched[i] {
+                return Some(pad_right(self.block[i].clone(), self.index_map.len() - self.outer_width));
+            }
+        }
+        None
+    }
+    fn next_unmatched_inner(&mut self) -> Result<Option<Row>, ExecError> {
+        if !keep_right(self.join_type) {
+            return Ok(None);
+        }
+        while let Some(row) = try!(self.next_inner()) {
+            if !self.inner_matched[self.inner_pos - 1] {
+                return Ok(Some(pad_left(self.outer_width, row)));
+            }
+        }
+        Ok(None)
+    }
+    fn fetch(&mut self) -> Result<Option<Row>, ExecError> {
+        if !self.inner_loaded {
+            self.inner_loaded = true;
+            try!(self.load_inner());
+            let inner_num = self.get_inner_num();
+            if (inner_num == 0 && !keep_left(self.join_type)) || self.inner.get_error().is_some() {
+                return Ok(None);
+            }
+            if keep_right(self.join_type) {
+                self.inner_matched = vec![false; inner_num];
+            }
+            self.load_block();
+        }
+        while !self.outer_done {
+            if let Some(ref inner_row) = self.curr_inner {
+                while self.block_pos < self.block.len() {
+                    let i = self.block_pos;
+                    self.block_pos += 1;
+                    let mut row = self.block[i].clone();
+                    row.extend(inner_row.iter().cloned());
+                    let matched = match self.condition {
+                        Some(ref cond) => eval_cond(cond, &gen_value_map(&self.index_map, &row)),
+                        None => true,
+                    };
+                    if matched {
+                        self.block_matched[i] = true;
+                        if let Some(flag) = self.inner_matched.get_mut(self.inner_pos - 1) {
+                            *flag = true;
+                        }
+                        return Ok(Some(row));
+                    }
+                }
+            }
+            self.block_pos = 0;
+            self.curr_inner = try!(self.next_inner());
+            if self.curr_inner.is_some() {
+                continue;
+            }
+            // all the inner rows are joined with the block
+            if let Some(row) = self.next_unmatched_outer() {
+                return Ok(Some(row));
+            }
+            self.load_block();
+            self.rewind_inner();
+        }
+        self.next_unmatched_inner()
+    }
+}
+
+impl ExecIter for NestedLoopJoin {
+    fn open(&mut self) {
+        self.outer.open();
+        self.inner.open();
+    }
+    fn close(&mut self) {
+        self.outer.close();
+        self.inner.close();
+        self.inner_row_list.clear();
+        self.inner_file = None;
+        self.inner_matched.clear();
+        self.block.clear();
+        self.finished = true;
+    }
+    fn explain(&self) -> String {
+        format!("nested loop {} on {:?} of {:?} and {:?}", self.join_type, self.condition, self.outer, self.inner)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        if self.finished {
+            return None;
+        }
+        match self.fetch() {
+            Ok(Some(row)) => Some(row),
+            Ok(None) => {
+                self.close();
+                None
+            }
+            Err(err) => {
+                self.error = Some(err);
+                self.close();
+                None
+            }
+        }
+    }
+    fn get_error(&self) -> Option<ExecError> {
+        match self.error {
+            Some(ref err) => Some(err.clone()),
+            None => self.outer.get_error().or(self.inner.get_error()),
+        }
+    }
+}
+
+
+pub const HASH_PARTITION_NUM : usize = 16;
+pub const MAX_PARTITION_DEPTH : usize = 3;
+
+// Hash join on the equality of the key attributes of the left and the right rows.
+// Both inputs are read by turns until one of them ends within the work memory,
+// then the hash table is built on that smaller input and probed with the other one.
+// Otherwise the rows of both inputs are partitioned to temporary files by the hash of the keys,
+// and each pair of partitions is joined in the same way, building on the smaller partition.
+// A pair whose smaller partition doesn't fit in the work memory is partitioned again with another hash seed,
+// up to MAX_PARTITION_DEPTH times since it doesn't help when many rows have the same key.
+// After that the hash table is built on each block of the build rows within the work memory
+// and probed with all the probe rows, which are padded after the last block for outer joins,
+// remembered by a flag for each probe row whether it matched the earlier blocks.
+// Rows with a null key never match. A joined row is the left row followed by the right row.
+// For outer joins, an unmatched probe row is padded as soon as it is probed,
+// and the unmatched build rows are padded after all the probe rows of the hash table.
+#[derive(Debug)]
+pub struct HashJoin {
+    left : ExecIterRef,
+    right : ExecIterRef,
+    join_type : JoinType,
+    left_key : Vec<usize>,
+    right_key : Vec<usize>,
+    condition : Option<CondRef>,  // checked on the rows with equal keys
+    index_map : IndexMap,  // of the joined rows
+    left_width : usize,
+    table_manager : TableManagerRef,
+    memory_size : usize,
+    loaded : bool,
+    partitioned : bool,
+    build_left : bool,  // whether the hash table is built on the left rows
+    hash_table : HashMap<Vec<u8>, Vec<(Row, bool)>>,  // the build rows and whether they are matched
+    null_key_list : Vec<Row>,  // the build rows with a null key, only kept for outer joins
+    unmatched_list : Vec<Row>,  // the build rows to be padded
+    build_drained : bool,  // whether the unmatched build rows are moved to unmatched_list
+    probe_buf : VecDeque<Row>,  // probe rows read before the hash table is built
+    probe_file : Option<SpillFile>,  // probe rows of the current partition
+    build_file : Option<SpillFile>,  // build rows of the current partition not loaded yet
+    block_matched : Vec<bool>,  // whether the probe rows matched the earlier blocks, only for outer joins
+    partition_list : Vec<(SpillFile, SpillFile, usize)>,  // the left and right partitions not joined yet and their depth
+    curr_probe : Option<Row>,
+    probe_pos : usize,  // number of the probe rows read since the current block is loaded
+    curr_key : Vec<u8>,  // empty if the key of curr_probe is null
+    probe_matched : bool,
+    bucket_pos : usize,  // next build row to join with curr_probe
+    finished : bool,
+    error : Option<ExecError>,
+}
+
+impl HashJoin {
+    pub fn new(left : ExecIterRef, right : ExecIterRef, join_type : JoinType,
+            left_key : Vec<usize>, right_key : Vec<usize>, condition : Option<CondRef>,
+            index_map : IndexMap, left_width : usize, table_manager : &TableManagerRef) -> ExecIterRef {
+        assert_eq!(left_key.len(), right_key.len());
+        let memory_size = table_manager.borrow().get_work_memory_size();
+        Box::new(HashJoin{
+            left : left,
+            right : right,
+            join_type : join_type,
+            left_key : left_key,
+            right_key : right_key,
+            condition : condition,
+            index_map : index_map,
+            left_width : left_width,
+            table_manager : table_manager.clone(),
+            memory_size : memory_size,
+            loaded : false,
+            partitioned : false,
+            build_left : false,
+            hash_table : HashMap::new(),
+            null_key_list : Vec::new(),
+            unmatched_list : Vec::new(),
+            build_drained : false,
+            probe_buf : VecDeque::new(),
+            probe_file : None,
+            build_file : None,
+            block_matched : Vec::new(),
+            partition_list : Vec::new(),
+            curr_probe : None,
+            probe_pos : 0,
+            curr_key : Vec::new(),
+            probe_matched : false,
+            bucket_pos : 0,
+            finished : false,
+            error : None,
+        })
+    }
+    fn load(&mut self) -> Result<(), ExecError> {
+        let mut left_row_list = Vec::new();
+        let mut right_row_list = Vec::new();
+        let mut size = 0;
+        let mut left_end = false;
+        let mut right_end = false;
+        while size <= self.memory_size {
+            match self.left.get_next() {
+                Some(row) => {
+                    size += get_row_size(&row);
+                    left_row_list.push(row);
+                }
+                None => {
+                    left_end = true;
+                    break;
+                }
+            }
+            match self.right.get_next() {
+                Some(row) => {
+                    size += get_row_size(&row);
+                    right_row_list.push(row);
+                }
+                None => {
+                    right_end = true;
+                    break;
+                }
+            }
+        }
+        if !left_end && !right_end {
+            return self.partition(left_row_list, right_row_list);
+        }
+        self.build_left = left_end;
+        let (build_row_list, probe_row_list) = if left_end {
+            (left_row_list, right_row_list)
+        } else {
+            (right_row_list, left_row_list)
+        };
+        for row in build_row_list.into_iter() {
+            self.insert_build_row(row);
+        }
+        self.probe_buf = probe_row_list.into_iter().collect();
+        Ok(())
+    }
+    fn partition(&mut self, left_row_list : Vec<Row>, right_row_list : Vec<Row>) -> Result<(), ExecError> {
+        self.partitioned = true;
+        let mut left_file_list = try!(self.create_partition_list());
+        let mut right_file_list = try!(self.create_partition_list());
+        let (keep_left_row, keep_right_row) = (keep_left(self.join_type), keep_right(self.join_type));
+        for row in left_row_list.into_iter() {
+            try!(write_partition(&mut left_file_list, &self.left_key, &row, keep_left_row, 0));
+        }
+        while let Some(row) = self.left.get_next() {
+            try!(write_partition(&mut left_file_list, &self.left_key, &row, keep_left_row, 0));
+        }
+        for row in right_row_list.into_iter() {
+            try!(write_partition(&mut right_file_list, &self.right_key, &row, keep_right_row, 0));
+        }
+        while let Some(row) = self.right.get_next() {
+            try!(write_partition(&mut right_file_list, &self.right_key, &row, keep_right_row, 0));
+        }
+        self.push_partition_list(left_file_list, right_file_list, 0)
+    }
+    fn repartition(&mut self, mut left_file : SpillFile, mut right_file : SpillFile, depth : usize)
+            -> Result<(), ExecError> {
+        // the depth is the hash seed, so the rows of a partition are spread to the new partitions
+        let mut left_file_list = try!(self.create_partition_list());
+        let mut right_file_list = try!(self.create_partition_list());
+        let (keep_left_row, keep_right_row) = (keep_left(self.join_type), keep_right(self.join_type));
+        while let Some(row) = try!(left_file.read_row()) {
+            try!(write_partition(&mut left_file_list, &self.left_key, &row, keep_left_row, depth));
+        }
+        while let Some(row) = try!(right_file.read_row()) {
+            try!(write_partition(&mut right_file_list, &self.right_key, &row, keep_right_row, depth));
+        }
+        self.push_partition_list(left_file_list, right_file_list, depth)
+    }
+    fn create_partition_list(&self) -> Result<Vec<SpillFile>, ExecError> {
+        let mut file_list = Vec::new();
+        for _ in 0..HASH_PARTITION_NUM {
+            file_list.push(try!(SpillFile::create(&self.table_manager, "hash")));
+        }
+        Ok(file_list)
+    }
+    fn push_partition_list(&mut self, mut left_file_list : Vec<SpillFile>, mut right_file_list : Vec<SpillFile>,
+            depth : usize) -> Result<(), ExecError> {
+        // the new partitions are joined next, from the first one
+        for file in left_file_list.iter_mut().chain(right_file_list.iter_mut()) {
+            try!(file.finish_write());
+        }
+        let start = self.partition_list.len();
+        self.partition_list.extend(left_file_list.into_iter().zip(right_file_list).map(|(l, r)| (l, r, depth)));
+        self.partition_list[start..].reverse();
+        Ok(())
+    }
+    fn load_partition(&mut self) -> Result<bool, ExecError> {
+        // false if all the partitions are joined
+        self.probe_file = None;
+        self.build_file = None;
+        self.block_matched.clear();
+        loop {
+            let (left_file, right_file, depth) = match self.partition_list.pop() {
+                Some(partition) => partition,
+                None => return Ok(false),
+            };
+            let build_left = left_file.get_data_size() <= right_file.get_data_size();
+            let fit = min(left_file.get_data_size(), right_file.get_data_size()) <= self.memory_size;
+            if !fit && depth < MAX_PARTITION_DEPTH {
+                try!(self.repartition(left_file, right_file, depth + 1));
+                continue;
+            }
+            self.build_left = build_left;
+            let (build_file, probe_file) = if build_left {
+                (left_file, right_file)
+            } else {
+                (right_file, left_file)
+            };
+            if !fit && self.keep_probe() {
+                self.block_matched = vec![false; probe_file.get_row_num()];
+            }
+            self.build_file = Some(build_file);
+            self.probe_file = Some(probe_file);
+            return self.load_block();
+        }
+    }
+    fn load_block(&mut self) -> Result<bool, ExecError> {
+        // load the next block of the build rows of the partition within the work memory,
+        // and probe with the probe rows from the first one, false if all the build rows are loaded
+        let mut build_file = match self.build_file.take() {
+            Some(file) => file,
+            None => return Ok(false),
+        };
+        self.hash_table.clear();
+        self.null_key_list.clear();
+        self.build_drained = false;
+        let mut size = 0;
+        while size <= self.memory_size {
+            match try!(build_file.read_row()) {
+                Some(row) => {
+                    size += get_row_size(&row);
+                    self.insert_build_row(row);
+                }
+                None => break,
+            }
+        }
+        if size > self.memory_size {
+            self.build_file = Some(build_file);
+        }
+        if let Some(ref mut file) = self.probe_file {
+            file.rewind();
+        }
+        self.probe_pos = 0;
+        Ok(true)
+    }
+    fn keep_build(&self) -> bool {
+        if self.build_left { keep_left(self.join_type) } else { keep_right(self.join_type) }
+    }
+    fn keep_probe(&self) -> bool {
+        if self.build_left { keep_right(self.join_type) } else { keep_left(self.join_type) }
+    }
+    fn insert_build_row(&mut self, row : Row) {
+        match gen_join_key(&row, if self.build_left { &self.left_key } else { &self.right_key }) {
+            Some(key) => self.hash_table.entry(key).or_insert_with(Vec::new).push((row, false)),
+            None => if self.keep_build() {
+                self.null_key_list.push(row);
+            },
+        }
+    }
+    fn drain_unmatched_build(&mut self) {
+        self.build_drained = true;
+        if !self.keep_build() {
+            return;
+        }
+        self.unmatched_list.extend(self.null_key_list.drain(..));
+        for (_, bucket) in self.hash_table.drain() {
+            self.unmatched_list.extend(bucket.into_iter().filter(|&(_, matched)| !matched).map(|(row, _)| row));
+        }
+    }
+    fn pad_build_row(&self, row : Row) -> Row {
+        if self.build_left {
+            pad_right(row, self.index_map.len() - self.left_width)
+        } else {
+            pad_left(self.left_width, row)
+        }
+    }
+    fn pad_probe_row(&self, row : Row) -> Row {
+        if self.build_left {
+            pad_left(self.left_width, row)
+        } else {
+            pad_right(row, self.index_map.len() - self.left_width)
+        }
+    }
+    fn next_probe_row(&mut self) -> Result<Option<Row>, ExecError> {
+        self.probe_pos += 1;
+        if let Some(row) = self.probe_buf.pop_front() {
+            return Ok(Some(row));
+        }
+        if let Some(ref mut file) = self.probe_file {
+            return file.read_row();
+        }
+        if self.partitioned {
+            return Ok(None);
+        }
+        Ok(if self.build_left { self.right.get_next() } else { self.left.get_next() })
+    }
+    fn fetch(&mut self) -> Result<Option<Row>, ExecError> {
+        if !self.loaded {
+            self.loaded = true;
+            try!(self.load());
+            if self.left.get_error().is_some() || self.right.get_error().is_some() {
+                return Ok(None);
+            }
+        }
+        loop {
+            if let Some(ref probe_row) = self.curr_probe {
+                if let Some(bucket) = self.hash_table.get_mut(&self.curr_key) {
+                    while self.bucket_pos < bucket.len() {
+                        let i = self.bucket_pos;
+                        self.bucket_pos += 1;
+                        let mut row = if self.build_left { bucket[i].0.clone() } else { probe_row.clone() };
+                        row.extend(if self.build_left { probe_row } else { &bucket[i].0 }.iter().cloned());
+                        let matched = match self.condition {
+                            Some(ref cond) => eval_cond(cond, &gen_value_map(&self.index_map, &row)),
+                            None => true,
+                        };
+                        if matched {
+                            bucket[i].1 = true;
+                            self.probe_matched = true;
+                            return Ok(Some(row));
+                        }
+                    }
+                }
+            }
+            if let Some(probe_row) = self.curr_probe.take() {
+                // the probe row is padded after it's probed with the last block
+                if self.keep_probe() {
+                    let i = self.probe_pos - 1;
+                    if self.build_file.is_some() {
+                        self.block_matched[i] = self.block_matched[i] || self.probe_matched;
+                    } else if !self.probe_matched && self.block_matched.get(i) != Some(&true) {
+                        return Ok(Some(self.pad_probe_row(probe_row)));
+                    }
+                }
+            }
+            self.bucket_pos = 0;
+            self.probe_matched = false;
+            match try!(self.next_probe_row()) {
+                Some(row) => {
+                    let key = gen_join_key(&row, if self.build_left { &self.right_key } else { &self.left_key });
+                    self.curr_key = key.unwrap_or(Vec::new());
+                    self.curr_probe = Some(row);
+                }
+                None => {
+                    if !self.build_drained {
+                        self.drain_unmatched_build();
+                    }
+                    if let Some(row) = self.unmatched_list.pop() {
+                        return Ok(Some(self.pad_build_row(row)));
+                    }
+                    if !try!(self.load_block()) && !try!(self.load_partition()) {
+                        return Ok(None);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl ExecIter for HashJoin {
+    fn open(&mut self) {
+        self.left.open();
+        self.right.open();
+    }
+    fn close(&mut self) {
+        self.left.close();
+        self.right.close();
+        self.hash_table.clear();
+        self.null_key_list.clear();
+        self.unmatched_list.clear();
+        self.probe_buf.clear();
+        self.probe_file = None;
+        self.build_file = None;
+        self.block_matched.clear();
+        self.partition_list.clear();
+        self.finished = true;
+    }
+    fn explain(&self) -> String {
+        format!("hash {} on {:?} = {:?} and {:?} of {:?} and {:?}",
+            self.join_type, self.left_key, self.right_key, self.condition, self.left, self.right)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        if self.finished {
+            return None;
+        }
+        match self.fetch() {
+            Ok(Some(row)) => Some(row),
+            Ok(None) => {
+                self.close();
+                None
+            }
+            Err(err) => {
+                self.error = Some(err);
+                self.close();
+                None
+            }
+        }
+    }
+    fn get_error(&self) -> Option<ExecError> {
+        match self.error {
+            Some(ref err) => Some(err.clone()),
+            None => self.left.get_error().or(self.right.get_error()),
+        }
+    }
+}
+
+fn keep_left(join_type : JoinType) -> bool {
+    // whether the unmatched left rows are padded
+    join_type == JoinType::Left || join_type == JoinType::Full
+}
+
+fn keep_right(join_type : JoinType) -> bool {
+    join_type == JoinType::Right || join_type == JoinType::Full
+}
+
+fn pad_right(mut row : Row, width : usize) -> Row {
+    row.extend((0..width).map(|_| TupleValue::Null));
+    row
+}
+
+fn pad_left(width : usize, row : Row) -> Row {
+    let mut padded : Row = (0..width).map(|_| TupleValue::Null).collect();
+    padded.extend(row);
+    padded
+}
+
+fn gen_join_key(row : &Row, key_index : &Vec<usize>) -> Option<Vec<u8>> {
+    // None if any key is null
+    let mut key = Vec::new();
+    for i in key_index.iter() {
+        match row[*i] {
+            TupleValue::Null => return None,
+            TupleValue::Float(f) if f == 0.0 => encode_value(&mut key, &TupleValue::Float(0.0)),  // -0.0 = 0.0
+            ref value => encode_value(&mut key, value),
+        }
+    }
+    Some(key)
+}
+
+fn write_partition(file_list : &mut Vec<SpillFile>, key_index : &Vec<usize>, row : &Row,
+        keep_null_key : bool, seed : usize) -> Result<(), ExecError> {
+    // the rows with a null key never match, they are only kept to be padded for outer joins
+    let i = match gen_join_key(row, key_index) {
+        Some(key) => {
+            let mut hasher = DefaultHasher::new();
+            hasher.write_usize(seed);
+            hasher.write(&key);
+            (hasher.finish() % file_list.len() as u64) as usize
+        }
+        None if keep_null_key => 0,
+        None => return Ok(()),
+    };
+    file_list[i].write_row(row)
+}
@@ -11,3 +11,15 @@ pub mod change;
 pub mod query;
 #[allow(dead_code)]
 pub mod evaluate;
+#[allow(dead_code)]
+pub mod vacuum;
+#[allow(dead_code)]
+pub mod alter;
+#[allow(dead_code)]
+pub mod spill;
+#[allow(dead_code)]
+pub mod sort;
+#[allow(dead_code)]
+pub mod aggregate;
+#[allow(dead_code)]
+pub mod join;
@@ -2,14 +2,14 @@ use std::boxed::Box;
 use std::option::Option;
 use std::collections::HashSet;
 use ::store::table::{TableManagerRef, IndexMap};
-use ::store::tuple::{TupleData, TupleDesc};
-use ::store::file::TableFileRef;
+use ::store::tuple::{Row, TupleDesc, TupleValue, gen_tuple_value};
+use ::store::file::{TableFileRef, RecordId};
+use ::store::btree::KeyRange;
 use ::store::buffer::PageKey;
 use ::parser::condition::CondRef;
 use super::iter::{ExecIter, ExecIterRef};
 use super::error::ExecError;
-use super::evaluate::PtrMap;
-use super::evaluate::eval_cond;
+use super::evaluate::{eval_cond, gen_value_map};
 
 
 #[derive(Debug)]
@@ -20,6 +20,7 @@ pub struct FileScan {
     pinned_pages : HashSet<PageKey>,
     file : TableFileRef,
     finished : bool,
+    error : Option<ExecError>,
 }
 
 impl FileScan {
@@ -32,6 +33,7 @@ impl FileScan {
             pinned_pages : HashSet::new(),
             file : file,
             finished : false,
+            error : None,
         })
     }
     fn find_page_helper(&mut self, page_index : &mut usize,
@@ -49,10 +51,16 @@ impl FileScan {
                     *page_index += 1;
                     *tuple_index = 0;
                     if *page_index < page_sum {
-                        self.table_manager.borrow_mut().file_manager.ensure_page_loaded(
+                        let result = self.table_manager.borrow_mut().file_manager.ensure_page_loaded(
                             &self.file, *page_index);
-                        self.table_manager.borrow_mut().file_manager.pin_page(fd, *page_index as u32);
+                        let result = result.and_then(|_| self.table_manager.borrow_mut().file_manager
+                            .pin_page(fd, *page_index as u32));
+                        if let Err(err) = result {
+                            self.error = Some(ExecError::from(err));
+                            return None;
+                        }
                         self.pinned_pages.insert(PageKey{ fd : fd, page_index : *page_index as u32 });
+                        self.table_manager.borrow_mut().file_manager.read_ahead(&self.file, *page_index + 1);
                     }
                 }
             }
@@ -70,10 +78,17 @@ impl ExecIter for FileScan {
             return;
         }
         let fd = self.file.borrow().get_fd();
+        let result = {
+            let file_manager = &mut self.table_manager.borrow_mut().file_manager;
+            file_manager.ensure_page_loaded(&self.file, 0).and_then(|_| file_manager.pin_page(fd, 0))
+        };
+        if let Err(err) = result {
+            self.error = Some(ExecError::from(err));
+            self.close();
+            return;
+        }
         self.pinned_pages.insert(PageKey{ fd : fd, page_index : 0 });
-        let mut table_manager = self.table_manager.borrow_mut();
-        table_manager.file_manager.ensure_page_loaded(&self.file, 0);
-        table_manager.file_manager.pin_page(fd, 0);
+        self.table_manager.borrow_mut().file_manager.read_ahead(&self.file, 1);
     }
     fn close(&mut self) {
         if self.finished {
@@ -89,7 +104,7 @@ impl ExecIter for FileScan {
         format!("file scan, page sum: {:?}",
             self.file.borrow().page_sum)
     }
-    fn get_next(&mut self) -> Option<TupleData> {
+    fn get_next(&mut self) -> Option<Row> {
         if self.finished {
             return None;
         }
@@ -104,25 +119,152 @@ impl ExecIter for FileScan {
         let mut tuple_index = self.curr_position - slot_sum * page_index;
         let index = self.find_page_helper(&mut page_index, &mut tuple_index);
         let result = match index {
-            Some(position) => Some((
-                self.table_manager.borrow_mut().file_manager.get_tuple_data(
-                    &self.table, position).unwrap(),
-                position
-            )),
+            Some(position) => {
+                let tuple_data = self.table_manager.borrow_mut().file_manager.get_tuple_data(
+                    &self.table, position);
+                match tuple_data {
+                    // copy the values out while the page is still pinned
+                    Ok(tuple_data) => Some((
+                        gen_tuple_value(&file.borrow().tuple_desc.attr_desc, tuple_data.unwrap()),
+                        position)),
+                    Err(err) => {
+                        self.error = Some(ExecError::from(err));
+                        None
+                    }
+                }
+            }
             None => None,
         };
         match result {
-            Some((tuple_data, new_position)) => {
+            Some((row, new_position)) => {
                 self.curr_position = new_position + 1;
-                Some(tuple_data)
+                Some(row)
             }
             None => {
+                if self.error.is_some() {
+                    self.close();
+                }
                 self.finished = true;
                 None
             }
         }
     }
-    fn get_error(&self) -> Option<ExecError> { None }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_record_id(&self) -> Option<RecordId> {
+        match self.curr_position {
+            0 => None,
+            p => Some(self.file.borrow().get_record_id(p - 1)),
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub struct IndexScan {
+    table : String,
+    index : String,
+    range : KeyRange,
+    table_manager : TableManagerRef,
+    position_list : Vec<usize>,
+    curr : usize,
+    pinned_page : Option<usize>,
+    file : TableFileRef,
+    finished : bool,
+    error : Option<ExecError>,
+}
+
+impl IndexScan {
+    pub fn new(table : &String, index : &str, range : KeyRange,
+            table_manager : &TableManagerRef) -> ExecIterRef {
+        let file = table_manager.borrow_mut().file_manager.get_file(&table);
+        Box::new(IndexScan{
+            table : table.clone(),
+            index : index.to_string(),
+            range : range,
+            table_manager : table_manager.clone(),
+            position_list : Vec::new(),
+            curr : 0,
+            pinned_page : None,
+            file : file,
+            finished : false,
+            error : None,
+        })
+    }
+    fn unpin_curr_page(&mut self) {
+        if let Some(page_index) = self.pinned_page.take() {
+            let fd = self.file.borrow().get_fd();
+            self.table_manager.borrow_mut().file_manager.unpin_page(fd, page_index as u32);
+        }
+    }
+}
+
+impl ExecIter for IndexScan {
+    fn open(&mut self) {
+        assert!(!self.finished);
+        let result = self.table_manager.borrow_mut().file_manager.index_scan(
+            &self.table, &self.index, &self.range);
+        match result {
+            Ok(position_list) => self.position_list = position_list,
+            Err(err) => {
+                self.error = Some(ExecError::from(err));
+                self.finished = true;
+            }
+        }
+    }
+    fn close(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        self.unpin_curr_page();
+    }
+    fn explain(&self) -> String {
+        format!("index scan on {}.{}, range: {:?}", self.table, self.index, self.range)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        if self.finished {
+            return None;
+        }
+        if self.curr >= self.position_list.len() {
+            self.close();
+            return None;
+        }
+        let position = self.position_list[self.curr];
+        self.curr += 1;
+        let page_index = position / self.file.borrow().get_page_slot_sum();
+        if self.pinned_page != Some(page_index) {
+            self.unpin_curr_page();
+            let fd = self.file.borrow().get_fd();
+            let result = {
+                let file_manager = &mut self.table_manager.borrow_mut().file_manager;
+                file_manager.ensure_page_loaded(&self.file, page_index)
+                    .and_then(|_| file_manager.pin_page(fd, page_index as u32))
+            };
+            if let Err(err) = result {
+                self.error = Some(ExecError::from(err));
+                self.close();
+                return None;
+            }
+            self.pinned_page = Some(page_index);
+        }
+        let result = self.table_manager.borrow_mut().file_manager.get_tuple_data(&self.table, position);
+        match result {
+            Ok(tuple_data) => tuple_data.map(|tuple_data|
+                gen_tuple_value(&self.file.borrow().tuple_desc.attr_desc, tuple_data)),
+            Err(err) => {
+                self.error = Some(ExecError::from(err));
+                self.close();
+                None
+            }
+        }
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+    fn get_record_id(&self) -> Option<RecordId> {
+        match self.curr {
+            0 => None,
+            i => Some(self.file.borrow().get_record_id(self.position_list[i - 1])),
+        }
+    }
 }
 
 
@@ -162,28 +304,23 @@ impl ExecIter for Filter {
     fn explain(&self) -> String {
         format!("filtered by condition: {:?} from source {:?}", self.condition, self.data_source)
     }
-    fn get_next(&mut self) -> Option<TupleData> {
+    fn get_next(&mut self) -> Option<Row> {
         if self.finished {
             return None;
         }
         assert_eq!(self.index_map.len(), self.tuple_desc.attr_desc.len());
-        while let Some(tuple_data) = self.data_source.get_next() {
-            assert_eq!(self.index_map.len(), tuple_data.len());
-            let mut ptr_map = PtrMap::new();
-            for (k, index) in &self.index_map {
-                ptr_map.insert(k.clone(), (
-                    tuple_data[*index],
-                    self.tuple_desc.attr_desc[*index].clone()
-                    ));
-            }
-            if eval_cond(&*self.condition, &ptr_map) {
-                return Some(tuple_data);
+        while let Some(row) = self.data_source.get_next() {
+            assert_eq!(self.index_map.len(), row.len());
+            let value_map = gen_value_map(&self.index_map, &row);
+            if eval_cond(&*self.condition, &value_map) {
+                return Some(row);
             }
         }
         self.close();
         None
     }
-    fn get_error(&self) -> Option<ExecError> { None }
+    fn get_error(&self) -> Option<ExecError> { self.data_source.get_error() }
+    fn get_record_id(&self) -> Option<RecordId> { self.data_source.get_record_id() }
 }
 
 
@@ -192,6 +329,7 @@ pub struct Projection {
     data_source : ExecIterRef,
     proj_attr_index : Vec<usize>,
     proj_attr_list : Vec<(String, String)>,
+    merge_list : Vec<(usize, usize)>,  // (projected index, index of the value used when it's null)
     finished : bool,
 }
 
@@ -200,10 +338,19 @@ impl Projection {
             attr_index : Vec<usize>,
             proj_attr_list : Vec<(String, String)>,
             inner_iter : ExecIterRef) -> ExecIterRef {
+        Projection::with_merge_list(attr_index, proj_attr_list, Vec::new(), inner_iter)
+    }
+    pub fn with_merge_list(
+            attr_index : Vec<usize>,
+            proj_attr_list : Vec<(String, String)>,
+            merge_list : Vec<(usize, usize)>,
+            inner_iter : ExecIterRef) -> ExecIterRef {
+        // merge_list is for the columns of USING joins
         Box::new(Projection{
             data_source : inner_iter,
             proj_attr_index : attr_index,
             proj_attr_list : proj_attr_list,
+            merge_list : merge_list,
             finished : false,
         })
     }
@@ -220,15 +367,20 @@ impl ExecIter for Projection {
     fn explain(&self) -> String {
         format!("Projection: {:?} from source {:?}", self.proj_attr_list, self.data_source)
     }
-    fn get_next(&mut self) -> Option<TupleData> {
+    fn get_next(&mut self) -> Option<Row> {
         if self.finished {
             return None;
         }
         match self.data_source.get_next() {
-            Some(tuple_data) => {
+            Some(row) => {
                 let mut result = Vec::new();
                 for i in &self.proj_attr_index {
-                    result.push(tuple_data[*i]);
+                    result.push(row[*i].clone());
+                }
+                for &(i, j) in &self.merge_list {
+                    if let TupleValue::Null = result[i] {
+                        result[i] = row[j].clone();
+                    }
                 }
                 Some(result)
             }
@@ -238,5 +390,6 @@ impl ExecIter for Projection {
             }
         }
     }
-    fn get_error(&self) -> Option<ExecError> { None }
+    fn get_error(&self) -> Option<ExecError> { self.data_source.get_error() }
+    fn get_record_id(&self) -> Option<RecordId> { self.data_source.get_record_id() }
 }
@@ -0,0 +1,205 @@
+use std::boxed::Box;
+use std::option::Option;
+use std::cmp::Ordering;
+use std::mem::replace;
+use ::store::table::TableManagerRef;
+use ::store::tuple::{Row, cmp_tuple_value};
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::ExecError;
+use super::spill::{SpillFile, get_row_size};
+
+
+// External merge sort.
+// The rows are sorted in memory until they take more than the work memory,
+// then every work memory of rows is sorted and spilled as a run,
+// and the runs are merged, at most MERGE_FAN_IN of them at a time.
+// Null is less than any other value, so it comes first in ascending order.
+const MERGE_FAN_IN : usize = 16;
+
+pub type SortKeyList = Vec<(usize, bool)>;  // index of the attribute and whether it's descending
+
+#[derive(Debug)]
+pub struct Sort {
+    data_source : ExecIterRef,
+    key_list : SortKeyList,
+    table_manager : TableManagerRef,
+    memory_size : usize,
+    sorted : bool,
+    row_list : Vec<Row>,  // in reverse order after sorted in memory, so pop returns the next row
+    run_list : Vec<SpillFile>,
+    merger : Option<RunMerger>,
+    finished : bool,
+    error : Option<ExecError>,
+}
+
+impl Sort {
+    pub fn new(key_list : SortKeyList, inner_iter : ExecIterRef, table_manager : &TableManagerRef)
+            -> ExecIterRef {
+        let memory_size = table_manager.borrow().get_work_memory_size();
+        Box::new(Sort{
+            data_source : inner_iter,
+            key_list : key_list,
+            table_manager : table_manager.clone(),
+            memory_size : memory_size,
+            sorted : false,
+            row_list : Vec::new(),
+            run_list : Vec::new(),
+            merger : None,
+            finished : false,
+            error : None,
+        })
+    }
+    fn sort(&mut self) -> Result<(), ExecError> {
+        let mut size = 0;
+        while let Some(row) = self.data_source.get_next() {
+            size += get_row_size(&row);
+            self.row_list.push(row);
+            if size > self.memory_size {
+                try!(self.spill_run());
+                size = 0;
+            }
+        }
+        if self.run_list.is_empty() {
+            let key_list = &self.key_list;
+            self.row_list.sort_by(|lhs, rhs| cmp_row(key_list, lhs, rhs));
+            self.row_list.reverse();
+            return Ok(());
+        }
+        if !self.row_list.is_empty() {
+            try!(self.spill_run());
+        }
+        while self.run_list.len() > MERGE_FAN_IN {
+            let mut merger = try!(RunMerger::new(self.run_list.drain(..MERGE_FAN_IN).collect()));
+            let mut run = try!(SpillFile::create(&self.table_manager, "sort"));
+            while let Some(row) = try!(merger.next(&self.key_list)) {
+                try!(run.write_row(&row));
+            }
+            try!(run.finish_write());
+            self.run_list.push(run);
+        }
+        let run_list = self.run_list.drain(..).collect();
+        self.merger = Some(try!(RunMerger::new(run_list)));
+        Ok(())
+    }
+    fn spill_run(&mut self) -> Result<(), ExecError> {
+        {
+            let key_list = &self.key_list;
+            self.row_list.sort_by(|lhs, rhs| cmp_row(key_list, lhs, rhs));
+        }
+        let mut run = try!(SpillFile::create(&self.table_manager, "sort"));
+        for row in self.row_list.drain(..) {
+            try!(run.write_row(&row));
+        }
+        try!(run.finish_write());
+        self.run_list.push(run);
+        Ok(())
+    }
+}
+
+impl ExecIter for Sort {
+    fn open(&mut self) {
+        self.data_source.open();
+    }
+    fn close(&mut self) {
+        // the runs are deleted when dropped
+        self.data_source.close();
+        self.row_list.clear();
+        self.run_list.clear();
+        self.merger = None;
+        self.finished = true;
+    }
+    fn explain(&self) -> String {
+        format!("sort by {:?} from source {:?}", self.key_list, self.data_source)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        if self.finished {
+            return None;
+        }
+        if !self.sorted {
+            self.sorted = true;
+            let result = self.sort();
+            if let Err(err) = result {
+                self.error = Some(err);
+            }
+            if self.error.is_some() || self.data_source.get_error().is_some() {
+                self.close();
+                return None;
+            }
+        }
+        let next = match self.merger {
+            Some(ref mut merger) => merger.next(&self.key_list),
+            None => Ok(self.row_list.pop()),
+        };
+        match next {
+            Ok(Some(row)) => Some(row),
+            Ok(None) => {
+                self.close();
+                None
+            }
+            Err(err) => {
+                self.error = Some(err);
+                self.close();
+                None
+            }
+        }
+    }
+    fn get_error(&self) -> Option<ExecError> {
+        match self.error {
+            Some(ref err) => Some(err.clone()),
+            None => self.data_source.get_error(),
+        }
+    }
+}
+
+
+#[derive(Debug)]
+struct RunMerger {
+    run_list : Vec<SpillFile>,
+    head_list : Vec<Option<Row>>,  // the next row of each run
+}
+
+impl RunMerger {
+    fn new(mut run_list : Vec<SpillFile>) -> Result<RunMerger, ExecError> {
+        let mut head_list = Vec::new();
+        for run in run_list.iter_mut() {
+            head_list.push(try!(run.read_row()));
+        }
+        Ok(RunMerger{
+            run_list : run_list,
+            head_list : head_list,
+        })
+    }
+    fn next(&mut self, key_list : &SortKeyList) -> Result<Option<Row>, ExecError> {
+        // the runs are few, so the smallest head is searched linearly
+        let mut min_index : Option<usize> = None;
+        for (i, head) in self.head_list.iter().enumerate() {
+            if let &Some(ref row) = head {
+                let is_less = match min_index {
+                    Some(j) => cmp_row(key_list, row, self.head_list[j].as_ref().unwrap()) == Ordering::Less,
+                    None => true,
+                };
+                if is_less {
+                    min_index = Some(i);
+                }
+            }
+        }
+        match min_index {
+            Some(i) => {
+                let next = try!(self.run_list[i].read_row());
+                Ok(replace(&mut self.head_list[i], next))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+pub fn cmp_row(key_list : &SortKeyList, lhs : &Row, rhs : &Row) -> Ordering {
+    for &(i, desc) in key_list.iter() {
+        match cmp_tuple_value(&lhs[i], &rhs[i]) {
+            Ordering::Equal => continue,
+            order if desc => return order.reverse(),
+            order => return order,
+        }
+    }
+    Ordering::Equal
+}
@@ -0,0 +1,261 @@
+use std::mem::size_of;
+use std::cmp::max;
+use std::io;
+use ::store::table::TableManagerRef;
+use ::store::tuple::{Row, TupleValue};
+use ::store::vfs::{VfsRef, VfsFileRef};
+use ::store::crypto::CipherRef;
+use super::error::{ExecError, ExecErrorType};
+
+
+// Temporary file of rows for the executors whose input doesn't fit in the work memory.
+// The rows are appended, then read back in the same order, and the file is deleted when dropped.
+// A row is the length of its values as u32 followed by the values, each is a tag byte
+// and the i32, the f32, or the u32 length and the bytes of a string, all little-endian.
+// With encryption the buffer is written as a sealed block, which is its length as u32
+// followed by the sealed rows, and the offset of the block is the associated data.
+const BUFFER_SIZE : usize = 64 * 1024;
+const TAG_NULL : u8 = 0;
+const TAG_INT : u8 = 1;
+const TAG_FLOAT : u8 = 2;
+const TAG_CHAR : u8 = 3;
+
+#[derive(Debug)]
+pub struct SpillFile {
+    path : String,
+    file : VfsFileRef,
+    vfs : VfsRef,
+    cipher : Option<CipherRef>,
+    buf : Vec<u8>,  // the rows not written yet, or read from the file but not returned
+    buf_start : usize,  // start of the rows not returned
+    offset : u64,  // where the next write or read starts
+    row_num : usize,
+    read_num : usize,
+    data_size : usize,  // the sum of get_row_size of the rows
+}
+
+impl SpillFile {
+    pub fn create(table_manager : &TableManagerRef, kind : &str) -> Result<SpillFile, ExecError> {
+        let mut manager = table_manager.borrow_mut();
+        let path = manager.file_manager.gen_temp_path(kind);
+        let vfs = manager.file_manager.vfs.clone();
+        let cipher = manager.file_manager.cipher.clone();
+        let mut file = try!(vfs.open(&path).map_err(|err| gen_io_error(&path, err)));
+        try!(file.truncate(0).map_err(|err| gen_io_error(&path, err)));  // may be left by a crash
+        Ok(SpillFile{
+            path : path,
+            file : file,
+            vfs : vfs,
+            cipher : cipher,
+            buf : Vec::new(),
+            buf_start : 0,
+            offset : 0,
+            row_num : 0,
+            read_num : 0,
+            data_size : 0,
+        })
+    }
+    pub fn get_row_num(&self) -> usize {
+        self.row_num
+    }
+    pub fn get_data_size(&self) -> usize {
+        // memory taken by the rows if they're all loaded
+        self.data_size
+    }
+    pub fn write_row(&mut self, row : &Row) -> Result<(), ExecError> {
+        let start = self.buf.len();
+        self.buf.extend_from_slice(&[0; 4]);
+        for value in row.iter() {
+            encode_value(&mut self.buf, value);
+        }
+        let len = (self.buf.len() - start - 4) as u32;
+        write_u32(&mut self.buf[start..], len);
+        self.row_num += 1;
+        self.data_size += get_row_size(row);
+        if self.buf.len() >= BUFFER_SIZE {
+            try!(self.flush());
+        }
+        Ok(())
+    }
+    pub fn finish_write(&mut self) -> Result<(), ExecError> {
+        // called after all the rows are written, the next read starts from the first row
+        try!(self.flush());
+        self.offset = 0;
+        Ok(())
+    }
+    pub fn rewind(&mut self) {
+        // read again from the first row, called after finish_write
+        self.buf.clear();
+        self.buf_start = 0;
+        self.offset = 0;
+        self.read_num = 0;
+    }
+    pub fn read_row(&mut self) -> Result<Option<Row>, ExecError> {
+        if self.read_num == self.row_num {
+            return Ok(None);
+        }
+        try!(self.fill(4));
+        let len = read_u32(&self.buf[self.buf_start..]) as usize;
+        try!(self.fill(4 + len));
+        let mut i = self.buf_start + 4;
+        let end = i + len;
+        let mut row = Vec::new();
+        while i < end {
+            match decode_value(&self.buf[..end], &mut i) {
+                Some(value) => row.push(value),
+                None => return Err(gen_io_error(&self.path, io::Error::new(io::ErrorKind::InvalidData,
+                    "invalid row"))),
+            }
+        }
+        self.buf_start = end;
+        self.read_num += 1;
+        Ok(Some(row))
+    }
+    fn fill(&mut self, len : usize) -> Result<(), ExecError> {
+        // read until len bytes not returned are in the buffer
+        if self.buf.len() - self.buf_start >= len {
+            return Ok(());
+        }
+        self.buf.drain(..self.buf_start);
+        self.buf_start = 0;
+        if self.cipher.is_some() {
+            while self.buf.len() < len {
+                try!(self.read_block());
+            }
+            return Ok(());
+        }
+        let old_len = self.buf.len();
+        self.buf.resize(max(len, BUFFER_SIZE), 0);
+        let n = try!(self.file.read_at(self.offset, &mut self.buf[old_len..])
+            .map_err(|err| gen_io_error(&self.path, err)));
+        self.buf.truncate(old_len + n);
+        self.offset += n as u64;
+        if self.buf.len() < len {
+            return Err(gen_io_error(&self.path, io::Error::new(io::ErrorKind::UnexpectedEof, "file too short")));
+        }
+        Ok(())
+    }
+    fn read_block(&mut self) -> Result<(), ExecError> {
+        // append the rows of the next sealed block to the buffer
+        let mut len_buf = [0; 4];
+        let n = try!(self.file.read_at(self.offset, &mut len_buf).map_err(|err| gen_io_error(&self.path, err)));
+        let mut block = vec![0; read_u32(&len_buf) as usize];
+        let m = try!(self.file.read_at(self.offset + 4, &mut block).map_err(|err| gen_io_error(&self.path, err)));
+        if n < len_buf.len() || m < block.len() {
+            return Err(gen_io_error(&self.path, io::Error::new(io::ErrorKind::UnexpectedEof, "file too short")));
+        }
+        let rows = try!(self.cipher.as_ref().unwrap().open(&gen_block_aad(self.offset), &block)
+            .map_err(|err| gen_io_error(&self.path, io::Error::new(io::ErrorKind::InvalidData, err))));
+        self.buf.extend_from_slice(&rows);
+        self.offset += (len_buf.len() + block.len()) as u64;
+        Ok(())
+    }
+    fn flush(&mut self) -> Result<(), ExecError> {
+        let result = match self.cipher {
+            Some(ref cipher) => {
+                let sealed = cipher.seal(&gen_block_aad(self.offset), &self.buf);
+                let mut block = vec![0; 4];
+                write_u32(&mut block, sealed.len() as u32);
+                block.extend_from_slice(&sealed);
+                self.file.write_at(self.offset, &block).map(|_| block.len())
+            }
+            None => self.file.write_at(self.offset, &self.buf).map(|_| self.buf.len()),
+        };
+        let len = try!(result.map_err(|err| gen_io_error(&self.path, err)));
+        self.offset += len as u64;
+        self.buf.clear();
+        Ok(())
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = self.vfs.delete(&self.path);
+    }
+}
+
+pub fn get_row_size(row : &Row) -> usize {
+    // memory taken by the row, counted against the work memory
+    let content_len : usize = row.iter().map(|v| match v {
+        &TupleValue::Char(ref s) => s.len(),
+        _ => 0,
+    }).sum();
+    size_of::<Row>() + row.len() * size_of::<TupleValue>() + content_len
+}
+
+fn gen_block_aad(offset : u64) -> Vec<u8> {
+    (0..8).map(|i| (offset >> (i * 8)) as u8).collect()
+}
+
+fn gen_io_error(path : &String, err : io::Error) -> ExecError {
+    ExecError{
+        error_type : ExecErrorType::IoError,
+        error_msg : format!("temporary file {}: {}", path, err),
+    }
+}
+
+pub fn encode_value(buf : &mut Vec<u8>, value : &TupleValue) {
+    match value {
+        &TupleValue::Null => buf.push(TAG_NULL),
+        &TupleValue::Int(n) => {
+            buf.push(TAG_INT);
+            push_u32(buf, n as u32);
+        }
+        &TupleValue::Float(f) => {
+            buf.push(TAG_FLOAT);
+            push_u32(buf, f.to_bits());
+        }
+        &TupleValue::Char(ref s) => {
+            buf.push(TAG_CHAR);
+            push_u32(buf, s.len() as u32);
+            buf.extend_from_slice(s.as_bytes());
+        }
+    }
+}
+
+fn decode_value(buf : &[u8], i : &mut usize) -> Option<TupleValue> {
+    let tag = buf[*i];
+    *i += 1;
+    if tag == TAG_NULL {
+        return Some(TupleValue::Null);
+    }
+    if *i + 4 > buf.len() {
+        return None;
+    }
+    let n = read_u32(&buf[*i..]);
+    *i += 4;
+    match tag {
+        TAG_INT => Some(TupleValue::Int(n as i32)),
+        TAG_FLOAT => Some(TupleValue::Float(f32::from_bits(n))),
+        TAG_CHAR => {
+            let end = *i + n as usize;
+            if end > buf.len() {
+                return None;
+            }
+            let s = String::from_utf8(buf[*i..end].to_vec()).ok();
+            *i = end;
+            s.map(TupleValue::Char)
+        }
+        _ => None,
+    }
+}
+
+fn push_u32(buf : &mut Vec<u8>, n : u32) {
+    for j in 0..4 {
+        buf.push((n >> (j * 8)) as u8);
+    }
+}
+
+fn write_u32(buf : &mut [u8], n : u32) {
+    for j in 0..4 {
+        buf[j] = (n >> (j * 8)) as u8;
+    }
+}
+
+fn read_u32(buf : &[u8]) -> u32 {
+    let mut n = 0;
+    for j in 0..4 {
+        n |= (buf[j] as u32) << (j * 8);
+    }
+    n
+}
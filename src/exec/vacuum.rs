@@ -0,0 +1,66 @@
+use std::boxed::Box;
+use std::option::Option;
+use ::store::table::TableManagerRef;
+use ::store::tuple::{Row, TupleValue};
+use ::parser::VacuumStatement;
+use super::iter::{ExecIter, ExecIterRef};
+use super::error::{ExecError, ExecErrorType};
+
+
+#[derive(Debug)]
+pub struct Vacuum {
+    stmt : VacuumStatement,
+    finished : bool,
+    error : Option<ExecError>,
+    table_manager : TableManagerRef,
+}
+
+impl Vacuum {
+    pub fn new(stmt : VacuumStatement, table_manager : &TableManagerRef) -> ExecIterRef {
+        Box::new(Vacuum{
+            finished : false,
+            error : None,
+            stmt : stmt,
+            table_manager : table_manager.clone(),
+        })
+    }
+}
+
+impl ExecIter for Vacuum {
+    fn open(&mut self) {}
+    fn close(&mut self) { self.finished = true; }
+    fn explain(&self) -> String {
+        format!("{}", self.stmt)
+    }
+    fn get_next(&mut self) -> Option<Row> {
+        // return the number of reclaimed pages
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        let mut reclaimed = 0;
+        let table_list = match self.stmt.table {
+            Some(ref table) => vec![table.clone()],
+            None => self.table_manager.borrow().get_table_names(),
+        };
+        for table in table_list.iter() {
+            let result = self.table_manager.borrow_mut().vacuum(table);
+            match result {
+                Ok(Some(n)) => reclaimed += n as i32,
+                Ok(None) => {
+                    self.error = Some(ExecError{
+                        error_type : ExecErrorType::PageInUse,
+                        error_msg : format!("pages of table {} are in use", table),
+                    });
+                    return None;
+                }
+                Err(err) => {
+                    self.error = Some(ExecError::from(err));
+                    return None;
+                }
+            }
+        }
+        Some(vec![TupleValue::Int(reclaimed)])
+    }
+    fn get_error(&self) -> Option<ExecError> { self.error.clone() }
+}
@@ -0,0 +1,109 @@
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::rc::Rc;
+use std::option::Option::{Some, None};
+use super::lexer::{TokenIter, TokenType};
+use super::compile_error::{CompileError, CompileErrorType, ErrorList};
+use super::create_drop::AttributeDeclaration;
+use super::common::{
+    ValueExpr,
+    consume_next_token_with_type,
+    consume_next_token_with_type_list,
+    check_parse_to_end,
+    get_next_token,
+};
+
+
+#[derive(Debug)]
+pub struct AlterStatement {
+    pub table : String,
+    pub action : AlterAction,
+}
+
+#[derive(Debug)]
+pub enum AlterAction {
+    AddColumn{ decl : AttributeDeclaration, default : Option<ValueExpr> },  // null if no default
+    DropColumn{ attr : String },
+    RenameColumn{ from : String, to : String },
+    RenameTable{ to : String },
+}
+
+impl Display for AlterStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        try!(write!(f, "alter table {} ", self.table));
+        match self.action {
+            AlterAction::AddColumn{ref decl, ref default} => {
+                try!(write!(f, "add column {}", decl));
+                match default {
+                    &Some(ref value) => write!(f, " default {}", value),
+                    &None => Ok(()),
+                }
+            }
+            AlterAction::DropColumn{ref attr} => write!(f, "drop column {}", attr),
+            AlterAction::RenameColumn{ref from, ref to} => write!(f, "rename column {} to {}", from, to),
+            AlterAction::RenameTable{ref to} => write!(f, "rename to {}", to),
+        }
+    }
+}
+
+impl AlterStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<AlterStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Alter));
+        try!(consume_next_token_with_type(it, TokenType::Table));
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        let token = try!(get_next_token(it));
+        let action = match token.token_type {
+            TokenType::Drop => try!(AlterStatement::parse_drop_column(it)),
+            TokenType::Rename => try!(AlterStatement::parse_rename(it)),
+            _ => try!(AlterStatement::parse_add_column(it)),
+        };
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(AlterStatement{
+                table : table_token.value.clone(),
+                action : action,
+            }),
+        }
+    }
+    fn parse_add_column(it : &mut TokenIter) -> Result<AlterAction, ErrorList> {
+        // "add" is lexed as an identifier since TokenType::Add is +
+        let add_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        if add_token.value.to_lowercase() != "add" {
+            return Err(vec![Rc::new(CompileError{
+                error_type : CompileErrorType::ParserUnExpectedTokenType,
+                token : add_token.clone(),
+                error_msg : format!("expect add, drop or rename, but got {}", add_token.value),
+            })]);
+        }
+        try!(consume_next_token_with_type(it, TokenType::Column));
+        let decl = try!(AttributeDeclaration::parse_decl(it));
+        let default = if is_match!(it.clone().next().map(|t| t.token_type), Some(TokenType::Default)) {
+            try!(consume_next_token_with_type(it, TokenType::Default));
+            Some(try!(ValueExpr::parse(it)))
+        } else {
+            None
+        };
+        Ok(AlterAction::AddColumn{ decl : decl, default : default })
+    }
+    fn parse_drop_column(it : &mut TokenIter) -> Result<AlterAction, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Drop));
+        try!(consume_next_token_with_type(it, TokenType::Column));
+        let attr_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        Ok(AlterAction::DropColumn{ attr : attr_token.value.clone() })
+    }
+    fn parse_rename(it : &mut TokenIter) -> Result<AlterAction, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Rename));
+        let token = try!(consume_next_token_with_type_list(it, &vec![TokenType::Column, TokenType::To]));
+        if token.token_type == TokenType::To {
+            let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+            return Ok(AlterAction::RenameTable{ to : table_token.value.clone() });
+        }
+        let from_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        try!(consume_next_token_with_type(it, TokenType::To));
+        let to_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        Ok(AlterAction::RenameColumn{
+            from : from_token.value.clone(),
+            to : to_token.value.clone(),
+        })
+    }
+}
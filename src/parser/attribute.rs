@@ -16,13 +16,13 @@ use super::common::{
 pub type ParseAttrResult = Result<AttributeExpr, ErrorList>;
 pub type AttributeList = Vec<AttributeExpr>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AttributeExpr {
     TableAttr { table : Option<String>, attr : String },
     AggreFuncCall {
         func : String,
         table : Option<String>,
-        attr : String,
+        attr : String,  // * for count(*)
     },
 }
 
@@ -74,6 +74,15 @@ impl AttributeExpr {
     pub fn parse_aggre_func(it : &mut TokenIter) -> ParseAttrResult {
         let func_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
         try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+        if is_match!(it.clone().next().map(|t| t.token_type), Some(TokenType::Star)) {
+            it.next();
+            try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+            return Ok(AttributeExpr::AggreFuncCall{
+                func : func_token.value.clone(),
+                table : None,
+                attr : "*".to_string(),
+            });
+        }
         let table_attr = try!(AttributeExpr::parse_table_attr(it));
         let (table_name, attr_name) = extract!(table_attr, AttributeExpr::TableAttr{ table, attr }, (table, attr));
         try!(consume_next_token_with_type(it, TokenType::CloseBracket));
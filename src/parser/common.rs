@@ -9,7 +9,9 @@ use super::select::SelectStatement;
 use super::update::UpdateStatement;
 use super::insert::InsertStatement;
 use super::delete::DeleteStatement;
-use super::create_drop::{CreateStatement, DropStatement};
+use super::create_drop::{CreateStatement, DropStatement, CreateIndexStatement, DropIndexStatement};
+use super::vacuum::VacuumStatement;
+use super::alter::AlterStatement;
 
 
 #[allow(dead_code)]  // lint bug
@@ -23,7 +25,7 @@ pub enum ValueType {
 
 pub type ValueList = Vec<ValueExpr>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ValueExpr {
     pub value : String,
     pub value_type : ValueType,
@@ -324,21 +326,41 @@ pub enum Statement {
     Delete(DeleteStatement),
     Create(CreateStatement),
     Drop(DropStatement),
+    CreateIndex(CreateIndexStatement),
+    DropIndex(DropIndexStatement),
+    Vacuum(VacuumStatement),
+    Alter(AlterStatement),
 }
 
 impl Statement {
     pub fn parse(it : &mut TokenIter) -> Result<Statement, ErrorList> {
         let mut tmp = it.clone();
         let type_list = vec![TokenType::Select, TokenType::Update,TokenType::Insert,
-            TokenType::Delete, TokenType::Create, TokenType::Drop];
+            TokenType::Delete, TokenType::Create, TokenType::Drop, TokenType::Vacuum,
+            TokenType::Alter];
         let token = try!(consume_next_token_with_type_list(&mut tmp, &type_list));
         Ok(match token.token_type {
             TokenType::Select => Statement::Select(try!(SelectStatement::parse(it))),
             TokenType::Update => Statement::Update(try!(UpdateStatement::parse(it))),
             TokenType::Insert => Statement::Insert(try!(InsertStatement::parse(it))),
             TokenType::Delete => Statement::Delete(try!(DeleteStatement::parse(it))),
-            TokenType::Create => Statement::Create(try!(CreateStatement::parse(it))),
-            TokenType::Drop => Statement::Drop(try!(DropStatement::parse(it))),
+            TokenType::Create => {
+                let index_type_list = vec![TokenType::Index, TokenType::Unique];
+                if consume_next_token_with_type_list(&mut tmp, &index_type_list).is_ok() {
+                    Statement::CreateIndex(try!(CreateIndexStatement::parse(it)))
+                } else {
+                    Statement::Create(try!(CreateStatement::parse(it)))
+                }
+            }
+            TokenType::Drop => {
+                if consume_next_token_with_type(&mut tmp, TokenType::Index).is_ok() {
+                    Statement::DropIndex(try!(DropIndexStatement::parse(it)))
+                } else {
+                    Statement::Drop(try!(DropStatement::parse(it)))
+                }
+            }
+            TokenType::Vacuum => Statement::Vacuum(try!(VacuumStatement::parse(it))),
+            TokenType::Alter => Statement::Alter(try!(AlterStatement::parse(it))),
             _ => panic!("invalid state"),
         })
     }
@@ -35,6 +35,12 @@ pub enum CompileErrorType {
     SemInvalidInsertCharLen,
     SemChangePrimaryAttr,
     SemSelectAllWithGroupBy,
+    SemIndexExist,
+    SemIndexNotExist,
+    SemInvalidIndexType,
+    SemInvalidTableOption,
+    SemInvalidAlter,
+    SemDuplicateTable,
 
     SemUnimplemented,
 }
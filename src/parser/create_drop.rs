@@ -1,6 +1,8 @@
 use std::fmt;
 use std::fmt::{Formatter, Display};
 use std::option::Option::{Some, None};
+use ::store::table::Attr;
+use ::store;
 use super::lexer::{TokenIter, TokenType};
 use super::compile_error::ErrorList;
 use super::common::{
@@ -17,11 +19,16 @@ use super::common::{
 pub struct CreateStatement {
     pub table : String,
     pub decl_list : AttrDeclList,
+    pub option_list : Vec<TableOption>,
 }
 
 impl Display for CreateStatement {
     fn fmt(&self, f : &mut Formatter) -> fmt::Result {
-        write!(f, "create table {} ({})", self.table, exp_list_to_string(&self.decl_list))
+        try!(write!(f, "create table {} ({})", self.table, exp_list_to_string(&self.decl_list)));
+        if !self.option_list.is_empty() {
+            try!(write!(f, " with ({})", exp_list_to_string(&self.option_list)));
+        }
+        Ok(())
     }
 }
 
@@ -33,14 +40,55 @@ impl CreateStatement {
         try!(consume_next_token_with_type(it, TokenType::OpenBracket));
         let decl_list = try!(AttributeDeclaration::parse_list(it));
         try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+        let option_list = if is_match!(it.clone().next().map(|t| t.token_type), Some(TokenType::With)) {
+            try!(TableOption::parse_list(it))
+        } else {
+            Vec::new()
+        };
         match check_parse_to_end(it) {
             Some(err) => Err(vec![err]),
             None => Ok(CreateStatement {
                 table : table_token.value.clone(),
                 decl_list : decl_list,
+                option_list : option_list,
             }),
         }
     }
+    pub fn get_option(&self, name : &str) -> Option<String> {
+        self.option_list.iter().filter(|o| o.name == name).map(|o| o.value.clone()).next()
+    }
+}
+
+#[derive(Debug)]
+pub struct TableOption {
+    pub name : String,
+    pub value : String,
+}
+
+impl Display for TableOption {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "{} = {}", self.name, self.value)
+    }
+}
+
+impl TableOption {
+    pub fn parse_list(it : &mut TokenIter) -> Result<Vec<TableOption>, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::With));
+        try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+        let option_list = try!(parse_list_helper(TableOption::parse, it));
+        try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+        Ok(option_list)
+    }
+    pub fn parse(it : &mut TokenIter) -> Result<TableOption, ErrorList> {
+        let name_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        try!(consume_next_token_with_type(it, TokenType::EQ));
+        let value_token = try!(consume_next_token_with_type_list(it,
+            &vec![TokenType::StringLiteral, TokenType::Identifier, TokenType::IntegerLiteral]));
+        Ok(TableOption{
+            name : name_token.value.to_lowercase(),
+            value : value_token.value.to_lowercase(),
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -48,6 +96,8 @@ pub enum AttrType {
     Int,
     Float,
     Char{ len : String },
+    Varchar{ len : String },
+    Text,
 }
 
 impl Display for AttrType {
@@ -56,13 +106,16 @@ impl Display for AttrType {
             &AttrType::Int => write!(f, "Int"),
             &AttrType::Float => write!(f, "Float"),
             &AttrType::Char{ ref len } => write!(f, "Char({})", len),
+            &AttrType::Varchar{ ref len } => write!(f, "Varchar({})", len),
+            &AttrType::Text => write!(f, "Text"),
         }
     }
 }
 
 impl AttrType {
     pub fn parse(it : &mut TokenIter) -> Result<AttrType, ErrorList> {
-        let data_type_tokens = vec![TokenType::Int, TokenType::Float, TokenType::Char];
+        let data_type_tokens = vec![
+            TokenType::Int, TokenType::Float, TokenType::Char, TokenType::Varchar, TokenType::Text];
         let token = try!(consume_next_token_with_type_list(it, &data_type_tokens));
         match token.token_type {
             TokenType::Int => Ok(AttrType::Int),
@@ -73,6 +126,13 @@ impl AttrType {
                 try!(consume_next_token_with_type(it, TokenType::CloseBracket));
                 Ok(AttrType::Char{ len : len_token.value.clone() })
             }
+            TokenType::Varchar => {
+                try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+                let len_token = try!(consume_next_token_with_type(it, TokenType::IntegerLiteral));
+                try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+                Ok(AttrType::Varchar{ len : len_token.value.clone() })
+            }
+            TokenType::Text => Ok(AttrType::Text),
             other => panic!("unexpected token: {:?}", other),
         }
     }
@@ -114,6 +174,21 @@ impl AttributeDeclaration {
             primary : primary,
         })
     }
+    pub fn gen_attr(&self) -> Attr {
+        // the len of char types has been checked by the lexer as an integer literal
+        Attr{
+            name : self.name.clone(),
+            attr_type : match self.attr_type {
+                AttrType::Int => store::table::AttrType::Int,
+                AttrType::Float => store::table::AttrType::Float,
+                AttrType::Char{ref len} => store::table::AttrType::Char{len : len.parse::<usize>().unwrap()},
+                AttrType::Varchar{ref len} => store::table::AttrType::Varchar{len : len.parse::<usize>().unwrap()},
+                AttrType::Text => store::table::AttrType::Text,
+            },
+            primary : self.primary,
+            nullable : self.nullable,
+        }
+    }
     fn parse_primary(it : &mut TokenIter) -> Result<bool, ErrorList> {
         try!(consume_next_token_with_type(it, TokenType::Primary));
         Ok(true)
@@ -159,3 +234,80 @@ impl DropStatement {
         }
     }
 }
+
+#[derive(Debug)]
+pub struct CreateIndexStatement {
+    pub index : String,
+    pub table : String,
+    pub attr_list : Vec<String>,
+    pub unique : bool,
+}
+
+impl Display for CreateIndexStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        let unique = if self.unique {"unique "} else {""};
+        write!(f, "create {}index {} on {} ({})",
+            unique, self.index, self.table, exp_list_to_string(&self.attr_list))
+    }
+}
+
+impl CreateIndexStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<CreateIndexStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Create));
+        let unique = is_match!(seq_parse_helper(
+            CreateIndexStatement::parse_unique, it), (Some(true), _));
+        try!(consume_next_token_with_type(it, TokenType::Index));
+        let index_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        try!(consume_next_token_with_type(it, TokenType::On));
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+        let attr_list = try!(parse_list_helper(CreateIndexStatement::parse_attr_name, it));
+        try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(CreateIndexStatement{
+                index : index_token.value.clone(),
+                table : table_token.value.clone(),
+                attr_list : attr_list,
+                unique : unique,
+            }),
+        }
+    }
+    fn parse_unique(it : &mut TokenIter) -> Result<bool, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Unique));
+        Ok(true)
+    }
+    fn parse_attr_name(it : &mut TokenIter) -> Result<String, ErrorList> {
+        let attr_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        Ok(attr_token.value.clone())
+    }
+}
+
+#[derive(Debug)]
+pub struct DropIndexStatement {
+    pub index : String,
+    pub table : String,
+}
+
+impl Display for DropIndexStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "drop index {} on {}", self.index, self.table)
+    }
+}
+
+impl DropIndexStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<DropIndexStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Drop));
+        try!(consume_next_token_with_type(it, TokenType::Index));
+        let index_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        try!(consume_next_token_with_type(it, TokenType::On));
+        let table_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(DropIndexStatement{
+                index : index_token.value.clone(),
+                table : table_token.value.clone(),
+            })
+        }
+    }
+}
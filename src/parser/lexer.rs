@@ -18,8 +18,17 @@ pub enum TokenType {
     Where,
     Order,
     By,
+    Asc,
+    Desc,
     Group,
     Having,
+    Join,
+    Inner,
+    Left,
+    Right,
+    Full,
+    Outer,
+    Using,
 
     Insert,
     Values,
@@ -30,10 +39,22 @@ pub enum TokenType {
     Create,
     Table,
     Drop,
+    Index,
+    Unique,
+    On,
+    Vacuum,
+    With,
+    Alter,
+    Column,
+    Rename,
+    To,
+    Default,
 
     Int,
     Float,
     Char,
+    Varchar,
+    Text,
     Primary,
 
     Null,         // null
@@ -102,6 +123,7 @@ impl TokenLine {
         let tail = code_string.len();
         let head_unused_tag = -1;
         let mut head = head_unused_tag;
+        let mut quote = '\"';  // strings can be quoted by either ' or "
 
         let add_token = |value : String,
                          token_type : TokenType,
@@ -188,9 +210,10 @@ impl TokenLine {
                         it.next();
                     } else if let Some(token_type) = convert_single_char_token(c) {
                         add_token(c.to_string(), token_type, head, i, &mut line);
-                    } else if c == '\"' {
+                    } else if c == '\"' || c == '\'' {
                          state = State::InString;
                          head = i;
+                         quote = c;
                     } else if let '0' ... '9' = c {
                         state = State::InInteger;
                         head = i;
@@ -219,14 +242,14 @@ impl TokenLine {
                         '\n' | '\0' => {
                             add_error(CompileErrorType::LexerInCompleteString,
                                 (&code_string[head as usize .. i as usize]).to_string(),
-                                "incomplete string, string must be closed with '\"'".to_string(),
+                                format!("incomplete string, string must be closed with {}", quote),
                                 head, i, &mut line);
                             head = head_unused_tag;
                             state = State::Begin;
                             it = tmp;
                         }
                         '\\' => state = State::InStringEscaping,
-                        '\"' => {
+                        c if c == quote => {
                             add_token((&code_string[(head+1) as usize .. i as usize]).to_string(),
                                 TokenType::StringLiteral,
                                 head, i, &mut line);
@@ -241,7 +264,7 @@ impl TokenLine {
                         '\n' | '\0' => {
                             add_error(CompileErrorType::LexerInCompleteString,
                                 (&code_string[head as usize .. i as usize]).to_string(),
-                                "incomplete string, string must be closed with '\"'".to_string(),
+                                format!("incomplete string, string must be closed with {}", quote),
                                 head, i, &mut line);
                             head = head_unused_tag;
                             state = State::Begin;
@@ -357,8 +380,17 @@ fn str_to_token_type(s : &str) -> Option<TokenType> {
         "where"  => Some(TokenType::Where),
         "order"  => Some(TokenType::Order),
         "by"     => Some(TokenType::By),
+        "asc"    => Some(TokenType::Asc),
+        "desc"   => Some(TokenType::Desc),
         "group"  => Some(TokenType::Group),
         "having" => Some(TokenType::Having),
+        "join"   => Some(TokenType::Join),
+        "inner"  => Some(TokenType::Inner),
+        "left"   => Some(TokenType::Left),
+        "right"  => Some(TokenType::Right),
+        "full"   => Some(TokenType::Full),
+        "outer"  => Some(TokenType::Outer),
+        "using"  => Some(TokenType::Using),
         "insert" => Some(TokenType::Insert),
         "values" => Some(TokenType::Values),
         "update" => Some(TokenType::Update),
@@ -367,6 +399,16 @@ fn str_to_token_type(s : &str) -> Option<TokenType> {
         "create" => Some(TokenType::Create),
         "table"  => Some(TokenType::Table),
         "drop"   => Some(TokenType::Drop),
+        "index"  => Some(TokenType::Index),
+        "unique" => Some(TokenType::Unique),
+        "on"     => Some(TokenType::On),
+        "vacuum" => Some(TokenType::Vacuum),
+        "with"   => Some(TokenType::With),
+        "alter"  => Some(TokenType::Alter),
+        "column" => Some(TokenType::Column),
+        "rename" => Some(TokenType::Rename),
+        "to"     => Some(TokenType::To),
+        "default"=> Some(TokenType::Default),
         "null"   => Some(TokenType::Null),
         "and"    => Some(TokenType::And),
         "or"     => Some(TokenType::Or),
@@ -375,6 +417,8 @@ fn str_to_token_type(s : &str) -> Option<TokenType> {
         "int"    => Some(TokenType::Int),
         "float"  => Some(TokenType::Float),
         "char"   => Some(TokenType::Char),
+        "varchar"=> Some(TokenType::Varchar),
+        "text"   => Some(TokenType::Text),
         "primary"=> Some(TokenType::Primary),
         _ => None,
     }
@@ -20,6 +20,10 @@ pub mod delete;
 #[allow(dead_code)]
 pub mod create_drop;
 #[allow(dead_code)]
+pub mod vacuum;
+#[allow(dead_code)]
+pub mod alter;
+#[allow(dead_code)]
 pub mod sem_check;
 #[allow(dead_code)]
 pub mod unimpl;
@@ -28,4 +32,6 @@ pub use self::select::SelectStatement;
 pub use self::update::UpdateStatement;
 pub use self::insert::InsertStatement;
 pub use self::delete::DeleteStatement;
-pub use self::create_drop::{CreateStatement, DropStatement};
+pub use self::create_drop::{CreateStatement, DropStatement, CreateIndexStatement, DropIndexStatement};
+pub use self::vacuum::VacuumStatement;
+pub use self::alter::{AlterStatement, AlterAction};
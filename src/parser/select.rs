@@ -9,6 +9,7 @@ use super::compile_error::ErrorList;
 use super::common::{
     get_next_token,
     consume_next_token_with_type,
+    consume_next_token_with_type_list,
     check_parse_to_end,
     seq_parse_helper,
     exp_list_to_string,
@@ -25,7 +26,7 @@ pub struct SelectStatement {
     pub relation_list : Vec<Relation>,
     pub where_condition : Option<ConditionExpr>,
     pub groupby_having : Option<GroupbyHaving>,
-    pub order_by_attr : Option<AttributeExpr>,
+    pub order_by : Option<OrderBy>,
 }
 
 impl Display for SelectStatement {
@@ -33,7 +34,7 @@ impl Display for SelectStatement {
         let mut s = format!("{} from {}", self.select_expr, exp_list_to_string(&self.relation_list));
         s = concat_format(s, "where ", &self.where_condition);
         s = concat_format(s, "", &self.groupby_having);
-        s = concat_format(s, "order by ", &self.order_by_attr);
+        s = concat_format(s, "", &self.order_by);
         write!(f, "{}", s)
     }
 }
@@ -45,7 +46,7 @@ impl SelectStatement {
         let relation_list = try!(Relation::parse(it));
         let (where_condition, es1) = seq_parse_helper(SelectStatement::parse_where, it);
         let (groupby_having, es2) = seq_parse_helper(GroupbyHaving::parse, it);
-        let (order_by_attr, es3) = seq_parse_helper(SelectStatement::parse_order_by, it);
+        let (order_by, es3) = seq_parse_helper(OrderBy::parse, it);
         match consume_next_token_with_type(it, TokenType::CloseBracket) {
             Err(errs) => Err(concat_error_list(vec![errs, es1, es2, es3])),
             Ok(..) => Ok(SelectStatement {
@@ -53,7 +54,7 @@ impl SelectStatement {
                     relation_list : relation_list,
                     where_condition : where_condition,
                     groupby_having : groupby_having,
-                    order_by_attr : order_by_attr,
+                    order_by : order_by,
                 })
         }
     }
@@ -62,7 +63,7 @@ impl SelectStatement {
         let relation_list = try!(Relation::parse(it));
         let (where_condition, es1) = seq_parse_helper(SelectStatement::parse_where, it);
         let (groupby_having, es2) = seq_parse_helper(GroupbyHaving::parse, it);
-        let (order_by_attr, es3) = seq_parse_helper(SelectStatement::parse_order_by, it);
+        let (order_by, es3) = seq_parse_helper(OrderBy::parse, it);
         match check_parse_to_end(it) {
             Some(err) => Err(concat_error_list(vec![vec![err], es1, es2, es3])),
             None => Ok(SelectStatement {
@@ -70,7 +71,7 @@ impl SelectStatement {
                 relation_list : relation_list,
                 where_condition : where_condition,
                 groupby_having : groupby_having,
-                order_by_attr : order_by_attr,
+                order_by : order_by,
             }),
         }
     }
@@ -78,11 +79,6 @@ impl SelectStatement {
         try!(consume_next_token_with_type(it, TokenType::Where));
         ConditionExpr::parse(it)
     }
-    pub fn parse_order_by(it : &mut TokenIter) -> Result<AttributeExpr, ErrorList> {
-        try!(consume_next_token_with_type(it, TokenType::Order));
-        try!(consume_next_token_with_type(it, TokenType::By));
-        AttributeExpr::parse(it)
-    }
 }
 
 #[derive(Debug)]
@@ -121,13 +117,23 @@ pub type RelationList = Vec<Relation>;
 pub enum Relation {
     TableName(String),
     Select(SelectStatement),
+    Join {
+        lhs : RelationRef,
+        rhs : RelationRef,
+        join_type : JoinType,
+        constraint : JoinConstraint,
+    },
 }
 
+pub type RelationRef = Box<Relation>;
+
 impl Display for Relation {
     fn fmt(&self, f : &mut Formatter) -> fmt::Result {
         match self {
             &Relation::TableName(ref name) => write!(f, "{}", name),
             &Relation::Select(ref select) => write!(f, "({})", select),
+            &Relation::Join{ref lhs, ref rhs, join_type, ref constraint} =>
+                write!(f, "{} {} {} {}", lhs, join_type, rhs, constraint),
         }
     }
 }
@@ -141,6 +147,26 @@ impl Relation {
         parse_list_helper(Relation::parse_relation, it)
     }
     pub fn parse_relation(it : &mut TokenIter) -> Result<Relation, ErrorList> {
+        // joins are left associative
+        let mut relation = try!(Relation::parse_single_relation(it));
+        loop {
+            match it.clone().next().map(|t| t.token_type) {
+                Some(TokenType::Join) | Some(TokenType::Inner) | Some(TokenType::Left)
+                    | Some(TokenType::Right) | Some(TokenType::Full) => (),
+                _ => return Ok(relation),
+            }
+            let join_type = try!(JoinType::parse(it));
+            let rhs = try!(Relation::parse_single_relation(it));
+            let constraint = try!(JoinConstraint::parse(it));
+            relation = Relation::Join{
+                lhs : Box::new(relation),
+                rhs : Box::new(rhs),
+                join_type : join_type,
+                constraint : constraint,
+            };
+        }
+    }
+    pub fn parse_single_relation(it : &mut TokenIter) -> Result<Relation, ErrorList> {
         let token = try!(get_next_token(it));
         match token.token_type {
             TokenType::OpenBracket =>
@@ -153,6 +179,80 @@ impl Relation {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinType {
+    Inner,
+    Left,  // the unmatched rows of the left relation are padded with null
+    Right,
+    Full,
+}
+
+impl Display for JoinType {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match self {
+            &JoinType::Inner => write!(f, "join"),
+            &JoinType::Left => write!(f, "left join"),
+            &JoinType::Right => write!(f, "right join"),
+            &JoinType::Full => write!(f, "full join"),
+        }
+    }
+}
+
+impl JoinType {
+    pub fn parse(it : &mut TokenIter) -> Result<JoinType, ErrorList> {
+        let token = try!(consume_next_token_with_type_list(it, &vec![TokenType::Join,
+            TokenType::Inner, TokenType::Left, TokenType::Right, TokenType::Full]));
+        let join_type = match token.token_type {
+            TokenType::Join => return Ok(JoinType::Inner),
+            TokenType::Inner => {
+                try!(consume_next_token_with_type(it, TokenType::Join));
+                return Ok(JoinType::Inner);
+            }
+            TokenType::Left => JoinType::Left,
+            TokenType::Right => JoinType::Right,
+            TokenType::Full => JoinType::Full,
+            other => panic!("unexpected token: {:?}", other),
+        };
+        if is_match!(it.clone().next().map(|t| t.token_type), Some(TokenType::Outer)) {
+            it.next();
+        }
+        try!(consume_next_token_with_type(it, TokenType::Join));
+        Ok(join_type)
+    }
+}
+
+#[derive(Debug)]
+pub enum JoinConstraint {
+    On(ConditionExpr),
+    Using(Vec<String>),  // equality of the attributes with the same names in both relations
+}
+
+impl Display for JoinConstraint {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match self {
+            &JoinConstraint::On(ref cond) => write!(f, "on {}", cond),
+            &JoinConstraint::Using(ref attr_list) => write!(f, "using ({})", attr_list.join(", ")),
+        }
+    }
+}
+
+impl JoinConstraint {
+    pub fn parse(it : &mut TokenIter) -> Result<JoinConstraint, ErrorList> {
+        let token = try!(consume_next_token_with_type_list(it, &vec![TokenType::On, TokenType::Using]));
+        if token.token_type == TokenType::On {
+            return Ok(JoinConstraint::On(try!(ConditionExpr::parse(it))));
+        }
+        try!(consume_next_token_with_type(it, TokenType::OpenBracket));
+        let attr_list = try!(parse_list_helper(JoinConstraint::parse_attr_name, it));
+        try!(consume_next_token_with_type(it, TokenType::CloseBracket));
+        Ok(JoinConstraint::Using(attr_list))
+    }
+    fn parse_attr_name(it : &mut TokenIter) -> Result<String, ErrorList> {
+        let attr_token = try!(consume_next_token_with_type(it, TokenType::Identifier));
+        Ok(attr_token.value.clone())
+    }
+}
+
 #[derive(Debug)]
 pub struct GroupbyHaving {
     pub attr : AttributeExpr,
@@ -188,3 +288,51 @@ impl GroupbyHaving {
         ConditionExpr::parse(it)
     }
 }
+
+#[derive(Debug)]
+pub struct OrderBy {
+    pub key_list : Vec<SortKey>,
+}
+
+impl Display for OrderBy {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "order by {}", exp_list_to_string(&self.key_list))
+    }
+}
+
+impl OrderBy {
+    pub fn parse(it : &mut TokenIter) -> Result<OrderBy, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Order));
+        try!(consume_next_token_with_type(it, TokenType::By));
+        let key_list = try!(parse_list_helper(SortKey::parse, it));
+        Ok(OrderBy{ key_list : key_list })
+    }
+}
+
+#[derive(Debug)]
+pub struct SortKey {
+    pub attr : AttributeExpr,
+    pub desc : bool,
+}
+
+impl Display for SortKey {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        if self.desc {
+            write!(f, "{} desc", self.attr)
+        } else {
+            write!(f, "{}", self.attr)
+        }
+    }
+}
+
+impl SortKey {
+    pub fn parse(it : &mut TokenIter) -> Result<SortKey, ErrorList> {
+        let attr = try!(AttributeExpr::parse(it));
+        let desc = match it.clone().next().map(|t| t.token_type) {
+            Some(TokenType::Asc) => { it.next(); false }
+            Some(TokenType::Desc) => { it.next(); true }
+            _ => false,  // ascending by default
+        };
+        Ok(SortKey{ attr : attr, desc : desc })
+    }
+}
@@ -4,16 +4,21 @@ use super::attribute::AttributeExpr;
 use super::lexer::{Token, TokenRef, TokenType};
 use super::compile_error::{CompileError, CompileErrorType, ErrorList, ErrorRef};
 use super::common::{Statement, ValueExpr, ValueType};
-use super::select::{SelectStatement, GroupbyHaving, SelectExpr, Relation};
+use super::select::{SelectStatement, GroupbyHaving, SelectExpr, Relation, JoinType, JoinConstraint};
 use super::update::UpdateStatement;
 use super::insert::InsertStatement;
 use super::delete::DeleteStatement;
-use super::create_drop::{CreateStatement, DropStatement};
+use super::create_drop::{CreateStatement, DropStatement, CreateIndexStatement, DropIndexStatement};
+use super::vacuum::VacuumStatement;
+use super::alter::{AlterStatement, AlterAction};
 use super::condition::{ConditionExpr, ArithExpr, CmpOperantExpr, CmpOp};
 use ::store::table::{TableSet, AttrType, Attr};
+use ::store::compress::is_valid_compression;
 
 
 pub type SemResult = Result<(), ErrorList>;
+// (attribute, table of the merged column, merged by a full join) for each attribute of USING joins
+type UsingAttrList = Vec<(String, String, bool)>;
 
 
 pub fn check_sem(statement : &mut Statement, table_set : &TableSet) -> SemResult {
@@ -24,14 +29,30 @@ pub fn check_sem(statement : &mut Statement, table_set : &TableSet) -> SemResult
         &mut Statement::Delete(ref mut stmt) => check_delete(stmt, table_set),
         &mut Statement::Create(ref stmt) => check_create(stmt, table_set),
         &mut Statement::Drop(ref stmt) => check_drop(stmt, table_set),
+        &mut Statement::CreateIndex(ref stmt) => check_create_index(stmt, table_set),
+        &mut Statement::DropIndex(ref stmt) => check_drop_index(stmt, table_set),
+        &mut Statement::Vacuum(ref stmt) => check_vacuum(stmt, table_set),
+        &mut Statement::Alter(ref stmt) => check_alter(stmt, table_set),
     }
 }
 
 pub fn check_select(stmt : &mut SelectStatement, table_set : &TableSet) -> SemResult {
-    // join not supported now
-    assert_eq!(stmt.relation_list.len(), 1);
-    let table_name = extract!(&stmt.relation_list[0], &Relation::TableName(ref name), name.clone());
-    try!(check_table_exist(&table_name, table_set));
+    // sub query not supported now
+    let mut name_list = Vec::new();
+    let mut padded_name_set = HashSet::new();
+    let mut using_list = Vec::new();
+    for relation in stmt.relation_list.iter_mut() {
+        try!(check_relation(relation, table_set, &mut name_list, &mut padded_name_set, &mut using_list));
+    }
+    // the attributes of the tables padded by outer joins can be null
+    let mut table_set = gen_sub_table_set(table_set, &name_list);
+    for name in padded_name_set.iter() {
+        for attr in table_set.tables.get_mut(name).unwrap().attr_list.iter_mut() {
+            attr.nullable = true;
+        }
+    }
+    let table_set = &table_set;
+    try!(resolve_using_attr(stmt, &using_list, table_set));
 
     if let Some(ref mut cond) = stmt.where_condition {
         try!(check_condition(cond, table_set, &None));
@@ -53,22 +74,237 @@ pub fn check_select(stmt : &mut SelectStatement, table_set : &TableSet) -> SemRe
                 }
             }
         }
-        if let Some(ref mut attr) = stmt.order_by_attr {
-            try!(check_attr(attr, table_set, &group_by_attr));
+        if let Some(ref mut order_by) = stmt.order_by {
+            for key in order_by.key_list.iter_mut() {
+                try!(check_attr(&mut key.attr, table_set, &group_by_attr));
+            }
+        }
+    } else if has_aggre_func(&stmt.select_expr) {
+        // all the rows are aggregated as a single group
+        let attr_list = extract!(&mut stmt.select_expr, &mut SelectExpr::AttrList(ref mut attr_list), attr_list);
+        for attr_expr in attr_list.iter_mut() {
+            try!(check_single_group_attr(attr_expr, table_set));
+        }
+        if let Some(ref mut order_by) = stmt.order_by {
+            for key in order_by.key_list.iter_mut() {
+                try!(check_single_group_attr(&mut key.attr, table_set));
+            }
         }
     } else {
         if let SelectExpr::AttrList(ref mut attr_list) = stmt.select_expr {
             for attr_expr in attr_list {
+                if is_full_merged_attr(attr_expr, &using_list, table_set) {
+                    continue;
+                }
                 try!(check_attr(attr_expr, table_set, &None));
             }
         }
-        if let Some(ref mut attr) = stmt.order_by_attr {
-            try!(check_attr(attr, table_set, &None));
+        if let Some(ref mut order_by) = stmt.order_by {
+            for key in order_by.key_list.iter_mut() {
+                try!(check_attr(&mut key.attr, table_set, &None));
+            }
         }
     }
     Ok(())
 }
 
+fn check_relation(relation : &mut Relation, table_set : &TableSet,
+        name_list : &mut Vec<String>, padded_name_set : &mut HashSet<String>,
+        using_list : &mut UsingAttrList) -> SemResult {
+    // name_list is the tables checked so far,
+    // a join condition can only use the tables of the relations it joins
+    match relation {
+        &mut Relation::TableName(ref name) => {
+            try!(check_table_exist(name, table_set));
+            if name_list.contains(name) {
+                return Err(create_error(CompileErrorType::SemDuplicateTable,
+                    format!("table {} appears more than once", name)));
+            }
+            name_list.push(name.clone());
+            Ok(())
+        }
+        &mut Relation::Select(..) => panic!("sub query not supported"),
+        &mut Relation::Join{ref mut lhs, ref mut rhs, join_type, ref mut constraint} => {
+            let start = name_list.len();
+            try!(check_relation(lhs, table_set, name_list, padded_name_set, using_list));
+            let mid = name_list.len();
+            try!(check_relation(rhs, table_set, name_list, padded_name_set, using_list));
+            match constraint {
+                &mut JoinConstraint::On(ref mut cond) =>
+                    try!(check_condition(cond, &gen_sub_table_set(table_set, &name_list[start..]), &None)),
+                &mut JoinConstraint::Using(ref attr_list) => {
+                    let lhs_set = gen_sub_table_set(table_set, &name_list[start..mid]);
+                    let rhs_set = gen_sub_table_set(table_set, &name_list[mid..]);
+                    let full = join_type == JoinType::Full;
+                    for attr in attr_list.iter() {
+                        try!(check_using_attr(attr, &lhs_set, &rhs_set, full));
+                        // the merged column is the one of the side not padded by the join
+                        let merged_set = if join_type == JoinType::Right { &rhs_set } else { &lhs_set };
+                        let mut table = None;
+                        merged_set.complete_table_name(&mut table, &mut attr.clone());
+                        using_list.push((attr.clone(), table.unwrap(), full));
+                    }
+                }
+            }
+            if join_type == JoinType::Right || join_type == JoinType::Full {
+                padded_name_set.extend(name_list[start..mid].iter().cloned());
+            }
+            if join_type == JoinType::Left || join_type == JoinType::Full {
+                padded_name_set.extend(name_list[mid..].iter().cloned());
+            }
+            Ok(())
+        }
+    }
+}
+
+fn check_using_attr(attr : &String, lhs_set : &TableSet, rhs_set : &TableSet, full : bool) -> SemResult {
+    // the attribute should be found in only one table of each side
+    match (lhs_set.get_attr(&None, attr), rhs_set.get_attr(&None, attr)) {
+        (Some(lhs), Some(rhs)) => {
+            let is_num = |attr_type : AttrType| match attr_type {
+                AttrType::Int | AttrType::Float => true,
+                _ => false,
+            };
+            if is_num(lhs.attr_type) != is_num(rhs.attr_type) {
+                return Err(create_error(CompileErrorType::SemInvalidValueType,
+                    format!("can't compare {:?} with {:?} in using ({})", lhs.attr_type, rhs.attr_type, attr)));
+            }
+            // either side may be the value of the merged column
+            if full && format!("{:?}", lhs.attr_type) != format!("{:?}", rhs.attr_type) {
+                return Err(create_error(CompileErrorType::SemInvalidValueType,
+                    format!("can't merge {:?} with {:?} in full join using ({})", lhs.attr_type, rhs.attr_type, attr)));
+            }
+            Ok(())
+        }
+        _ => Err(create_error(CompileErrorType::SemInvalidAttribute,
+            format!("{} not exist or multiple found in a side of the join", attr))),
+    }
+}
+
+fn resolve_using_attr(stmt : &mut SelectStatement, using_list : &UsingAttrList,
+        table_set : &TableSet) -> SemResult {
+    // an unqualified attribute of a USING join refers to the merged column,
+    // the column merged by a full join is only computed in the projection,
+    // so it can only be selected without aggregation
+    let merged_allowed = stmt.groupby_having.is_none() && !has_aggre_func(&stmt.select_expr);
+    if let SelectExpr::AttrList(ref mut attr_list) = stmt.select_expr {
+        for attr_expr in attr_list.iter_mut() {
+            try!(resolve_using_attr_expr(attr_expr, using_list, table_set, merged_allowed));
+        }
+    }
+    if let Some(ref mut cond) = stmt.where_condition {
+        try!(resolve_using_cond(cond, using_list, table_set));
+    }
+    if let Some(GroupbyHaving{ref mut attr, ref mut having_condition}) = stmt.groupby_having {
+        try!(resolve_using_attr_expr(attr, using_list, table_set, false));
+        if let &mut Some(ref mut cond) = having_condition {
+            try!(resolve_using_cond(cond, using_list, table_set));
+        }
+    }
+    if let Some(ref mut order_by) = stmt.order_by {
+        for key in order_by.key_list.iter_mut() {
+            try!(resolve_using_attr_expr(&mut key.attr, using_list, table_set, false));
+        }
+    }
+    Ok(())
+}
+
+fn resolve_using_cond(cond : &mut ConditionExpr, using_list : &UsingAttrList,
+        table_set : &TableSet) -> SemResult {
+    match cond {
+        &mut ConditionExpr::LogicExpr{ref mut lhs, ref mut rhs, ..} => {
+            try!(resolve_using_cond(lhs, using_list, table_set));
+            resolve_using_cond(rhs, using_list, table_set)
+        }
+        &mut ConditionExpr::NotExpr{ref mut operant} => resolve_using_cond(operant, using_list, table_set),
+        &mut ConditionExpr::CmpExpr{ref mut lhs, ref mut rhs, ..} => {
+            if let &mut CmpOperantExpr::Arith(ref mut arith) = lhs {
+                try!(resolve_using_arith(arith, using_list, table_set));
+            }
+            if let &mut CmpOperantExpr::Arith(ref mut arith) = rhs {
+                try!(resolve_using_arith(arith, using_list, table_set));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn resolve_using_arith(arith : &mut ArithExpr, using_list : &UsingAttrList,
+        table_set : &TableSet) -> SemResult {
+    match arith {
+        &mut ArithExpr::BinaryExpr{ref mut lhs, ref mut rhs, ..} => {
+            try!(resolve_using_arith(lhs, using_list, table_set));
+            resolve_using_arith(rhs, using_list, table_set)
+        }
+        &mut ArithExpr::MinusExpr{ref mut operant} => resolve_using_arith(operant, using_list, table_set),
+        &mut ArithExpr::Attr(ref mut attr_expr) => resolve_using_attr_expr(attr_expr, using_list, table_set, false),
+        &mut ArithExpr::Value(..) => Ok(()),
+    }
+}
+
+fn resolve_using_attr_expr(attr_expr : &mut AttributeExpr, using_list : &UsingAttrList,
+        table_set : &TableSet, merged_allowed : bool) -> SemResult {
+    let err_msg = format!("{} is merged by a full join, qualify it with a table", attr_expr);
+    let (table, attr) = attr_expr.get_attr();
+    if table.is_some() {
+        return Ok(());
+    }
+    match find_using_attr(attr, using_list, table_set) {
+        Some(&(_, ref merged_table, false)) => *table = Some(merged_table.clone()),
+        Some(&(_, _, true)) if !merged_allowed =>
+            return Err(create_error(CompileErrorType::SemInvalidAttribute, err_msg)),
+        _ => (),
+    }
+    Ok(())
+}
+
+fn is_full_merged_attr(attr_expr : &AttributeExpr, using_list : &UsingAttrList, table_set : &TableSet) -> bool {
+    match attr_expr {
+        &AttributeExpr::TableAttr{table : None, ref attr} =>
+            is_match!(find_using_attr(attr, using_list, table_set), Some(&(_, _, true))),
+        _ => false,
+    }
+}
+
+fn find_using_attr<'a>(attr : &str, using_list : &'a UsingAttrList, table_set : &TableSet)
+        -> Option<&'a (String, String, bool)> {
+    // the attribute should be in only one USING join and not in the other tables
+    let mut found = using_list.iter().filter(|u| u.0 == attr);
+    let table_num = table_set.tables.values().filter(|t| t.get_attr_index(attr).is_some()).count();
+    match (found.next(), found.next()) {
+        (Some(using_attr), None) if table_num == 2 => Some(using_attr),
+        _ => None,
+    }
+}
+
+fn gen_sub_table_set(table_set : &TableSet, name_list : &[String]) -> TableSet {
+    let mut sub_set = TableSet::new();
+    for name in name_list.iter() {
+        sub_set.add_table(table_set.tables.get(name).unwrap().clone());
+    }
+    sub_set
+}
+
+pub fn has_aggre_func(select_expr : &SelectExpr) -> bool {
+    match select_expr {
+        &SelectExpr::AllAttribute => false,
+        &SelectExpr::AttrList(ref attr_list) =>
+            attr_list.iter().any(|attr| is_match!(attr, &AttributeExpr::AggreFuncCall{..})),
+    }
+}
+
+fn check_single_group_attr(attr_expr : &mut AttributeExpr, table_set : &TableSet) -> SemResult {
+    let err_msg = format!("expected aggregate function without group by, got {}", attr_expr);
+    match attr_expr {
+        &mut AttributeExpr::AggreFuncCall{ref func, ref mut table, ref mut attr} =>
+            check_aggre_func(func, table, attr, table_set),
+        &mut AttributeExpr::TableAttr{ref mut table, ref mut attr} => {
+            try!(check_attr_exist(table, attr, table_set));
+            Err(create_error(CompileErrorType::SemShouldUseGroupByAttribute, err_msg))
+        }
+    }
+}
+
 pub fn check_update(stmt : &mut UpdateStatement, table_set : &TableSet) -> SemResult {
     try!(check_table_exist(&stmt.table, table_set));
     if let Some(ref mut cond) = stmt.where_condition {
@@ -106,12 +342,13 @@ pub fn check_assign(value : &ValueExpr, attr : &Attr) -> SemResult {
             (ValueType::Integer, AttrType::Int)
         | (ValueType::Integer, AttrType::Float)
         | (ValueType::Float, AttrType::Float) => (),
-        (ValueType::String, AttrType::Char{len}) => {
+        (ValueType::String, AttrType::Char{len}) | (ValueType::String, AttrType::Varchar{len}) => {
             if value.value.len() > len {
                 return Err(create_error(CompileErrorType::SemInvalidInsertCharLen,
                     format!("invalid char len, expected {}, found {}", len, value.value.len())));
             }
         }
+        (ValueType::String, AttrType::Text) => (),
         (ValueType::Null, _) => {
             if !attr.nullable {
                 return Err(create_error(CompileErrorType::SemAttributeNotNullable,
@@ -196,6 +433,13 @@ pub fn check_condition(
 }
 
 pub fn check_is_nullable(attr_expr : &mut AttributeExpr, table_set : &TableSet) -> SemResult {
+    if let &mut AttributeExpr::AggreFuncCall{ref func, ..} = attr_expr {
+        // null if all the values are null, except count
+        if func == "count" {
+            return Err(create_error(CompileErrorType::SemAttributeNotNullable,
+                format!("`{}` is not nullable", func)));
+        }
+    }
     let (table, attr) = attr_expr.get_attr();
     try!(check_attr_exist(table, attr, table_set));
     if !table_set.get_attr(table, attr).unwrap().nullable {
@@ -236,12 +480,18 @@ pub fn check_arith_expr(
 
 pub fn check_attr_num_type(attr_expr : &mut AttributeExpr, table_set : &TableSet) -> SemResult {
     let err_msg = format!("invalid attribute type: {}", attr_expr);
+    if let &mut AttributeExpr::AggreFuncCall{ref func, ..} = attr_expr {
+        if func == "count" || func == "avg" {
+            return Ok(());
+        }
+    }
     let (table, attr) = attr_expr.get_attr();
     let attr = table_set.get_attr(table, attr).unwrap();
-    if let AttrType::Char{..} = attr.attr_type {
-        return Err(create_error(CompileErrorType::SemInvalidValueType, err_msg));
+    match attr.attr_type {
+        AttrType::Char{..} | AttrType::Varchar{..} | AttrType::Text =>
+            Err(create_error(CompileErrorType::SemInvalidValueType, err_msg)),
+        _ => Ok(()),
     }
-    Ok(())
 }
 
 pub fn check_attr(
@@ -257,13 +507,13 @@ pub fn check_attr(
             (table, attr)
         }
         &mut AttributeExpr::AggreFuncCall{ref func, ref mut table, ref mut attr} => {
-            try!(check_aggre_func_name(func));
-            try!(check_attr_exist(table, attr, table_set));
+            try!(check_aggre_func(func, table, attr, table_set));
             if let &None = group_by_attr {
                 return Err(create_error(CompileErrorType::SemInvalidAggregateFunctionUse,
                     invalid_aggre_func_use_err_msg));
             }
-            (table, attr)
+            // any attribute can be aggregated
+            return Ok(());
         }
     };
     let group_by_attr = match group_by_attr {
@@ -290,8 +540,30 @@ pub fn check_attr_exist(table : &mut Option<String>, attr : &mut String,
     }
 }
 
+pub fn check_aggre_func(func : &String, table : &mut Option<String>, attr : &mut String,
+        table_set : &TableSet) -> SemResult {
+    try!(check_aggre_func_name(func));
+    if attr == "*" {
+        if func != "count" {
+            return Err(create_error(CompileErrorType::SemInvalidAggregateFunctionUse,
+                format!("only count can take *, found {}(*)", func)));
+        }
+        return Ok(());
+    }
+    try!(check_attr_exist(table, attr, table_set));
+    if func == "sum" || func == "avg" {
+        match table_set.get_attr(table, attr).unwrap().attr_type {
+            AttrType::Char{..} | AttrType::Varchar{..} | AttrType::Text =>
+                return Err(create_error(CompileErrorType::SemInvalidValueType,
+                    format!("{} of non-numeric attribute {}", func, attr))),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
 pub fn check_aggre_func_name(name : &String) -> SemResult {
-    let aggre_func_list = ["max", "min", "count", "sum"];
+    let aggre_func_list = ["max", "min", "count", "sum", "avg"];
     if aggre_func_list.into_iter().filter(|s| *name == s.to_string()).next().is_some() {
         Ok(())
     } else {
@@ -305,6 +577,8 @@ pub fn check_create(stmt : &CreateStatement, table_set : &TableSet) -> SemResult
     try!(check_unique_primary(stmt));
     try!(check_primary_not_null(stmt));
     try!(check_attr_unique(stmt));
+    try!(check_primary_type(stmt));
+    try!(check_table_option(stmt));
     Ok(())
 }
 
@@ -351,6 +625,35 @@ pub fn check_primary_not_null(stmt : &CreateStatement) -> SemResult {
     Ok(())
 }
 
+pub fn check_primary_type(stmt : &CreateStatement) -> SemResult {
+    for decl in stmt.decl_list.iter().filter(|d| d.primary) {
+        if let super::create_drop::AttrType::Text = decl.attr_type {
+            return Err(create_error(CompileErrorType::SemInvalidIndexType,
+                format!("TEXT attribute can't be primary: {}", decl.name)));
+        }
+    }
+    Ok(())
+}
+
+pub fn check_table_option(stmt : &CreateStatement) -> SemResult {
+    let mut name_set = HashSet::new();
+    for option in stmt.option_list.iter() {
+        if !name_set.insert(&option.name) {
+            return Err(create_error(CompileErrorType::SemInvalidTableOption,
+                format!("duplicate table option: {}", option.name)));
+        }
+        match &option.name[..] {
+            "compression" => if !is_valid_compression(&option.value) {
+                return Err(create_error(CompileErrorType::SemInvalidTableOption,
+                    format!("invalid compression {}, should be one of none and lz4", option.value)));
+            },
+            _ => return Err(create_error(CompileErrorType::SemInvalidTableOption,
+                format!("unknown table option: {}", option.name))),
+        }
+    }
+    Ok(())
+}
+
 pub fn check_attr_unique(stmt : &CreateStatement) -> SemResult {
     let mut table_set = HashSet::new();
     for name in stmt.decl_list.iter().map(|d| &d.name) {
@@ -371,6 +674,107 @@ pub fn check_drop(stmt : &DropStatement, table_set : &TableSet) -> SemResult {
     check_table_exist(&stmt.table, table_set)
 }
 
+pub fn check_create_index(stmt : &CreateIndexStatement, table_set : &TableSet) -> SemResult {
+    try!(check_table_exist(&stmt.table, table_set));
+    let table = table_set.tables.get(&stmt.table).unwrap();
+    if table.get_index_desc(&stmt.index).is_some() {
+        return Err(create_error(CompileErrorType::SemIndexExist,
+            format!("index {} already exist on table {}", stmt.index, stmt.table)));
+    }
+    let mut attr_set = HashSet::new();
+    for attr in stmt.attr_list.iter() {
+        let attr_index = match table.get_attr_index(attr) {
+            Some(i) => i,
+            None => return Err(create_error(CompileErrorType::SemInvalidAttribute,
+                format!("attribute {} not exist in table {}", attr, stmt.table))),
+        };
+        if let AttrType::Text = table.attr_list[attr_index].attr_type {
+            return Err(create_error(CompileErrorType::SemInvalidIndexType,
+                format!("can't create index on TEXT attribute {}", attr)));
+        }
+        if !attr_set.insert(attr) {
+            return Err(create_error(CompileErrorType::SemDuplicateAttr,
+                format!("duplicate attribute name :{}", attr)));
+        }
+    }
+    Ok(())
+}
+
+pub fn check_drop_index(stmt : &DropIndexStatement, table_set : &TableSet) -> SemResult {
+    try!(check_table_exist(&stmt.table, table_set));
+    let table = table_set.tables.get(&stmt.table).unwrap();
+    match table.get_index_desc(&stmt.index) {
+        Some(..) => Ok(()),
+        None => Err(create_error(CompileErrorType::SemIndexNotExist,
+            format!("index {} not exist on table {}", stmt.index, stmt.table))),
+    }
+}
+
+pub fn check_vacuum(stmt : &VacuumStatement, table_set : &TableSet) -> SemResult {
+    match stmt.table {
+        Some(ref table) => check_table_exist(table, table_set),
+        None => Ok(()),
+    }
+}
+
+pub fn check_alter(stmt : &AlterStatement, table_set : &TableSet) -> SemResult {
+    try!(check_table_exist(&stmt.table, table_set));
+    let table = table_set.tables.get(&stmt.table).unwrap();
+    match stmt.action {
+        AlterAction::AddColumn{ref decl, ref default} => {
+            if table.get_attr_index(&decl.name).is_some() {
+                return Err(create_error(CompileErrorType::SemDuplicateAttr,
+                    format!("duplicate attribute name :{}", decl.name)));
+            }
+            if decl.primary {
+                return Err(create_error(CompileErrorType::SemMultiplePrimary,
+                    format!("can't add primary attribute {}", decl.name)));
+            }
+            match default {
+                &Some(ref value) => try!(check_assign(value, &decl.gen_attr())),
+                // the existing tuples are filled with null
+                &None => if !decl.nullable {
+                    return Err(create_error(CompileErrorType::SemInvalidAlter,
+                        format!("attribute {} is not nullable but has no default value", decl.name)));
+                },
+            }
+        }
+        AlterAction::DropColumn{ref attr} => {
+            try!(check_alter_attr_exist(attr, &stmt.table, table_set));
+            if table.get_primary_key_attr().name == *attr {
+                return Err(create_error(CompileErrorType::SemChangePrimaryAttr,
+                    format!("can't drop primary attribute {}", attr)));
+            }
+            if let Some(index) = table.index_list.iter().filter(|i| i.attr_list.contains(attr)).next() {
+                return Err(create_error(CompileErrorType::SemInvalidAlter,
+                    format!("attribute {} is used by index {}", attr, index.name)));
+            }
+        }
+        AlterAction::RenameColumn{ref from, ref to} => {
+            try!(check_alter_attr_exist(from, &stmt.table, table_set));
+            if table.get_attr_index(to).is_some() {
+                return Err(create_error(CompileErrorType::SemDuplicateAttr,
+                    format!("duplicate attribute name :{}", to)));
+            }
+        }
+        AlterAction::RenameTable{ref to} => {
+            if table_set.exist(to) {
+                return Err(create_error(CompileErrorType::SemTableExist,
+                    format!("table {} already exist", to)));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_alter_attr_exist(attr : &str, table : &str, table_set : &TableSet) -> SemResult {
+    match table_set.tables.get(table).unwrap().get_attr_index(attr) {
+        Some(..) => Ok(()),
+        None => Err(create_error(CompileErrorType::SemInvalidAttribute,
+            format!("attribute {} not exist in table {}", attr, table))),
+    }
+}
+
 pub fn check_table_exist(table : &str, table_set : &TableSet) -> SemResult {
     if table_set.exist(table) {
         Ok(())
@@ -0,0 +1,43 @@
+use std::fmt;
+use std::fmt::{Formatter, Display};
+use std::option::Option::{Some, None};
+use super::lexer::{TokenIter, TokenType};
+use super::compile_error::ErrorList;
+use super::common::{
+    consume_next_token_with_type,
+    check_parse_to_end,
+    align_iter,
+};
+
+
+#[derive(Debug)]
+pub struct VacuumStatement {
+    pub table : Option<String>,  // all the tables if not specified
+}
+
+impl Display for VacuumStatement {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        match self.table {
+            Some(ref table) => write!(f, "vacuum {}", table),
+            None => write!(f, "vacuum"),
+        }
+    }
+}
+
+impl VacuumStatement {
+    pub fn parse(it : &mut TokenIter) -> Result<VacuumStatement, ErrorList> {
+        try!(consume_next_token_with_type(it, TokenType::Vacuum));
+        let mut tmp = it.clone();
+        let table = match consume_next_token_with_type(&mut tmp, TokenType::Identifier) {
+            Ok(token) => {
+                align_iter(it, &mut tmp);
+                Some(token.value.clone())
+            }
+            Err(..) => None,
+        };
+        match check_parse_to_end(it) {
+            Some(err) => Err(vec![err]),
+            None => Ok(VacuumStatement{ table : table }),
+        }
+    }
+}
@@ -4,19 +4,19 @@ use ::parser::compile_error::ErrorList;
 use ::parser::lexer::{TokenLine, TokenType};
 use ::parser::sem_check::check_sem;
 use ::parser::unimpl::check_stmt_unimpl;
-use ::store::tuple::TupleData;
-use ::store::table::{TableManagerRef, Table, TableSet, AttrType};
+use ::store::tuple::Row;
+use ::store::table::{TableManagerRef, AttrType};
 use ::exec::gen_plan::{gen_table_set, gen_plan};
-use ::exec::gen_plan::gen_proj_info;
-use ::exec::error::ExecError;
-use ::utils::array::projection;
+use ::exec::gen_plan::{gen_proj_info, gen_select_attr_desc, get_select_table_list};
+use ::exec::error::{ExecError, ExecErrorType};
+use ::exec::iter::ExecIterRef;
 
 
 pub type ResultHandlerRef = Box<ResultHandler>;
 
 pub trait ResultHandler {
     fn handle_error(&mut self, err_msg : String);
-    fn handle_tuple_data(&mut self, tuple_data : Option<TupleData>);
+    fn handle_tuple_data(&mut self, row : Option<Row>);
     fn handle_non_query_finished(&mut self);
     fn set_tuple_info(&mut self, attr_desc : Vec<AttrType>, attr_index : Vec<usize>);
 }
@@ -31,9 +31,13 @@ fn gen_parse_result(input : &String) -> Result<Statement, ErrorList> {
 }
 
 
+const RESIZE_POOL : &'static str = "resize pool ";
+
 pub fn process_table_command(input : &String, manager : &TableManagerRef) -> Result<String, ()> {
     match input.as_ref() {
         "show tables" => Ok(show_tables(manager)),
+        "show pool" => Ok(show_pool(manager)),
+        s if s.starts_with(RESIZE_POOL) => Ok(resize_pool(&s[RESIZE_POOL.len()..], manager)),
         _ => Err(()),
     }
 }
@@ -42,8 +46,36 @@ fn show_tables(manager : &TableManagerRef) -> String {
     manager.borrow().show_tables()
 }
 
+fn show_pool(manager : &TableManagerRef) -> String {
+    let file_manager = &manager.borrow().file_manager;
+    let capacity = file_manager.page_pool.get_capacity();
+    format!("pool size: {}, loaded: {}, pinned: {}, dirty: {}",
+        capacity, file_manager.page_pool.get_load(),
+        capacity - file_manager.get_unpinned_num(), file_manager.get_dirty_page_num())
+}
+
+fn resize_pool(size : &str, manager : &TableManagerRef) -> String {
+    // takes effect immediately, the config file is not changed
+    let size = match size.trim().parse::<usize>() {
+        Ok(size) => size,
+        Err(..) => return format!("invalid pool size: {}", size),
+    };
+    let result = manager.borrow_mut().file_manager.resize_pool(size);
+    match result {
+        Ok(()) => show_pool(manager),
+        Err(err_msg) => err_msg,
+    }
+}
+
 
 pub fn sql_handler(input : &String, result_handler : &mut ResultHandler, manager : &TableManagerRef) {
+    if let Some(err_msg) = manager.borrow().get_failure() {
+        // the database should be reopened to undo the failed statement
+        return result_handler.handle_error(handle_exec_err(&ExecError{
+            error_type : ExecErrorType::IoError,
+            error_msg : err_msg,
+        }));
+    }
     let parse_result = gen_parse_result(input);
     let mut stmt = match parse_result {
         Ok(stmt) => stmt,
@@ -59,29 +91,18 @@ pub fn sql_handler(input : &String, result_handler : &mut ResultHandler, manager
 
     match &stmt {
         &Statement::Select(..) => {
-            let table = get_table(&table_set);
-            let mut attr_desc = table.gen_tuple_desc().attr_desc;
+            let select = extract!(&stmt, &Statement::Select(ref s), s);
+            let attr_desc = gen_select_attr_desc(select, &get_select_table_list(select, manager));
             let (attr_index, _) = gen_proj_info(&stmt, &manager);
-            attr_desc = projection(&attr_index, attr_desc);
             result_handler.set_tuple_info(attr_desc, attr_index);
-
-            let mut plan = gen_plan(stmt, manager);
-            plan.open();
-            loop {
-                match plan.get_next() {
-                    Some(tuple_data) => {
-                        result_handler.handle_tuple_data(Some(tuple_data));
-                    }
-                    None => {
-                        if let Some(ref err) = plan.get_error() {
-                            result_handler.handle_error(handle_exec_err(err));
-                        } else {
-                            result_handler.handle_tuple_data(None);
-                        }
-                        break;
-                    }
-                }
-            }
+            let plan = gen_plan(stmt, manager);
+            handle_query_plan(plan, result_handler);
+        }
+        &Statement::Vacuum(..) => {
+            // return the number of reclaimed pages, the files are flushed by vacuum itself
+            result_handler.set_tuple_info(vec![AttrType::Int], vec![0]);
+            let plan = gen_plan(stmt, manager);
+            handle_query_plan(plan, result_handler);
         }
         _ => {
             let mut plan = gen_plan(stmt, manager);
@@ -92,23 +113,45 @@ pub fn sql_handler(input : &String, result_handler : &mut ResultHandler, manager
                     None => break,
                 }
             }
-            if let Some(ref err) = plan.get_error() {
-                result_handler.handle_error(handle_exec_err(err));
+            let error = plan.get_error();
+            drop(plan);  // release the files before they are reloaded by the rollback
+            if let Some(ref err) = error {
+                // the statement may have failed halfway, e.g. at the second of the updated rows
+                let result = manager.borrow_mut().rollback();
+                match result {
+                    Ok(()) => result_handler.handle_error(handle_exec_err(err)),
+                    Err(rollback_err) => result_handler.handle_error(format!("{}, can't roll back the statement: {}",
+                        handle_exec_err(err), rollback_err)),
+                }
+            } else if let Err(err) = manager.borrow_mut().commit() {
+                result_handler.handle_error(handle_exec_err(&ExecError{
+                    error_type : ExecErrorType::IoError,
+                    error_msg : format!("can't commit the statement: {}", err),
+                }));
             } else {
                 result_handler.handle_non_query_finished();
-                manager.borrow_mut().save_to_file();
             }
         }
     }
 }
 
-fn get_table(table_set : &TableSet) -> Table {
-    // only suport one table now
-    assert_eq!(table_set.tables.len(), 1);
-    for (_, t) in table_set.tables.iter() {
-        return t.clone();
+fn handle_query_plan(mut plan : ExecIterRef, result_handler : &mut ResultHandler) {
+    plan.open();
+    loop {
+        match plan.get_next() {
+            Some(row) => {
+                result_handler.handle_tuple_data(Some(row));
+            }
+            None => {
+                if let Some(ref err) = plan.get_error() {
+                    result_handler.handle_error(handle_exec_err(err));
+                } else {
+                    result_handler.handle_tuple_data(None);
+                }
+                break;
+            }
+        }
     }
-    panic!("empty table set");
 }
 
 fn handle_sql_err(err_list : &ErrorList) -> String {
@@ -2,52 +2,105 @@ use std::io::{stdin, stdout};
 use std::io::Write;
 use std::rc::Rc;
 use std::cell::RefCell;
+use std::thread::{spawn, sleep};
+use std::time::Duration;
 use ::store::table::TableManager;
-use ::store::tuple::TupleData;
+use ::store::tuple::Row;
 use ::store::table::AttrType;
-use ::store::tuple::gen_tuple_value;
 use ::utils::config::Config;
 use super::handler::{sql_handler, ResultHandler, process_table_command};
+use super::queue::{BlockingQueueRef, BlockingQueue};
+use super::server::DEFAULT_CHECKPOINT_INTERVAL;
 
 
 #[derive(Debug)]
 pub struct LocalClient;
 
+enum Input {
+    Line(String),
+    Checkpoint,
+    End,
+}
+
+type InputQueueRef = BlockingQueueRef<Input>;
+
 impl LocalClient {
     pub fn shell_loop(&mut self) {
         let config = Config::from_cwd_config();
+        let checkpoint_interval = config.get_opt_int("checkpoint_interval")
+            .map_or(DEFAULT_CHECKPOINT_INTERVAL, |i| i as u64);
         let mut manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        // as in the server, the tables are owned by this thread and the checkpoint is queued with the input,
+        // so it's done between statements, and also while waiting for the input
+        let q = BlockingQueueRef::new(BlockingQueue::new(16));
+        let q_clone = q.clone();
+        spawn(move || read_line_loop(q_clone));
+        if checkpoint_interval > 0 {
+            let q_clone = q.clone();
+            spawn(move || checkpoint_timer_loop(q_clone, Duration::from_millis(checkpoint_interval)));
+        }
         let mut sql = String::new();
-        let mut line = String::new();
         let mut process = Process::new();
+        print_prompt();
         loop {
-            print!("Blastoise> ");
-            stdout().flush().ok();
-            match stdin().read_line(&mut line) {
-                Ok(n) => {
-                    line.pop();  // remove '\n'
-                    if n == 0 { continue }
-                    if line == "q" { break; }
-                    sql.push_str(&line);
-                    if let Some(';') = line.chars().rev().take(1).next() {
-                        sql.pop();  // remove ';'
-                        if let Ok(out) = process_table_command(&sql, &manager) {
-                            println!("{}", out);
-                        } else {
-                            println!("processing {:?}", sql);
-                            sql_handler(&sql, &mut process, &mut manager);
-                            process = Process::new();
-                        }
-                        sql.clear();
+            let mut line = match q.pop_front() {
+                Input::Line(line) => line,
+                Input::Checkpoint => {
+                    if let Err(err) = manager.borrow_mut().checkpoint_if_needed(true) {
+                        println!("checkpoint failed: {}", err);
                     }
-                    line.clear();
+                    continue;
                 }
-                Err(error) => println!("error: {}", error),
+                Input::End => break,
+            };
+            line.pop();  // remove '\n'
+            if line == "q" { break; }
+            sql.push_str(&line);
+            if let Some(';') = line.chars().rev().take(1).next() {
+                sql.pop();  // remove ';'
+                if let Ok(out) = process_table_command(&sql, &manager) {
+                    println!("{}", out);
+                } else {
+                    println!("processing {:?}", sql);
+                    sql_handler(&sql, &mut process, &mut manager);
+                    if let Err(err) = manager.borrow_mut().checkpoint_if_needed(false) {
+                        println!("checkpoint failed: {}", err);
+                    }
+                    process = Process::new();
+                }
+                sql.clear();
+            }
+            print_prompt();
+        }
+    }
+}
+
+fn print_prompt() {
+    print!("Blastoise> ");
+    stdout().flush().ok();
+}
+
+fn read_line_loop(input_que : InputQueueRef) {
+    loop {
+        let mut line = String::new();
+        match stdin().read_line(&mut line) {
+            Ok(0) => {
+                input_que.push_back(Input::End);
+                return;
             }
+            Ok(..) => input_que.push_back(Input::Line(line)),
+            Err(error) => println!("error: {}", error),
         }
     }
 }
 
+fn checkpoint_timer_loop(input_que : InputQueueRef, interval : Duration) {
+    loop {
+        sleep(interval);
+        input_que.push_back(Input::Checkpoint);
+    }
+}
+
 #[derive(Debug)]
 struct Process {
     attr_desc : Vec<AttrType>,
@@ -67,11 +120,11 @@ impl ResultHandler for Process {
     fn handle_error(&mut self, err_msg : String) {
         println!("{}", err_msg);
     }
-    fn handle_tuple_data(&mut self, tuple_data : Option<TupleData>) {
-        match tuple_data {
-            Some(data) => {
-                let value = gen_tuple_value(&self.attr_desc, data);
-                println!("{:?}", value);
+    fn handle_tuple_data(&mut self, row : Option<Row>) {
+        match row {
+            Some(row) => {
+                assert_eq!(self.attr_desc.len(), row.len());
+                println!("{:?}", row);
             }
             None => println!("end"),
         }
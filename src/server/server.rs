@@ -2,10 +2,10 @@ use std::io::{Write, ErrorKind};
 use std::sync::{Arc, Mutex};
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::thread::{JoinHandle, spawn};
+use std::thread::{JoinHandle, spawn, sleep};
+use std::time::Duration;
 use std::mem::transmute;
-use std::ptr::read;
-use std::slice;
+use std::cmp::min;
 use mio::*;
 use mio::tcp::{TcpListener, TcpStream};
 use mio::util::Slab;
@@ -14,34 +14,51 @@ use rustc_serialize::json::encode;
 use ::utils::config::Config;
 use ::utils::pointer::to_cstring;
 use ::store::table::{TableManager, AttrType};
-use ::store::tuple::TupleData;
+use ::store::tuple::{Row, TupleValue};
 use super::queue::{BlockingQueueRef, BlockingQueue};
 use super::handler::{sql_handler, ResultHandler, process_table_command};
 use super::buf::Buffer;
 
 
 const SERVER : Token = Token(0);
-type TaskQueueRef = BlockingQueueRef<(String, ConnRef)>;
+pub const DEFAULT_CHECKPOINT_INTERVAL : u64 = 5000;  // ms
+
+enum Task {
+    Sql(String, ConnRef),
+    Checkpoint,
+}
+
+type TaskQueueRef = BlockingQueueRef<Task>;
 
 struct SqlServer {
     listener : TcpListener,
     conn_list : Slab<ConnRef>,
     req_que : TaskQueueRef,
     worker : JoinHandle<()>,
+    checkpointer : Option<JoinHandle<()>>,
 }
 
 impl SqlServer {
-    fn new(listener : TcpListener) -> Self {
+    fn new(listener : TcpListener, checkpoint_interval : u64) -> Self {
         let q = BlockingQueueRef::new(BlockingQueue::new(64));
         let q_clone = q.clone();
         let worker = spawn(|| {
             consume_task_loop(q_clone);
         });
+        let checkpointer = if checkpoint_interval > 0 {
+            let q_clone = q.clone();
+            Some(spawn(move || {
+                checkpoint_timer_loop(q_clone, Duration::from_millis(checkpoint_interval));
+            }))
+        } else {
+            None
+        };
         SqlServer{
             listener : listener,
             conn_list : Slab::new_starting_at(Token(1), 1024),
             req_que : q,
             worker : worker,
+            checkpointer : checkpointer,
         }
     }
 }
@@ -81,7 +98,7 @@ impl Handler for SqlServer {
                     match conn.get_state() {
                         State::Ready => {
                             let sql = conn.get_sql();
-                            self.req_que.push_back((sql, clone));
+                            self.req_que.push_back(Task::Sql(sql, clone));
                         }
                         State::Closed => closed = true,
                         _ => (),
@@ -320,7 +337,15 @@ fn consume_task_loop(req_que : TaskQueueRef) {
     let config = Config::from_cwd_config();
     let mut manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
     loop {
-        let (sql, conn) = req_que.pop_front();
+        let (sql, conn) = match req_que.pop_front() {
+            Task::Sql(sql, conn) => (sql, conn),
+            Task::Checkpoint => {
+                if let Err(err) = manager.borrow_mut().checkpoint_if_needed(true) {
+                    println!("checkpoint failed: {}", err);
+                }
+                continue;
+            }
+        };
         conn.lock().unwrap().transition_to_writing();
         if let Ok(out) = process_table_command(&sql, &manager) {
             let mut c = conn.lock().unwrap();
@@ -331,6 +356,18 @@ fn consume_task_loop(req_que : TaskQueueRef) {
             let mut process = Process::new(conn);
             sql_handler(&sql, &mut process, &mut manager);
         }
+        // the result has been sent, flushing here doesn't delay the statement
+        if let Err(err) = manager.borrow_mut().checkpoint_if_needed(false) {
+            println!("checkpoint failed: {}", err);
+        }
+    }
+}
+
+fn checkpoint_timer_loop(req_que : TaskQueueRef, interval : Duration) {
+    // the tables are owned by the worker thread, so the checkpoint is queued and done by the worker
+    loop {
+        sleep(interval);
+        req_que.push_back(Task::Checkpoint);
     }
 }
 
@@ -381,23 +418,52 @@ impl ResultHandler for Process {
         c.write_buffer(cstring.as_bytes());
         c.change_to_finished_in_loop();
     }
-    fn handle_tuple_data(&mut self, tuple_data : Option<TupleData>) {
+    fn handle_tuple_data(&mut self, row : Option<Row>) {
         if !self.header_sended {
             self.send_header();
         }
-        match tuple_data {
+        match row {
             Some(data) => {
                 assert_eq!(self.attr_desc.len(), data.len());
                 let mut c = self.conn.lock().unwrap();
-                for (attr, p) in self.attr_desc.iter().zip(data.iter()) {
-                    match attr {
-                        &AttrType::Int | &AttrType::Float => {
-                            let bytes = unsafe{read::<[u8; 4]>(*p as *const [u8; 4])};
+                // every row starts with a null bitmap, null attributes are filled with zero,
+                // VARCHAR and TEXT are sent as u32 length followed by the content
+                let mut null_bitmap = vec![0u8; (data.len() + 7) / 8];
+                for (i, value) in data.iter().enumerate() {
+                    if is_match!(value, &TupleValue::Null) {
+                        null_bitmap[i / 8] |= 1 << (i % 8);
+                    }
+                }
+                c.write_buffer(&null_bitmap);
+                for (attr, value) in self.attr_desc.iter().zip(data.iter()) {
+                    match (attr, value) {
+                        (&AttrType::Int, &TupleValue::Int(n)) => {
+                            let bytes : [u8; 4] = unsafe { transmute(n.to_le()) };
                             c.write_buffer(&bytes);
                         }
-                        &AttrType::Char{len} => {
-                            let bytes : &[u8] = unsafe{ slice::from_raw_parts(*p as *const u8, len) };
-                            c.write_buffer(bytes);
+                        (&AttrType::Float, &TupleValue::Float(f)) => {
+                            let bytes : [u8; 4] = unsafe { transmute(f.to_bits().to_le()) };
+                            c.write_buffer(&bytes);
+                        }
+                        (&AttrType::Char{len}, &TupleValue::Char(ref s)) => {
+                            let mut bytes = vec![0u8; len];
+                            let n = min(len, s.len());
+                            bytes[..n].copy_from_slice(&s.as_bytes()[..n]);
+                            c.write_buffer(&bytes);
+                        }
+                        (&AttrType::Varchar{..}, &TupleValue::Char(ref s))
+                                | (&AttrType::Text, &TupleValue::Char(ref s)) => {
+                            let len_bytes : [u8; 4] = unsafe { transmute((s.len() as u32).to_le()) };
+                            c.write_buffer(&len_bytes);
+                            c.write_buffer(s.as_bytes());
+                        }
+                        (attr, _) => {
+                            // null
+                            let len = match attr {
+                                &AttrType::Char{len} => len,
+                                _ => 4,
+                            };
+                            c.write_buffer(&vec![0u8; len]);
                         }
                     };
                 }
@@ -414,12 +480,14 @@ impl ResultHandler for Process {
 pub fn run_server() {
     let config = Config::from_cwd_config();
     let port = config.get_int("port");
+    let checkpoint_interval = config.get_opt_int("checkpoint_interval")
+        .map_or(DEFAULT_CHECKPOINT_INTERVAL, |i| i as u64);
     let addr = format!("0.0.0.0:{}", port).parse().unwrap();
     let listener = TcpListener::bind(&addr).unwrap();
     let mut event_loop = EventLoop::new().unwrap();
 
     event_loop.register(&listener, SERVER, EventSet::readable(),
                         PollOpt::level()).unwrap();
-    let mut sqlserver = SqlServer::new(listener);
+    let mut sqlserver = SqlServer::new(listener, checkpoint_interval);
     event_loop.run(&mut sqlserver).unwrap();
 }
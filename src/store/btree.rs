@@ -0,0 +1,362 @@
+use std::vec::Vec;
+use std::cmp::Ordering;
+use std::mem::size_of;
+use std::ptr::write_bytes;
+use ::utils::pointer::{read_string, write_string, pointer_offset};
+use ::utils::pointer::{read_u32_le, write_u32_le, read_i32_le, write_i32_le, read_f32_le, write_f32_le};
+use super::buffer::DataPtr;
+use super::table::AttrType;
+use super::tuple::{TupleValue, cmp_tuple_value};
+use super::error::StorageResult;
+
+
+// is_leaf, entry_num, next leaf
+const NODE_HEADER_SIZE : usize = 3 * 4;
+const INVALID_PAGE : u32 = ::std::u32::MAX;
+
+
+pub type KeyDesc = Vec<AttrType>;
+
+#[derive(Debug, Clone)]
+pub struct IndexEntry {
+    pub key : Vec<TupleValue>,
+    pub position : usize,  // position of tuple in table file
+}
+
+impl IndexEntry {
+    pub fn cmp(&self, other : &IndexEntry) -> Ordering {
+        // position makes every entry unique even if keys are duplicated
+        match cmp_key(&self.key, &other.key) {
+            Ordering::Equal => self.position.cmp(&other.position),
+            other => other,
+        }
+    }
+}
+
+pub fn cmp_key(lhs : &Vec<TupleValue>, rhs : &Vec<TupleValue>) -> Ordering {
+    // only compare the common prefix, so that a prefix of key can be used in search
+    for (l, r) in lhs.iter().zip(rhs.iter()) {
+        match cmp_tuple_value(l, r) {
+            Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+    Ordering::Equal
+}
+
+fn attr_len(attr_type : &AttrType) -> usize {
+    match attr_type {
+        &AttrType::Int | &AttrType::Float => 4,
+        // variable-length attributes are stored with their max length in keys
+        &AttrType::Char{len} | &AttrType::Varchar{len} => (len + 3) / 4 * 4,
+        &AttrType::Text => panic!("TEXT can not be used as key"),
+    }
+}
+
+pub fn key_len(key_desc : &KeyDesc) -> usize {
+    // u32 null mask followed by the attributes
+    assert!(key_desc.len() <= 32);
+    key_desc.iter().map(attr_len).fold(size_of::<u32>(), |sum, l| sum + l)
+}
+
+pub fn max_entry_num(key_desc : &KeyDesc, page_size : usize) -> usize {
+    // internal node need one more child pointer than entries
+    let entry_size = key_len(key_desc) + size_of::<u32>();
+    (page_size - NODE_HEADER_SIZE - size_of::<u32>()) / (entry_size + size_of::<u32>())
+}
+
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub is_leaf : bool,
+    pub entries : Vec<IndexEntry>,
+    pub children : Vec<usize>,  // only for internal node, children.len() == entries.len() + 1
+    pub next : Option<usize>,  // only for leaf node
+}
+
+impl Node {
+    pub fn new_leaf() -> Node {
+        Node{
+            is_leaf : true,
+            entries : Vec::new(),
+            children : Vec::new(),
+            next : None,
+        }
+    }
+    pub fn read_from_page(data : DataPtr, key_desc : &KeyDesc) -> Node {
+        let (is_leaf, entry_num, next) = unsafe{(
+            read_u32_le(data) == 1,
+            read_u32_le(pointer_offset(data, 4)) as usize,
+            read_u32_le(pointer_offset(data, 8)),
+        )};
+        let mut p = pointer_offset(data, NODE_HEADER_SIZE);
+        let mut children = Vec::new();
+        if !is_leaf {
+            for _ in 0..entry_num + 1 {
+                children.push(unsafe{ read_u32_le(p) } as usize);
+                p = pointer_offset(p, size_of::<u32>());
+            }
+        }
+        let mut entries = Vec::with_capacity(entry_num);
+        for _ in 0..entry_num {
+            let mut key = Vec::with_capacity(key_desc.len());
+            let null_mask = unsafe{ read_u32_le(p) };
+            p = pointer_offset(p, size_of::<u32>());
+            for (i, attr_type) in key_desc.iter().enumerate() {
+                if null_mask & (1 << i) != 0 {
+                    key.push(TupleValue::Null);
+                    p = pointer_offset(p, attr_len(attr_type));
+                    continue;
+                }
+                unsafe{
+                    match attr_type {
+                        &AttrType::Int => key.push(TupleValue::Int(read_i32_le(p))),
+                        &AttrType::Float => key.push(TupleValue::Float(read_f32_le(p))),
+                        &AttrType::Char{len} | &AttrType::Varchar{len} =>
+                            key.push(TupleValue::Char(read_string(p, len))),
+                        &AttrType::Text => panic!("TEXT can not be used as key"),
+                    }
+                }
+                p = pointer_offset(p, attr_len(attr_type));
+            }
+            let position = unsafe{ read_u32_le(p) } as usize;
+            p = pointer_offset(p, size_of::<u32>());
+            entries.push(IndexEntry{ key : key, position : position });
+        }
+        Node{
+            is_leaf : is_leaf,
+            entries : entries,
+            children : children,
+            next : if next == INVALID_PAGE { None } else { Some(next as usize) },
+        }
+    }
+    pub fn write_to_page(&self, data : DataPtr, key_desc : &KeyDesc) {
+        let next = match self.next {
+            Some(n) => n as u32,
+            None => INVALID_PAGE,
+        };
+        unsafe{
+            write_u32_le(data, if self.is_leaf { 1 } else { 0 });
+            write_u32_le(pointer_offset(data, 4), self.entries.len() as u32);
+            write_u32_le(pointer_offset(data, 8), next);
+        }
+        let mut p = pointer_offset(data, NODE_HEADER_SIZE);
+        for child in self.children.iter() {
+            unsafe{ write_u32_le(p, *child as u32) };
+            p = pointer_offset(p, size_of::<u32>());
+        }
+        for entry in self.entries.iter() {
+            assert_eq!(entry.key.len(), key_desc.len());
+            let mut null_mask = 0;
+            for (i, value) in entry.key.iter().enumerate() {
+                if is_match!(value, &TupleValue::Null) {
+                    null_mask |= 1 << i;
+                }
+            }
+            unsafe{ write_u32_le(p, null_mask) };
+            p = pointer_offset(p, size_of::<u32>());
+            for (value, attr_type) in entry.key.iter().zip(key_desc.iter()) {
+                unsafe{
+                    match (value, attr_type) {
+                        (&TupleValue::Null, _) => write_bytes(p, 0, attr_len(attr_type)),
+                        (&TupleValue::Int(n), &AttrType::Int) => write_i32_le(p, n),
+                        (&TupleValue::Int(n), &AttrType::Float) => write_f32_le(p, n as f32),
+                        (&TupleValue::Float(n), &AttrType::Float) => write_f32_le(p, n),
+                        (&TupleValue::Char(ref s), &AttrType::Char{len})
+                            | (&TupleValue::Char(ref s), &AttrType::Varchar{len}) => write_string(p, s, len),
+                        _ => panic!("invalid key value, expected {:?}, found {:?}", attr_type, value),
+                    }
+                }
+                p = pointer_offset(p, attr_len(attr_type));
+            }
+            unsafe{ write_u32_le(p, entry.position as u32) };
+            p = pointer_offset(p, size_of::<u32>());
+        }
+    }
+    fn child_index(&self, entry : &IndexEntry) -> usize {
+        // entries in children[i] are in [entries[i-1], entries[i])
+        self.entries.iter().take_while(|e| e.cmp(entry) != Ordering::Greater).count()
+    }
+    fn lower_bound_child_index(&self, key : &Vec<TupleValue>) -> usize {
+        self.entries.iter().take_while(|e| cmp_key(&e.key, key) == Ordering::Less).count()
+    }
+}
+
+
+// the storage of the nodes, implemented by index file,
+// loading a node fails if no page of the pool can be swapped out
+pub trait NodeStore {
+    fn get_root(&self) -> Option<usize>;
+    fn set_root(&mut self, root : usize);
+    fn read_node(&mut self, page_index : usize) -> StorageResult<Node>;
+    fn write_node(&mut self, page_index : usize, node : &Node) -> StorageResult<()>;
+    fn alloc_node(&mut self) -> StorageResult<usize>;
+    fn max_entry_num(&self) -> usize;
+}
+
+pub fn insert<S : NodeStore>(store : &mut S, entry : IndexEntry) -> StorageResult<()> {
+    let root = match store.get_root() {
+        Some(root) => root,
+        None => {
+            let root = try!(store.alloc_node());
+            try!(store.write_node(root, &Node::new_leaf()));
+            store.set_root(root);
+            root
+        }
+    };
+    if let Some((separator, new_page)) = try!(insert_helper(store, root, entry)) {
+        let new_root = try!(store.alloc_node());
+        let node = Node{
+            is_leaf : false,
+            entries : vec![separator],
+            children : vec![root, new_page],
+            next : None,
+        };
+        try!(store.write_node(new_root, &node));
+        store.set_root(new_root);
+    }
+    Ok(())
+}
+
+fn insert_helper<S : NodeStore>(store : &mut S, page_index : usize, entry : IndexEntry)
+        -> StorageResult<Option<(IndexEntry, usize)>> {
+    // return the separator and the new page if split
+    let mut node = try!(store.read_node(page_index));
+    let i = node.child_index(&entry);
+    if node.is_leaf {
+        node.entries.insert(i, entry);
+    } else {
+        let child = node.children[i];
+        match try!(insert_helper(store, child, entry)) {
+            None => return Ok(None),
+            Some((separator, new_child)) => {
+                node.entries.insert(i, separator);
+                node.children.insert(i + 1, new_child);
+            }
+        }
+    }
+    if node.entries.len() <= store.max_entry_num() {
+        try!(store.write_node(page_index, &node));
+        return Ok(None);
+    }
+    let new_page = try!(store.alloc_node());
+    let mid = node.entries.len() / 2;
+    let mut right = Node::new_leaf();
+    right.is_leaf = node.is_leaf;
+    let separator;
+    if node.is_leaf {
+        right.entries = node.entries.split_off(mid);
+        separator = right.entries[0].clone();
+        right.next = node.next;
+        node.next = Some(new_page);
+    } else {
+        right.entries = node.entries.split_off(mid + 1);
+        separator = node.entries.pop().unwrap();
+        right.children = node.children.split_off(mid + 1);
+    }
+    try!(store.write_node(page_index, &node));
+    try!(store.write_node(new_page, &right));
+    Ok(Some((separator, new_page)))
+}
+
+pub fn delete<S : NodeStore>(store : &mut S, entry : &IndexEntry) -> StorageResult<bool> {
+    // underflowed nodes are not merged, empty leaves are skipped when searching
+    let mut page_index = match store.get_root() {
+        Some(root) => root,
+        None => return Ok(false),
+    };
+    loop {
+        let mut node = try!(store.read_node(page_index));
+        if !node.is_leaf {
+            page_index = node.children[node.child_index(entry)];
+            continue;
+        }
+        match node.entries.iter().position(|e| e.cmp(entry) == Ordering::Equal) {
+            Some(i) => {
+                node.entries.remove(i);
+                try!(store.write_node(page_index, &node));
+                return Ok(true);
+            }
+            None => return Ok(false),
+        }
+    }
+}
+
+pub fn lookup<S : NodeStore>(store : &mut S, key : &Vec<TupleValue>) -> StorageResult<Vec<usize>> {
+    // key can be a prefix of the index key
+    scan(store, &KeyRange::equal(key.clone()))
+}
+
+pub fn scan<S : NodeStore>(store : &mut S, range : &KeyRange) -> StorageResult<Vec<usize>> {
+    let mut result = Vec::new();
+    let mut page_index = match store.get_root() {
+        Some(root) => root,
+        None => return Ok(result),
+    };
+    let mut start_key = range.prefix.clone();
+    if let Some((ref value, _)) = range.lower {
+        start_key.push(value.clone());
+    }
+    loop {
+        let node = try!(store.read_node(page_index));
+        if !node.is_leaf {
+            page_index = node.children[node.lower_bound_child_index(&start_key)];
+            continue;
+        }
+        for entry in node.entries.iter() {
+            match range.check(&entry.key) {
+                Ordering::Less => continue,
+                Ordering::Equal => result.push(entry.position),
+                Ordering::Greater => return Ok(result),
+            }
+        }
+        match node.next {
+            Some(next) => page_index = next,
+            None => return Ok(result),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+pub struct KeyRange {
+    pub prefix : Vec<TupleValue>,  // equal to the first attributes of key
+    pub lower : Option<(TupleValue, bool)>,  // bound of the attribute after prefix, true if inclusive
+    pub upper : Option<(TupleValue, bool)>,
+}
+
+impl KeyRange {
+    pub fn equal(key : Vec<TupleValue>) -> KeyRange {
+        KeyRange{
+            prefix : key,
+            lower : None,
+            upper : None,
+        }
+    }
+    pub fn check(&self, key : &Vec<TupleValue>) -> Ordering {
+        // Less if key is before the range, Greater if after the range, otherwise Equal
+        match cmp_key(key, &self.prefix) {
+            Ordering::Equal => (),
+            other => return other,
+        }
+        if self.lower.is_none() && self.upper.is_none() {
+            return Ordering::Equal;
+        }
+        let value = &key[self.prefix.len()];
+        if let Some((ref lower, inclusive)) = self.lower {
+            match cmp_tuple_value(value, lower) {
+                Ordering::Less => return Ordering::Less,
+                Ordering::Equal if !inclusive => return Ordering::Less,
+                _ => (),
+            }
+        }
+        if let Some((ref upper, inclusive)) = self.upper {
+            match cmp_tuple_value(value, upper) {
+                Ordering::Greater => return Ordering::Greater,
+                Ordering::Equal if !inclusive => return Ordering::Greater,
+                _ => (),
+            }
+        }
+        Ordering::Equal
+    }
+}
@@ -4,14 +4,15 @@ use std::option::Option;
 use std::rc::Rc;
 use std::cell::RefCell;
 use libc::{c_void, free};
-use super::lru::{CacheValue, LruCache};
+use super::lru::CacheValue;
+use super::cache::{ReplaceCache, new_cache, DEFAULT_POLICY};
 use ::utils::libwrapper::alloc_page;
 
 
 pub type DataPtr = *mut c_void;
 
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct PageKey {
     pub fd : i32,
     pub page_index : u32,
@@ -34,14 +35,15 @@ pub struct Page {
     pub fd : i32,
     pub page_index : u32,
     pub data : DataPtr,
-    pub dirty : bool,
-    pub pinned : bool,
+    pub dirty : bool,  // changed since the page was read from or saved to the file
+    pub logged : bool,  // the latest change has been written to the log
+    pub pin_count : usize,  // a page can be pinned by several scans
 }
 
 impl CacheValue for PageRef {
     type KeyType = PageKey;
     fn is_pinned(&self) -> bool {
-        self.borrow().pinned
+        self.borrow().is_pinned()
     }
 }
 
@@ -52,15 +54,23 @@ impl Page {
             page_index : page_index,
             data : null_mut(),
             dirty : false,
-            pinned : false,
+            logged : false,
+            pin_count : 0,
         }
     }
     pub fn alloc(&mut self) {
         assert!(self.data.is_null());
         self.data = alloc_page();
     }
+    pub fn is_pinned(&self) -> bool {
+        self.pin_count > 0
+    }
     pub fn mark_dirty(&mut self) {
         self.dirty = true;
+        self.logged = false;
+    }
+    pub fn need_log(&self) -> bool {
+        self.dirty && !self.logged
     }
 }
 
@@ -77,19 +87,37 @@ impl Drop for Page {
 #[derive(Debug)]
 pub struct PagePool {
     // should be protected by mutex
-    cache: LruCache<PageRef>,
+    cache: Box<ReplaceCache<PageRef>>,
+    policy : String,
     unpinned : usize,
 }
 
 impl PagePool {
     pub fn new(capacity : usize) -> PagePool {
+        Self::with_policy(capacity, DEFAULT_POLICY)
+    }
+    pub fn with_policy(capacity : usize, policy : &str) -> PagePool {
         PagePool{
-            cache : LruCache::new(capacity),
+            cache : new_cache(policy, capacity),
+            policy : policy.to_string(),
             unpinned : capacity,
         }
     }
+    pub fn resize(&mut self, capacity : usize, pages : Vec<PageRef>) {
+        // pages are all the pages in the pool, they are put into a new cache of the capacity
+        assert!(pages.len() <= capacity);
+        assert_eq!(pages.len(), self.cache.get_load());
+        let pinned = self.cache.capacity() - self.unpinned;
+        assert!(pinned < capacity);
+        self.cache = new_cache(&self.policy, capacity);
+        self.unpinned = capacity - pinned;
+        for page in pages {
+            let key = PageKey{ fd : page.borrow().fd, page_index : page.borrow().page_index };
+            self.cache.put(&key, page);
+        }
+    }
     pub fn get_capacity(&self) -> usize {
-        self.cache.capacity
+        self.cache.capacity()
     }
     pub fn get_page(&mut self, fd : i32, page_index : u32) -> Option<PageRef> {
         let key = PageKey{ fd : fd, page_index : page_index };
@@ -110,18 +138,36 @@ impl PagePool {
         }
         self.cache.put(&key, Rc::new(RefCell::new(new_page)));
     }
+    pub fn remove_page(&mut self, fd : i32, page_index : u32) {
+        // the memory will be freed when all the PageRef dropped
+        let key = PageKey{ fd : fd, page_index : page_index };
+        if let Some(page) = self.cache.remove(&key) {
+            assert!(!page.borrow().is_pinned());
+        }
+    }
+    pub fn can_pin(&mut self, fd : i32, page_index : u32) -> bool {
+        // keep a frame unpinned so that other pages can still be loaded
+        let page = self.get_page(fd, page_index).unwrap();
+        let pinned = page.borrow().is_pinned();
+        pinned || self.unpinned > 1
+    }
     pub fn pin_page(&mut self, fd : i32, page_index : u32) {
-        assert!(self.unpinned > 0);
-        self.unpinned -= 1;
+        assert!(self.can_pin(fd, page_index));
         let page = self.get_page(fd, page_index).unwrap();
-        page.borrow_mut().pinned = true;
+        if !page.borrow().is_pinned() {
+            self.unpinned -= 1;
+        }
+        page.borrow_mut().pin_count += 1;
     }
     pub fn unpin_page(&mut self, fd : i32, page_index : u32) {
-        assert!(self.unpinned < self.cache.capacity);
-        self.unpinned += 1;
         let page = self.get_page(fd, page_index).unwrap();
-        assert!(page.borrow().pinned);
-        page.borrow_mut().pinned = false;
+        assert!(page.borrow().is_pinned());
+        page.borrow_mut().pin_count -= 1;
+        if !page.borrow().is_pinned() {
+            assert!(self.unpinned < self.cache.capacity());
+            self.unpinned += 1;
+        }
     }
     pub fn get_unpinned_num(&self) -> usize { self.unpinned }
+    pub fn get_load(&self) -> usize { self.cache.get_load() }
 }
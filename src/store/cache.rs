@@ -0,0 +1,44 @@
+use std::boxed::Box;
+use std::fmt::Debug;
+use super::lru::{CacheValue, LruCache};
+use super::clock::ClockCache;
+use super::lru_k::LruKCache;
+use super::two_q::TwoQueueCache;
+
+
+// Replacement policy of the page pool.
+// prepare_page returns the value to be evicted if the cache is full and None if there is a free slot,
+// then remove_tail evicts it. Pinned values are never evicted and it panics if all the values are pinned.
+pub trait ReplaceCache<ValueType : CacheValue> : Debug {
+    fn get(&mut self, key : &ValueType::KeyType) -> Option<ValueType>;
+    fn prepare_page(&mut self) -> Option<ValueType>;
+    fn remove_tail(&mut self);
+    fn remove(&mut self, key : &ValueType::KeyType) -> Option<ValueType>;
+    fn put(&mut self, key : &ValueType::KeyType, value : ValueType);
+    fn capacity(&self) -> usize;
+    fn get_load(&self) -> usize;
+}
+
+pub const DEFAULT_POLICY : &'static str = "lru";
+const LRU_K : usize = 2;
+
+pub fn new_cache<ValueType : CacheValue + 'static>(policy : &str, capacity : usize)
+        -> Box<ReplaceCache<ValueType>> {
+    match policy {
+        "lru" => Box::new(LruCache::new(capacity)),
+        "clock" => Box::new(ClockCache::new(capacity)),
+        "lru-k" => Box::new(LruKCache::new(capacity, LRU_K)),
+        "2q" => Box::new(TwoQueueCache::new(capacity)),
+        _ => panic!("invalid buffer policy {}, should be one of lru, clock, lru-k and 2q", policy),
+    }
+}
+
+impl<ValueType : CacheValue> ReplaceCache<ValueType> for LruCache<ValueType> {
+    fn get(&mut self, key : &ValueType::KeyType) -> Option<ValueType> { LruCache::get(self, key) }
+    fn prepare_page(&mut self) -> Option<ValueType> { LruCache::prepare_page(self) }
+    fn remove_tail(&mut self) { LruCache::remove_tail(self) }
+    fn remove(&mut self, key : &ValueType::KeyType) -> Option<ValueType> { LruCache::remove(self, key) }
+    fn put(&mut self, key : &ValueType::KeyType, value : ValueType) { LruCache::put(self, key, value) }
+    fn capacity(&self) -> usize { self.capacity }
+    fn get_load(&self) -> usize { LruCache::get_load(self) }
+}
@@ -0,0 +1,27 @@
+// CRC-32 (IEEE 802.3) shared by the log records, the pages and the schema fingerprints,
+// computed a byte at a time with a table of the reflected polynomial built once for each thread.
+const POLYNOMIAL : u32 = 0xedb88320;
+
+thread_local!(static CRC_TABLE : [u32; 256] = gen_crc_table());
+
+fn gen_crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    for (i, entry) in table.iter_mut().enumerate() {
+        let mut crc = i as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ POLYNOMIAL } else { crc >> 1 };
+        }
+        *entry = crc;
+    }
+    table
+}
+
+pub fn crc32(data : &[u8]) -> u32 {
+    CRC_TABLE.with(|table| {
+        let mut crc = !0;
+        for b in data.iter() {
+            crc = (crc >> 8) ^ table[((crc ^ *b as u32) & 0xff) as usize];
+        }
+        !crc
+    })
+}
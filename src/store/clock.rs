@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::vec::Vec;
+use super::lru::{CacheValue, hash};
+use super::cache::ReplaceCache;
+
+
+// Clock approximates LRU with a reference bit for each frame.
+// The hand sweeps the frames, clears the bits it meets
+// and evicts the first unpinned value whose bit is already clear.
+#[derive(Debug)]
+struct Frame<ValueType> {
+    key : u64,
+    value : ValueType,
+    referenced : bool,
+}
+
+#[derive(Debug)]
+pub struct ClockCache<ValueType : CacheValue> {
+    capacity : usize,
+    hash_map : HashMap<u64, usize>,  // key to frame index
+    frames : Vec<Option<Frame<ValueType>>>,
+    free_frames : Vec<usize>,
+    hand : usize,
+    victim : Option<usize>,
+}
+
+impl<ValueType : CacheValue> ClockCache<ValueType> {
+    pub fn new(capacity : usize) -> ClockCache<ValueType> {
+        assert!(capacity > 0);
+        ClockCache{
+            capacity : capacity,
+            hash_map : HashMap::with_capacity(capacity),
+            frames : (0..capacity).map(|_| None).collect(),
+            free_frames : (0..capacity).rev().collect(),
+            hand : 0,
+            victim : None,
+        }
+    }
+}
+
+impl<ValueType : CacheValue> ReplaceCache<ValueType> for ClockCache<ValueType> {
+    fn get(&mut self, key : &ValueType::KeyType) -> Option<ValueType> {
+        let i = match self.hash_map.get(&hash(key)) {
+            Some(i) => *i,
+            None => return None,
+        };
+        let frame = self.frames[i].as_mut().unwrap();
+        frame.referenced = true;
+        Some(frame.value.clone())
+    }
+    fn prepare_page(&mut self) -> Option<ValueType> {
+        if self.hash_map.len() < self.capacity {
+            return None;
+        }
+        // all the bits are cleared in the first round, so two rounds are enough
+        for _ in 0..2 * self.capacity {
+            let i = self.hand;
+            self.hand = (self.hand + 1) % self.capacity;
+            let frame = self.frames[i].as_mut().unwrap();
+            if frame.value.is_pinned() {
+                continue;
+            }
+            if frame.referenced {
+                frame.referenced = false;
+                continue;
+            }
+            self.victim = Some(i);
+            return Some(frame.value.clone());
+        }
+        panic!("all pages were pinned");
+    }
+    fn remove_tail(&mut self) {
+        if self.victim.is_none() {
+            assert!(self.prepare_page().is_some());
+        }
+        let i = self.victim.take().unwrap();
+        let frame = self.frames[i].take().unwrap();
+        assert!(!frame.value.is_pinned());
+        assert!(self.hash_map.remove(&frame.key).is_some());
+        self.free_frames.push(i);
+    }
+    fn remove(&mut self, key : &ValueType::KeyType) -> Option<ValueType> {
+        let i = match self.hash_map.remove(&hash(key)) {
+            Some(i) => i,
+            None => return None,
+        };
+        if self.victim == Some(i) {
+            self.victim = None;
+        }
+        self.free_frames.push(i);
+        self.frames[i].take().map(|frame| frame.value)
+    }
+    fn put(&mut self, key : &ValueType::KeyType, value : ValueType) {
+        // before call this function, you should call prepare_page and remove_tail first
+        let k = hash(key);
+        assert!(!self.hash_map.contains_key(&k));
+        let i = self.free_frames.pop().unwrap();
+        self.frames[i] = Some(Frame{
+            key : k,
+            value : value,
+            referenced : true,
+        });
+        self.hash_map.insert(k, i);
+    }
+    fn capacity(&self) -> usize { self.capacity }
+    fn get_load(&self) -> usize { self.hash_map.len() }
+}
@@ -0,0 +1,174 @@
+use std::boxed::Box;
+use std::cmp::min;
+use std::fmt::Debug;
+
+
+// Compression of the pages in table files.
+// Pages are compressed when they are written and decompressed into the frame when they are read,
+// so the pages in the page pool are the same as the uncompressed ones.
+pub trait PageCodec : Debug {
+    fn compress(&self, page : &[u8]) -> Vec<u8>;
+    // fill the whole page, fail if data is corrupted or not decompressed to the page size
+    fn decompress(&self, data : &[u8], page : &mut [u8]) -> Result<(), String>;
+}
+
+pub type PageCodecRef = Box<PageCodec>;
+
+pub const DEFAULT_COMPRESSION : &'static str = "none";
+
+pub fn is_valid_compression(name : &str) -> bool {
+    match name {
+        "none" | "lz4" => true,
+        _ => false,
+    }
+}
+
+pub fn new_codec(name : &str) -> Option<PageCodecRef> {
+    match name {
+        "none" => None,
+        "lz4" => Some(Box::new(Lz4Codec)),
+        _ => panic!("invalid compression {}, should be one of none and lz4", name),
+    }
+}
+
+
+// LZ4 block format, a sequence is a token, literals, the offset of the match and the match length.
+// The high 4 bits of the token is the literal length and the low 4 bits is the match length minus 4,
+// 15 means the length continues in the following bytes, each added to it until a byte is not 255.
+// The last sequence only has literals, and the last 5 bytes are always literals.
+const MIN_MATCH : usize = 4;
+const LAST_LITERALS : usize = 5;
+const MATCH_FIND_LIMIT : usize = 12;  // no match starts in the last 12 bytes
+const MAX_OFFSET : usize = 65535;
+const HASH_LOG : usize = 12;
+
+#[derive(Debug)]
+pub struct Lz4Codec;
+
+impl PageCodec for Lz4Codec {
+    fn compress(&self, page : &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut table = vec![0; 1 << HASH_LOG];  // last position of each hash plus one, 0 for none
+        let mut anchor = 0;  // start of the literals not written yet
+        let mut i = 0;
+        while i + MATCH_FIND_LIMIT < page.len() {
+            let seq = read_u32(page, i);
+            let h = hash(seq);
+            let candidate = table[h];
+            table[h] = i + 1;
+            if candidate == 0 || i + 1 - candidate > MAX_OFFSET || read_u32(page, candidate - 1) != seq {
+                i += 1;
+                continue;
+            }
+            let m = candidate - 1;
+            let mut len = MIN_MATCH;
+            while i + len < page.len() - LAST_LITERALS && page[m + len] == page[i + len] {
+                len += 1;
+            }
+            write_sequence(&mut data, &page[anchor..i], i - m, len);
+            i += len;
+            anchor = i;
+        }
+        write_last_literals(&mut data, &page[anchor..]);
+        data
+    }
+    fn decompress(&self, data : &[u8], page : &mut [u8]) -> Result<(), String> {
+        let mut i = 0;
+        let mut o = 0;
+        loop {
+            if i >= data.len() {
+                return Err("unexpected end of the compressed data".to_string());
+            }
+            let token = data[i];
+            i += 1;
+            let mut literal_len = (token >> 4) as usize;
+            if literal_len == 15 {
+                literal_len += try!(read_len(data, &mut i));
+            }
+            if i + literal_len > data.len() || o + literal_len > page.len() {
+                return Err("literals out of range".to_string());
+            }
+            page[o..o + literal_len].copy_from_slice(&data[i..i + literal_len]);
+            i += literal_len;
+            o += literal_len;
+            if i == data.len() {
+                break;  // the last sequence
+            }
+            if i + 2 > data.len() {
+                return Err("unexpected end of the compressed data".to_string());
+            }
+            let offset = data[i] as usize | (data[i + 1] as usize) << 8;
+            i += 2;
+            let mut match_len = (token & 15) as usize;
+            if match_len == 15 {
+                match_len += try!(read_len(data, &mut i));
+            }
+            match_len += MIN_MATCH;
+            if offset == 0 || offset > o || o + match_len > page.len() {
+                return Err("match out of range".to_string());
+            }
+            // the match may overlap the bytes it produces
+            for j in o..o + match_len {
+                page[j] = page[j - offset];
+            }
+            o += match_len;
+        }
+        if o != page.len() {
+            return Err(format!("decompressed to {} bytes, expected {}", o, page.len()));
+        }
+        Ok(())
+    }
+}
+
+fn read_u32(data : &[u8], i : usize) -> u32 {
+    data[i] as u32 | (data[i + 1] as u32) << 8 | (data[i + 2] as u32) << 16 | (data[i + 3] as u32) << 24
+}
+
+fn hash(seq : u32) -> usize {
+    (seq.wrapping_mul(2654435761) >> (32 - HASH_LOG)) as usize
+}
+
+fn write_len(data : &mut Vec<u8>, mut len : usize) {
+    while len >= 255 {
+        data.push(255);
+        len -= 255;
+    }
+    data.push(len as u8);
+}
+
+fn read_len(data : &[u8], i : &mut usize) -> Result<usize, String> {
+    let mut len = 0;
+    loop {
+        if *i >= data.len() {
+            return Err("unexpected end of the compressed data".to_string());
+        }
+        let n = data[*i] as usize;
+        *i += 1;
+        len += n;
+        if n != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+fn write_sequence(data : &mut Vec<u8>, literals : &[u8], offset : usize, match_len : usize) {
+    let match_len = match_len - MIN_MATCH;
+    data.push((min(literals.len(), 15) << 4 | min(match_len, 15)) as u8);
+    if literals.len() >= 15 {
+        write_len(data, literals.len() - 15);
+    }
+    data.extend_from_slice(literals);
+    data.push(offset as u8);
+    data.push((offset >> 8) as u8);
+    if match_len >= 15 {
+        write_len(data, match_len - 15);
+    }
+}
+
+fn write_last_literals(data : &mut Vec<u8>, literals : &[u8]) {
+    data.push((min(literals.len(), 15) << 4) as u8);
+    if literals.len() >= 15 {
+        write_len(data, literals.len() - 15);
+    }
+    data.extend_from_slice(literals);
+}
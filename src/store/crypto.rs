@@ -0,0 +1,271 @@
+use std::rc::Rc;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::Read;
+use std::hash::{BuildHasher, Hasher};
+use std::collections::hash_map::RandomState;
+
+
+// Encryption at rest with ChaCha20-Poly1305 (RFC 8439).
+// The sealed data is the nonce, the ciphertext and the tag. Every write uses a fresh random nonce,
+// and the associated data binds the data to its place, e.g. the file name and the page index of a page,
+// so a page moved to another place or another file fails the authentication like a wrong key.
+// The pages of table files and index files, the catalog and the temporary files of the executors
+// are encrypted, including the copies of the pages in the log,
+// while the file headers and the side files of tables are not.
+pub const KEY_SIZE : usize = 32;
+pub const NONCE_SIZE : usize = 12;
+pub const TAG_SIZE : usize = 16;
+
+pub type CipherRef = Rc<Cipher>;
+
+pub struct Cipher {
+    key : [u8; KEY_SIZE],
+    nonce_state : RandomState,  // randomly keyed by the os
+    nonce_counter : Cell<u64>,
+}
+
+impl Debug for Cipher {
+    fn fmt(&self, f : &mut Formatter) -> fmt::Result {
+        write!(f, "Cipher")  // never print the key
+    }
+}
+
+impl Cipher {
+    pub fn new(key : [u8; KEY_SIZE]) -> Cipher {
+        Cipher{
+            key : key,
+            nonce_state : RandomState::new(),
+            nonce_counter : Cell::new(0),
+        }
+    }
+    pub fn seal(&self, aad : &[u8], plaintext : &[u8]) -> Vec<u8> {
+        let nonce = self.gen_nonce();
+        let mut data = nonce.to_vec();
+        data.extend_from_slice(&chacha20_poly1305_seal(&self.key, &nonce, aad, plaintext));
+        data
+    }
+    pub fn open(&self, aad : &[u8], data : &[u8]) -> Result<Vec<u8>, String> {
+        if data.len() < NONCE_SIZE + TAG_SIZE {
+            return Err("sealed data too short".to_string());
+        }
+        let mut nonce = [0; NONCE_SIZE];
+        nonce.copy_from_slice(&data[..NONCE_SIZE]);
+        chacha20_poly1305_open(&self.key, &nonce, aad, &data[NONCE_SIZE..])
+    }
+    fn gen_nonce(&self) -> [u8; NONCE_SIZE] {
+        // siphash of a counter with a random key, 96 random bits are never reused in practice
+        let n = self.nonce_counter.get();
+        self.nonce_counter.set(n + 1);
+        let mut nonce = [0; NONCE_SIZE];
+        for (i, chunk) in nonce.chunks_mut(8).enumerate() {
+            let mut hasher = self.nonce_state.build_hasher();
+            hasher.write_u64(n);
+            hasher.write_usize(i);
+            let h = hasher.finish();
+            for (j, b) in chunk.iter_mut().enumerate() {
+                *b = (h >> (j * 8)) as u8;
+            }
+        }
+        nonce
+    }
+}
+
+pub fn load_key(path : &String) -> Result<[u8; KEY_SIZE], String> {
+    // the key file has the key in 64 hex digits, e.g. generated by `openssl rand -hex 32`
+    let mut content = String::new();
+    if let Err(err) = File::open(path).and_then(|mut f| f.read_to_string(&mut content)) {
+        return Err(format!("can't read key file {}: {}", path, err));
+    }
+    let digits = content.trim();
+    if digits.len() != KEY_SIZE * 2 || !digits.chars().all(|c| c.is_digit(16)) {
+        return Err(format!("key file {} should have {} hex digits", path, KEY_SIZE * 2));
+    }
+    let mut key = [0; KEY_SIZE];
+    for i in 0..KEY_SIZE {
+        key[i] = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    Ok(key)
+}
+
+pub fn chacha20_poly1305_seal(key : &[u8; KEY_SIZE], nonce : &[u8; NONCE_SIZE], aad : &[u8], plaintext : &[u8])
+        -> Vec<u8> {
+    // return the ciphertext followed by the tag
+    let mut data = plaintext.to_vec();
+    chacha20_xor(key, nonce, 1, &mut data);
+    let tag = gen_tag(key, nonce, aad, &data);
+    data.extend_from_slice(&tag);
+    data
+}
+
+pub fn chacha20_poly1305_open(key : &[u8; KEY_SIZE], nonce : &[u8; NONCE_SIZE], aad : &[u8], data : &[u8])
+        -> Result<Vec<u8>, String> {
+    if data.len() < TAG_SIZE {
+        return Err("sealed data too short".to_string());
+    }
+    let (ciphertext, tag) = data.split_at(data.len() - TAG_SIZE);
+    let expected = gen_tag(key, nonce, aad, ciphertext);
+    // compare in constant time
+    if expected.iter().zip(tag.iter()).fold(0, |d, (a, b)| d | (a ^ b)) != 0 {
+        return Err("authentication failed".to_string());
+    }
+    let mut plaintext = ciphertext.to_vec();
+    chacha20_xor(key, nonce, 1, &mut plaintext);
+    Ok(plaintext)
+}
+
+fn gen_tag(key : &[u8; KEY_SIZE], nonce : &[u8; NONCE_SIZE], aad : &[u8], ciphertext : &[u8]) -> [u8; TAG_SIZE] {
+    let block = chacha20_block(key, nonce, 0);
+    let mut mac_data = aad.to_vec();
+    let padding = (16 - aad.len() % 16) % 16;
+    mac_data.extend(vec![0; padding]);
+    mac_data.extend_from_slice(ciphertext);
+    let padding = (16 - ciphertext.len() % 16) % 16;
+    mac_data.extend(vec![0; padding]);
+    for len in [aad.len() as u64, ciphertext.len() as u64].iter() {
+        for i in 0..8 {
+            mac_data.push((len >> (i * 8)) as u8);
+        }
+    }
+    poly1305(&block[..32], &mac_data)
+}
+
+
+fn quarter_round(s : &mut [u32; 16], a : usize, b : usize, c : usize, d : usize) {
+    s[a] = s[a].wrapping_add(s[b]); s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]); s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]); s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]); s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+pub fn chacha20_block(key : &[u8; KEY_SIZE], nonce : &[u8; NONCE_SIZE], counter : u32) -> [u8; 64] {
+    let mut state = [0u32; 16];
+    state[0] = 0x61707865;  // "expand 32-byte k"
+    state[1] = 0x3320646e;
+    state[2] = 0x79622d32;
+    state[3] = 0x6b206574;
+    for i in 0..8 {
+        state[4 + i] = read_u32(key, i * 4);
+    }
+    state[12] = counter;
+    for i in 0..3 {
+        state[13 + i] = read_u32(nonce, i * 4);
+    }
+    let mut s = state;
+    for _ in 0..10 {
+        quarter_round(&mut s, 0, 4, 8, 12);
+        quarter_round(&mut s, 1, 5, 9, 13);
+        quarter_round(&mut s, 2, 6, 10, 14);
+        quarter_round(&mut s, 3, 7, 11, 15);
+        quarter_round(&mut s, 0, 5, 10, 15);
+        quarter_round(&mut s, 1, 6, 11, 12);
+        quarter_round(&mut s, 2, 7, 8, 13);
+        quarter_round(&mut s, 3, 4, 9, 14);
+    }
+    let mut block = [0; 64];
+    for i in 0..16 {
+        let n = s[i].wrapping_add(state[i]);
+        for j in 0..4 {
+            block[i * 4 + j] = (n >> (j * 8)) as u8;
+        }
+    }
+    block
+}
+
+fn chacha20_xor(key : &[u8; KEY_SIZE], nonce : &[u8; NONCE_SIZE], counter : u32, data : &mut [u8]) {
+    for (i, chunk) in data.chunks_mut(64).enumerate() {
+        let block = chacha20_block(key, nonce, counter.wrapping_add(i as u32));
+        for (b, k) in chunk.iter_mut().zip(block.iter()) {
+            *b ^= *k;
+        }
+    }
+}
+
+pub fn poly1305(key : &[u8], msg : &[u8]) -> [u8; TAG_SIZE] {
+    // 130-bit arithmetic in 26-bit limbs
+    const MASK : u32 = 0x3ffffff;
+    let r0 = read_u32(key, 0) & 0x3ffffff;
+    let r1 = (read_u32(key, 3) >> 2) & 0x3ffff03;
+    let r2 = (read_u32(key, 6) >> 4) & 0x3ffc0ff;
+    let r3 = (read_u32(key, 9) >> 6) & 0x3f03fff;
+    let r4 = (read_u32(key, 12) >> 8) & 0x00fffff;
+    let (s1, s2, s3, s4) = (r1 * 5, r2 * 5, r3 * 5, r4 * 5);
+    let mut h = [0u32; 5];
+    for chunk in msg.chunks(16) {
+        let mut block = [0u8; 17];
+        block[..chunk.len()].copy_from_slice(chunk);
+        block[chunk.len()] = 1;
+        h[0] += read_u32(&block, 0) & MASK;
+        h[1] += (read_u32(&block, 3) >> 2) & MASK;
+        h[2] += (read_u32(&block, 6) >> 4) & MASK;
+        h[3] += (read_u32(&block, 9) >> 6) & MASK;
+        h[4] += (read_u32(&block, 12) >> 8) | (block[16] as u32) << 24;
+
+        let m = |a : u32, b : u32| a as u64 * b as u64;
+        let d0 = m(h[0], r0) + m(h[1], s4) + m(h[2], s3) + m(h[3], s2) + m(h[4], s1);
+        let mut d1 = m(h[0], r1) + m(h[1], r0) + m(h[2], s4) + m(h[3], s3) + m(h[4], s2);
+        let mut d2 = m(h[0], r2) + m(h[1], r1) + m(h[2], r0) + m(h[3], s4) + m(h[4], s3);
+        let mut d3 = m(h[0], r3) + m(h[1], r2) + m(h[2], r1) + m(h[3], r0) + m(h[4], s4);
+        let mut d4 = m(h[0], r4) + m(h[1], r3) + m(h[2], r2) + m(h[3], r1) + m(h[4], r0);
+        h[0] = d0 as u32 & MASK;
+        d1 += d0 >> 26;
+        h[1] = d1 as u32 & MASK;
+        d2 += d1 >> 26;
+        h[2] = d2 as u32 & MASK;
+        d3 += d2 >> 26;
+        h[3] = d3 as u32 & MASK;
+        d4 += d3 >> 26;
+        h[4] = d4 as u32 & MASK;
+        h[0] += (d4 >> 26) as u32 * 5;
+        h[1] += h[0] >> 26;
+        h[0] &= MASK;
+    }
+    // fully carry h
+    for i in 1..5 {
+        h[i] += h[i - 1] >> 26;
+        h[i - 1] &= MASK;
+    }
+    h[0] += (h[4] >> 26) * 5;
+    h[4] &= MASK;
+    h[1] += h[0] >> 26;
+    h[0] &= MASK;
+    // h - p, used if h >= p
+    let mut g = [0u32; 5];
+    let mut carry = 5;
+    for i in 0..5 {
+        g[i] = h[i].wrapping_add(carry);
+        carry = g[i] >> 26;
+        g[i] &= MASK;
+    }
+    g[4] = g[4].wrapping_add(carry << 26).wrapping_sub(1 << 26);
+    let use_g = (g[4] >> 31).wrapping_sub(1);  // all ones if no borrow
+    for i in 0..5 {
+        h[i] = (h[i] & !use_g) | (g[i] & use_g);
+    }
+    // h % 2^128 + s
+    let words = [
+        h[0] | h[1] << 26,
+        h[1] >> 6 | h[2] << 20,
+        h[2] >> 12 | h[3] << 14,
+        h[3] >> 18 | h[4] << 8,
+    ];
+    let mut tag = [0; TAG_SIZE];
+    let mut f = 0u64;
+    for i in 0..4 {
+        f = words[i] as u64 + read_u32(key, 16 + i * 4) as u64 + (f >> 32);
+        for j in 0..4 {
+            tag[i * 4 + j] = (f >> (j * 8)) as u8;
+        }
+    }
+    tag
+}
+
+fn read_u32(buf : &[u8], offset : usize) -> u32 {
+    let mut n = 0;
+    for j in 0..4 {
+        n |= (buf[offset + j] as u32) << (j * 8);
+    }
+    n
+}
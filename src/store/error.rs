@@ -0,0 +1,31 @@
+use std::io;
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum StorageErrorType {
+    PageCorrupted,
+    PoolExhausted,
+    IoError,
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageError {
+    pub error_type : StorageErrorType,
+    pub table : String,
+    pub page_index : usize,
+    pub error_msg : String,
+}
+
+pub type StorageResult<T> = Result<T, StorageError>;
+
+impl StorageError {
+    pub fn from_io(table : &String, err : io::Error) -> StorageError {
+        // page_index is not used for the errors not about a page
+        StorageError{
+            error_type : StorageErrorType::IoError,
+            table : table.clone(),
+            page_index : 0,
+            error_msg : format!("io error on table {}: {}", table, err),
+        }
+    }
+}
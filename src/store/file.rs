@@ -1,127 +1,75 @@
+use std::io;
 use std::collections::HashMap;
-use std::mem::size_of;
-use std::ptr::{write, read, write_bytes, null_mut};
-use std::fs::{OpenOptions, File};
-use std::os::unix::io::AsRawFd;
+use std::cmp::min;
+use std::ptr::{null_mut, copy_nonoverlapping};
 use std::slice::{from_raw_parts, from_raw_parts_mut};
-use std::io::{Read, Write, Seek, SeekFrom};
 use std::rc::Rc;
 use std::cell::RefCell;
-use ::utils::libwrapper::get_page_size;
-use ::utils::pointer::{read_string, write_string, pointer_offset};
+use ::utils::libwrapper::{get_page_size, set_page_size};
+use ::utils::pointer::{pointer_offset, read_u16_le, write_u16_le, read_u32_le, write_u32_le};
 use ::utils::config::Config;
-use ::utils::file::{path_join, ensure_dir_exist, assert_file_exist};
-use ::parser::common::{ValueList, ValueType};
-use super::buffer::{DataPtr, PageRef, PagePool};
-use super::table::{TableRef, AttrType, IndexMap};
-use super::tuple::{TupleDesc, TupleValue, TupleData};
+use ::utils::file::path_join;
+use ::parser::common::ValueList;
+use super::buffer::{DataPtr, PageRef, PagePool, PageKey};
+use super::cache::DEFAULT_POLICY;
+use super::table::{TableRef, IndexMap, IndexDesc};
+use super::tuple::{TupleDesc, TupleValue, TupleData, gen_tuple_data, read_tuple_value, read_overflow_ref,
+    gen_value_list, encode_tuple, is_var_len, inline_attr_len, min_attr_len, null_bitmap_len};
+use super::index::{IndexFile, IndexFileRef, IndexStore, PRIMARY_INDEX};
+use super::btree::{self, IndexEntry, KeyRange};
+use super::wal::{Wal, LogRecord};
+use super::checksum::crc32;
+use super::header::{FileHeader, HEADER_SIZE, gen_table_fingerprint};
+use super::fsm::{FreeSpaceMap, EMPTY_PAGE};
+use super::error::{StorageError, StorageErrorType, StorageResult};
+use super::vfs::{VfsRef, VfsFileRef, DEFAULT_VFS, new_vfs};
+use super::prefetch::{Prefetcher, DEFAULT_READ_AHEAD_PAGES};
+use super::compress::{PageCodecRef, new_codec};
+use super::page_map::PageMap;
+use super::crypto::{Cipher, CipherRef, load_key};
 
 
+// Slotted page: header, slot directory growing forward and tuples growing backward from the page end.
+// Each slot is (offset, len) in u16, offset 0 means the slot is free.
+// Values that don't fit in a tuple are stored in a chain of overflow pages of the same file,
+// an overflow page starts with page type, next page and content length.
+// Both kinds of page keep a checksum of the whole page at the end of the header,
+// which is filled when the page is saved and verified when the page is loaded.
+const PAGE_TYPE_DATA : u32 = 0;
+const PAGE_TYPE_OVERFLOW : u32 = 1;
+const PAGE_HEADER_SIZE : usize = 5 * 4;
+const CHECKSUM_OFFSET : usize = 4 * 4;
+const SLOT_SIZE : usize = 2 * 2;
+const INVALID_PAGE : u32 = !0;
+
 #[derive(Debug)]
 pub struct PageHeader {
-    pub slot_sum : usize,
+    pub page_type : u32,  // other fields are only for data page
+    pub slot_num : usize,  // number of slots in the directory
     pub first_free_slot : usize,
+    pub data_start : usize,  // offset of the lowest tuple
+    pub checksum : u32,  // only valid in saved pages
     pub data : DataPtr,
 }
 
 impl PageHeader {
     pub fn save_to_page_data(&mut self) {
         unsafe{
-            write::<u32>(self.data as *mut u32, self.slot_sum as u32);
-            let next_data_ptr = pointer_offset(self.data, size_of::<u32>());
-            write::<u32>(next_data_ptr as *mut u32, self.first_free_slot as u32);
+            write_u32_le(self.data, self.page_type);
+            write_u32_le(pointer_offset(self.data, 4), self.slot_num as u32);
+            write_u32_le(pointer_offset(self.data, 8), self.first_free_slot as u32);
+            write_u32_le(pointer_offset(self.data, 12), self.data_start as u32);
+            write_u32_le(pointer_offset(self.data, CHECKSUM_OFFSET), self.checksum);
         }
     }
 
     pub fn init_from_page_data(&mut self) {
         unsafe{
-            let slot_sum = read::<u32>(self.data as *const u32) as usize;
-            assert_eq!(slot_sum, self.slot_sum);
-            let next_data_ptr = pointer_offset(self.data, size_of::<u32>());
-            self.first_free_slot = read::<u32>(next_data_ptr as *const u32) as usize;
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct BitMap {
-    pub data : DataPtr,
-    pub slot_sum : usize,
-}
-
-impl BitMap {
-    pub fn next_tuple_index(&self, from : usize) -> usize {
-        if from >= self.slot_sum {
-            return self.slot_sum;
-        }
-        let mut count = from / 8;
-        let mut bit_count = from % 8;
-        while count < (self.slot_sum + 7) / 8 {
-            let n = unsafe{ read::<u8>((self.data as *const u8).offset(count as isize)) };
-            let mut mask = 1 << bit_count;
-            if n < mask { count += 1; bit_count = 0; continue; }
-            loop {
-                // assume the bits whose index > slot_sum is 0
-                if mask & n > 0 {
-                    return count * 8 + bit_count;
-                }
-                mask *= 2;
-                bit_count += 1;
-            }
-        }
-        self.slot_sum
-    }
-    pub fn get_first_free_slot(&self) -> usize {
-        let mut count = 0;
-        while count < (self.slot_sum + 7) / 8 {
-            let n = unsafe{ read::<u8>((self.data as *const u8).offset(count as isize)) };
-            if n == 255 { count += 1; continue; }
-            let mut mask : u8 = 1;
-            let mut bit_count = 0;
-            loop {
-                // assume the bits whose index > slot_sum is 0
-                if (255 - mask) | n < 255 {
-                    return count * 8 + bit_count;
-                }
-                mask *= 2;
-                bit_count += 1;
-            }
-        }
-        self.slot_sum
-    }
-    pub fn get_byte_size(&self) -> usize {
-        (self.slot_sum + 7) / 8
-    }
-    pub fn clean(&mut self) {
-        unsafe{
-            write_bytes(self.data, 0, self.get_byte_size());
-        }
-    }
-    pub fn set_inuse(&mut self, index : usize, inuse : bool) {
-        assert!(index < self.slot_sum);
-        let byte_offset = index / 8;
-        let bit_offset = index % 8;
-        let n = 1 << bit_offset;
-        unsafe{
-            let p = pointer_offset(self.data, byte_offset);
-            let a = read::<u8>(p as *const u8);
-            let b = if inuse {
-                a | n
-            } else {
-                a & (255 - n)
-            };
-            write::<u8>(p as *mut u8, b);
-        }
-    }
-    pub fn is_inuse(&self, index : usize) -> bool {
-        assert!(index < self.slot_sum);
-        let byte_offset = index / 8;
-        let bit_offset = index % 8;
-        let n = 1 << bit_offset;
-        unsafe{
-            let p = pointer_offset(self.data, byte_offset);
-            let a = read::<u8>(p as *const u8);
-            (a & n) > 0
+            self.page_type = read_u32_le(self.data);
+            self.slot_num = read_u32_le(pointer_offset(self.data, 4)) as usize;
+            self.first_free_slot = read_u32_le(pointer_offset(self.data, 8)) as usize;
+            self.data_start = read_u32_le(pointer_offset(self.data, 12)) as usize;
+            self.checksum = read_u32_le(pointer_offset(self.data, CHECKSUM_OFFSET));
         }
     }
 }
@@ -129,250 +77,546 @@ impl BitMap {
 #[derive(Debug)]
 pub struct FilePage {
     pub header : PageHeader,
-    pub bitmap : BitMap,
-    pub tuple_data : DataPtr,
+    pub slot_sum : usize,  // max slot number, for computing position
     pub mem_page : PageRef,
-    pub tuple_len : usize,
+    pub min_tuple_len : usize,
 }
 
 impl FilePage {
-    pub fn new(mem_page : PageRef, tuple_len : usize) -> FilePage {
+    pub fn new(mem_page : PageRef, min_tuple_len : usize) -> FilePage {
+        assert!(get_page_size() <= 1 << 16);  // offset in slot is u16
         let data = mem_page.borrow_mut().data;
-        let header_size = 2 * size_of::<u32>();  // PageHeader
-        let slot_sum = get_slot_sum(tuple_len);
-        let bitmap_data = unsafe{ data.offset(header_size as isize) };
-        let bitmap_size = (slot_sum + 7) / 8;
-        let tuple_data = unsafe{ bitmap_data.offset(bitmap_size as isize) };
         FilePage{
             header : PageHeader{
-                slot_sum : slot_sum,
+                page_type : PAGE_TYPE_DATA,
+                slot_num : 0,
                 first_free_slot : 0,
+                data_start : get_page_size(),
+                checksum : 0,
                 data : data,
             },
-            bitmap : BitMap{
-                data : bitmap_data,
-                slot_sum : slot_sum,
-            },
-            tuple_data : tuple_data,
+            slot_sum : get_slot_sum(min_tuple_len),
             mem_page : mem_page,
-            tuple_len : tuple_len,
+            min_tuple_len : min_tuple_len,
         }
     }
     pub fn init_empty_page(&mut self) {
-        self.header.save_to_page_data();
-        self.bitmap.clean();
+        self.header.page_type = PAGE_TYPE_DATA;
+        self.header.slot_num = 0;
+        self.header.first_free_slot = 0;
+        self.header.data_start = get_page_size();
+        self.save_to_page();
     }
     pub fn init_from_page_data(&mut self) {
         self.header.init_from_page_data();
     }
     pub fn save_to_page(&mut self) {
         self.header.save_to_page_data();
+        self.mark_dirty();
     }
-    pub fn is_inuse(&self, index : usize) -> bool {
-        self.bitmap.is_inuse(index)
+    fn mark_dirty(&mut self) {
+        self.mem_page.borrow_mut().mark_dirty();
     }
-    pub fn set_inuse(&mut self, index : usize, inuse : bool) {
-        self.bitmap.set_inuse(index, inuse);
+    pub fn is_dirty(&self) -> bool {
+        self.mem_page.borrow().dirty
     }
-    pub fn insert(&mut self, value_list : &ValueList, tuple_desc : &TupleDesc) {
-        assert!(!self.is_inuse(self.header.first_free_slot));
-        assert_eq!(value_list.len(), tuple_desc.attr_desc.len());
-        assert!(self.header.first_free_slot < self.bitmap.slot_sum);
-
-        let first_free_slot = self.header.first_free_slot;
-        self.set_inuse(first_free_slot, true);
-        self.header.first_free_slot = self.bitmap.get_first_free_slot();
+    pub fn is_overflow(&self) -> bool {
+        self.header.page_type == PAGE_TYPE_OVERFLOW
+    }
+    fn get_slot(&self, slot : usize) -> (usize, usize) {
+        assert!(slot < self.header.slot_num);
+        let p = pointer_offset(self.header.data, PAGE_HEADER_SIZE + slot * SLOT_SIZE);
+        unsafe{(
+            read_u16_le(p) as usize,
+            read_u16_le(pointer_offset(p, 2)) as usize,
+        )}
+    }
+    fn set_slot(&mut self, slot : usize, offset : usize, len : usize) {
+        assert!(slot < self.header.slot_num);
+        let p = pointer_offset(self.header.data, PAGE_HEADER_SIZE + slot * SLOT_SIZE);
+        unsafe{
+            write_u16_le(p, offset as u16);
+            write_u16_le(pointer_offset(p, 2), len as u16);
+        }
+    }
+    pub fn is_inuse(&self, slot : usize) -> bool {
+        !self.is_overflow() && slot < self.header.slot_num && self.get_slot(slot).0 != 0
+    }
+    pub fn get_free_space(&self) -> usize {
+        // including the fragments between tuples
+        if self.is_overflow() {
+            return 0;
+        }
+        let used : usize = (0..self.header.slot_num).map(|i| self.get_slot(i).1).sum();
+        get_page_size() - PAGE_HEADER_SIZE - self.header.slot_num * SLOT_SIZE - used
+    }
+    pub fn can_insert(&self, len : usize) -> bool {
+        !self.is_overflow() && self.header.first_free_slot < self.slot_sum && self.get_insertable_len() >= len
+    }
+    pub fn get_insertable_len(&self) -> usize {
+        // max length of a new tuple
+        if self.is_overflow() || self.header.first_free_slot >= self.slot_sum {
+            return 0;
+        }
+        let new_slot_size = if self.header.first_free_slot == self.header.slot_num { SLOT_SIZE } else { 0 };
+        let free_space = self.get_free_space();
+        if free_space < new_slot_size { 0 } else { free_space - new_slot_size }
+    }
+    pub fn is_empty(&self) -> bool {
+        !self.is_overflow() && self.header.slot_num == 0
+    }
+    pub fn is_full(&self) -> bool {
+        !self.can_insert(self.min_tuple_len)
+    }
+    pub fn insert(&mut self, tuple : &[u8]) -> usize {
+        // return the slot of the new tuple
+        assert!(self.can_insert(tuple.len()));
+        let slot = self.header.first_free_slot;
+        if slot == self.header.slot_num {
+            self.header.slot_num += 1;
+            self.set_slot(slot, 0, 0);
+        }
+        let offset = self.alloc(tuple.len());
+        self.write_tuple(slot, offset, tuple);
+        self.header.first_free_slot = (slot + 1..self.header.slot_num)
+            .find(|i| self.get_slot(*i).0 == 0).unwrap_or(self.header.slot_num);
         self.save_to_page();
-
-        let mut p = unsafe{
-            self.tuple_data.offset(
-                (tuple_desc.tuple_len * first_free_slot) as isize
-                )
-        };
-        for (v, d) in value_list.iter().zip(&tuple_desc.attr_desc) {
-            match (v.value_type, d) {
-                (ValueType::Integer, &AttrType::Int) => {
-                    let n : i32 = v.value.parse::<i32>().unwrap();
-                    unsafe{ write::<i32>(p as *mut i32, n) };
-                    p = pointer_offset(p, 4);
-                }
-                (ValueType::Float, &AttrType::Float) | (ValueType::Integer, &AttrType::Float) => {
-                    let n : f32 = v.value.parse::<f32>().unwrap();
-                    unsafe{ write::<f32>(p as *mut f32, n) };
-                    p = pointer_offset(p, 4);
-                }
-                (ValueType::String, &AttrType::Char{len}) => {
-                    let aligned_len = (len + 3) / 4 * 4;
-                    unsafe{ write_string(p, &v.value, len) };
-                    p = pointer_offset(p, aligned_len);
-                }
-                (ValueType::Null, &AttrType::Int) | (ValueType::Null, &AttrType::Float) => {
-                    unsafe{ write_bytes(p, 0, 4) };
-                    p = pointer_offset(p, 4);
-                }
-                (ValueType::Null, &AttrType::Char{len}) => {
-                    let aligned_len = (len + 3) / 4 * 4;
-                    unsafe{ write_bytes(p, 0, aligned_len) };
-                    p = pointer_offset(p, aligned_len);
-                }
-                _ => panic!("invalid value, expected {:?}, found {:?}", d, v),
+        slot
+    }
+    pub fn update(&mut self, slot : usize, tuple : &[u8]) -> bool {
+        // the tuple stays in the same slot, return false if the page has no enough space
+        assert!(self.is_inuse(slot));
+        let (offset, len) = self.get_slot(slot);
+        if tuple.len() <= len {
+            self.write_tuple(slot, offset, tuple);
+            self.mark_dirty();
+            return true;
+        }
+        if self.get_free_space() + len < tuple.len() {
+            return false;
+        }
+        self.set_slot(slot, 0, 0);
+        let offset = self.alloc(tuple.len());
+        self.write_tuple(slot, offset, tuple);
+        self.save_to_page();
+        true
+    }
+    fn write_tuple(&mut self, slot : usize, offset : usize, tuple : &[u8]) {
+        let p = pointer_offset(self.header.data, offset);
+        unsafe{ copy_nonoverlapping(tuple.as_ptr(), p as *mut u8, tuple.len()) };
+        self.set_slot(slot, offset, tuple.len());
+    }
+    fn alloc(&mut self, len : usize) -> usize {
+        // return the offset of the allocated space, compact the page if the free space is fragmented
+        let directory_end = PAGE_HEADER_SIZE + self.header.slot_num * SLOT_SIZE;
+        if self.header.data_start < directory_end + len {
+            self.compact();
+        }
+        assert!(self.header.data_start >= directory_end + len);
+        self.header.data_start -= len;
+        self.header.data_start
+    }
+    pub fn compact(&mut self) {
+        // move all the tuples to the end of page, pointers to the tuples in this page become invalid
+        let mut tuple_list = Vec::new();
+        for slot in 0..self.header.slot_num {
+            if self.get_slot(slot).0 != 0 {
+                tuple_list.push((slot, self.get_tuple_bytes(slot)));
+                self.set_slot(slot, 0, 0);
             }
         }
+        self.header.data_start = get_page_size();
+        for (slot, tuple) in tuple_list {
+            self.header.data_start -= tuple.len();
+            let offset = self.header.data_start;
+            self.write_tuple(slot, offset, &tuple);
+        }
+        self.save_to_page();
+    }
+    pub fn get_tuple_ptr(&self, slot : usize) -> DataPtr {
+        assert!(self.is_inuse(slot));
+        pointer_offset(self.header.data, self.get_slot(slot).0)
+    }
+    pub fn get_tuple_bytes(&self, slot : usize) -> Vec<u8> {
+        let (offset, len) = self.get_slot(slot);
+        let p = pointer_offset(self.header.data, offset);
+        unsafe{ from_raw_parts::<u8>(p as *const u8, len) }.to_vec()
     }
-    pub fn get_tuple_value(&self, tuple_index : usize,
+    pub fn get_tuple_value(&self, slot : usize,
             attr_position : usize,
             tuple_desc : &TupleDesc) -> TupleValue {
-        assert!(self.is_inuse(tuple_index));
-        let mut p = pointer_offset(self.tuple_data, tuple_index * tuple_desc.tuple_len);
-        p = Self::attr_offset(p, tuple_desc, attr_position);
-        unsafe{
-            match tuple_desc.attr_desc[attr_position] {
-                AttrType::Int => TupleValue::Int(read::<i32>(p as *const i32)),
-                AttrType::Float => TupleValue::Float(read::<f32>(p as *const f32)),
-                AttrType::Char{len} => TupleValue::Char(read_string(p, len)),
-            }
-        }
+        // the attribute must be stored inline
+        let tuple_data = gen_tuple_data(self.get_tuple_ptr(slot), &tuple_desc.attr_desc);
+        read_tuple_value(tuple_data[attr_position], &tuple_desc.attr_desc[attr_position])
     }
-    pub fn get_tuple_data(&self, tuple_index : usize, tuple_desc : &TupleDesc) -> Option<TupleData> {
-        if tuple_index >= self.bitmap.slot_sum {
+    pub fn get_tuple_data(&self, slot : usize, tuple_desc : &TupleDesc) -> Option<TupleData> {
+        if !self.is_inuse(slot) {
             return None;
         }
-        assert!(self.is_inuse(tuple_index));
-        let mut tuple_data = Vec::new();
-        let data = pointer_offset(self.tuple_data, tuple_index * tuple_desc.tuple_len);
-        let mut p = data;
-        for i in 1..tuple_desc.attr_desc.len() + 1 {
-            tuple_data.push(p);
-            p = Self::attr_offset(data, tuple_desc, i);
-        }
-        Some(tuple_data)
+        Some(gen_tuple_data(self.get_tuple_ptr(slot), &tuple_desc.attr_desc))
     }
-    pub fn attr_offset(p : DataPtr, tuple_desc : &TupleDesc, attr_position : usize) -> DataPtr {
-        let mut offset = 0;
-        for (attr_type, _) in tuple_desc.attr_desc.iter().zip(0..attr_position) {
-            match attr_type {
-                &AttrType::Int | &AttrType::Float => offset += 4,
-                &AttrType::Char{len} => offset += (len + 3) / 4 * 4,
-            }
+    pub fn next_tuple_index(&self, from : usize) -> Option<usize> {
+        if self.is_overflow() {
+            return None;
         }
-        pointer_offset(p, offset)
+        (from..self.header.slot_num).find(|i| self.get_slot(*i).0 != 0)
     }
-    pub fn is_full(&self) -> bool {
-        self.header.first_free_slot == self.bitmap.slot_sum
+    pub fn delete(&mut self, slot : usize) {
+        assert!(self.is_inuse(slot));
+        let (offset, len) = self.get_slot(slot);
+        if offset == self.header.data_start {
+            self.header.data_start += len;
+        }
+        self.set_slot(slot, 0, 0);
+        if slot < self.header.first_free_slot {
+            self.header.first_free_slot = slot;
+        }
+        while self.header.slot_num > 0 && self.get_slot(self.header.slot_num - 1).0 == 0 {
+            self.header.slot_num -= 1;
+        }
+        if self.header.first_free_slot > self.header.slot_num {
+            self.header.first_free_slot = self.header.slot_num;
+        }
+        self.save_to_page();
     }
-    pub fn is_in_page(&self, ptr : DataPtr) -> bool {
-        let page_start = self.mem_page.borrow().data;
-        let page_end = pointer_offset(page_start, get_page_size());
-        page_start <= ptr && ptr < page_end
+    pub fn init_overflow_page(&mut self, next : Option<usize>, content : &[u8]) {
+        assert!(content.len() <= get_overflow_content_size());
+        self.header.page_type = PAGE_TYPE_OVERFLOW;
+        let data = self.header.data;
+        unsafe{
+            write_u32_le(data, PAGE_TYPE_OVERFLOW);
+            write_u32_le(pointer_offset(data, 4), next.map(|n| n as u32).unwrap_or(INVALID_PAGE));
+            write_u32_le(pointer_offset(data, 8), content.len() as u32);
+            let p = pointer_offset(data, PAGE_HEADER_SIZE);
+            copy_nonoverlapping(content.as_ptr(), p as *mut u8, content.len());
+        }
+        self.mark_dirty();
     }
-    pub fn delete(&mut self, ptr : DataPtr) {
-        let d = ptr as usize - self.tuple_data as usize;
-        let index = d / self.tuple_len;
-        assert!(self.is_inuse(index));
-        self.set_inuse(index, false);
+    pub fn read_overflow_page(&self) -> (Option<usize>, Vec<u8>) {
+        // return the next overflow page and the content
+        assert!(self.is_overflow());
+        let data = self.header.data;
+        let (next, len) = unsafe{(
+            read_u32_le(pointer_offset(data, 4)),
+            read_u32_le(pointer_offset(data, 8)) as usize,
+        )};
+        let p = pointer_offset(data, PAGE_HEADER_SIZE);
+        let content = unsafe{ from_raw_parts::<u8>(p as *const u8, len) }.to_vec();
+        (if next == INVALID_PAGE { None } else { Some(next as usize) }, content)
     }
 }
 
 
+// Address of a tuple in the table file, it stays valid until the tuple is deleted or the table is vacuumed.
+// A position is the same address flattened by the slot number of a page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecordId {
+    pub page_index : usize,
+    pub slot : usize,
+}
+
+
 pub type TableFileRef = Rc<RefCell<TableFile>>;
 
 #[derive(Debug)]
 pub struct TableFile {
     pub saved_name : String,
-    pub file : File,
+    pub file : VfsFileRef,
     pub loaded_pages : HashMap<usize, FilePage>,
     pub page_sum : usize,  // including pages not loaded in memory
+    pub header_changed : bool,  // page_sum changed since the header was logged last time
     pub table : TableRef,
-    pub first_free_page : usize,
+    pub fsm : FreeSpaceMap,
+    pub page_map : Option<PageMap>,  // set if the table is compressed or encrypted
+    codec : Option<PageCodecRef>,
+    cipher : Option<CipherRef>,
+    sealed_name : String,  // table name and file kind in the associated data of the sealed pages
     pub tuple_desc : TupleDesc,  // for FilePage
+    fingerprint : u32,
+    materialized : Option<(usize, Vec<u8>)>,  // position and the last tuple read with overflow values
 }
 
 impl TableFile {
-    pub fn new(mut name : String, table : TableRef, dir : &String) -> TableFile {
+    pub fn new(mut name : String, table : TableRef, dir : &String, vfs : &VfsRef, cipher : Option<CipherRef>)
+            -> TableFile {
+        let fsm = FreeSpaceMap::new(&name, dir, vfs);
+        let codec = table.borrow().compression.as_ref().and_then(|c| new_codec(c));
+        // the stored pages are not of page size
+        let page_map = if codec.is_some() || cipher.is_some() { Some(PageMap::new(&name, dir, vfs)) } else { None };
         name.push_str(".table");
+        let sealed_name = name.clone();
         name = path_join(dir, &name);
-        let file = OpenOptions::new().read(true).write(true).create(true).open(&name).unwrap();
+        let file = vfs.open(&name).unwrap();
         let tuple_desc = table.borrow().gen_tuple_desc();
+        let fingerprint = gen_table_fingerprint(&table.borrow());
         TableFile{
             saved_name : name,
             file : file,
             loaded_pages : HashMap::new(),
             page_sum : 0,
+            header_changed : true,
             table : table,
-            first_free_page : 0,
+            fsm : fsm,
+            page_map : page_map,
+            codec : codec,
+            cipher : cipher,
+            sealed_name : sealed_name,
             tuple_desc : tuple_desc,
+            fingerprint : fingerprint,
+            materialized : None,
+        }
+    }
+    pub fn init_from_file(&mut self) -> Result<(), String> {
+        let mut buf = vec![0; HEADER_SIZE];
+        // the part beyond a short file is left zero
+        try!(self.file.read_at(0, &mut buf).map_err(|err| format!("can't read the header: {}", err)));
+        let header = try!(FileHeader::decode(&buf));
+        try!(header.check(self.fingerprint));
+        self.page_sum = header.page_sum as usize;
+        self.header_changed = false;
+        self.fsm.init_from_file(self.page_sum);
+        if let Some(ref mut page_map) = self.page_map {
+            page_map.init_from_file(self.page_sum);
+        }
+        Ok(())
+    }
+    pub fn seal_for_table(&mut self, table : &str) {
+        // the files of a rewritten table are renamed after it's built,
+        // so the pages are sealed for the name the file will take
+        self.sealed_name = format!("{}.table", table);
+    }
+    fn gen_page_aad(&self, page_index : usize) -> Vec<u8> {
+        format!("{}:{}", self.sealed_name, page_index).into_bytes()
+    }
+    pub fn get_path_list(&self) -> Vec<String> {
+        // the table file and its side files
+        let mut path_list = vec![self.saved_name.clone(), self.fsm.saved_name.clone()];
+        if let Some(ref page_map) = self.page_map {
+            path_list.push(page_map.saved_name.clone());
         }
+        path_list
     }
-    pub fn init_from_file(&mut self) {
-        is_match!(self.file.seek(SeekFrom::Start(0)), Ok(..));
-        let mut header = [0 as u32, 0 as u32];
-        is_match!(self.file.read_exact(unsafe{
-            from_raw_parts_mut::<u8>((&mut header).as_ptr() as *mut u8, 8)
-        }), Ok(..));
-        self.page_sum = header[0] as usize;
-        self.first_free_page = header[1] as usize;
+    pub fn get_page_location(&self, page_index : usize) -> (u64, usize) {
+        // offset and length of the page in the file
+        match self.page_map {
+            Some(ref page_map) => page_map.get(page_index).map_or((0, 0), |l| (l.offset, l.len)),
+            None => ((get_page_size() * (page_index + 1)) as u64, get_page_size()),  // the first page is the header
+        }
+    }
+    pub fn read_page_from_file(&mut self, data : DataPtr, page_index : usize) -> StorageResult<()> {
+        assert!(page_index < self.page_sum);
+        let (offset, len) = self.get_page_location(page_index);
+        let mut buf = vec![0; len];
+        match self.file.read_at(offset, &mut buf) {
+            Ok(n) => buf.truncate(n),
+            Err(err) => {
+                let table = self.table.borrow().name.clone();
+                return Err(StorageError{
+                    error_type : StorageErrorType::IoError,
+                    error_msg : format!("can't read page {} of table {}: {}", page_index, table, err),
+                    table : table,
+                    page_index : page_index,
+                });
+            }
+        }
+        self.read_page_from_buffer(data, page_index, &buf)
     }
-    pub fn read_page_from_file(&mut self, data : DataPtr, page_index : usize) {
+    pub fn read_page_from_buffer(&mut self, data : DataPtr, page_index : usize, buf : &[u8]) -> StorageResult<()> {
+        // buf is the page stored in the file, read by read_page_from_file or read ahead
         assert!(page_index < self.page_sum);
-        let page_size = get_page_size();
-        let offset = page_size * (page_index + 1);
-        is_match!(self.file.seek(SeekFrom::Start(offset as u64)), Ok(..));
-        is_match!(self.file.read_exact(unsafe{
-            from_raw_parts_mut::<u8>(data as *mut u8, page_size)
-        }), Ok(..));
+        let decrypted;
+        let buf = match self.cipher {
+            Some(ref cipher) => match cipher.open(&self.gen_page_aad(page_index), buf) {
+                Ok(data) => {
+                    decrypted = data;
+                    &decrypted[..]
+                }
+                Err(err) => {
+                    let table = self.table.borrow().name.clone();
+                    return Err(StorageError{
+                        error_type : StorageErrorType::PageCorrupted,
+                        error_msg : format!("can't decrypt page {} of table {}: {}", page_index, table, err),
+                        table : table,
+                        page_index : page_index,
+                    });
+                }
+            },
+            None => buf,
+        };
+        let page = unsafe{ from_raw_parts_mut::<u8>(data as *mut u8, get_page_size()) };
+        match self.codec {
+            Some(ref codec) if buf.len() != page.len() => {
+                if let Err(err) = codec.decompress(buf, page) {
+                    let table = self.table.borrow().name.clone();
+                    return Err(StorageError{
+                        error_type : StorageErrorType::PageCorrupted,
+                        error_msg : format!("can't decompress page {} of table {}: {}", page_index, table, err),
+                        table : table,
+                        page_index : page_index,
+                    });
+                }
+            }
+            _ => {
+                // a truncated page fails the checksum
+                let len = min(buf.len(), page.len());
+                page[..len].copy_from_slice(&buf[..len]);
+                for b in page[len..].iter_mut() {
+                    *b = 0;
+                }
+            }
+        }
+        self.check_page(data, page_index)
+    }
+    fn check_page(&self, data : DataPtr, page_index : usize) -> StorageResult<()> {
+        let page = unsafe{ from_raw_parts::<u8>(data as *const u8, get_page_size()) };
+        let saved_checksum = unsafe{ read_u32_le(pointer_offset(data, CHECKSUM_OFFSET)) };
+        if saved_checksum != gen_page_checksum(page) {
+            let table = self.table.borrow().name.clone();
+            return Err(StorageError{
+                error_type : StorageErrorType::PageCorrupted,
+                error_msg : format!("checksum mismatch in page {} of table {}", page_index, table),
+                table : table,
+                page_index : page_index,
+            });
+        }
+        Ok(())
     }
     pub fn get_page_slot_sum(&self) -> usize {
-        get_slot_sum(self.tuple_desc.tuple_len)
+        get_slot_sum(self.tuple_desc.min_tuple_len)
     }
-    pub fn save_to_file(&mut self) {
+    pub fn save_to_file(&mut self) -> io::Result<()> {
         // the first page only save header for alignment
-        is_match!(self.file.seek(SeekFrom::Start(0)), Ok(..));
-        let header = [self.page_sum as u32, self.first_free_page as u32];
-        is_match!(self.file.write_all(unsafe{
-            from_raw_parts::<u8>((&header).as_ptr() as *const u8, 8)
-        }), Ok(..));
-        let index_list : Vec<_> = self.loaded_pages.iter().map(|(i, _)| *i).collect();
+        let header = self.gen_header();
+        try!(self.file.write_at(0, &header));
+        // clean pages are the same as the file
+        let index_list : Vec<_> = self.loaded_pages.iter()
+            .filter(|&(_, page)| page.is_dirty()).map(|(i, _)| *i).collect();
         for i in index_list.iter() {
-            self.save_page(*i);
+            try!(self.save_page(*i));
+        }
+        try!(self.fsm.save_to_file());
+        if let Some(ref mut page_map) = self.page_map {
+            try!(page_map.save_to_file());
         }
+        Ok(())
     }
-    pub fn save_page(&mut self, page_index : usize) {
-        // the first page only save header for alignment
-        let page_size = get_page_size();
-        let offset = page_size * (page_index + 1);
-        let page = self.loaded_pages.get(&page_index).unwrap();
-        is_match!(self.file.seek(SeekFrom::Start(offset as u64)), Ok(..));
-        is_match!(self.file.write_all(unsafe{
-            from_raw_parts::<u8>(page.mem_page.borrow().data as *const u8, page_size)
-        }), Ok(..));
+    pub fn sync(&mut self) -> io::Result<()> {
+        // fsync the table file and its side files
+        try!(self.file.fsync());
+        try!(self.fsm.file.fsync());
+        if let Some(ref mut page_map) = self.page_map {
+            try!(page_map.file.fsync());
+        }
+        Ok(())
+    }
+    fn gen_header(&self) -> Vec<u8> {
+        FileHeader::new(self.fingerprint, self.page_sum, 0).encode()
+    }
+    pub fn gen_header_record(&self) -> LogRecord {
+        LogRecord::Write{ path : self.saved_name.clone(), offset : 0, data : self.gen_header() }
     }
-    pub fn delete(&mut self, ptr : DataPtr) {
-        for (_, page) in &mut self.loaded_pages {
-            if page.is_in_page(ptr) {
-                page.delete(ptr);
-                return;
+    pub fn gen_page_record(&mut self, page_index : usize) -> LogRecord {
+        let (offset, data) = self.place_page(page_index);
+        LogRecord::Write{ path : self.saved_name.clone(), offset : offset, data : data }
+    }
+    pub fn gen_page_map_record(&self) -> Option<LogRecord> {
+        // None if the page map is not changed since it was logged last time
+        match self.page_map {
+            Some(ref page_map) if page_map.changed => Some(page_map.gen_record()),
+            _ => None,
+        }
+    }
+    pub fn save_page(&mut self, page_index : usize) -> io::Result<()> {
+        let (offset, data) = self.place_page(page_index);
+        try!(self.file.write_at(offset, &data));
+        self.loaded_pages.get(&page_index).unwrap().mem_page.borrow_mut().dirty = false;
+        Ok(())
+    }
+    fn place_page(&mut self, page_index : usize) -> (u64, Vec<u8>) {
+        // return the offset and the data to write, compressed or encrypted pages may be moved
+        let data = self.gen_page_data(page_index);
+        if self.page_map.is_none() {
+            // the first page only save header for alignment
+            return ((get_page_size() * (page_index + 1)) as u64, data);
+        }
+        let data = match self.codec {
+            Some(ref codec) => {
+                let compressed = codec.compress(&data);
+                if compressed.len() < data.len() { compressed } else { data }
             }
+            None => data,
+        };
+        let data = match self.cipher {
+            Some(ref cipher) => cipher.seal(&self.gen_page_aad(page_index), &data),
+            None => data,
+        };
+        let offset = self.page_map.as_mut().unwrap().place(page_index, data.len());
+        (offset, data)
+    }
+    fn gen_page_data(&self, page_index : usize) -> Vec<u8> {
+        // copy of the page with the checksum filled
+        let page = self.loaded_pages.get(&page_index).unwrap();
+        let mut data = unsafe{
+            from_raw_parts::<u8>(page.mem_page.borrow().data as *const u8, get_page_size())
+        }.to_vec();
+        let page_checksum = gen_page_checksum(&data);
+        unsafe{ write_u32_le(pointer_offset(data.as_mut_ptr() as DataPtr, CHECKSUM_OFFSET), page_checksum) };
+        data
+    }
+    pub fn delete(&mut self, position : usize) {
+        let page_index = position / self.get_page_slot_sum();
+        let slot = position % self.get_page_slot_sum();
+        self.loaded_pages.get_mut(&page_index).unwrap().delete(slot);
+        self.update_free_space(page_index);
+        let is_materialized = match self.materialized {
+            Some((p, _)) => p == position,
+            None => false,
+        };
+        if is_materialized {
+            self.materialized = None;
         }
     }
-    pub fn insert(&mut self, value_list : &ValueList) {
-        // must call add_page first if need_new_page() is true
-        let first_free_page = self.first_free_page;
-        self.insert_in_page(first_free_page, value_list)
+    pub fn get_position(&self, record_id : &RecordId) -> usize {
+        assert!(record_id.page_index < self.page_sum);
+        record_id.page_index * self.get_page_slot_sum() + record_id.slot
     }
-    pub fn insert_in_page(&mut self, page_index : usize, value_list : &ValueList) {
-        // for test
+    pub fn get_record_id(&self, position : usize) -> RecordId {
+        RecordId{
+            page_index : position / self.get_page_slot_sum(),
+            slot : position % self.get_page_slot_sum(),
+        }
+    }
+    pub fn insert_in_page(&mut self, page_index : usize, tuple : &[u8]) -> usize {
+        // return the position of the new tuple
         assert!(page_index < self.page_sum);
+        let slot_sum = self.get_page_slot_sum();
         let file_page = self.loaded_pages.get_mut(&page_index).unwrap();
-        assert!(!file_page.is_full());
-        file_page.insert(value_list, &self.tuple_desc)
+        let slot = file_page.insert(tuple);
+        self.update_free_space(page_index);
+        page_index * slot_sum + slot
     }
-    pub fn get_tuple_value(&self, position : usize, attr_position : usize) -> TupleValue {
-        // only for test
+    pub fn update(&mut self, position : usize, tuple : &[u8]) -> bool {
         let page_index = position / self.get_page_slot_sum();
         let tuple_index = position % self.get_page_slot_sum();
-        assert!(self.loaded_pages.get(&page_index).is_some());
+        self.materialized = None;
+        let updated = self.loaded_pages.get_mut(&page_index).unwrap().update(tuple_index, tuple);
+        self.update_free_space(page_index);
+        updated
+    }
+    pub fn update_free_space(&mut self, page_index : usize) {
+        let page = self.loaded_pages.get(&page_index).unwrap();
+        self.fsm.update(page_index, page.get_insertable_len(), page.is_empty());
+    }
+    pub fn get_tuple_bytes(&self, position : usize) -> Vec<u8> {
+        let page_index = position / self.get_page_slot_sum();
+        let tuple_index = position % self.get_page_slot_sum();
+        self.loaded_pages.get(&page_index).unwrap().get_tuple_bytes(tuple_index)
+    }
+    pub fn get_max_update_len(&self, position : usize) -> usize {
+        // max length of the tuple that can be updated in place
+        let page_index = position / self.get_page_slot_sum();
         let page = self.loaded_pages.get(&page_index).unwrap();
-        page.get_tuple_value(tuple_index, attr_position, &self.tuple_desc)
+        page.get_free_space() + self.get_tuple_bytes(position).len()
     }
     pub fn get_tuple_data(&self, position : usize) -> Option<TupleData> {
         let page_index = position / self.get_page_slot_sum();
@@ -381,23 +625,24 @@ impl TableFile {
         let page = self.loaded_pages.get(&page_index).unwrap();
         page.get_tuple_data(tuple_index, &self.tuple_desc)
     }
+    pub fn set_materialized(&mut self, position : usize, tuple : Vec<u8>) -> TupleData {
+        // keep the tuple until the next one is materialized
+        let tuple_data = gen_tuple_data(tuple.as_ptr() as DataPtr, &self.tuple_desc.attr_desc);
+        self.materialized = Some((position, tuple));
+        tuple_data
+    }
     pub fn next_tuple_index(&self, page_index : usize, tuple_index : usize) -> Option<usize> {
         assert!(self.loaded_pages.get(&page_index).is_some());
         let page = self.loaded_pages.get(&page_index).unwrap();
-        let next = page.bitmap.next_tuple_index(tuple_index);
-        if next == self.get_page_slot_sum() {
-            None
-        } else {
-            Some(next)
-        }
+        page.next_tuple_index(tuple_index)
     }
     pub fn add_page(&mut self, mem_page : PageRef) {
-        let file_page = FilePage::new(mem_page, self.tuple_desc.tuple_len);
+        let file_page = FilePage::new(mem_page, self.tuple_desc.min_tuple_len);
         let index = file_page.mem_page.borrow().page_index as usize;
         self.loaded_pages.insert(index, file_page);
     }
     pub fn get_fd(&self) -> i32 {
-        self.file.as_raw_fd()
+        self.file.get_id()
     }
     pub fn is_inuse(&self, page_index : usize, tuple_index : usize) -> bool {
         assert!(self.loaded_pages.get(&page_index).is_some());
@@ -413,18 +658,47 @@ impl TableFile {
 #[derive(Debug)]
 pub struct TableFileManager {
     files : HashMap<String, TableFileRef>,  // key is table name
+    indexes : HashMap<(String, String), IndexFileRef>,  // key is (table name, index name)
     pub page_pool : PagePool,
     table_file_dir : String,
+    pub vfs : VfsRef,
+    prefetcher : Option<Prefetcher>,  // read-ahead of sequential scans, None if disabled
+    pub cipher : Option<CipherRef>,  // encryption of the table files and the catalog, None if disabled
+    wal : Option<Wal>,  // changes are flushed to files directly if not set
+    removed_files : Vec<String>,  // files of dropped tables and indexes, deleted after the drop is durable
+    temp_file_num : usize,
 }
 
 impl TableFileManager {
     pub fn new(config : &Config) -> TableFileManager {
+        let vfs = config.get_opt_str("vfs").unwrap_or(DEFAULT_VFS.to_string());
+        Self::with_vfs(config, new_vfs(&vfs))
+    }
+    pub fn with_vfs(config : &Config, vfs : VfsRef) -> TableFileManager {
+        if let Some(page_size) = config.get_opt_int("page_size") {
+            set_page_size(page_size as usize);
+        }
         let table_file_dir = config.get_str("table_file_dir");
-        ensure_dir_exist(&table_file_dir);
+        vfs.create_dir(&table_file_dir);
+        let pool_size = config.get_int("max_memory_pool_page_num") as usize;
+        let policy = config.get_opt_str("buffer_policy").unwrap_or(DEFAULT_POLICY.to_string());
+        let read_ahead_pages = config.get_opt_int("read_ahead_pages")
+            .map_or(DEFAULT_READ_AHEAD_PAGES, |n| n as usize);
+        let cipher = config.get_opt_str("encryption_key_file").map(|path| match load_key(&path) {
+            Ok(key) => Rc::new(Cipher::new(key)),
+            Err(err) => panic!("can't load the encryption key: {}", err),
+        });
         TableFileManager{
             files : HashMap::new(),
-            page_pool : PagePool::new(config.get_int("max_memory_pool_page_num") as usize),
+            indexes : HashMap::new(),
+            page_pool : PagePool::with_policy(pool_size, &policy),
             table_file_dir : table_file_dir,
+            vfs : vfs,
+            prefetcher : if read_ahead_pages > 0 { Some(Prefetcher::new(read_ahead_pages)) } else { None },
+            cipher : cipher,
+            wal : None,
+            removed_files : Vec::new(),
+            temp_file_num : 0,
         }
     }
     pub fn init_from_file(&mut self, tables : Vec<TableRef>) {
@@ -433,64 +707,662 @@ impl TableFileManager {
             let mut file_name = table_name.clone();
             file_name.push_str(".table");
             let full_path = path_join(&self.table_file_dir, &file_name);
-            assert_file_exist(&full_path);
+            assert!(self.vfs.exists(&full_path), "{:?} not exists", full_path);
             self.create_file(table_name.clone(), table.clone());
-            self.files.get_mut(&table_name).unwrap().borrow_mut().init_from_file();
+            if let Err(err) = self.get_file(&table_name).borrow_mut().init_from_file() {
+                panic!("can't open table file {}: {}", full_path, err);
+            }
+            for index in self.get_table_indexes(&table_name) {
+                if index.borrow().is_file_empty() {
+                    // index file of old version not exist
+                    match self.rebuild_index(&table_name, &index) {
+                        Ok(rebuilt) => assert!(rebuilt),
+                        Err(err) => panic!("can't rebuild index {}: {}", index.borrow().name, err.error_msg),
+                    }
+                } else {
+                    let result = index.borrow_mut().init_from_file();
+                    if let Err(err) = result {
+                        panic!("can't open index file {}: {}", index.borrow().saved_name, err);
+                    }
+                }
+            }
+        }
+    }
+    pub fn save_all(&mut self) -> io::Result<()> {
+        for (_, f)  in self.files.iter() {
+            try!(f.borrow_mut().save_to_file());
+        }
+        for (_, index) in self.indexes.iter() {
+            try!(index.borrow_mut().save_to_file());
+        }
+        Ok(())
+    }
+    pub fn sync_all(&mut self) -> io::Result<()> {
+        for (_, f)  in self.files.iter() {
+            try!(f.borrow_mut().sync());
+        }
+        for (_, index) in self.indexes.iter() {
+            try!(index.borrow_mut().file.fsync());
+        }
+        Ok(())
+    }
+    pub fn open_wal(&mut self, path : &String) {
+        self.wal = Some(Wal::open(path, &self.vfs));
+    }
+    pub fn has_wal(&self) -> bool {
+        self.wal.is_some()
+    }
+    pub fn log(&mut self, record : &LogRecord) -> io::Result<()> {
+        match self.wal {
+            Some(ref mut wal) => wal.append(record),
+            None => Ok(()),
+        }
+    }
+    pub fn log_removed_files(&mut self) -> io::Result<()> {
+        // the files are deleted by remove_files after the commit
+        for path in self.removed_files.clone() {
+            try!(self.log(&LogRecord::Remove{ path : path }));
+        }
+        Ok(())
+    }
+    pub fn log_dirty_pages(&mut self) -> io::Result<()> {
+        // only the pages, headers and free space maps changed since they were logged last time,
+        // the pages swapped out by the statement have been logged before they were written
+        if let Some(ref mut wal) = self.wal {
+            for (_, f)  in self.files.iter() {
+                let mut f = f.borrow_mut();
+                if f.header_changed {
+                    try!(wal.append(&f.gen_header_record()));
+                    f.header_changed = false;
+                }
+                if f.fsm.changed {
+                    try!(wal.append(&f.fsm.gen_record()));
+                    f.fsm.changed = false;
+                }
+                let index_list : Vec<_> = f.loaded_pages.iter()
+                    .filter(|&(_, page)| page.mem_page.borrow().need_log()).map(|(i, _)| *i).collect();
+                for i in index_list {
+                    try!(wal.append(&f.gen_page_record(i)));
+                    f.loaded_pages.get(&i).unwrap().mem_page.borrow_mut().logged = true;
+                }
+                // after the pages, which may be moved when they are logged
+                if let Some(record) = f.gen_page_map_record() {
+                    try!(wal.append(&record));
+                    f.page_map.as_mut().unwrap().changed = false;
+                }
+            }
+            for (_, index) in self.indexes.iter() {
+                let mut index = index.borrow_mut();
+                if index.header_changed {
+                    try!(wal.append(&index.gen_header_record()));
+                    index.header_changed = false;
+                }
+                for (i, page) in index.loaded_pages.iter() {
+                    if page.borrow().need_log() {
+                        try!(wal.append(&index.gen_page_record(*i)));
+                        page.borrow_mut().logged = true;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn get_dirty_page_num(&self) -> usize {
+        let mut num = 0;
+        for (_, f)  in self.files.iter() {
+            num += f.borrow().loaded_pages.iter().filter(|&(_, page)| page.is_dirty()).count();
+        }
+        for (_, index) in self.indexes.iter() {
+            num += index.borrow().loaded_pages.iter().filter(|&(_, page)| page.borrow().dirty).count();
+        }
+        num
+    }
+    pub fn commit_wal(&mut self) -> io::Result<()> {
+        self.wal.as_mut().unwrap().commit()
+    }
+    pub fn discard_wal(&mut self) -> io::Result<()> {
+        // drop the records of a failed commit,
+        // the pages and headers logged by it are logged again by the next commit
+        for (_, f)  in self.files.iter() {
+            let mut f = f.borrow_mut();
+            f.header_changed = true;
+            f.fsm.changed = true;
+            if let Some(ref mut page_map) = f.page_map {
+                page_map.changed = true;
+            }
+            for (_, page) in f.loaded_pages.iter() {
+                page.mem_page.borrow_mut().logged = false;
+            }
+        }
+        for (_, index) in self.indexes.iter() {
+            index.borrow_mut().header_changed = true;
+            for (_, page) in index.borrow().loaded_pages.iter() {
+                page.borrow_mut().logged = false;
+            }
+        }
+        match self.wal {
+            Some(ref mut wal) => wal.discard(),
+            None => Ok(()),
         }
     }
-    pub fn save_all(&mut self) {
+    pub fn has_uncommitted_changes(&self) -> bool {
+        // the pages changed since the last commit, including those swapped out, and the files dropped since then
+        let mut changed = !self.removed_files.is_empty()
+            || self.wal.as_ref().map_or(false, |wal| wal.has_uncommitted());
         for (_, f)  in self.files.iter() {
-            f.borrow_mut().save_to_file();
+            changed |= f.borrow().loaded_pages.iter().any(|(_, page)| page.mem_page.borrow().need_log());
+        }
+        for (_, index) in self.indexes.iter() {
+            changed |= index.borrow().loaded_pages.iter().any(|(_, page)| page.borrow().need_log());
+        }
+        changed
+    }
+    pub fn close_all(&mut self) {
+        // drop all the pages and files, the changes not committed are lost
+        let table_list : Vec<_> = self.files.keys().cloned().collect();
+        for table in table_list.iter() {
+            self.close_files(table);
+        }
+        self.removed_files.clear();
+    }
+    pub fn get_wal_size(&self) -> u64 {
+        self.wal.as_ref().map_or(0, |wal| wal.get_size())
+    }
+    pub fn truncate_wal(&mut self) -> io::Result<()> {
+        match self.wal {
+            Some(ref mut wal) => wal.truncate(),
+            None => Ok(()),
         }
     }
-    pub fn delete(&mut self, table : &String, ptr : DataPtr) {
+    pub fn drop_file(&mut self, table : &String) {
+        // discard the pages and close the files of the table,
+        // the files are deleted by remove_files after the catalog change is committed
         let file = self.get_file(table);
-        file.borrow_mut().delete(ptr);
+        let mut path_list = file.borrow().get_path_list();
+        for index in self.get_table_indexes(table) {
+            path_list.push(index.borrow().saved_name.clone());
+        }
+        self.close_files(table);
+        self.removed_files.extend(path_list);
+    }
+    pub fn gen_temp_path(&mut self, kind : &str) -> String {
+        // temporary file of the executors, e.g. a sorted run of the external sort,
+        // the file is deleted by its user and never logged
+        self.temp_file_num += 1;
+        path_join(&self.table_file_dir, &format!("{}.{}.tmp", kind, self.temp_file_num))
+    }
+    pub fn remove_files(&mut self) {
+        for path in self.removed_files.drain(..) {
+            if self.vfs.exists(&path) {
+                is_match!(self.vfs.delete(&path), Ok(..));
+            }
+        }
     }
-    pub fn insert(&mut self, table : &String, value_list : &ValueList) {
+    pub fn delete(&mut self, table : &String, record_id : &RecordId) -> StorageResult<()> {
         let file = self.get_file(table);
-        let is_new_page = self.need_new_page(&file);  // fight the borrow checker, RefCell
-        if is_new_page {
-            let new_page_index = file.borrow().page_sum;
-            self.ensure_page_loaded(&file, new_page_index);
-            file.borrow_mut().loaded_pages.get_mut(&new_page_index).unwrap().init_empty_page();
+        let position = file.borrow().get_position(record_id);
+        let entries = try!(self.gen_index_entries(table, position));
+        let overflow_pages = try!(self.get_overflow_pages(&file, position));
+        try!(self.ensure_page_loaded(&file, record_id.page_index));
+        file.borrow_mut().delete(position);
+        for first_page in overflow_pages {
+            try!(self.free_overflow_pages(&file, first_page));
+        }
+        for (index, entry) in entries {
+            let mut store = IndexStore::new(self, index);
+            assert!(try!(btree::delete(&mut store, &entry)));
+        }
+        Ok(())
+    }
+    pub fn update(&mut self, table : &String, record_id : &RecordId, set_values : &HashMap<usize, TupleValue>)
+            -> StorageResult<Option<String>> {
+        // return the name of the unique index if the updated key already exist
+        let file = self.get_file(table);
+        let position = file.borrow().get_position(record_id);
+        let mut old_entries = Vec::new();
+        for (index, entry) in try!(self.gen_index_entries(table, position)) {
+            if index.borrow().attr_index.iter().any(|i| set_values.contains_key(i)) {
+                old_entries.push((index, entry));
+            }
+        }
+        for &(ref index, ref entry) in old_entries.iter() {
+            if !index.borrow().unique {
+                continue;
+            }
+            let new_key : Vec<TupleValue> = index.borrow().attr_index.iter().zip(entry.key.iter()).map(
+                |(i, v)| set_values.get(i).unwrap_or(v).clone()).collect();
+            if new_key.iter().any(|v| is_match!(v, &TupleValue::Null)) {
+                continue;  // key containing null never conflicts
+            }
+            let mut store = IndexStore::new(self, index.clone());
+            if try!(btree::lookup(&mut store, &new_key)).iter().any(|p| *p != position) {
+                return Ok(Some(index.borrow().name.clone()));
+            }
+        }
+        let mut value_list = try!(self.get_tuple_values(&file, position));
+        for (i, v) in set_values.iter() {
+            value_list[*i] = v.clone();
+        }
+        let overflow_pages = try!(self.get_overflow_pages(&file, position));
+        let max_len = min(file.borrow().get_max_update_len(position), get_max_tuple_len());
+        let tuple = try!(self.encode_tuple(&file, &value_list, max_len));
+        try!(self.ensure_page_loaded(&file, record_id.page_index));
+        assert!(file.borrow_mut().update(position, &tuple));
+        for first_page in overflow_pages {
+            try!(self.free_overflow_pages(&file, first_page));
+        }
+        let new_entries = try!(self.gen_index_entries(table, position));
+        for (index, old_entry) in old_entries {
+            let new_entry = new_entries.iter().filter(|&&(ref i, _)| Rc::ptr_eq(i, &index))
+                .map(|&(_, ref e)| e.clone()).next().unwrap();
+            let mut store = IndexStore::new(self, index);
+            assert!(try!(btree::delete(&mut store, &old_entry)));
+            try!(btree::insert(&mut store, new_entry));
+        }
+        Ok(None)
+    }
+    pub fn insert(&mut self, table : &String, value_list : &ValueList) -> StorageResult<()> {
+        let file = self.get_file(table);
+        let value_list = gen_value_list(value_list, &file.borrow().tuple_desc.attr_desc);
+        self.insert_values(table, &value_list)
+    }
+    fn insert_values(&mut self, table : &String, value_list : &Vec<TupleValue>) -> StorageResult<()> {
+        let file = self.get_file(table);
+        let tuple = try!(self.encode_tuple(&file, value_list, get_max_tuple_len()));
+        let page_index = try!(self.find_page_to_insert(&file, tuple.len()));
+        if page_index == file.borrow().page_sum {
+            try!(self.ensure_page_loaded(&file, page_index));
+            file.borrow_mut().loaded_pages.get_mut(&page_index).unwrap().init_empty_page();
         } else {
-            let first_free_page = file.borrow().first_free_page;
-            self.ensure_page_loaded(&file, first_free_page);
+            try!(self.ensure_page_loaded(&file, page_index));
         }
-        file.borrow_mut().insert(value_list);
+        let position = file.borrow_mut().insert_in_page(page_index, &tuple);
+        self.insert_index_entries(table, position)
     }
     pub fn insert_in_page(&mut self, table : &String, page_index : usize, value_list : &ValueList) {
         // for test
+        let file = self.get_file(table);
+        let value_list = gen_value_list(value_list, &file.borrow().tuple_desc.attr_desc);
+        let tuple = self.encode_tuple(&file, &value_list, get_max_tuple_len()).unwrap();
         self.prepare_page(table, page_index);
+        let position = file.borrow_mut().insert_in_page(page_index, &tuple);
+        self.insert_index_entries(table, position).unwrap();
+    }
+    fn encode_tuple(&mut self, file : &TableFileRef, value_list : &Vec<TupleValue>, max_len : usize)
+            -> StorageResult<Vec<u8>> {
+        // move the largest variable-length values to overflow pages until the tuple is not longer than max_len
+        let attr_desc = file.borrow().tuple_desc.attr_desc.clone();
+        let mut len_list : Vec<(usize, usize)> = value_list.iter().zip(attr_desc.iter()).enumerate()
+            .filter(|&(_, (v, a))| is_var_len(a) && !is_match!(v, &TupleValue::Null))
+            .map(|(i, (v, a))| (inline_attr_len(v, a), i)).collect();
+        len_list.sort();
+        let mut tuple_len = null_bitmap_len(attr_desc.len()) + value_list.iter().zip(attr_desc.iter())
+            .map(|(v, a)| inline_attr_len(v, a)).sum::<usize>();
+        let mut overflow = HashMap::new();
+        while tuple_len > max_len {
+            let (len, i) = len_list.pop().expect("tuple too long");
+            let content = extract!(value_list[i], TupleValue::Char(ref s), s.as_bytes().to_vec());
+            overflow.insert(i, try!(self.write_overflow_pages(file, &content)));
+            tuple_len = tuple_len - len + min_attr_len(&attr_desc[i]);
+        }
+        Ok(encode_tuple(&attr_desc, value_list, &overflow))
+    }
+    fn write_overflow_pages(&mut self, file : &TableFileRef, content : &[u8]) -> StorageResult<usize> {
+        // return the first page of the chain
+        let chunk_list : Vec<&[u8]> = content.chunks(get_overflow_content_size()).collect();
+        let mut next = None;
+        for chunk in chunk_list.iter().rev() {
+            let page_index = try!(self.alloc_overflow_page(file));
+            let mut f = file.borrow_mut();
+            f.loaded_pages.get_mut(&page_index).unwrap().init_overflow_page(next, chunk);
+            f.update_free_space(page_index);
+            next = Some(page_index);
+        }
+        match next {
+            Some(first_page) => Ok(first_page),
+            None => {
+                // empty content still takes one page
+                let page_index = try!(self.alloc_overflow_page(file));
+                let mut f = file.borrow_mut();
+                f.loaded_pages.get_mut(&page_index).unwrap().init_overflow_page(None, &[]);
+                f.update_free_space(page_index);
+                Ok(page_index)
+            }
+        }
+    }
+    fn alloc_overflow_page(&mut self, file : &TableFileRef) -> StorageResult<usize> {
+        // reuse an empty page or append a new page
+        loop {
+            let page_index = match file.borrow().fsm.find(EMPTY_PAGE) {
+                Some(page_index) => page_index,
+                None => break,
+            };
+            try!(self.ensure_page_loaded(file, page_index));
+            let is_empty = file.borrow().loaded_pages.get(&page_index).unwrap().is_empty();
+            if is_empty {
+                return Ok(page_index);
+            }
+            file.borrow_mut().update_free_space(page_index);  // the map is out of date
+        }
+        let page_index = file.borrow().page_sum;
+        try!(self.ensure_page_loaded(file, page_index));
+        Ok(page_index)
+    }
+    fn read_overflow_pages(&mut self, file : &TableFileRef, first_page : usize) -> StorageResult<Vec<u8>> {
+        let mut content = Vec::new();
+        let mut next = Some(first_page);
+        while let Some(page_index) = next {
+            try!(self.ensure_page_loaded(file, page_index));
+            let (n, chunk) = file.borrow().loaded_pages.get(&page_index).unwrap().read_overflow_page();
+            content.extend_from_slice(&chunk);
+            next = n;
+        }
+        Ok(content)
+    }
+    fn free_overflow_pages(&mut self, file : &TableFileRef, first_page : usize) -> StorageResult<()> {
+        // freed pages become empty data pages
+        let mut next = Some(first_page);
+        while let Some(page_index) = next {
+            try!(self.ensure_page_loaded(file, page_index));
+            let mut f = file.borrow_mut();
+            next = f.loaded_pages.get(&page_index).unwrap().read_overflow_page().0;
+            f.loaded_pages.get_mut(&page_index).unwrap().init_empty_page();
+            f.update_free_space(page_index);
+        }
+        Ok(())
+    }
+    fn get_overflow_pages(&mut self, file : &TableFileRef, position : usize) -> StorageResult<Vec<usize>> {
+        // return the first overflow page of each value not stored inline
+        let page_index = position / file.borrow().get_page_slot_sum();
+        try!(self.ensure_page_loaded(file, page_index));
+        let tuple = file.borrow().get_tuple_bytes(position);
+        let attr_desc = &file.borrow().tuple_desc.attr_desc;
+        Ok(gen_tuple_data(tuple.as_ptr() as DataPtr, attr_desc).iter().zip(attr_desc.iter())
+            .filter(|&(p, a)| !p.is_null() && is_var_len(a))
+            .filter_map(|(p, _)| read_overflow_ref(*p).map(|(_, first_page)| first_page))
+            .collect())
+    }
+    fn get_tuple_values(&mut self, file : &TableFileRef, position : usize) -> StorageResult<Vec<TupleValue>> {
+        let page_index = position / file.borrow().get_page_slot_sum();
+        try!(self.ensure_page_loaded(file, page_index));
+        // copy the tuple since reading overflow pages may swap out its page
+        let tuple = file.borrow().get_tuple_bytes(position);
+        let attr_desc = file.borrow().tuple_desc.attr_desc.clone();
+        let tuple_data = gen_tuple_data(tuple.as_ptr() as DataPtr, &attr_desc);
+        let mut value_list = Vec::new();
+        for (p, attr_type) in tuple_data.iter().zip(attr_desc.iter()) {
+            let overflow_ref = if !p.is_null() && is_var_len(attr_type) { read_overflow_ref(*p) } else { None };
+            value_list.push(match overflow_ref {
+                Some((_, first_page)) => {
+                    let content = try!(self.read_overflow_pages(file, first_page));
+                    TupleValue::Char(content.iter().map(|c| *c as char).collect())
+                }
+                None => read_tuple_value(*p, attr_type),
+            });
+        }
+        Ok(value_list)
+    }
+    fn insert_index_entries(&mut self, table : &String, position : usize) -> StorageResult<()> {
+        for (index, entry) in try!(self.gen_index_entries(table, position)) {
+            let mut store = IndexStore::new(self, index);
+            try!(btree::insert(&mut store, entry));
+        }
+        Ok(())
+    }
+    fn gen_index_entries(&mut self, table : &String, position : usize)
+            -> StorageResult<Vec<(IndexFileRef, IndexEntry)>> {
+        // read all the keys before modifying any index, which may swap out the table page
+        let file = self.get_file(table);
+        let value_list = try!(self.get_tuple_values(&file, position));
+        let mut entries = Vec::new();
+        for index in self.get_table_indexes(table) {
+            let key = index.borrow().attr_index.iter().map(|i| value_list[*i].clone()).collect();
+            entries.push((index, IndexEntry{ key : key, position : position }));
+        }
+        Ok(entries)
+    }
+    fn rebuild_index(&mut self, table : &String, index : &IndexFileRef) -> StorageResult<bool> {
+        // return false if the index is unique but duplicate keys found
+        let mut position = 0;
+        while let Some(p) = try!(self.get_next_position(table, position)) {
+            let file = self.get_file(table);
+            let value_list = try!(self.get_tuple_values(&file, p));
+            let key : Vec<TupleValue> = index.borrow().attr_index.iter().map(
+                |i| value_list[*i].clone()).collect();
+            let has_null = key.iter().any(|v| is_match!(v, &TupleValue::Null));
+            let mut store = IndexStore::new(self, index.clone());
+            if index.borrow().unique && !has_null && !try!(btree::lookup(&mut store, &key)).is_empty() {
+                return Ok(false);
+            }
+            try!(btree::insert(&mut store, IndexEntry{ key : key, position : p }));
+            position = p + 1;
+        }
+        Ok(true)
+    }
+    pub fn create_index(&mut self, table : &TableRef, desc : &IndexDesc) -> StorageResult<bool> {
+        // build index for the existing tuples,
+        // return false and remove the index if the index is unique but duplicate keys found
+        let table_name = table.borrow().name.clone();
+        let attr_index = desc.attr_list.iter().map(
+            |a| table.borrow().get_attr_index(a).unwrap()).collect();
+        let index = IndexFile::new(&table.borrow(), &desc.name, attr_index, desc.unique,
+            &self.table_file_dir, &self.vfs, self.cipher.clone());
+        // an index dropped but not committed yet can be created again
+        self.removed_files.retain(|p| *p != index.saved_name);
+        let index = Rc::new(RefCell::new(index));
+        self.indexes.insert((table_name.clone(), desc.name.clone()), index.clone());
+        match self.rebuild_index(&table_name, &index) {
+            Ok(true) => Ok(true),
+            result => {
+                self.drop_index(&table_name, &desc.name);
+                result
+            }
+        }
+    }
+    pub fn drop_index(&mut self, table : &String, index : &String) {
+        let index = self.indexes.remove(&(table.clone(), index.clone())).unwrap();
+        let fd = index.borrow().get_fd();
+        let index_list : Vec<_> = index.borrow().loaded_pages.iter().map(|(i, _)| *i).collect();
+        for i in index_list.iter() {
+            self.page_pool.remove_page(fd, *i as u32);
+        }
+        index.borrow_mut().loaded_pages.clear();
+        self.removed_files.push(index.borrow().saved_name.clone());
+    }
+    pub fn vacuum(&mut self, table : &String) -> StorageResult<Option<usize>> {
+        // copy the live tuples into new files and switch to them,
+        // return the number of reclaimed pages, None if any page of the table is pinned.
+        let file = self.get_file(table);
+        let table_ref = file.borrow().table.clone();
+        let old_page_sum = file.borrow().page_sum;
+        if !try!(self.rewrite_files(table, table_ref, &|value_list| value_list, Vec::new())) {
+            return Ok(None);
+        }
+        let new_page_sum = self.get_file(table).borrow().page_sum;
+        Ok(Some(old_page_sum.saturating_sub(new_page_sum)))
+    }
+    pub fn rewrite_files(&mut self, table : &String, new_table : TableRef,
+            convert : &Fn(Vec<TupleValue>) -> Vec<TupleValue>, mut record_list : Vec<LogRecord>)
+            -> StorageResult<bool> {
+        // copy the live tuples converted to the layout of new_table into new files and switch to them,
+        // the files take the name of new_table, return false if any page of the table is pinned.
+        // The new files are written without logging and the switch is logged as renames
+        // in the same commit as record_list, so the wal should be empty before calling this.
+        let file = self.get_file(table);
+        let has_pinned = file.borrow().loaded_pages.iter().any(|(_, p)| p.mem_page.borrow().is_pinned());
+        if has_pinned {
+            return Ok(false);
+        }
+        let new_name = new_table.borrow().name.clone();
+        let tmp_name = format!("{}.rewrite", table);
+        let mut tmp_table = new_table.borrow().clone();
+        tmp_table.name = tmp_name.clone();
+        self.create_file(tmp_name.clone(), Rc::new(RefCell::new(tmp_table)));
+        self.clear_files(&tmp_name);  // left by an interrupted rewrite
+        self.get_file(&tmp_name).borrow_mut().seal_for_table(&new_name);
+        for index in self.get_table_indexes(&tmp_name) {
+            index.borrow_mut().seal_for_table(&new_name);
+        }
+        let wal = self.wal.take();
+        let result = self.copy_tuples(table, &tmp_name, convert)
+            .and_then(|_| self.flush_files(&tmp_name).map_err(|err| StorageError::from_io(table, err)));
+        if let Err(err) = result {
+            self.clear_files(&tmp_name);
+            self.close_files(&tmp_name);
+            self.wal = wal;
+            return Err(err);
+        }
+        let mut tmp_path_list = self.get_file(&tmp_name).borrow().get_path_list();
+        for index in self.get_table_indexes(&tmp_name) {
+            tmp_path_list.push(index.borrow().saved_name.clone());
+        }
+        let mut old_path_list = file.borrow().get_path_list();
+        for index in self.get_table_indexes(table) {
+            old_path_list.push(index.borrow().saved_name.clone());
+        }
+        self.close_files(&tmp_name);
+        self.wal = wal;
+        // all the files are named after the table, e.g. a.rewrite.table is renamed to a.table
+        let tmp_prefix = path_join(&self.table_file_dir, &tmp_name);
+        let new_prefix = path_join(&self.table_file_dir, &new_name);
+        let new_path_list : Vec<_> = tmp_path_list.iter().map(
+            |p| format!("{}{}", new_prefix, &p[tmp_prefix.len()..])).collect();
+        for (from, to) in tmp_path_list.iter().zip(new_path_list.iter()) {
+            record_list.push(LogRecord::Rename{ from : from.clone(), to : to.clone() });
+        }
+        for path in old_path_list.into_iter().filter(|p| !new_path_list.contains(p)) {
+            record_list.push(LogRecord::Remove{ path : path });
+        }
+        if self.has_wal() {
+            if let Err(err) = self.commit_records(&record_list) {
+                for path in tmp_path_list.iter() {
+                    is_match!(self.vfs.delete(path), Ok(..));  // not used by anything committed
+                }
+                return Err(StorageError::from_io(table, err));
+            }
+        }
+        self.close_files(table);
+        for record in record_list.iter() {
+            // the switch is committed and is redone at the next start if it fails here
+            if let Err(err) = record.apply(&self.vfs) {
+                panic!("can't switch the files of table {}: {}", table, err);
+            }
+        }
+        self.create_file(new_name.clone(), new_table);
+        assert!(self.get_file(&new_name).borrow_mut().init_from_file().is_ok());
+        for index in self.get_table_indexes(&new_name) {
+            assert!(index.borrow_mut().init_from_file().is_ok());
+        }
+        Ok(true)
+    }
+    fn copy_tuples(&mut self, from : &String, to : &String, convert : &Fn(Vec<TupleValue>) -> Vec<TupleValue>)
+            -> StorageResult<()> {
+        let file = self.get_file(from);
+        let mut position = 0;
+        while let Some(p) = try!(self.get_next_position(from, position)) {
+            let value_list = convert(try!(self.get_tuple_values(&file, p)));
+            try!(self.insert_values(to, &value_list));
+            position = p + 1;
+        }
+        Ok(())
+    }
+    pub fn clear_files(&mut self, table : &String) {
         let file = self.get_file(table);
-        file.borrow_mut().insert_in_page(page_index, value_list);
+        self.discard_prefetched(file.borrow().get_fd());
+        is_match!(file.borrow_mut().file.truncate(0), Ok(..));
+        is_match!(file.borrow_mut().fsm.file.truncate(0), Ok(..));
+        if let Some(ref mut page_map) = file.borrow_mut().page_map {
+            is_match!(page_map.file.truncate(0), Ok(..));
+        }
+        for index in self.get_table_indexes(table) {
+            is_match!(index.borrow_mut().file.truncate(0), Ok(..));
+        }
+    }
+    fn flush_files(&mut self, table : &String) -> io::Result<()> {
+        // save and sync the files of the table and its indexes
+        let file = self.get_file(table);
+        try!(file.borrow_mut().save_to_file());
+        try!(file.borrow_mut().sync());
+        for index in self.get_table_indexes(table) {
+            try!(index.borrow_mut().save_to_file());
+            try!(index.borrow_mut().file.fsync());
+        }
+        Ok(())
+    }
+    fn commit_records(&mut self, record_list : &Vec<LogRecord>) -> io::Result<()> {
+        let mut result = Ok(());
+        for record in record_list.iter() {
+            result = result.and_then(|_| self.log(record));
+        }
+        result = result.and_then(|_| self.commit_wal());
+        if result.is_err() {
+            is_match!(self.discard_wal(), Ok(..));  // the error of the commit is reported instead
+        }
+        result
+    }
+    fn close_files(&mut self, table : &String) {
+        // remove the pages of the table and its indexes from the pool, the changed pages are discarded
+        let file = self.files.remove(table).unwrap();
+        let indexes = self.get_table_indexes(table);
+        self.indexes.retain(|k, _| k.0 != *table);
+        let fd = file.borrow().get_fd();
+        self.discard_prefetched(fd);
+        let page_list : Vec<_> = file.borrow().loaded_pages.iter().map(|(i, _)| *i).collect();
+        for i in page_list.iter() {
+            self.page_pool.remove_page(fd, *i as u32);
+        }
+        file.borrow_mut().loaded_pages.clear();
+        for index in indexes.iter() {
+            let fd = index.borrow().get_fd();
+            let page_list : Vec<_> = index.borrow().loaded_pages.iter().map(|(i, _)| *i).collect();
+            for i in page_list.iter() {
+                self.page_pool.remove_page(fd, *i as u32);
+            }
+            index.borrow_mut().loaded_pages.clear();
+        }
+    }
+    pub fn index_lookup(&mut self, table : &String, index : &str, key : &Vec<TupleValue>)
+            -> StorageResult<Vec<usize>> {
+        // return positions of the tuples whose index key equal to key
+        self.index_scan(table, index, &KeyRange::equal(key.clone()))
+    }
+    pub fn index_scan(&mut self, table : &String, index : &str, range : &KeyRange)
+            -> StorageResult<Vec<usize>> {
+        // return positions of the tuples whose index key in range, ordered by key
+        let index = self.get_index(table, index);
+        let mut store = IndexStore::new(self, index);
+        btree::scan(&mut store, range)
+    }
+    pub fn get_index(&self, table : &String, index : &str) -> IndexFileRef {
+        self.indexes.get(&(table.clone(), index.to_string())).unwrap().clone()
+    }
+    pub fn get_table_indexes(&self, table : &String) -> Vec<IndexFileRef> {
+        self.indexes.iter().filter(|&(k, _)| k.0 == *table).map(|(_, index)| index.clone()).collect()
     }
     pub fn prepare_page(&mut self, table : &String, page_index : usize) {
         // for test, will init empty page
         let file = self.get_file(&table);
         let page_exist = file.borrow().loaded_pages.get(&page_index).is_some();  // fight borrow checker
         if !page_exist {
-            self.ensure_page_loaded(&file, page_index);
+            self.ensure_page_loaded(&file, page_index).unwrap();
             file.borrow_mut().loaded_pages.get_mut(&page_index).unwrap().init_empty_page();
         }
     }
-    pub fn need_new_page(&mut self, file : &TableFileRef) -> bool {
-        let page_sum = file.borrow().page_sum;
-        let mut first_free_page;
+    pub fn find_page_to_insert(&mut self, file : &TableFileRef, tuple_len : usize) -> StorageResult<usize> {
+        // return page_sum if need new page
         loop {
-            first_free_page = file.borrow().first_free_page;
-            assert!(first_free_page <= page_sum);
-            if first_free_page == page_sum { break; }
-            self.ensure_page_loaded(&file, first_free_page);
-            let is_full = file.borrow().loaded_pages.get(&first_free_page).unwrap().is_full();  // fight borrow checker
-            if is_full {
-                file.borrow_mut().first_free_page += 1;
-            } else {
-                return false;
+            let page_index = match file.borrow().fsm.find_page(tuple_len) {
+                Some(page_index) => page_index,
+                None => break,
+            };
+            try!(self.ensure_page_loaded(&file, page_index));
+            let can_insert = file.borrow().loaded_pages.get(&page_index).unwrap().can_insert(tuple_len);  // fight borrow checker
+            if can_insert {
+                return Ok(page_index);
             }
+            file.borrow_mut().update_free_space(page_index);  // the map is out of date
         }
-        true
+        Ok(file.borrow().page_sum)
     }
     pub fn get_file(&mut self, table : &String) -> TableFileRef {
         self.files.get_mut(table).unwrap().clone()
@@ -499,98 +1371,313 @@ impl TableFileManager {
             position : usize,
             attr_position : usize) -> TupleValue{
         // only for test
-        let file = self.files.get(table).unwrap().clone();
-        let page_index = {
-            let f = file.borrow_mut();
-            position / f.tuple_desc.tuple_len
-        };
-        self.prepare_page(table, page_index);
-        // declare v only to fight lifetime checker
-        let v = file.borrow().get_tuple_value(position, attr_position);
-        v
+        let file = self.get_file(table);
+        self.get_tuple_values(&file, position).unwrap().swap_remove(attr_position)
     }
-    pub fn get_tuple_data(&mut self, table : &String, position : usize) -> Option<TupleData> {
+    pub fn get_tuple_data(&mut self, table : &String, position : usize) -> StorageResult<Option<TupleData>> {
         let file = self.files.get(table).unwrap().clone();
         let page_index = {
             let f = file.borrow_mut();
             position / f.get_page_slot_sum()
         };
-        self.ensure_page_loaded(&file, page_index);
+        try!(self.ensure_page_loaded(&file, page_index));
+        let tuple_data = match file.borrow().get_tuple_data(position) {
+            Some(tuple_data) => tuple_data,
+            None => return Ok(None),
+        };
+        let attr_desc = file.borrow().tuple_desc.attr_desc.clone();
+        let has_overflow = tuple_data.iter().zip(attr_desc.iter()).any(
+            |(p, a)| !p.is_null() && is_var_len(a) && read_overflow_ref(*p).is_some());
+        if !has_overflow {
+            return Ok(Some(tuple_data));
+        }
+        let value_list = try!(self.get_tuple_values(&file, position));
+        let tuple = encode_tuple(&attr_desc, &value_list, &HashMap::new());
         // declare v only to fight lifetime checker
-        let v = file.borrow().get_tuple_data(position);
+        let v = Ok(Some(file.borrow_mut().set_materialized(position, tuple)));
         v
     }
-    pub fn get_next_tuple_data(&mut self, table : &String, from : usize) -> Option<(TupleData, usize)> {
-        match self.get_next_position(table, from) {
-            Some(position) => Some((self.get_tuple_data(table, position).unwrap(), position)),
-            None => None,
+    pub fn get_next_tuple_data(&mut self, table : &String, from : usize)
+            -> StorageResult<Option<(TupleData, usize)>> {
+        match try!(self.get_next_position(table, from)) {
+            Some(position) => Ok(Some((try!(self.get_tuple_data(table, position)).unwrap(), position))),
+            None => Ok(None),
         }
     }
-    pub fn get_next_position(&mut self, table : &String, from : usize) -> Option<usize> {
+    pub fn get_next_position(&mut self, table : &String, from : usize) -> StorageResult<Option<usize>> {
         let file = self.get_file(table);
         let page_sum = file.borrow().page_sum;
         let slot_sum = file.borrow().get_page_slot_sum();
         let mut page_index = from / slot_sum;
         let mut tuple_index = from % slot_sum;
         while page_index < page_sum {
+            try!(self.ensure_page_loaded(&file, page_index));
             let next = file.borrow().next_tuple_index(page_index, tuple_index);
             match next {
-                Some(i) => return Some(page_index * slot_sum + i),
+                Some(i) => return Ok(Some(page_index * slot_sum + i)),
                 None => {
                    page_index += 1;
                    tuple_index = 0;
                 }
             }
         }
-        None
+        Ok(None)
     }
-    pub fn ensure_page_loaded(&mut self, file : &TableFileRef, page_index : usize) {
+    pub fn ensure_page_loaded(&mut self, file : &TableFileRef, page_index : usize) -> StorageResult<()> {
         let page_sum = file.borrow().page_sum;
         assert!(page_index < page_sum || page_index == page_sum);  // old page or new page
         let page_exist = file.borrow().loaded_pages.get(&page_index).is_some();  // fight borrow checker
         if !page_exist {
             let fd = file.borrow().get_fd();
-            let mut ptr = null_mut();
-            if let Some(page) = self.page_pool.prepare_page() {
-                // save tail page
-                let old_page_index = page.borrow().page_index;
-                ptr = page.borrow().data;
-                let old_fd = page.borrow().fd;
-                let old_file = self.get_file_by_fd(old_fd);
-                old_file.borrow_mut().save_page(old_page_index as usize);
-                page.borrow_mut().data = null_mut();
-                old_file.borrow_mut().loaded_pages.remove(&(old_page_index as usize));
-                self.page_pool.remove_tail();
-            }
+            let mut ptr = try!(self.prepare_frame());
             self.page_pool.put_page(fd, page_index as u32, ptr);
             {
                 let page = self.page_pool.get_page(fd, page_index as u32).unwrap();
                 ptr = page.borrow().data.clone();
             }
             if page_index < page_sum {
-                file.borrow_mut().read_page_from_file(ptr, page_index);
+                let prefetched = match self.prefetcher {
+                    Some(ref mut prefetcher) => prefetcher.take(&PageKey{ fd : fd, page_index : page_index as u32 }),
+                    None => None,
+                };
+                let result = match prefetched {
+                    Some(buf) => file.borrow_mut().read_page_from_buffer(ptr, page_index, &buf),
+                    None => file.borrow_mut().read_page_from_file(ptr, page_index),
+                };
+                if let Err(err) = result {
+                    self.page_pool.remove_page(fd, page_index as u32);  // never keep a corrupted page
+                    return Err(err);
+                }
                 file.borrow_mut().add_page(self.page_pool.get_page(fd, page_index as u32).unwrap());
                 file.borrow_mut().loaded_pages.get_mut(&page_index).unwrap().init_from_page_data();
             } else {
                 file.borrow_mut().page_sum += 1;
-                file.borrow_mut().add_page(self.page_pool.get_page(fd, page_index as u32).unwrap());
+                file.borrow_mut().header_changed = true;
+                let page = self.page_pool.get_page(fd, page_index as u32).unwrap();
+                page.borrow_mut().mark_dirty();
+                file.borrow_mut().add_page(page);
+            }
+        }
+        Ok(())
+    }
+    pub fn read_ahead(&mut self, file : &TableFileRef, start : usize) {
+        // ask the io thread for the pages from start which are not loaded yet
+        let prefetcher = match self.prefetcher {
+            Some(ref mut prefetcher) => prefetcher,
+            None => return,
+        };
+        let f = file.borrow();
+        let end = min(f.page_sum, start + prefetcher.get_window());
+        for page_index in start..end {
+            if f.loaded_pages.contains_key(&page_index) {
+                continue;
+            }
+            let key = PageKey{ fd : f.get_fd(), page_index : page_index as u32 };
+            let (offset, len) = f.get_page_location(page_index);
+            prefetcher.request(&*f.file, key, offset, len);
+        }
+    }
+    pub fn get_read_ahead_num(&self) -> usize {
+        self.prefetcher.as_ref().map_or(0, |prefetcher| prefetcher.get_prefetched_num())
+    }
+    fn discard_prefetched(&mut self, fd : i32) {
+        if let Some(ref mut prefetcher) = self.prefetcher {
+            prefetcher.discard(fd);
+        }
+    }
+    pub fn ensure_index_page_loaded(&mut self, index : &IndexFileRef, page_index : usize) -> StorageResult<()> {
+        let page_sum = index.borrow().page_sum;
+        assert!(page_index <= page_sum);  // old page or new page
+        let fd = index.borrow().get_fd();
+        let page_exist = index.borrow().loaded_pages.get(&page_index).is_some();  // fight borrow checker
+        if page_exist {
+            // index pages are accessed frequently, keep them in the head of lru list
+            self.page_pool.get_page(fd, page_index as u32);
+            return Ok(());
+        }
+        let ptr = try!(self.prepare_frame());
+        self.page_pool.put_page(fd, page_index as u32, ptr);
+        let page = self.page_pool.get_page(fd, page_index as u32).unwrap();
+        if page_index < page_sum {
+            let data = page.borrow().data;
+            let result = index.borrow_mut().read_page_from_file(data, page_index);
+            if let Err(err_msg) = result {
+                self.page_pool.remove_page(fd, page_index as u32);  // never keep a corrupted page
+                return Err(StorageError{
+                    error_type : StorageErrorType::PageCorrupted,
+                    table : self.get_table_name_by_fd(fd),
+                    page_index : page_index,
+                    error_msg : err_msg,
+                });
+            }
+        } else {
+            index.borrow_mut().page_sum += 1;
+            index.borrow_mut().header_changed = true;
+            page.borrow_mut().mark_dirty();
+        }
+        index.borrow_mut().loaded_pages.insert(page_index, page);
+        Ok(())
+    }
+    fn prepare_frame(&mut self) -> StorageResult<DataPtr> {
+        // swap out a page if the pool is full and reuse its memory
+        let page = match self.page_pool.prepare_page() {
+            Some(page) => page,
+            None => return Ok(null_mut()),
+        };
+        try!(self.swap_out_page(&page));
+        self.page_pool.remove_tail();
+        let ptr = page.borrow().data;
+        page.borrow_mut().data = null_mut();
+        Ok(ptr)
+    }
+    fn swap_out_page(&mut self, page : &PageRef) -> StorageResult<()> {
+        // remove the page from its file, the page is written back only if it's dirty.
+        // With the log a page changed by the running statement is logged before it's written (steal).
+        // The page stays in the pool if it can't be written
+        let old_page_index = page.borrow().page_index as usize;
+        let old_fd = page.borrow().fd;
+        let dirty = page.borrow().dirty;
+        let result = if self.wal.is_some() && page.borrow().need_log() {
+            self.steal_page(old_fd, old_page_index)
+        } else if let Some(old_file) = self.find_file_by_fd(old_fd) {
+            let result = if dirty { old_file.borrow_mut().save_page(old_page_index) } else { Ok(()) };
+            if result.is_ok() {
+                old_file.borrow_mut().loaded_pages.remove(&old_page_index);
+            }
+            result
+        } else {
+            let old_index = self.get_index_by_fd(old_fd);
+            let result = if dirty { old_index.borrow_mut().save_page(old_page_index) } else { Ok(()) };
+            if result.is_ok() {
+                old_index.borrow_mut().loaded_pages.remove(&old_page_index);
             }
+            result
+        };
+        result.map_err(|err| StorageError{
+            error_type : StorageErrorType::IoError,
+            table : self.get_table_name_by_fd(old_fd),
+            page_index : old_page_index,
+            error_msg : format!("can't write page {}: {}", old_page_index, err),
+        })
+    }
+    fn steal_page(&mut self, fd : i32, page_index : usize) -> io::Result<()> {
+        // the old content is logged to revert the write if the statement fails,
+        // and the new one to redo it once the statement commits
+        let record = match self.find_file_by_fd(fd) {
+            Some(file) => file.borrow_mut().gen_page_record(page_index),
+            None => self.get_index_by_fd(fd).borrow().gen_page_record(page_index),
+        };
+        let undo = try!(record.gen_undo(&self.vfs));
+        {
+            let wal = self.wal.as_mut().unwrap();
+            try!(wal.append(&undo));
+            try!(wal.append(&record));
+            try!(wal.sync());
         }
+        try!(record.apply(&self.vfs));
+        match self.find_file_by_fd(fd) {
+            Some(file) => { file.borrow_mut().loaded_pages.remove(&page_index); }
+            None => { self.get_index_by_fd(fd).borrow_mut().loaded_pages.remove(&page_index); }
+        }
+        Ok(())
+    }
+    pub fn resize_pool(&mut self, capacity : usize) -> Result<(), String> {
+        // the pinned pages stay in the pool and at least a frame is left for the others,
+        // shrinking the pool swaps out the clean pages first
+        let pinned = self.page_pool.get_capacity() - self.page_pool.get_unpinned_num();
+        if capacity <= pinned {
+            return Err(format!("{} pages are pinned, the pool size should be larger than that", pinned));
+        }
+        let mut pages = Vec::new();
+        for (_, f) in self.files.iter() {
+            pages.extend(f.borrow().loaded_pages.iter().map(|(_, page)| page.mem_page.clone()));
+        }
+        for (_, index) in self.indexes.iter() {
+            pages.extend(index.borrow().loaded_pages.iter().map(|(_, page)| page.clone()));
+        }
+        pages.sort_by_key(|page| (page.borrow().is_pinned(), page.borrow().dirty));
+        let evicted_num = pages.len().saturating_sub(capacity);
+        for page in pages.drain(..evicted_num) {
+            // the memory is freed when the page is dropped
+            if let Err(err) = self.swap_out_page(&page) {
+                return Err(err.error_msg);
+            }
+            let (fd, page_index) = (page.borrow().fd, page.borrow().page_index);
+            self.page_pool.remove_page(fd, page_index);
+        }
+        self.page_pool.resize(capacity, pages);
+        Ok(())
     }
     pub fn get_file_by_fd(&self, fd : i32) -> TableFileRef {
+        match self.find_file_by_fd(fd) {
+            Some(file) => file,
+            None => panic!("invalid fd"),
+        }
+    }
+    fn find_file_by_fd(&self, fd : i32) -> Option<TableFileRef> {
         for (_, file) in self.files.iter() {
             if file.borrow().get_fd() == fd {
-                return file.clone();
+                return Some(file.clone());
+            }
+        }
+        None
+    }
+    fn get_table_name_by_fd(&self, fd : i32) -> String {
+        // the table of a table file or an index file
+        if let Some(file) = self.find_file_by_fd(fd) {
+            return file.borrow().table.borrow().name.clone();
+        }
+        self.indexes.iter().filter(|&(_, index)| index.borrow().get_fd() == fd)
+            .map(|(k, _)| k.0.clone()).next().expect("invalid fd")
+    }
+    fn get_index_by_fd(&self, fd : i32) -> IndexFileRef {
+        for (_, index) in self.indexes.iter() {
+            if index.borrow().get_fd() == fd {
+                return index.clone();
             }
         }
         panic!("invalid fd");
     }
     pub fn create_file(&mut self, name : String, table : TableRef) {
-        let file = TableFile::new(name.clone(), table, &self.table_file_dir);
-        self.files.insert(name, Rc::new(RefCell::new(file)));
+        let mut index_list = Vec::new();
+        {
+            let t = table.borrow();
+            let pk_index = t.get_primary_key_index();
+            index_list.push(IndexFile::new(&t, PRIMARY_INDEX, vec![pk_index], true,
+                &self.table_file_dir, &self.vfs, self.cipher.clone()));
+            for desc in t.index_list.iter() {
+                let attr_index = desc.attr_list.iter().map(|a| t.get_attr_index(a).unwrap()).collect();
+                index_list.push(IndexFile::new(&t, &desc.name, attr_index, desc.unique,
+                    &self.table_file_dir, &self.vfs, self.cipher.clone()));
+            }
+        }
+        let file = TableFile::new(name.clone(), table, &self.table_file_dir, &self.vfs, self.cipher.clone());
+        // a table dropped but not committed yet can be created again
+        let path_list = file.get_path_list();
+        self.removed_files.retain(|p| !path_list.contains(p)
+            && index_list.iter().all(|index| *p != index.saved_name));
+        self.files.insert(name.clone(), Rc::new(RefCell::new(file)));
+        for index in index_list {
+            let index_name = index.name.clone();
+            self.indexes.insert((name.clone(), index_name), Rc::new(RefCell::new(index)));
+        }
     }
-    pub fn pin_page(&mut self, fd : i32, page_index : u32) {
+    pub fn pin_page(&mut self, fd : i32, page_index : u32) -> StorageResult<()> {
+        // no page can be loaded if all the pages are pinned, so the last frame is never pinned,
+        // the statement fails instead of waiting because the pages are only unpinned by itself
+        if !self.page_pool.can_pin(fd, page_index) {
+            let table = self.get_file_by_fd(fd).borrow().table.borrow().name.clone();
+            return Err(StorageError{
+                error_type : StorageErrorType::PoolExhausted,
+                table : table,
+                page_index : page_index as usize,
+                error_msg : format!("buffer pool exhausted, {} of {} pages are pinned",
+                    self.page_pool.get_capacity() - self.page_pool.get_unpinned_num(),
+                    self.page_pool.get_capacity()),
+            });
+        }
         self.page_pool.pin_page(fd, page_index);
+        Ok(())
     }
     pub fn unpin_page(&mut self, fd : i32, page_index : u32) {
         self.page_pool.unpin_page(fd, page_index);
@@ -603,9 +1690,24 @@ impl TableFileManager {
     }
 }
 
-fn get_slot_sum(tuple_len : usize) -> usize {
-    let header_size = 2 * size_of::<u32>();  // PageHeader
-    let page_size = get_page_size();
-    // (n + 8 - 1) / 8 + tuple_len * n <= page_size - header_size
-    (8 * (page_size - header_size) - 7) / (8 * tuple_len + 1)
+fn get_slot_sum(min_tuple_len : usize) -> usize {
+    (get_page_size() - PAGE_HEADER_SIZE) / (min_tuple_len + SLOT_SIZE)
+}
+
+fn get_max_tuple_len() -> usize {
+    // a page can hold at least 4 tuples
+    (get_page_size() - PAGE_HEADER_SIZE) / 4 - SLOT_SIZE
+}
+
+fn get_overflow_content_size() -> usize {
+    get_page_size() - PAGE_HEADER_SIZE
+}
+
+fn gen_page_checksum(page : &[u8]) -> u32 {
+    // the checksum field is taken as zero
+    let mut page = page.to_vec();
+    for b in page[CHECKSUM_OFFSET..CHECKSUM_OFFSET + 4].iter_mut() {
+        *b = 0;
+    }
+    crc32(&page)
 }
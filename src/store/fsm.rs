@@ -0,0 +1,135 @@
+use std::io;
+use std::cmp::max;
+use ::utils::libwrapper::get_page_size;
+use ::utils::file::path_join;
+use super::wal::LogRecord;
+use super::vfs::{VfsRef, VfsFileRef};
+
+
+// Free space map of a table file, saved in a side file with one byte for each page.
+// The byte is the space for a new tuple in units of page_size / 256,
+// EMPTY_PAGE means the page has no tuple and can also be used as overflow page.
+// A max tree on top of the bytes finds the first page with enough space in O(log n).
+// The map is only a hint, pages missing in the file are considered empty
+// and callers should correct the map when the page turns out to have less space.
+pub const EMPTY_PAGE : u8 = 255;
+const MAX_CATEGORY : u8 = 254;
+
+#[derive(Debug)]
+pub struct FreeSpaceMap {
+    pub saved_name : String,
+    pub file : VfsFileRef,
+    page_num : usize,
+    tree : Vec<u8>,  // leaf of page i is tree[capacity + i], tree[i] is the max of its children
+    capacity : usize,
+    pub changed : bool,  // since the map was logged last time
+}
+
+impl FreeSpaceMap {
+    pub fn new(table_file_name : &String, dir : &String, vfs : &VfsRef) -> FreeSpaceMap {
+        let saved_name = path_join(dir, &format!("{}.fsm", table_file_name));
+        let file = vfs.open(&saved_name).unwrap();
+        FreeSpaceMap{
+            saved_name : saved_name,
+            file : file,
+            page_num : 0,
+            tree : vec![0, 0],
+            capacity : 1,
+            changed : true,
+        }
+    }
+    pub fn init_from_file(&mut self, page_sum : usize) {
+        let mut data = self.file.read_all().unwrap_or(Vec::new());
+        data.resize(page_sum, EMPTY_PAGE);
+        self.page_num = 0;
+        for (page_index, category) in data.iter().enumerate() {
+            self.set(page_index, *category);
+        }
+        self.changed = false;
+    }
+    pub fn save_to_file(&mut self) -> io::Result<()> {
+        let data = self.gen_data();
+        try!(self.file.truncate(0));
+        self.file.write_at(0, &data)
+    }
+    pub fn gen_record(&self) -> LogRecord {
+        LogRecord::Replace{ path : self.saved_name.clone(), data : self.gen_data() }
+    }
+    fn gen_data(&self) -> Vec<u8> {
+        self.tree[self.capacity..self.capacity + self.page_num].to_vec()
+    }
+    pub fn get_page_num(&self) -> usize {
+        self.page_num
+    }
+    pub fn get(&self, page_index : usize) -> u8 {
+        assert!(page_index < self.page_num);
+        self.tree[self.capacity + page_index]
+    }
+    pub fn set(&mut self, page_index : usize, category : u8) {
+        while page_index >= self.capacity {
+            self.grow();
+        }
+        if page_index >= self.page_num {
+            self.page_num = page_index + 1;
+            self.changed = true;
+        }
+        let mut i = self.capacity + page_index;
+        self.changed |= self.tree[i] != category;
+        self.tree[i] = category;
+        while i > 1 {
+            i /= 2;
+            self.tree[i] = max(self.tree[2 * i], self.tree[2 * i + 1]);
+        }
+    }
+    pub fn update(&mut self, page_index : usize, insertable_len : usize, is_empty : bool) {
+        let category = if is_empty { EMPTY_PAGE } else { get_category(insertable_len) };
+        self.set(page_index, category);
+    }
+    pub fn find(&self, category : u8) -> Option<usize> {
+        // return the first page not less than category
+        if self.tree[1] < category {
+            return None;
+        }
+        let mut i = 1;
+        while i < self.capacity {
+            i = if self.tree[2 * i] >= category { 2 * i } else { 2 * i + 1 };
+        }
+        Some(i - self.capacity)
+    }
+    pub fn find_page(&self, tuple_len : usize) -> Option<usize> {
+        self.find(get_required_category(tuple_len))
+    }
+    fn grow(&mut self) {
+        let data = self.gen_data();
+        self.capacity *= 2;
+        self.tree = vec![0; 2 * self.capacity];
+        for (page_index, category) in data.iter().enumerate() {
+            self.tree[self.capacity + page_index] = *category;
+        }
+        for i in (1..self.capacity).rev() {
+            self.tree[i] = max(self.tree[2 * i], self.tree[2 * i + 1]);
+        }
+    }
+}
+
+fn get_unit() -> usize {
+    get_page_size() / 256
+}
+
+pub fn get_category(insertable_len : usize) -> u8 {
+    // round down so that a page of this category always has enough space
+    let category = insertable_len / get_unit();
+    if category > MAX_CATEGORY as usize { MAX_CATEGORY } else { category as u8 }
+}
+
+pub fn get_required_category(tuple_len : usize) -> u8 {
+    // round up, and full pages of category 0 never match
+    let category = (tuple_len + get_unit() - 1) / get_unit();
+    if category == 0 {
+        1
+    } else if category > MAX_CATEGORY as usize {
+        EMPTY_PAGE
+    } else {
+        category as u8
+    }
+}
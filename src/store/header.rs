@@ -0,0 +1,108 @@
+use ::utils::libwrapper::get_page_size;
+use super::table::{Table, AttrType};
+use super::checksum::crc32;
+
+
+// Header in the first page of table files and index files.
+// All the fields are little-endian u32, so are the numbers in the pages following it.
+// Files with a different version or page size can't be read
+// and the schema fingerprint detects files not matching the catalog.
+pub const MAGIC : u32 = 0x54534c42;  // "BLST"
+pub const FORMAT_VERSION : u32 = 3;  // 3 for the crc32 of the pages
+const LITTLE_ENDIAN : u32 = 1;
+pub const HEADER_SIZE : usize = 28;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileHeader {
+    pub version : u32,
+    pub page_size : u32,
+    pub fingerprint : u32,
+    pub page_sum : u32,
+    pub extra : u32,  // root page of index file, not used by table file
+}
+
+impl FileHeader {
+    pub fn new(fingerprint : u32, page_sum : usize, extra : usize) -> FileHeader {
+        FileHeader{
+            version : FORMAT_VERSION,
+            page_size : get_page_size() as u32,
+            fingerprint : fingerprint,
+            page_sum : page_sum as u32,
+            extra : extra as u32,
+        }
+    }
+    pub fn encode(&self) -> Vec<u8> {
+        let field_list = [MAGIC, self.version, self.page_size, LITTLE_ENDIAN,
+            self.fingerprint, self.page_sum, self.extra];
+        let mut buf = Vec::new();
+        for n in field_list.iter() {
+            for i in 0..4 {
+                buf.push((n >> (i * 8)) as u8);
+            }
+        }
+        buf
+    }
+    pub fn decode(buf : &[u8]) -> Result<FileHeader, String> {
+        if buf.len() < HEADER_SIZE || read_field(buf, 0) != MAGIC {
+            return Err("invalid magic number, not a data file or created by an old version".to_string());
+        }
+        let version = read_field(buf, 1);
+        if version != FORMAT_VERSION {
+            return Err(format!("unsupported format version {}, expected {}", version, FORMAT_VERSION));
+        }
+        if read_field(buf, 3) != LITTLE_ENDIAN {
+            return Err("unsupported byte order".to_string());
+        }
+        Ok(FileHeader{
+            version : version,
+            page_size : read_field(buf, 2),
+            fingerprint : read_field(buf, 4),
+            page_sum : read_field(buf, 5),
+            extra : read_field(buf, 6),
+        })
+    }
+    pub fn check(&self, fingerprint : u32) -> Result<(), String> {
+        if self.page_size as usize != get_page_size() {
+            return Err(format!("page size {} of the file not equal to the configured page size {}",
+                self.page_size, get_page_size()));
+        }
+        if self.fingerprint != fingerprint {
+            return Err("schema of the file not match the catalog".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn read_field(buf : &[u8], i : usize) -> u32 {
+    let mut n = 0;
+    for j in 0..4 {
+        n |= (buf[i * 4 + j] as u32) << (j * 8);
+    }
+    n
+}
+
+pub fn gen_table_fingerprint(table : &Table) -> u32 {
+    let schema : Vec<String> = table.attr_list.iter().map(
+        |a| format!("{} {} {} {}", a.name, attr_type_str(&a.attr_type), a.nullable, a.primary)).collect();
+    let mut schema = schema.join(",");
+    if let Some(ref compression) = table.compression {
+        schema.push_str(&format!(" compression {}", compression));  // not changed for uncompressed tables
+    }
+    crc32(schema.as_bytes())
+}
+
+pub fn gen_index_fingerprint(key_desc : &Vec<AttrType>, attr_index : &Vec<usize>, unique : bool) -> u32 {
+    let schema : Vec<String> = key_desc.iter().zip(attr_index.iter()).map(
+        |(t, i)| format!("{} {}", i, attr_type_str(t))).collect();
+    crc32(format!("{} {}", schema.join(","), unique).as_bytes())
+}
+
+fn attr_type_str(attr_type : &AttrType) -> String {
+    match attr_type {
+        &AttrType::Int => "int".to_string(),
+        &AttrType::Float => "float".to_string(),
+        &AttrType::Char{len} => format!("char({})", len),
+        &AttrType::Varchar{len} => format!("varchar({})", len),
+        &AttrType::Text => "text".to_string(),
+    }
+}
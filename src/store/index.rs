@@ -0,0 +1,209 @@
+use std::io;
+use std::collections::HashMap;
+use std::slice::{from_raw_parts, from_raw_parts_mut};
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::utils::libwrapper::get_page_size;
+use ::utils::file::path_join;
+use super::buffer::{DataPtr, PageRef};
+use super::table::Table;
+use super::tuple::TupleValue;
+use super::btree::{KeyDesc, Node, NodeStore, max_entry_num};
+use super::file::TableFileManager;
+use super::error::StorageResult;
+use super::wal::LogRecord;
+use super::header::{FileHeader, HEADER_SIZE, gen_index_fingerprint};
+use super::vfs::{VfsRef, VfsFileRef};
+use super::crypto::{CipherRef, NONCE_SIZE, TAG_SIZE};
+
+
+pub const PRIMARY_INDEX : &'static str = "primary";
+
+
+pub type IndexFileRef = Rc<RefCell<IndexFile>>;
+
+#[derive(Debug)]
+pub struct IndexFile {
+    pub name : String,
+    pub saved_name : String,
+    pub file : VfsFileRef,
+    pub loaded_pages : HashMap<usize, PageRef>,
+    pub page_sum : usize,  // including pages not loaded in memory
+    pub root : Option<usize>,
+    pub header_changed : bool,  // page_sum or root changed since the header was logged last time
+    pub key_desc : KeyDesc,
+    pub attr_index : Vec<usize>,  // position of key attributes in table
+    pub unique : bool,
+    fingerprint : u32,
+    cipher : Option<CipherRef>,
+    sealed_name : String,  // table name, index name and file kind in the associated data of the sealed pages
+}
+
+impl IndexFile {
+    pub fn new(table : &Table, index_name : &str, attr_index : Vec<usize>, unique : bool,
+            dir : &String, vfs : &VfsRef, cipher : Option<CipherRef>) -> IndexFile {
+        let saved_name = path_join(dir, &format!("{}.{}.index", table.name, index_name));
+        let file = vfs.open(&saved_name).unwrap();
+        let key_desc = attr_index.iter().map(|i| table.attr_list[*i].attr_type.clone()).collect();
+        let fingerprint = gen_index_fingerprint(&key_desc, &attr_index, unique);
+        IndexFile{
+            name : index_name.to_string(),
+            saved_name : saved_name,
+            file : file,
+            loaded_pages : HashMap::new(),
+            page_sum : 0,
+            root : None,
+            header_changed : true,
+            key_desc : key_desc,
+            attr_index : attr_index,
+            unique : unique,
+            fingerprint : fingerprint,
+            cipher : cipher,
+            sealed_name : format!("{}.{}.index", table.name, index_name),
+        }
+    }
+    pub fn is_file_empty(&self) -> bool {
+        self.file.get_len() == 0
+    }
+    pub fn init_from_file(&mut self) -> Result<(), String> {
+        let mut buf = vec![0; HEADER_SIZE];
+        // the part beyond a short file is left zero
+        try!(self.file.read_at(0, &mut buf).map_err(|err| format!("can't read the header: {}", err)));
+        let header = try!(FileHeader::decode(&buf));
+        try!(header.check(self.fingerprint));
+        self.page_sum = header.page_sum as usize;
+        self.root = if self.page_sum == 0 { None } else { Some(header.extra as usize) };
+        self.header_changed = false;
+        Ok(())
+    }
+    pub fn seal_for_table(&mut self, table : &str) {
+        // the files of a rewritten table are renamed after it's built,
+        // so the pages are sealed for the name the file will take
+        self.sealed_name = format!("{}.{}.index", table, self.name);
+    }
+    fn get_slot_size(&self) -> usize {
+        // sealed pages are larger than the page size
+        match self.cipher {
+            Some(..) => get_page_size() + NONCE_SIZE + TAG_SIZE,
+            None => get_page_size(),
+        }
+    }
+    fn get_page_offset(&self, page_index : usize) -> u64 {
+        // the first slot only save header for alignment
+        (self.get_slot_size() * (page_index + 1)) as u64
+    }
+    fn gen_page_aad(&self, page_index : usize) -> Vec<u8> {
+        format!("{}:{}", self.sealed_name, page_index).into_bytes()
+    }
+    pub fn read_page_from_file(&mut self, data : DataPtr, page_index : usize) -> Result<(), String> {
+        assert!(page_index < self.page_sum);
+        let page_size = get_page_size();
+        let page = unsafe{ from_raw_parts_mut::<u8>(data as *mut u8, page_size) };
+        let mut buf = vec![0; self.get_slot_size()];
+        is_match!(self.file.read_at(self.get_page_offset(page_index), &mut buf), Ok(..));
+        match self.cipher {
+            Some(ref cipher) => match cipher.open(&self.gen_page_aad(page_index), &buf) {
+                Ok(plain) => page.copy_from_slice(&plain[..page_size]),
+                Err(err) => return Err(format!("can't decrypt page {} of index {}: {}", page_index, self.name, err)),
+            },
+            None => page.copy_from_slice(&buf),
+        }
+        Ok(())
+    }
+    fn gen_page_data(&self, page_index : usize) -> Vec<u8> {
+        let page = self.loaded_pages.get(&page_index).unwrap();
+        let data = unsafe{ from_raw_parts::<u8>(page.borrow().data as *const u8, get_page_size()) };
+        match self.cipher {
+            Some(ref cipher) => cipher.seal(&self.gen_page_aad(page_index), data),
+            None => data.to_vec(),
+        }
+    }
+    pub fn save_to_file(&mut self) -> io::Result<()> {
+        // the first page only save header for alignment
+        let header = self.gen_header();
+        try!(self.file.write_at(0, &header));
+        // clean pages are the same as the file
+        let index_list : Vec<_> = self.loaded_pages.iter()
+            .filter(|&(_, page)| page.borrow().dirty).map(|(i, _)| *i).collect();
+        for i in index_list.iter() {
+            try!(self.save_page(*i));
+        }
+        Ok(())
+    }
+    fn gen_header(&self) -> Vec<u8> {
+        FileHeader::new(self.fingerprint, self.page_sum, self.root.unwrap_or(0)).encode()
+    }
+    pub fn gen_header_record(&self) -> LogRecord {
+        LogRecord::Write{ path : self.saved_name.clone(), offset : 0, data : self.gen_header() }
+    }
+    pub fn gen_page_record(&self, page_index : usize) -> LogRecord {
+        LogRecord::Write{
+            path : self.saved_name.clone(),
+            offset : self.get_page_offset(page_index),
+            data : self.gen_page_data(page_index),
+        }
+    }
+    pub fn save_page(&mut self, page_index : usize) -> io::Result<()> {
+        let data = self.gen_page_data(page_index);
+        try!(self.file.write_at(self.get_page_offset(page_index), &data));
+        self.loaded_pages.get(&page_index).unwrap().borrow_mut().dirty = false;
+        Ok(())
+    }
+    pub fn get_fd(&self) -> i32 {
+        self.file.get_id()
+    }
+    pub fn gen_key(&self, tuple : &Vec<TupleValue>) -> Vec<TupleValue> {
+        self.attr_index.iter().map(|i| tuple[*i].clone()).collect()
+    }
+}
+
+
+// access the b+tree nodes of an index through the page pool
+pub struct IndexStore<'a> {
+    manager : &'a mut TableFileManager,
+    index : IndexFileRef,
+}
+
+impl<'a> IndexStore<'a> {
+    pub fn new(manager : &'a mut TableFileManager, index : IndexFileRef) -> IndexStore<'a> {
+        IndexStore{
+            manager : manager,
+            index : index,
+        }
+    }
+    fn get_page_data(&mut self, page_index : usize) -> StorageResult<DataPtr> {
+        try!(self.manager.ensure_index_page_loaded(&self.index, page_index));
+        let index = self.index.borrow();
+        let data = index.loaded_pages.get(&page_index).unwrap().borrow().data;
+        Ok(data)
+    }
+}
+
+impl<'a> NodeStore for IndexStore<'a> {
+    fn get_root(&self) -> Option<usize> {
+        self.index.borrow().root
+    }
+    fn set_root(&mut self, root : usize) {
+        let mut index = self.index.borrow_mut();
+        index.root = Some(root);
+        index.header_changed = true;
+    }
+    fn read_node(&mut self, page_index : usize) -> StorageResult<Node> {
+        let data = try!(self.get_page_data(page_index));
+        Ok(Node::read_from_page(data, &self.index.borrow().key_desc))
+    }
+    fn write_node(&mut self, page_index : usize, node : &Node) -> StorageResult<()> {
+        let data = try!(self.get_page_data(page_index));
+        node.write_to_page(data, &self.index.borrow().key_desc);
+        self.index.borrow().loaded_pages.get(&page_index).unwrap().borrow_mut().mark_dirty();
+        Ok(())
+    }
+    fn alloc_node(&mut self) -> StorageResult<usize> {
+        let page_index = self.index.borrow().page_sum;
+        try!(self.manager.ensure_index_page_loaded(&self.index, page_index));
+        Ok(page_index)
+    }
+    fn max_entry_num(&self) -> usize {
+        max_entry_num(&self.index.borrow().key_desc, get_page_size())
+    }
+}
@@ -136,6 +136,33 @@ impl<ValueType : CacheValue> LruCache<ValueType> {
         dre!(*tail).value = None;
     }
 
+    pub fn remove(&mut self, key : &ValueType::KeyType) -> Option<ValueType> {
+        // move the removed node to the tail so that it will be reused first
+        let k = hash(key);
+        let node = match self.hash_map.remove(&k) {
+            Some(node) => node,
+            None => return None,
+        };
+        let value = dr!(node).value.take();
+        let head = &mut self.head;
+        let tail = &mut self.tail;
+        if node == *head {
+            *head = dr!(node).next;
+            *tail = node;
+        } else if node != *tail {
+            // remove
+            dr!(node.last).next = dr!(node).next;
+            dr!(node.next).last = dr!(node).last;
+            // add between tail and head
+            dr!(node).last = *tail;
+            dr!(node).next = *head;
+            dre!(*tail).next = node;
+            dre!(*head).last = node;
+            *tail = node;
+        }
+        value
+    }
+
     pub fn put(&mut self, key : &ValueType::KeyType, value : ValueType) {
         // before call this function, you should call prepare_page and remove_tail first
         let k = hash(key);
@@ -179,7 +206,7 @@ impl<ValueType : CacheValue> LruCache<ValueType> {
     pub fn capacity(&self) -> usize { self.capacity }
 }
 
-fn hash<T: Hash>(t: &T) -> u64 {
+pub fn hash<T: Hash>(t: &T) -> u64 {
     let mut s = SipHasher::new();
     t.hash(&mut s);
     s.finish()
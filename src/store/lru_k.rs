@@ -0,0 +1,111 @@
+use std::collections::{HashMap, VecDeque};
+use super::lru::{CacheValue, hash};
+use super::cache::ReplaceCache;
+
+
+// LRU-K evicts the value whose K-th most recent reference is the oldest.
+// Values referenced less than K times are evicted first in LRU order,
+// so pages read only once by a scan go before the pages looked up again and again.
+// Consecutive accesses to the same value are correlated and count as one reference.
+#[derive(Debug)]
+struct Entry<ValueType> {
+    value : ValueType,
+    history : VecDeque<u64>,  // time of the last K references, the latest at the back
+}
+
+#[derive(Debug)]
+pub struct LruKCache<ValueType : CacheValue> {
+    capacity : usize,
+    k : usize,
+    hash_map : HashMap<u64, Entry<ValueType>>,
+    time : u64,
+    last_key : Option<u64>,
+    victim : Option<u64>,
+}
+
+impl<ValueType : CacheValue> LruKCache<ValueType> {
+    pub fn new(capacity : usize, k : usize) -> LruKCache<ValueType> {
+        assert!(capacity > 0 && k > 0);
+        LruKCache{
+            capacity : capacity,
+            k : k,
+            hash_map : HashMap::with_capacity(capacity),
+            time : 0,
+            last_key : None,
+            victim : None,
+        }
+    }
+    fn touch(&mut self, k : u64) {
+        self.time += 1;
+        let entry = self.hash_map.get_mut(&k).unwrap();
+        if self.last_key == Some(k) {
+            *entry.history.back_mut().unwrap() = self.time;
+        } else {
+            entry.history.push_back(self.time);
+            if entry.history.len() > self.k {
+                entry.history.pop_front();
+            }
+        }
+        self.last_key = Some(k);
+    }
+    fn get_backward_time(&self, entry : &Entry<ValueType>) -> (bool, u64) {
+        // compared to find the victim, the smallest one is evicted
+        if entry.history.len() < self.k {
+            (false, *entry.history.back().unwrap())
+        } else {
+            (true, *entry.history.front().unwrap())
+        }
+    }
+}
+
+impl<ValueType : CacheValue> ReplaceCache<ValueType> for LruKCache<ValueType> {
+    fn get(&mut self, key : &ValueType::KeyType) -> Option<ValueType> {
+        let k = hash(key);
+        if !self.hash_map.contains_key(&k) {
+            return None;
+        }
+        self.touch(k);
+        Some(self.hash_map.get(&k).unwrap().value.clone())
+    }
+    fn prepare_page(&mut self) -> Option<ValueType> {
+        if self.hash_map.len() < self.capacity {
+            return None;
+        }
+        let victim = self.hash_map.iter()
+            .filter(|&(_, entry)| !entry.value.is_pinned())
+            .min_by_key(|&(_, entry)| self.get_backward_time(entry))
+            .map(|(k, _)| *k);
+        let victim = victim.expect("all pages were pinned");
+        self.victim = Some(victim);
+        Some(self.hash_map.get(&victim).unwrap().value.clone())
+    }
+    fn remove_tail(&mut self) {
+        if self.victim.is_none() {
+            assert!(self.prepare_page().is_some());
+        }
+        let k = self.victim.take().unwrap();
+        let entry = self.hash_map.remove(&k).unwrap();
+        assert!(!entry.value.is_pinned());
+    }
+    fn remove(&mut self, key : &ValueType::KeyType) -> Option<ValueType> {
+        let k = hash(key);
+        if self.victim == Some(k) {
+            self.victim = None;
+        }
+        self.hash_map.remove(&k).map(|entry| entry.value)
+    }
+    fn put(&mut self, key : &ValueType::KeyType, value : ValueType) {
+        // before call this function, you should call prepare_page and remove_tail first
+        let k = hash(key);
+        assert!(!self.hash_map.contains_key(&k));
+        assert!(self.hash_map.len() < self.capacity);
+        self.hash_map.insert(k, Entry{
+            value : value,
+            history : VecDeque::with_capacity(self.k + 1),
+        });
+        self.last_key = None;
+        self.touch(k);
+    }
+    fn capacity(&self) -> usize { self.capacity }
+    fn get_load(&self) -> usize { self.hash_map.len() }
+}
@@ -4,8 +4,40 @@ pub mod table;
 #[allow(unused_unsafe)]
 pub mod lru;
 #[allow(dead_code)]
+pub mod cache;
+#[allow(dead_code)]
+pub mod clock;
+#[allow(dead_code)]
+pub mod lru_k;
+#[allow(dead_code)]
+pub mod two_q;
+#[allow(dead_code)]
 pub mod buffer;
 #[allow(dead_code)]
 pub mod tuple;
 #[allow(dead_code)]
 pub mod file;
+#[allow(dead_code)]
+pub mod btree;
+#[allow(dead_code)]
+pub mod index;
+#[allow(dead_code)]
+pub mod wal;
+#[allow(dead_code)]
+pub mod fsm;
+#[allow(dead_code)]
+pub mod header;
+#[allow(dead_code)]
+pub mod error;
+#[allow(dead_code)]
+pub mod vfs;
+#[allow(dead_code)]
+pub mod prefetch;
+#[allow(dead_code)]
+pub mod compress;
+#[allow(dead_code)]
+pub mod page_map;
+#[allow(dead_code)]
+pub mod crypto;
+#[allow(dead_code)]
+pub mod checksum;
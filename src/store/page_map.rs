@@ -0,0 +1,111 @@
+use std::io;
+use ::utils::libwrapper::get_page_size;
+use ::utils::file::path_join;
+use super::wal::LogRecord;
+use super::vfs::{VfsRef, VfsFileRef};
+
+
+// Locations of the pages in a compressed or encrypted table file,
+// saved in a side file with 16 bytes for each page:
+// the offset as u64, the length of the stored page and the space allocated for it as u32, all little-endian.
+// The space is allocated in units of page_size / 16 after the header page.
+// A page is rewritten in place while it fits in its space, otherwise it's moved to the end of the file
+// and the old space is left unused until the table is vacuumed.
+// A page stored (or decrypted) with page_size bytes is not compressed.
+const ENTRY_SIZE : usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageLocation {
+    pub offset : u64,
+    pub len : usize,
+    pub capacity : usize,
+}
+
+#[derive(Debug)]
+pub struct PageMap {
+    pub saved_name : String,
+    pub file : VfsFileRef,
+    location_list : Vec<Option<PageLocation>>,  // None for pages never saved
+    end : u64,  // end of the allocated space
+    pub changed : bool,  // since the map was logged last time
+}
+
+impl PageMap {
+    pub fn new(table_file_name : &String, dir : &String, vfs : &VfsRef) -> PageMap {
+        let saved_name = path_join(dir, &format!("{}.pmap", table_file_name));
+        let file = vfs.open(&saved_name).unwrap();
+        PageMap{
+            saved_name : saved_name,
+            file : file,
+            location_list : Vec::new(),
+            end : get_page_size() as u64,
+            changed : true,
+        }
+    }
+    pub fn init_from_file(&mut self, page_sum : usize) {
+        let data = self.file.read_all().unwrap_or(Vec::new());
+        self.location_list = data.chunks(ENTRY_SIZE).filter(|e| e.len() == ENTRY_SIZE).map(|e| {
+            let location = PageLocation{
+                offset : read_u32(e, 0) as u64 | (read_u32(e, 4) as u64) << 32,
+                len : read_u32(e, 8) as usize,
+                capacity : read_u32(e, 12) as usize,
+            };
+            if location.capacity == 0 { None } else { Some(location) }
+        }).collect();
+        self.location_list.resize(page_sum, None);
+        self.end = self.location_list.iter().filter_map(|l| l.map(|l| l.offset + l.capacity as u64))
+            .max().unwrap_or(get_page_size() as u64);
+        self.changed = false;
+    }
+    pub fn save_to_file(&mut self) -> io::Result<()> {
+        let data = self.gen_data();
+        try!(self.file.truncate(0));
+        self.file.write_at(0, &data)
+    }
+    pub fn gen_record(&self) -> LogRecord {
+        LogRecord::Replace{ path : self.saved_name.clone(), data : self.gen_data() }
+    }
+    fn gen_data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        for location in self.location_list.iter() {
+            let l = location.unwrap_or(PageLocation{ offset : 0, len : 0, capacity : 0 });
+            for n in [l.offset as u32, (l.offset >> 32) as u32, l.len as u32, l.capacity as u32].iter() {
+                for i in 0..4 {
+                    data.push((n >> (i * 8)) as u8);
+                }
+            }
+        }
+        data
+    }
+    pub fn get(&self, page_index : usize) -> Option<PageLocation> {
+        self.location_list.get(page_index).cloned().unwrap_or(None)
+    }
+    pub fn place(&mut self, page_index : usize, len : usize) -> u64 {
+        // return the offset to write the page of len bytes
+        if page_index >= self.location_list.len() {
+            self.location_list.resize(page_index + 1, None);
+        }
+        if let Some(ref mut location) = self.location_list[page_index] {
+            if len <= location.capacity {
+                self.changed |= location.len != len;
+                location.len = len;
+                return location.offset;
+            }
+        }
+        self.changed = true;
+        let unit = get_page_size() / 16;
+        let capacity = (len + unit - 1) / unit * unit;
+        let offset = self.end;
+        self.end += capacity as u64;
+        self.location_list[page_index] = Some(PageLocation{ offset : offset, len : len, capacity : capacity });
+        offset
+    }
+}
+
+fn read_u32(buf : &[u8], offset : usize) -> u32 {
+    let mut n = 0;
+    for j in 0..4 {
+        n |= (buf[offset + j] as u32) << (j * 8);
+    }
+    n
+}
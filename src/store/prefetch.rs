@@ -0,0 +1,126 @@
+use std::boxed::Box;
+use std::collections::{HashSet, VecDeque};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread::spawn;
+use super::buffer::PageKey;
+use super::vfs::VfsFile;
+
+
+// Read-ahead of sequential scans.
+// An io thread reads the requested pages into buffers, a page waits in the buffer
+// and only takes a frame of the page pool when the scan reaches it,
+// so read-ahead never swaps out pinned or recently used pages.
+// At most window pages are being read or buffered, the oldest buffer is dropped when it's full.
+
+pub const DEFAULT_READ_AHEAD_PAGES : usize = 4;
+
+#[derive(Debug)]
+struct ReadRequest {
+    file : Box<VfsFile + Send>,
+    key : PageKey,
+    offset : u64,
+    len : usize,
+}
+
+#[derive(Debug)]
+struct ReadResult {
+    key : PageKey,
+    data : Option<Vec<u8>>,  // None if the read fails, the page is read again by the scan
+}
+
+#[derive(Debug)]
+pub struct Prefetcher {
+    window : usize,
+    sender : Sender<ReadRequest>,
+    receiver : Receiver<ReadResult>,
+    pending : HashSet<PageKey>,
+    ready : VecDeque<(PageKey, Vec<u8>)>,
+}
+
+impl Prefetcher {
+    pub fn new(window : usize) -> Prefetcher {
+        let (request_sender, request_receiver) = channel::<ReadRequest>();
+        let (result_sender, result_receiver) = channel();
+        // exit when the prefetcher is dropped
+        spawn(move || {
+            while let Ok(mut request) = request_receiver.recv() {
+                let mut data = vec![0; request.len];
+                let data = match request.file.read_at(request.offset, &mut data) {
+                    Ok(len) if len == request.len => Some(data),
+                    _ => None,
+                };
+                if result_sender.send(ReadResult{ key : request.key, data : data }).is_err() {
+                    break;
+                }
+            }
+        });
+        Prefetcher{
+            window : window,
+            sender : request_sender,
+            receiver : result_receiver,
+            pending : HashSet::new(),
+            ready : VecDeque::new(),
+        }
+    }
+    pub fn get_window(&self) -> usize {
+        self.window
+    }
+    pub fn request(&mut self, file : &VfsFile, key : PageKey, offset : u64, len : usize) {
+        self.collect();
+        if self.pending.contains(&key) || self.ready.iter().any(|&(k, _)| k == key) {
+            return;
+        }
+        if self.pending.len() >= self.window {
+            return;
+        }
+        let file = match file.clone_for_io() {
+            Some(file) => file,
+            None => return,
+        };
+        let request = ReadRequest{ file : file, key : key, offset : offset, len : len };
+        if self.sender.send(request).is_ok() {
+            self.pending.insert(key);
+        }
+    }
+    pub fn take(&mut self, key : &PageKey) -> Option<Vec<u8>> {
+        // wait for the page if it's being read, so that it's never read twice
+        self.collect();
+        while self.pending.contains(key) {
+            self.wait_one();
+        }
+        let i = match self.ready.iter().position(|&(k, _)| k == *key) {
+            Some(i) => i,
+            None => return None,
+        };
+        self.ready.remove(i).map(|(_, data)| data)
+    }
+    pub fn discard(&mut self, fd : i32) {
+        // called when the file is closed or changed without the page pool,
+        // the fd may be reused by another file
+        while self.pending.iter().any(|k| k.fd == fd) {
+            self.wait_one();
+        }
+        self.ready.retain(|&(k, _)| k.fd != fd);
+    }
+    pub fn get_prefetched_num(&self) -> usize {
+        self.pending.len() + self.ready.len()
+    }
+    fn collect(&mut self) {
+        while let Ok(result) = self.receiver.try_recv() {
+            self.add_result(result);
+        }
+    }
+    fn wait_one(&mut self) {
+        let result = self.receiver.recv().unwrap();
+        self.add_result(result);
+    }
+    fn add_result(&mut self, result : ReadResult) {
+        self.pending.remove(&result.key);
+        if let Some(data) = result.data {
+            if self.ready.len() >= self.window {
+                self.ready.pop_front();
+            }
+            self.ready.push_back((result.key, data));
+        }
+    }
+}
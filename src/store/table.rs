@@ -1,18 +1,21 @@
+use std::io;
 use std::vec::Vec;
 use std::collections::{BTreeMap, HashMap};
 use std::option::Option;
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::fs::OpenOptions;
-use std::io::{Read, Write};
 use rustc_serialize::{Encodable, Decodable, Encoder, Decoder};
 use rustc_serialize::json::{encode, decode};
 use ::parser::common::ValueList;
 use ::utils::config::Config;
-use ::utils::file::{path_join, ensure_dir_exist};
+use ::utils::file::path_join;
 use ::store::tuple::TupleValue;
 use super::tuple::TupleDesc;
 use super::file::TableFileManager;
+use super::wal::{Wal, LogRecord};
+use super::vfs::{VfsRef, DEFAULT_VFS, new_vfs};
+use super::compress::{DEFAULT_COMPRESSION, is_valid_compression};
+use super::error::{StorageError, StorageResult};
 
 
 macro_rules! unwrap {
@@ -29,6 +32,8 @@ pub enum AttrType {
     Int,
     Float,
     Char{ len : usize },
+    Varchar{ len : usize },  // variable-length, len is the max length
+    Text,
 }
 
 #[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
@@ -44,9 +49,19 @@ pub type TableRef = Rc<RefCell<Table>>;
 pub type IndexMap = HashMap<(String, String), usize>;
 
 #[derive(Debug, Clone, RustcDecodable, RustcEncodable)]
+pub struct IndexDesc {
+    pub name : String,
+    pub attr_list : Vec<String>,
+    pub unique : bool,
+}
+
+
+#[derive(Debug, Clone)]
 pub struct Table {
     pub name : String,
     pub attr_list : Vec<Attr>,
+    pub index_list : Vec<IndexDesc>,  // not including the primary key index
+    pub compression : Option<String>,  // page compression of the table file, None if not compressed
 }
 
 impl Table {
@@ -76,12 +91,25 @@ impl Table {
     pub fn get_attr_name_list(&self) -> Vec<String> {
         self.attr_list.iter().map(|a| a.name.clone()).collect()
     }
+    pub fn get_attr_index(&self, attr : &str) -> Option<usize> {
+        self.attr_list.iter().position(|a| a.name == attr)
+    }
+    pub fn get_index_desc(&self, index : &str) -> Option<IndexDesc> {
+        self.index_list.iter().filter(|i| i.name == index).next().cloned()
+    }
     pub fn desc(&self) -> String {
         let mut result = format!("table: {}\n", self.name);
         for attr in self.attr_list.iter() {
             result.push_str(&format!("{} {:?} {} {}\n", attr.name, attr.attr_type,
                 if attr.nullable {"null"}else{"not null"}, if attr.primary {"primary"}else{""}))
         }
+        for index in self.index_list.iter() {
+            result.push_str(&format!("{}index {} ({})\n",
+                if index.unique {"unique "}else{""}, index.name, index.attr_list.join(", ")))
+        }
+        if let Some(ref compression) = self.compression {
+            result.push_str(&format!("compression {}\n", compression));
+        }
         result
     }
 }
@@ -144,11 +172,21 @@ impl TableSet {
 
 pub type TableManagerRef = Rc<RefCell<TableManager>>;
 
+const WAL_CHECKPOINT_SIZE : u64 = 4 * 1024 * 1024;  // flush the dirty pages when the log grows larger
+const DEFAULT_CHECKPOINT_DIRTY_RATIO : usize = 50;  // percent of the page pool
+const DEFAULT_WORK_MEMORY_SIZE : usize = 4 * 1024 * 1024;
+const ENCRYPTED_CATALOG_MAGIC : &'static [u8] = b"BLSE";  // followed by the sealed json
+
 #[derive(Debug)]
 pub struct TableManager {
     tables : BTreeMap<String, TableRef>,
     pub file_manager : TableFileManager,
     table_meta_dir : String,
+    saved_catalog : String,  // catalog in table_meta.json or the log
+    checkpoint_dirty_ratio : usize,
+    default_compression : Option<String>,  // for the tables created without the compression option
+    failure : Option<String>,  // error of a failed rollback, no statement runs until the database is reopened
+    work_memory_size : usize,  // bytes of rows in memory for a sort, the outer block or the inner rows of a nested loop join or the hash table of a hash join
 }
 
 impl TableManager {
@@ -156,35 +194,196 @@ impl TableManager {
         Rc::new(RefCell::new(TableManager::new(config)))
     }
     pub fn new(config : &Config) -> TableManager {
+        let vfs = config.get_opt_str("vfs").unwrap_or(DEFAULT_VFS.to_string());
+        Self::with_vfs(config, new_vfs(&vfs))
+    }
+    pub fn with_vfs(config : &Config, vfs : VfsRef) -> TableManager {
         let table_meta_dir = config.get_str("table_meta_dir");
-        ensure_dir_exist(&table_meta_dir);
+        vfs.create_dir(&table_meta_dir);
+        let compression = config.get_opt_str("compression").unwrap_or(DEFAULT_COMPRESSION.to_string());
+        assert!(is_valid_compression(&compression), "invalid compression {}, should be one of none and lz4",
+            compression);
         TableManager{
             tables : BTreeMap::new(),
-            file_manager : TableFileManager::new(config),
+            file_manager : TableFileManager::with_vfs(config, vfs),
             table_meta_dir : table_meta_dir,
+            saved_catalog : String::new(),
+            failure : None,
+            checkpoint_dirty_ratio : config.get_opt_int("checkpoint_dirty_ratio")
+                .map_or(DEFAULT_CHECKPOINT_DIRTY_RATIO, |r| r as usize),
+            default_compression : if compression == "none" { None } else { Some(compression) },
+            work_memory_size : config.get_opt_int("work_memory_size")
+                .map_or(DEFAULT_WORK_MEMORY_SIZE, |n| n as usize),
         }
     }
-    pub fn save_to_file(&mut self) {
-        self.file_manager.save_all();
-        let full_path = path_join(&self.table_meta_dir, &"table_meta.json".to_string());
-        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(true).open(
-            &full_path).unwrap();
+    fn get_meta_path(&self) -> String {
+        path_join(&self.table_meta_dir, &"table_meta.json".to_string())
+    }
+    fn get_wal_path(&self) -> String {
+        path_join(&self.table_meta_dir, &"table_meta.wal".to_string())
+    }
+    pub fn save_to_file(&mut self) -> io::Result<()> {
+        try!(self.file_manager.save_all());
+        let full_path = self.get_meta_path();
+        let tmp_path = format!("{}.tmp", full_path);
         let json_str = self.to_json();
-        is_match!(file.write_all(json_str.as_bytes()), Ok(..));
+        let vfs = self.file_manager.vfs.clone();
+        {
+            let mut file = try!(vfs.open(&tmp_path));
+            try!(file.truncate(0));
+            try!(file.write_at(0, &self.encode_catalog(&json_str)));
+            try!(file.fsync());
+        }
+        // replace the catalog atomically
+        try!(vfs.rename(&tmp_path, &full_path));
+        self.saved_catalog = json_str;
+        self.file_manager.remove_files();
+        Ok(())
+    }
+    pub fn commit(&mut self) -> io::Result<()> {
+        // make the changes of the finished statement durable,
+        // with the log only the pages changed by the statement are written.
+        // The statement can only be acknowledged if this returns Ok
+        if !self.file_manager.has_wal() {
+            return self.save_to_file();
+        }
+        let json_str = self.to_json();
+        if let Err(err) = self.log_changes(&json_str) {
+            // the error of the commit is reported, a failed rollback is left in failure
+            let _ = self.rollback();
+            return Err(err);
+        }
+        self.saved_catalog = json_str;
+        self.file_manager.remove_files();
+        Ok(())
+    }
+    fn log_changes(&mut self, json_str : &String) -> io::Result<()> {
+        if *json_str != self.saved_catalog {
+            let path = self.get_meta_path();
+            let data = self.encode_catalog(json_str);
+            try!(self.file_manager.log(&LogRecord::Replace{ path : path, data : data }));
+        }
+        try!(self.file_manager.log_removed_files());
+        try!(self.file_manager.log_dirty_pages());
+        self.file_manager.commit_wal()
+    }
+    pub fn rollback(&mut self) -> io::Result<()> {
+        // undo the changes of a failed statement. The pool is dropped,
+        // the pages swapped out by the statement are reverted by their undo records in the log
+        // and the committed state is reloaded from the files and the log.
+        // Without the log the changes may have been written and can't be undone
+        if !self.file_manager.has_wal() {
+            return Ok(());
+        }
+        if self.to_json() == self.saved_catalog && !self.file_manager.has_uncommitted_changes() {
+            return Ok(());  // e.g. an insert rejected by the primary key check
+        }
+        let wal_path = self.get_wal_path();
+        let vfs = self.file_manager.vfs.clone();
+        self.file_manager.close_all();
+        let result = Wal::recover(&wal_path, &vfs).and_then(|_| self.file_manager.discard_wal());
+        let result = result.and_then(|_| {
+            let json_str = self.saved_catalog.clone();
+            self.load_catalog(&json_str, true);
+            self.checkpoint()
+        });
+        if let Err(ref err) = result {
+            // the pool and the files may not match any more,
+            // the statement cut by the failure is undone at the next start
+            self.failure = Some(format!("can't roll back the statement: {}", err));
+        }
+        result
+    }
+    pub fn get_failure(&self) -> Option<String> {
+        self.failure.clone()
+    }
+    pub fn checkpoint(&mut self) -> io::Result<()> {
+        // flush the dirty pages so that the log can be dropped,
+        // the log is kept if any of them fails to be written or the rollback has failed
+        if let Some(ref err_msg) = self.failure {
+            return Err(io::Error::new(io::ErrorKind::Other, err_msg.clone()));
+        }
+        try!(self.save_to_file());
+        try!(self.file_manager.sync_all());
+        self.file_manager.truncate_wal()
+    }
+    pub fn need_checkpoint(&self) -> bool {
+        let dirty_num = self.file_manager.get_dirty_page_num();
+        let capacity = self.file_manager.page_pool.get_capacity();
+        self.file_manager.get_wal_size() > WAL_CHECKPOINT_SIZE
+            || dirty_num * 100 >= capacity * self.checkpoint_dirty_ratio && dirty_num > 0
+    }
+    pub fn checkpoint_if_needed(&mut self, timeout : bool) -> io::Result<bool> {
+        // called between statements, the interval timeout flushes any dirty page
+        if self.failure.is_some() {
+            return Ok(false);
+        }
+        let dirty = self.file_manager.get_dirty_page_num() > 0;
+        if self.need_checkpoint() || timeout && dirty {
+            try!(self.checkpoint());
+            return Ok(true);
+        }
+        Ok(false)
     }
     pub fn from_json_file(config : &Config) -> TableManager {
-        let table_meta_dir = config.get_str("table_meta_dir");
-        ensure_dir_exist(&table_meta_dir);
-        let full_path = path_join(&table_meta_dir, &"table_meta.json".to_string());
-        let mut file = OpenOptions::new().read(true).write(true).create(true).open(
-            &full_path).unwrap();
-        let mut json_str = String::new();
-        assert!(file.read_to_string(&mut json_str).is_ok());
-        if json_str.len() == 0 { return TableManager::new(config) }
-        Self::from_json(config, &json_str, true)
+        let vfs = config.get_opt_str("vfs").unwrap_or(DEFAULT_VFS.to_string());
+        Self::from_json_file_with_vfs(config, new_vfs(&vfs))
+    }
+    pub fn from_json_file_with_vfs(config : &Config, vfs : VfsRef) -> TableManager {
+        let mut manager = TableManager::with_vfs(config, vfs.clone());
+        let wal_path = manager.get_wal_path();
+        // undo the statement cut by a crash and redo the committed changes before loading the catalog
+        if let Err(err) = Wal::recover(&wal_path, &vfs) {
+            panic!("can't redo the log {}: {}", wal_path, err);
+        }
+        let data = vfs.open(&manager.get_meta_path()).and_then(|mut file| file.read_all()).unwrap();
+        if data.len() != 0 {
+            let json_str = match manager.decode_catalog(data) {
+                Ok(json_str) => json_str,
+                Err(err) => panic!("can't open the catalog {}: {}", manager.get_meta_path(), err),
+            };
+            manager.load_catalog(&json_str, true);
+        }
+        manager.file_manager.open_wal(&wal_path);
+        if let Err(err) = manager.checkpoint() {
+            panic!("can't flush the redone changes: {}", err);
+        }
+        manager
+    }
+    fn encode_catalog(&self, json_str : &String) -> Vec<u8> {
+        match self.file_manager.cipher {
+            Some(ref cipher) => {
+                let mut data = ENCRYPTED_CATALOG_MAGIC.to_vec();
+                data.extend(cipher.seal(b"catalog", json_str.as_bytes()));
+                data
+            }
+            None => json_str.as_bytes().to_vec(),
+        }
+    }
+    fn decode_catalog(&self, data : Vec<u8>) -> Result<String, String> {
+        let encrypted = data.starts_with(ENCRYPTED_CATALOG_MAGIC);
+        let data = match self.file_manager.cipher {
+            Some(ref cipher) if encrypted => {
+                match cipher.open(b"catalog", &data[ENCRYPTED_CATALOG_MAGIC.len()..]) {
+                    Ok(data) => data,
+                    Err(..) => return Err("wrong encryption key or the catalog is corrupted".to_string()),
+                }
+            }
+            Some(..) => return Err("the catalog is not encrypted, \
+                encryption can only be enabled for a new database".to_string()),
+            None if encrypted => return Err("the catalog is encrypted, \
+                set encryption_key_file to open the database".to_string()),
+            None => data,
+        };
+        String::from_utf8(data).map_err(|err| format!("invalid catalog: {}", err))
     }
     pub fn from_json(config : &Config, json : &String, init_file : bool) -> TableManager {
         // setting init_file to false only for tests
+        let mut manager = Self::new(config);
+        manager.load_catalog(json, init_file);
+        manager
+    }
+    fn load_catalog(&mut self, json : &String, init_file : bool) {
         let mut tables = BTreeMap::new();
         let mut table_list = Vec::new();
         let tree : BTreeMap<String, Table> = unwrap!(decode(json));
@@ -193,12 +392,10 @@ impl TableManager {
             tables.insert(name.clone(), t.clone());
             table_list.push(t);
         }
-        let mut manager = Self::new(config);
-        manager.tables = tables;
+        self.tables = tables;
         if init_file {
-            manager.file_manager.init_from_file(table_list);
+            self.file_manager.init_from_file(table_list);
         }
-        manager
     }
     pub fn to_json(&self) -> String {
         let mut tree : BTreeMap<String, Table> = BTreeMap::new();
@@ -207,16 +404,74 @@ impl TableManager {
         }
         unwrap!(encode(&tree))
     }
+    pub fn get_work_memory_size(&self) -> usize {
+        self.work_memory_size
+    }
+    pub fn get_default_compression(&self) -> Option<String> {
+        self.default_compression.clone()
+    }
     pub fn add_table(&mut self, table : Table) {
         // add new table and create empty file
         let name = table.name.clone();
         assert!(!self.tables.get(&name).is_some());
         let table_ref = Rc::new(RefCell::new(table));
         self.file_manager.create_file(name.clone(), table_ref.clone());
+        self.file_manager.clear_files(&name);  // files of a dropped table may be left by a crash
         self.tables.insert(name, table_ref);
     }
     pub fn remove_table(&mut self, table : &String) {
+        // the files are deleted when the removal from the catalog is committed
         self.tables.remove(table);
+        self.file_manager.drop_file(table);
+    }
+    pub fn create_index(&mut self, table : &String, index : IndexDesc) -> StorageResult<bool> {
+        // return false if the index is unique but duplicate keys exist
+        let table_ref = self.tables.get(table).unwrap().clone();
+        if !try!(self.file_manager.create_index(&table_ref, &index)) {
+            return Ok(false);
+        }
+        table_ref.borrow_mut().index_list.push(index);
+        Ok(true)
+    }
+    pub fn drop_index(&mut self, table : &String, index : &String) {
+        let table_ref = self.tables.get(table).unwrap().clone();
+        self.file_manager.drop_index(table, index);
+        table_ref.borrow_mut().index_list.retain(|i| i.name != *index);
+    }
+    pub fn vacuum(&mut self, table : &String) -> StorageResult<Option<usize>> {
+        // return the number of reclaimed pages, None if any page of the table is in use
+        try!(self.checkpoint().map_err(|err| StorageError::from_io(table, err)));
+        let reclaimed = try!(self.file_manager.vacuum(table));
+        try!(self.checkpoint().map_err(|err| StorageError::from_io(table, err)));
+        Ok(reclaimed)
+    }
+    pub fn alter_table(&mut self, table : &String, new_table : Table,
+            convert : &Fn(Vec<TupleValue>) -> Vec<TupleValue>) -> StorageResult<bool> {
+        // rewrite the tuples of the table by convert to the layout of new_table,
+        // the new files and the catalog are switched in one commit.
+        // return false if any page of the table is in use
+        try!(self.checkpoint().map_err(|err| StorageError::from_io(table, err)));
+        let old_table = self.tables.remove(table).unwrap();
+        let new_name = new_table.name.clone();
+        let table_ref = Rc::new(RefCell::new(new_table));
+        self.tables.insert(new_name.clone(), table_ref.clone());
+        let json_str = self.to_json();
+        let record = LogRecord::Replace{ path : self.get_meta_path(), data : self.encode_catalog(&json_str) };
+        match self.file_manager.rewrite_files(table, table_ref, convert, vec![record]) {
+            Ok(true) => {
+                self.saved_catalog = json_str;
+                try!(self.checkpoint().map_err(|err| StorageError::from_io(&new_name, err)));
+                Ok(true)
+            }
+            result => {
+                self.tables.remove(&new_name);
+                self.tables.insert(table.clone(), old_table);
+                result
+            }
+        }
+    }
+    pub fn get_table_names(&self) -> Vec<String> {
+        self.tables.keys().cloned().collect()
     }
     pub fn get_table(&self, name : &str) -> Option<TableRef> {
         match self.tables.get(name) {
@@ -238,8 +493,8 @@ impl TableManager {
             attr_position : usize) -> TupleValue{
         self.file_manager.get_tuple_value(table, position, attr_position)
     }
-    pub fn insert(&mut self, table : &String, value_list : &ValueList) {
-        self.file_manager.insert(table, value_list);
+    pub fn insert(&mut self, table : &String, value_list : &ValueList) -> StorageResult<()> {
+        self.file_manager.insert(table, value_list)
     }
     pub fn show_tables(&self) -> String {
         let mut result = String::new();
@@ -251,6 +506,41 @@ impl TableManager {
     }
 }
 
+impl Encodable for Table {
+    fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
+        // compression is left out for uncompressed tables, so their meta is the same as the old version
+        let field_num = if self.compression.is_some() { 4 } else { 3 };
+        s.emit_struct("Table", field_num, |s| {
+            try!(s.emit_struct_field("name", 0, |s| self.name.encode(s)));
+            try!(s.emit_struct_field("attr_list", 1, |s| self.attr_list.encode(s)));
+            try!(s.emit_struct_field("index_list", 2, |s| self.index_list.encode(s)));
+            if let Some(ref compression) = self.compression {
+                try!(s.emit_struct_field("compression", 3, |s| compression.encode(s)));
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Decodable for Table {
+    fn decode<D: Decoder>(d: &mut D) -> Result<Self, D::Error> {
+        d.read_struct("Table", 4, |d| {
+            let name = try!(d.read_struct_field("name", 0, Decodable::decode));
+            let attr_list = try!(d.read_struct_field("attr_list", 1, Decodable::decode));
+            // index_list not exist in the table meta saved by old version
+            let index_list : Option<Vec<IndexDesc>> =
+                try!(d.read_struct_field("index_list", 2, Decodable::decode));
+            let compression = try!(d.read_struct_field("compression", 3, Decodable::decode));
+            Ok(Table{
+                name : name,
+                attr_list : attr_list,
+                index_list : index_list.unwrap_or(Vec::new()),
+                compression : compression,
+            })
+        })
+    }
+}
+
 impl Encodable for AttrType {
     fn encode<S: Encoder>(&self, s: &mut S) -> Result<(), S::Error> {
         let mut t = BTreeMap::new();
@@ -261,6 +551,11 @@ impl Encodable for AttrType {
                 t.insert("type".to_string(), "Char".to_string());
                 t.insert("len".to_string(), len.to_string())
             }
+            &AttrType::Varchar{len} => {
+                t.insert("type".to_string(), "Varchar".to_string());
+                t.insert("len".to_string(), len.to_string())
+            }
+            &AttrType::Text => t.insert("type".to_string(), "Text".to_string()),
         };
         t.encode(s)
     }
@@ -281,6 +576,14 @@ impl Decodable for AttrType {
                     };
                     AttrType::Char{ len : len }
                 }
+                "Varchar" => {
+                    let len = match t.get("len") {
+                        None => panic!("can't find key 'len' for Varchar in AttrType json data"),
+                        Some(len) => len.parse::<usize>().unwrap(),
+                    };
+                    AttrType::Varchar{ len : len }
+                }
+                "Text" => AttrType::Text,
                 _ => panic!("unexpected type {}", s),
             }
         };
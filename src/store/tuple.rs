@@ -1,6 +1,10 @@
 use std::vec::Vec;
-use std::ptr::read;
-use utils::pointer::read_string;
+use std::ptr::{read, write, null_mut};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use utils::pointer::{read_string, write_string, pointer_offset};
+use utils::pointer::{read_u32_le, write_u32_le, read_i32_le, write_i32_le, read_f32_le, write_f32_le};
+use ::parser::common::{ValueList, ValueType};
 use super::buffer::DataPtr;
 use super::table::{AttrType, Attr};
 
@@ -10,48 +14,244 @@ pub enum TupleValue {
     Int(i32),
     Float(f32),
     Char(String),
+    Null,
 }
 
 #[derive(Debug, Clone)]
 pub struct TupleDesc {
     pub attr_desc : Vec<AttrType>,
-    pub tuple_len : usize,
+    pub min_tuple_len : usize,  // the real length is larger if any variable-length attribute is not empty
 }
 
 impl TupleDesc {
     pub fn new(attr_list : &Vec<Attr>) -> TupleDesc {
-        let attr_desc = attr_list.iter().map(|attr| attr.attr_type.clone()).collect();
-        let tuple_len = tuple_len(attr_list);
+        let attr_desc : Vec<AttrType> = attr_list.iter().map(|attr| attr.attr_type.clone()).collect();
+        let min_tuple_len = min_tuple_len(&attr_desc);
         TupleDesc{
             attr_desc : attr_desc,
-            tuple_len : tuple_len,
+            min_tuple_len : min_tuple_len,
         }
     }
 }
 
+// the pointer of a null attribute is null,
+// the pointer of a variable-length attribute points to its length header
 pub type TupleData = Vec<DataPtr>;
 
-pub fn tuple_len(attr_list : &Vec<Attr>) -> usize {
-    let mut l = 0;
-    for attr in attr_list {
-        l += match attr.attr_type {
-            AttrType::Int | AttrType::Float => 4,
-            AttrType::Char{len} => (len + 3) / 4 * 4,  // align to 4 bytes
-        }
+// an owned copy of a tuple, stays valid after its page is swapped out
+pub type Row = Vec<TupleValue>;
+
+// Tuple layout: null bitmap, then the attributes in order.
+// Int, Float and Char take fixed length aligned to 4 bytes.
+// Varchar and Text are stored as a u32 length header followed by the aligned content,
+// at least 4 bytes content is reserved so that the value can always be replaced
+// by an overflow reference, which is the header with OVERFLOW_FLAG and the first overflow page.
+const VAR_ATTR_HEADER_LEN : usize = 4;
+const MIN_VAR_CONTENT_LEN : usize = 4;
+const OVERFLOW_FLAG : u32 = 1 << 31;
+
+pub fn min_tuple_len(attr_desc : &Vec<AttrType>) -> usize {
+    let mut l = null_bitmap_len(attr_desc.len());
+    for attr_type in attr_desc {
+        l += min_attr_len(attr_type);
     }
     l
 }
 
+pub fn min_attr_len(attr_type : &AttrType) -> usize {
+    match attr_type {
+        &AttrType::Int | &AttrType::Float => 4,
+        &AttrType::Char{len} => (len + 3) / 4 * 4,  // align to 4 bytes
+        &AttrType::Varchar{..} | &AttrType::Text => VAR_ATTR_HEADER_LEN + MIN_VAR_CONTENT_LEN,
+    }
+}
+
+pub fn is_var_len(attr_type : &AttrType) -> bool {
+    match attr_type {
+        &AttrType::Varchar{..} | &AttrType::Text => true,
+        _ => false,
+    }
+}
+
+pub fn var_attr_len(content_len : usize) -> usize {
+    // length of the inline variable-length attribute
+    let aligned = (content_len + 3) / 4 * 4;
+    VAR_ATTR_HEADER_LEN + if aligned < MIN_VAR_CONTENT_LEN { MIN_VAR_CONTENT_LEN } else { aligned }
+}
+
+pub fn stored_attr_len(p : DataPtr, attr_type : &AttrType) -> usize {
+    if !is_var_len(attr_type) {
+        return min_attr_len(attr_type);
+    }
+    match read_overflow_ref(p) {
+        Some(..) => VAR_ATTR_HEADER_LEN + MIN_VAR_CONTENT_LEN,
+        None => var_attr_len(unsafe{ read_u32_le(p) } as usize),
+    }
+}
+
+pub fn read_overflow_ref(p : DataPtr) -> Option<(usize, usize)> {
+    // return the content length and the first overflow page if the value is not inline
+    let header = unsafe{ read_u32_le(p) };
+    if header & OVERFLOW_FLAG == 0 {
+        return None;
+    }
+    let first_page = unsafe{ read_u32_le(pointer_offset(p, 4)) };
+    Some(((header & !OVERFLOW_FLAG) as usize, first_page as usize))
+}
+
+pub fn read_var_string(p : DataPtr) -> String {
+    assert!(read_overflow_ref(p).is_none());
+    let len = unsafe{ read_u32_le(p) } as usize;
+    let content = pointer_offset(p, VAR_ATTR_HEADER_LEN);
+    let mut s = String::with_capacity(len);
+    for i in 0..len {
+        let n = unsafe{ read::<u8>((content as *const u8).offset(i as isize)) };
+        if n == 0 {
+            break;
+        }
+        s.push(n as char);
+    }
+    s
+}
+
+pub fn null_bitmap_len(attr_num : usize) -> usize {
+    // the null bitmap is in the beginning of tuple, align to 4 bytes
+    (attr_num + 31) / 32 * 4
+}
+
+pub unsafe fn is_attr_null(tuple : DataPtr, attr_position : usize) -> bool {
+    let byte = read::<u8>((tuple as *const u8).offset((attr_position / 8) as isize));
+    byte & (1 << (attr_position % 8)) != 0
+}
+
+pub unsafe fn set_attr_null(tuple : DataPtr, attr_position : usize, null : bool) {
+    let p = (tuple as *mut u8).offset((attr_position / 8) as isize);
+    let mask = 1 << (attr_position % 8);
+    write::<u8>(p, if null { read::<u8>(p) | mask } else { read::<u8>(p) & !mask });
+}
+
+pub fn gen_tuple_data(tuple : DataPtr, attr_desc : &Vec<AttrType>) -> TupleData {
+    let mut tuple_data = Vec::with_capacity(attr_desc.len());
+    let mut p = pointer_offset(tuple, null_bitmap_len(attr_desc.len()));
+    for (i, attr_type) in attr_desc.iter().enumerate() {
+        if unsafe{ is_attr_null(tuple, i) } {
+            tuple_data.push(null_mut());
+        } else {
+            tuple_data.push(p);
+        }
+        p = pointer_offset(p, stored_attr_len(p, attr_type));
+    }
+    tuple_data
+}
+
+pub fn read_tuple_value(p : DataPtr, attr_type : &AttrType) -> TupleValue {
+    if p.is_null() {
+        return TupleValue::Null;
+    }
+    match attr_type {
+        &AttrType::Int => TupleValue::Int(unsafe{read_i32_le(p)}),
+        &AttrType::Float => TupleValue::Float(unsafe{read_f32_le(p)}),
+        &AttrType::Char{len} => TupleValue::Char(unsafe{read_string(p, len)}),
+        &AttrType::Varchar{..} | &AttrType::Text => TupleValue::Char(read_var_string(p)),
+    }
+}
+
 pub fn gen_tuple_value(attr_desc : &Vec<AttrType>, tuple_data : TupleData) -> Vec<TupleValue> {
-    let mut value_list = Vec::new();
     assert_eq!(attr_desc.len(), tuple_data.len());
-    for (attr, p) in attr_desc.iter().zip(tuple_data.iter()) {
-        let value = match attr {
-            &AttrType::Int => TupleValue::Int(unsafe{read::<i32>(*p as *const i32)}),
-            &AttrType::Float => TupleValue::Float(unsafe{read::<f32>(*p as *const f32)}),
-            &AttrType::Char{len} => TupleValue::Char(unsafe{read_string(*p, len)}),
+    attr_desc.iter().zip(tuple_data.iter()).map(|(attr, p)| read_tuple_value(*p, attr)).collect()
+}
+
+pub fn gen_value_list(value_list : &ValueList, attr_desc : &Vec<AttrType>) -> Vec<TupleValue> {
+    assert_eq!(value_list.len(), attr_desc.len());
+    value_list.iter().zip(attr_desc.iter()).map(|(v, attr_type)| {
+        match (v.value_type, attr_type) {
+            (ValueType::Integer, &AttrType::Int) => TupleValue::Int(v.value.parse::<i32>().unwrap()),
+            (ValueType::Float, &AttrType::Float) | (ValueType::Integer, &AttrType::Float) =>
+                TupleValue::Float(v.value.parse::<f32>().unwrap()),
+            (ValueType::String, &AttrType::Char{..}) | (ValueType::String, &AttrType::Varchar{..})
+            | (ValueType::String, &AttrType::Text) => TupleValue::Char(v.value.clone()),
+            (ValueType::Null, _) => TupleValue::Null,
+            _ => panic!("invalid value, expected {:?}, found {:?}", attr_type, v),
+        }
+    }).collect()
+}
+
+pub fn inline_attr_len(value : &TupleValue, attr_type : &AttrType) -> usize {
+    match (value, attr_type) {
+        (&TupleValue::Char(ref s), &AttrType::Varchar{..})
+        | (&TupleValue::Char(ref s), &AttrType::Text) => var_attr_len(s.len()),
+        _ => min_attr_len(attr_type),
+    }
+}
+
+pub fn encode_tuple(attr_desc : &Vec<AttrType>,
+        value_list : &Vec<TupleValue>,
+        overflow : &HashMap<usize, usize>) -> Vec<u8> {
+    // overflow maps the attributes not stored inline to their first overflow page
+    assert_eq!(value_list.len(), attr_desc.len());
+    let mut len = null_bitmap_len(attr_desc.len());
+    for (i, (v, attr_type)) in value_list.iter().zip(attr_desc.iter()).enumerate() {
+        len += if overflow.contains_key(&i) {
+            min_attr_len(attr_type)
+        } else {
+            inline_attr_len(v, attr_type)
         };
-        value_list.push(value);
     }
-    value_list
+    let mut buf = vec![0u8; len];
+    let tuple = buf.as_mut_ptr() as DataPtr;
+    let mut p = pointer_offset(tuple, null_bitmap_len(attr_desc.len()));
+    for (i, (v, attr_type)) in value_list.iter().zip(attr_desc.iter()).enumerate() {
+        if let Some(first_page) = overflow.get(&i) {
+            let content_len = extract!(v, &TupleValue::Char(ref s), s.len());
+            unsafe{
+                write_u32_le(p, content_len as u32 | OVERFLOW_FLAG);
+                write_u32_le(pointer_offset(p, 4), *first_page as u32);
+            }
+            p = pointer_offset(p, min_attr_len(attr_type));
+            continue;
+        }
+        unsafe{
+            match (v, attr_type) {
+                (&TupleValue::Null, _) => set_attr_null(tuple, i, true),
+                (&TupleValue::Int(n), &AttrType::Int) => write_i32_le(p, n),
+                (&TupleValue::Int(n), &AttrType::Float) => write_f32_le(p, n as f32),
+                (&TupleValue::Float(n), &AttrType::Float) => write_f32_le(p, n),
+                (&TupleValue::Char(ref s), &AttrType::Char{len}) => write_string(p, s, len),
+                (&TupleValue::Char(ref s), &AttrType::Varchar{..})
+                | (&TupleValue::Char(ref s), &AttrType::Text) => {
+                    write_u32_le(p, s.len() as u32);
+                    write_string(pointer_offset(p, VAR_ATTR_HEADER_LEN), s, s.len());
+                }
+                _ => panic!("invalid value, expected {:?}, found {:?}", attr_type, v),
+            }
+        }
+        p = pointer_offset(p, inline_attr_len(v, attr_type));
+    }
+    buf
+}
+
+pub fn cmp_tuple_value(lhs : &TupleValue, rhs : &TupleValue) -> Ordering {
+    match (lhs, rhs) {
+        // null is less than any other value
+        (&TupleValue::Null, &TupleValue::Null) => Ordering::Equal,
+        (&TupleValue::Null, _) => Ordering::Less,
+        (_, &TupleValue::Null) => Ordering::Greater,
+        (&TupleValue::Int(l), &TupleValue::Int(r)) => l.cmp(&r),
+        (&TupleValue::Char(ref l), &TupleValue::Char(ref r)) => l.cmp(r),
+        (&TupleValue::Char(..), _) | (_, &TupleValue::Char(..)) =>
+            panic!("can't compare {:?} with {:?}", lhs, rhs),
+        _ => {
+            let l = tuple_value_to_float(lhs);
+            let r = tuple_value_to_float(rhs);
+            l.partial_cmp(&r).unwrap_or(Ordering::Equal)
+        }
+    }
+}
+
+fn tuple_value_to_float(value : &TupleValue) -> f32 {
+    match value {
+        &TupleValue::Int(n) => n as f32,
+        &TupleValue::Float(n) => n,
+        &TupleValue::Char(..) | &TupleValue::Null => panic!("expected number, found {:?}", value),
+    }
 }
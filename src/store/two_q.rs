@@ -0,0 +1,141 @@
+use std::collections::{HashMap, BTreeMap, VecDeque};
+use std::cmp::max;
+use super::lru::{CacheValue, hash};
+use super::cache::ReplaceCache;
+
+
+// 2Q keeps the values referenced only once in the FIFO queue a1in
+// and the values referenced again in the LRU queue am.
+// Keys evicted from a1in are remembered in a1out,
+// a value put again while its key is in a1out goes to am directly.
+// A scan only flows through a1in and can't push the hot values out of am.
+#[derive(Debug)]
+struct Entry<ValueType> {
+    value : ValueType,
+    in_am : bool,
+    time : u64,  // key in a1in or am
+}
+
+#[derive(Debug)]
+pub struct TwoQueueCache<ValueType : CacheValue> {
+    capacity : usize,
+    kin : usize,  // a1in is preferred to evict when it's longer than kin
+    kout : usize,  // max length of a1out
+    hash_map : HashMap<u64, Entry<ValueType>>,
+    a1in : BTreeMap<u64, u64>,  // time to key, ordered by the first reference
+    am : BTreeMap<u64, u64>,  // time to key, ordered by the last reference
+    a1out : VecDeque<u64>,
+    time : u64,
+    victim : Option<u64>,
+}
+
+impl<ValueType : CacheValue> TwoQueueCache<ValueType> {
+    pub fn new(capacity : usize) -> TwoQueueCache<ValueType> {
+        assert!(capacity > 0);
+        TwoQueueCache{
+            capacity : capacity,
+            kin : max(capacity / 4, 1),
+            kout : max(capacity / 2, 1),
+            hash_map : HashMap::with_capacity(capacity),
+            a1in : BTreeMap::new(),
+            am : BTreeMap::new(),
+            a1out : VecDeque::new(),
+            time : 0,
+            victim : None,
+        }
+    }
+    fn find_unpinned(&self, queue : &BTreeMap<u64, u64>) -> Option<u64> {
+        queue.values().find(|k| !self.hash_map.get(k).unwrap().value.is_pinned()).cloned()
+    }
+    fn remove_from_queue(&mut self, k : u64) -> Option<Entry<ValueType>> {
+        let entry = match self.hash_map.remove(&k) {
+            Some(entry) => entry,
+            None => return None,
+        };
+        if entry.in_am {
+            assert!(self.am.remove(&entry.time).is_some());
+        } else {
+            assert!(self.a1in.remove(&entry.time).is_some());
+        }
+        Some(entry)
+    }
+}
+
+impl<ValueType : CacheValue> ReplaceCache<ValueType> for TwoQueueCache<ValueType> {
+    fn get(&mut self, key : &ValueType::KeyType) -> Option<ValueType> {
+        // references in a1in are correlated to the first one and ignored
+        let k = hash(key);
+        self.time += 1;
+        let time = self.time;
+        let entry = match self.hash_map.get_mut(&k) {
+            Some(entry) => entry,
+            None => return None,
+        };
+        if entry.in_am {
+            assert!(self.am.remove(&entry.time).is_some());
+            entry.time = time;
+            self.am.insert(time, k);
+        }
+        Some(entry.value.clone())
+    }
+    fn prepare_page(&mut self) -> Option<ValueType> {
+        if self.hash_map.len() < self.capacity {
+            return None;
+        }
+        let victim = if self.a1in.len() > self.kin || self.am.is_empty() {
+            self.find_unpinned(&self.a1in).or_else(|| self.find_unpinned(&self.am))
+        } else {
+            self.find_unpinned(&self.am).or_else(|| self.find_unpinned(&self.a1in))
+        };
+        let victim = victim.expect("all pages were pinned");
+        self.victim = Some(victim);
+        Some(self.hash_map.get(&victim).unwrap().value.clone())
+    }
+    fn remove_tail(&mut self) {
+        if self.victim.is_none() {
+            assert!(self.prepare_page().is_some());
+        }
+        let k = self.victim.take().unwrap();
+        let entry = self.remove_from_queue(k).unwrap();
+        assert!(!entry.value.is_pinned());
+        if !entry.in_am {
+            self.a1out.push_back(k);
+            if self.a1out.len() > self.kout {
+                self.a1out.pop_front();
+            }
+        }
+    }
+    fn remove(&mut self, key : &ValueType::KeyType) -> Option<ValueType> {
+        let k = hash(key);
+        if self.victim == Some(k) {
+            self.victim = None;
+        }
+        self.remove_from_queue(k).map(|entry| entry.value)
+    }
+    fn put(&mut self, key : &ValueType::KeyType, value : ValueType) {
+        // before call this function, you should call prepare_page and remove_tail first
+        let k = hash(key);
+        assert!(!self.hash_map.contains_key(&k));
+        assert!(self.hash_map.len() < self.capacity);
+        self.time += 1;
+        let in_am = match self.a1out.iter().position(|out| *out == k) {
+            Some(i) => {
+                self.a1out.remove(i);
+                true
+            }
+            None => false,
+        };
+        if in_am {
+            self.am.insert(self.time, k);
+        } else {
+            self.a1in.insert(self.time, k);
+        }
+        self.hash_map.insert(k, Entry{
+            value : value,
+            in_am : in_am,
+            time : self.time,
+        });
+    }
+    fn capacity(&self) -> usize { self.capacity }
+    fn get_load(&self) -> usize { self.hash_map.len() }
+}
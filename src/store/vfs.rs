@@ -0,0 +1,217 @@
+use std::boxed::Box;
+use std::rc::Rc;
+use std::cmp::min;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::{OpenOptions, File, remove_file, rename, metadata};
+use std::os::unix::io::AsRawFd;
+use std::io::{self, Read, Write, Seek, SeekFrom, ErrorKind};
+use ::utils::file::ensure_dir_exist;
+
+
+// File system under the table files, index files, the catalog and the log.
+// The disk backend maps to std::fs, the memory backend keeps ephemeral databases for tests
+// and can inject I/O failures and torn writes into a file.
+pub trait VfsFile : Debug {
+    // return the number of bytes read, less than buf.len() at the end of the file
+    fn read_at(&mut self, offset : u64, buf : &mut [u8]) -> io::Result<usize>;
+    fn write_at(&mut self, offset : u64, data : &[u8]) -> io::Result<()>;
+    fn fsync(&mut self) -> io::Result<()>;
+    fn truncate(&mut self, len : u64) -> io::Result<()>;
+    fn get_len(&self) -> u64;
+    fn get_id(&self) -> i32;  // unique among the open files, used as the fd of the page pool
+    // another handle of the file for the read-ahead thread, None if it can't be sent to other threads
+    fn clone_for_io(&self) -> Option<Box<VfsFile + Send>> { None }
+    fn read_all(&mut self) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; self.get_len() as usize];
+        let len = try!(self.read_at(0, &mut buf));
+        buf.truncate(len);
+        Ok(buf)
+    }
+}
+
+pub type VfsFileRef = Box<VfsFile>;
+
+pub trait Vfs : Debug {
+    fn open(&self, path : &String) -> io::Result<VfsFileRef>;  // the file is created if not exists
+    fn delete(&self, path : &String) -> io::Result<()>;
+    fn rename(&self, from : &String, to : &String) -> io::Result<()>;
+    fn exists(&self, path : &String) -> bool;
+    fn create_dir(&self, path : &String);
+}
+
+pub type VfsRef = Rc<Vfs>;
+
+pub const DEFAULT_VFS : &'static str = "disk";
+
+pub fn new_vfs(name : &str) -> VfsRef {
+    match name {
+        "disk" => Rc::new(DiskVfs),
+        "memory" => Rc::new(MemVfs::new()),
+        _ => panic!("invalid vfs {}, should be one of disk and memory", name),
+    }
+}
+
+
+#[derive(Debug)]
+pub struct DiskVfs;
+
+impl Vfs for DiskVfs {
+    fn open(&self, path : &String) -> io::Result<VfsFileRef> {
+        let file = try!(OpenOptions::new().read(true).write(true).create(true).open(path));
+        Ok(Box::new(DiskFile{ file : file }))
+    }
+    fn delete(&self, path : &String) -> io::Result<()> { remove_file(path) }
+    fn rename(&self, from : &String, to : &String) -> io::Result<()> { rename(from, to) }
+    fn exists(&self, path : &String) -> bool { metadata(path).is_ok() }
+    fn create_dir(&self, path : &String) { ensure_dir_exist(path) }
+}
+
+#[derive(Debug)]
+struct DiskFile {
+    file : File,
+}
+
+impl VfsFile for DiskFile {
+    fn read_at(&mut self, offset : u64, buf : &mut [u8]) -> io::Result<usize> {
+        try!(self.file.seek(SeekFrom::Start(offset)));
+        let mut len = 0;
+        while len < buf.len() {
+            match self.file.read(&mut buf[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(ref err) if err.kind() == ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(len)
+    }
+    fn write_at(&mut self, offset : u64, data : &[u8]) -> io::Result<()> {
+        try!(self.file.seek(SeekFrom::Start(offset)));
+        self.file.write_all(data)
+    }
+    fn fsync(&mut self) -> io::Result<()> { self.file.sync_data() }
+    fn truncate(&mut self, len : u64) -> io::Result<()> { self.file.set_len(len) }
+    fn get_len(&self) -> u64 { self.file.metadata().map(|m| m.len()).unwrap_or(0) }
+    fn get_id(&self) -> i32 { self.file.as_raw_fd() }
+    fn clone_for_io(&self) -> Option<Box<VfsFile + Send>> {
+        match self.file.try_clone() {
+            Ok(file) => Some(Box::new(DiskFile{ file : file })),
+            Err(..) => None,
+        }
+    }
+}
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoFault {
+    ReadError,  // the next read fails
+    WriteError,  // the next write fails and changes nothing
+    TornWrite(usize),  // only the first n bytes of the next write reach the file, the write still succeeds
+}
+
+#[derive(Debug)]
+struct MemNode {
+    data : Vec<u8>,
+    fault : Option<IoFault>,
+}
+
+type MemNodeRef = Rc<RefCell<MemNode>>;
+
+#[derive(Debug)]
+pub struct MemVfs {
+    files : RefCell<HashMap<String, MemNodeRef>>,
+    next_id : Cell<i32>,
+}
+
+impl MemVfs {
+    pub fn new() -> MemVfs {
+        MemVfs{
+            files : RefCell::new(HashMap::new()),
+            next_id : Cell::new(1),
+        }
+    }
+    pub fn inject_fault(&self, path : &String, fault : IoFault) {
+        // the fault is consumed by the next read or write of the file
+        let files = self.files.borrow();
+        let node = files.get(path).expect("file not exists");
+        node.borrow_mut().fault = Some(fault);
+    }
+}
+
+impl Vfs for MemVfs {
+    fn open(&self, path : &String) -> io::Result<VfsFileRef> {
+        let node = self.files.borrow_mut().entry(path.clone()).or_insert_with(
+            || Rc::new(RefCell::new(MemNode{ data : Vec::new(), fault : None }))).clone();
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        Ok(Box::new(MemFile{ node : node, id : id }))
+    }
+    fn delete(&self, path : &String) -> io::Result<()> {
+        // like unlink, the opened files can still be used
+        match self.files.borrow_mut().remove(path) {
+            Some(..) => Ok(()),
+            None => Err(io::Error::new(ErrorKind::NotFound, path.clone())),
+        }
+    }
+    fn rename(&self, from : &String, to : &String) -> io::Result<()> {
+        let mut files = self.files.borrow_mut();
+        match files.remove(from) {
+            Some(node) => {
+                files.insert(to.clone(), node);
+                Ok(())
+            }
+            None => Err(io::Error::new(ErrorKind::NotFound, from.clone())),
+        }
+    }
+    fn exists(&self, path : &String) -> bool { self.files.borrow().contains_key(path) }
+    fn create_dir(&self, _path : &String) {}
+}
+
+#[derive(Debug)]
+struct MemFile {
+    node : MemNodeRef,
+    id : i32,
+}
+
+impl VfsFile for MemFile {
+    fn read_at(&mut self, offset : u64, buf : &mut [u8]) -> io::Result<usize> {
+        let mut node = self.node.borrow_mut();
+        if node.fault == Some(IoFault::ReadError) {
+            node.fault = None;
+            return Err(io::Error::new(ErrorKind::Other, "injected read error"));
+        }
+        let start = min(offset as usize, node.data.len());
+        let len = min(buf.len(), node.data.len() - start);
+        buf[..len].copy_from_slice(&node.data[start..start + len]);
+        Ok(len)
+    }
+    fn write_at(&mut self, offset : u64, data : &[u8]) -> io::Result<()> {
+        let mut node = self.node.borrow_mut();
+        let len = match node.fault {
+            Some(IoFault::WriteError) => {
+                node.fault = None;
+                return Err(io::Error::new(ErrorKind::Other, "injected write error"));
+            }
+            Some(IoFault::TornWrite(n)) => {
+                node.fault = None;
+                min(n, data.len())
+            }
+            _ => data.len(),
+        };
+        let start = offset as usize;
+        if node.data.len() < start + len {
+            node.data.resize(start + len, 0);
+        }
+        node.data[start..start + len].copy_from_slice(&data[..len]);
+        Ok(())
+    }
+    fn fsync(&mut self) -> io::Result<()> { Ok(()) }
+    fn truncate(&mut self, len : u64) -> io::Result<()> {
+        self.node.borrow_mut().data.resize(len as usize, 0);
+        Ok(())
+    }
+    fn get_len(&self) -> u64 { self.node.borrow().data.len() as u64 }
+    fn get_id(&self) -> i32 { self.id }
+}
@@ -0,0 +1,320 @@
+use std::io;
+use std::cmp::min;
+use super::vfs::{VfsRef, VfsFileRef};
+use super::checksum::crc32;
+
+
+// Redo log of the physical writes to table files, index files and the catalog.
+// Records of a statement are durable once the commit record following them is synced,
+// the log is replayed at startup and truncated after all the files are flushed.
+// A page written before its statement commits is preceded by an undo record of the old content,
+// the undo records following the last commit record are reverted before the committed writes are redone.
+
+const RECORD_WRITE : u8 = 1;
+const RECORD_REPLACE : u8 = 2;
+const RECORD_REMOVE : u8 = 3;
+const RECORD_COMMIT : u8 = 4;
+const RECORD_RENAME : u8 = 5;
+const RECORD_UNDO : u8 = 6;
+const RECORD_HEADER_SIZE : usize = 5;  // kind u8 and body length u32
+const RECORD_CHECKSUM_SIZE : usize = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogRecord {
+    Write{ path : String, offset : u64, data : Vec<u8> },  // overwrite part of a file
+    Replace{ path : String, data : Vec<u8> },  // replace the whole content of a file
+    Remove{ path : String },
+    Rename{ from : String, to : String },  // skipped if from not exists, so that replaying it again is harmless
+    Undo{ path : String, offset : u64, data : Vec<u8>, len : u64 },  // old content of a write and the file length
+    Commit,
+}
+
+impl LogRecord {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        let kind = match self {
+            &LogRecord::Write{ref path, offset, ref data} => {
+                push_str(&mut body, path);
+                push_u64(&mut body, offset);
+                body.extend_from_slice(data);
+                RECORD_WRITE
+            }
+            &LogRecord::Replace{ref path, ref data} => {
+                push_str(&mut body, path);
+                body.extend_from_slice(data);
+                RECORD_REPLACE
+            }
+            &LogRecord::Remove{ref path} => {
+                push_str(&mut body, path);
+                RECORD_REMOVE
+            }
+            &LogRecord::Rename{ref from, ref to} => {
+                push_str(&mut body, from);
+                push_str(&mut body, to);
+                RECORD_RENAME
+            }
+            &LogRecord::Undo{ref path, offset, ref data, len} => {
+                push_str(&mut body, path);
+                push_u64(&mut body, offset);
+                push_u64(&mut body, len);
+                body.extend_from_slice(data);
+                RECORD_UNDO
+            }
+            &LogRecord::Commit => RECORD_COMMIT,
+        };
+        let mut buf = vec![kind];
+        push_u32(&mut buf, body.len() as u32);
+        buf.extend_from_slice(&body);
+        let sum = crc32(&buf);
+        push_u32(&mut buf, sum);
+        buf
+    }
+    pub fn decode(buf : &[u8]) -> Option<(LogRecord, usize)> {
+        // return the record and its encoded length,
+        // None if the record is incomplete or broken
+        if buf.len() < RECORD_HEADER_SIZE {
+            return None;
+        }
+        let body_len = read_u32(buf, 1) as usize;
+        let len = RECORD_HEADER_SIZE + body_len + RECORD_CHECKSUM_SIZE;
+        if buf.len() < len {
+            return None;
+        }
+        if crc32(&buf[..len - RECORD_CHECKSUM_SIZE]) != read_u32(buf, len - RECORD_CHECKSUM_SIZE) {
+            return None;
+        }
+        let body = &buf[RECORD_HEADER_SIZE..len - RECORD_CHECKSUM_SIZE];
+        let record = match buf[0] {
+            RECORD_WRITE => {
+                let (path, end) = match read_str(body) { Some(r) => r, None => return None };
+                if body.len() < end + 8 {
+                    return None;
+                }
+                LogRecord::Write{
+                    path : path,
+                    offset : read_u64(body, end),
+                    data : body[end + 8..].to_vec(),
+                }
+            }
+            RECORD_REPLACE => {
+                let (path, end) = match read_str(body) { Some(r) => r, None => return None };
+                LogRecord::Replace{ path : path, data : body[end..].to_vec() }
+            }
+            RECORD_REMOVE => {
+                let (path, _) = match read_str(body) { Some(r) => r, None => return None };
+                LogRecord::Remove{ path : path }
+            }
+            RECORD_RENAME => {
+                let (from, end) = match read_str(body) { Some(r) => r, None => return None };
+                let (to, _) = match read_str(&body[end..]) { Some(r) => r, None => return None };
+                LogRecord::Rename{ from : from, to : to }
+            }
+            RECORD_UNDO => {
+                let (path, end) = match read_str(body) { Some(r) => r, None => return None };
+                if body.len() < end + 16 {
+                    return None;
+                }
+                LogRecord::Undo{
+                    path : path,
+                    offset : read_u64(body, end),
+                    len : read_u64(body, end + 8),
+                    data : body[end + 16..].to_vec(),
+                }
+            }
+            RECORD_COMMIT => LogRecord::Commit,
+            _ => return None,
+        };
+        Some((record, len))
+    }
+    pub fn apply(&self, vfs : &VfsRef) -> io::Result<()> {
+        match self {
+            &LogRecord::Write{ref path, offset, ref data} => {
+                let mut file = try!(vfs.open(path));
+                try!(file.write_at(offset, data));
+                file.fsync()
+            }
+            &LogRecord::Replace{ref path, ref data} => {
+                let mut file = try!(vfs.open(path));
+                try!(file.truncate(0));
+                try!(file.write_at(0, data));
+                file.fsync()
+            }
+            &LogRecord::Remove{ref path} => {
+                if vfs.exists(path) { vfs.delete(path) } else { Ok(()) }
+            }
+            &LogRecord::Rename{ref from, ref to} => {
+                if vfs.exists(from) { vfs.rename(from, to) } else { Ok(()) }
+            }
+            &LogRecord::Undo{..} | &LogRecord::Commit => Ok(()),
+        }
+    }
+    pub fn gen_undo(&self, vfs : &VfsRef) -> io::Result<LogRecord> {
+        // undo record of a write, with the content it overwrites
+        let (path, offset, len) = extract!(self, &LogRecord::Write{ref path, offset, ref data},
+            (path, offset, data.len()));
+        let mut file = try!(vfs.open(path));
+        let file_len = file.get_len();
+        let mut data = vec![0; min(len as u64, file_len.saturating_sub(offset)) as usize];
+        let n = try!(file.read_at(offset, &mut data));
+        data.truncate(n);
+        Ok(LogRecord::Undo{ path : path.clone(), offset : offset, data : data, len : file_len })
+    }
+    pub fn revert(&self, vfs : &VfsRef) -> io::Result<()> {
+        // only undo records are reverted, the part appended by the write is cut
+        match self {
+            &LogRecord::Undo{ref path, offset, ref data, len} if vfs.exists(path) => {
+                let mut file = try!(vfs.open(path));
+                try!(file.write_at(offset, data));
+                if file.get_len() > len {
+                    try!(file.truncate(len));
+                }
+                file.fsync()
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub struct Wal {
+    file : VfsFileRef,
+    size : u64,
+    committed_size : u64,  // end of the last commit record
+}
+
+impl Wal {
+    pub fn open(path : &String, vfs : &VfsRef) -> Wal {
+        let file = vfs.open(path).unwrap();
+        let size = file.get_len();
+        Wal{
+            file : file,
+            size : size,
+            committed_size : size,
+        }
+    }
+    pub fn append(&mut self, record : &LogRecord) -> io::Result<()> {
+        let buf = record.encode();
+        try!(self.file.write_at(self.size, &buf));
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+    pub fn commit(&mut self) -> io::Result<()> {
+        // the statement can only be acknowledged if this returns Ok
+        try!(self.append(&LogRecord::Commit));
+        try!(self.file.fsync());
+        self.committed_size = self.size;
+        Ok(())
+    }
+    pub fn sync(&mut self) -> io::Result<()> {
+        // the records appended so far are durable but not committed
+        self.file.fsync()
+    }
+    pub fn has_uncommitted(&self) -> bool {
+        self.size > self.committed_size
+    }
+    pub fn discard(&mut self) -> io::Result<()> {
+        // drop the records following the last commit record, e.g. those of a failed commit,
+        // so that they are not committed with the next statement
+        self.size = self.committed_size;
+        try!(self.file.truncate(self.size));
+        self.file.fsync()
+    }
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+    pub fn truncate(&mut self) -> io::Result<()> {
+        // should only be called after all the logged writes are synced to files
+        try!(self.file.truncate(0));
+        self.size = 0;
+        self.committed_size = 0;
+        self.file.fsync()
+    }
+    pub fn read_committed(path : &String, vfs : &VfsRef) -> Vec<LogRecord> {
+        // records following the last commit record and the broken tail are discarded
+        let (mut records, committed_num) = Self::read_records(path, vfs);
+        records.truncate(committed_num);
+        records
+    }
+    fn read_records(path : &String, vfs : &VfsRef) -> (Vec<LogRecord>, usize) {
+        // all the records before the broken tail and the number of the committed ones
+        let mut buf = Vec::new();
+        if vfs.exists(path) {
+            if let Ok(data) = vfs.open(path).and_then(|mut file| file.read_all()) {
+                buf = data;
+            }
+        }
+        let mut records = Vec::new();
+        let mut committed_num = 0;
+        let mut start = 0;
+        while let Some((record, len)) = LogRecord::decode(&buf[start..]) {
+            start += len;
+            if record == LogRecord::Commit {
+                committed_num = records.len();
+            } else {
+                records.push(record);
+            }
+        }
+        (records, committed_num)
+    }
+    pub fn recover(path : &String, vfs : &VfsRef) -> io::Result<usize> {
+        // undo the pages written by the statement not committed, then redo the committed writes,
+        // return the number of replayed records
+        let (records, committed_num) = Self::read_records(path, vfs);
+        for record in records[committed_num..].iter().rev() {
+            try!(record.revert(vfs));
+        }
+        for record in records[..committed_num].iter() {
+            try!(record.apply(vfs));
+        }
+        Ok(committed_num)
+    }
+}
+
+fn push_u32(buf : &mut Vec<u8>, n : u32) {
+    for i in 0..4 {
+        buf.push((n >> (i * 8)) as u8);
+    }
+}
+
+fn push_u64(buf : &mut Vec<u8>, n : u64) {
+    for i in 0..8 {
+        buf.push((n >> (i * 8)) as u8);
+    }
+}
+
+fn push_str(buf : &mut Vec<u8>, s : &String) {
+    push_u32(buf, s.len() as u32);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn read_u32(buf : &[u8], start : usize) -> u32 {
+    let mut n = 0;
+    for i in 0..4 {
+        n |= (buf[start + i] as u32) << (i * 8);
+    }
+    n
+}
+
+fn read_u64(buf : &[u8], start : usize) -> u64 {
+    let mut n = 0;
+    for i in 0..8 {
+        n |= (buf[start + i] as u64) << (i * 8);
+    }
+    n
+}
+
+fn read_str(buf : &[u8]) -> Option<(String, usize)> {
+    // return the string and where it ends
+    if buf.len() < 4 {
+        return None;
+    }
+    let end = 4 + read_u32(buf, 0) as usize;
+    if buf.len() < end {
+        return None;
+    }
+    match String::from_utf8(buf[4..end].to_vec()) {
+        Ok(s) => Some((s, end)),
+        Err(..) => None,
+    }
+}
@@ -13,3 +13,18 @@ mod test_evaluate;
 #[allow(dead_code)]
 #[allow(unused_imports)]
 mod test_gen_plan;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_vacuum;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_alter;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_sort;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_aggregate;
+#[allow(dead_code)]
+#[allow(unused_imports)]
+mod test_join;
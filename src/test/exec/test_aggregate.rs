@@ -0,0 +1,105 @@
+use ::store::table::{TableManagerRef, AttrType};
+use ::store::tuple::TupleValue;
+use ::parser::common::Statement;
+use ::exec::gen_plan::gen_select_attr_desc;
+use ::test::utils::{gen_exec_manager, query_helper, assert_row_list, char_value};
+
+
+fn gen_aggre_manager(dir : &str) -> TableManagerRef {
+    gen_exec_manager(dir, 1024 * 1024, &[
+        "create table test_aggre_message(id int not null primary, score float null, \
+        author char(16) null, likes int null)",
+    ])
+}
+
+fn insert_aggre_data(manager : &TableManagerRef) {
+    let value_list = [
+        "1, 1.5, \"doyoubi\", 10",
+        "2, 2.5, \"huang\", null",
+        "3, null, \"doyoubi\", 30",
+        "4, 4.0, null, 5",
+        "5, 3.0, \"huang\", 7",
+        "6, 0.5, \"doyoubi\", 20",
+    ];
+    for values in value_list.iter() {
+        let sql = format!("insert test_aggre_message values({})", values);
+        assert_pattern!(exec_helper!(&sql[..], manager), None);
+    }
+}
+
+#[test]
+fn test_hash_aggregate() {
+    let manager = gen_aggre_manager("test_file/test_hash_aggregate");
+    insert_aggre_data(&manager);
+    let (row_list, explain) = query_helper(
+        "select author, count(*), count(score), sum(likes), avg(score), min(id), max(author) \
+        from test_aggre_message group by author", &manager);
+    assert!(explain.contains("HashAggregate"));
+    // in the order the groups first appear, nulls are a group
+    assert_row_list(&row_list, vec![
+        vec![char_value("doyoubi"), TupleValue::Int(3), TupleValue::Int(2), TupleValue::Int(60),
+            TupleValue::Float(1.0), TupleValue::Int(1), char_value("doyoubi")],
+        vec![char_value("huang"), TupleValue::Int(2), TupleValue::Int(2), TupleValue::Int(7),
+            TupleValue::Float(2.75), TupleValue::Int(2), char_value("huang")],
+        vec![TupleValue::Null, TupleValue::Int(1), TupleValue::Int(1), TupleValue::Int(5),
+            TupleValue::Float(4.0), TupleValue::Int(4), TupleValue::Null],
+    ]);
+
+    let (row_list, _) = query_helper(
+        "select author, sum(score) from test_aggre_message where id > 1 \
+        group by author having count(*) > 1 and sum(likes) > 20", &manager);
+    assert_row_list(&row_list, vec![vec![char_value("doyoubi"), TupleValue::Float(0.5)]]);
+
+    let (row_list, _) = query_helper(
+        "select count(*), max(likes), avg(likes) from test_aggre_message", &manager);
+    assert_row_list(&row_list, vec![vec![TupleValue::Int(6), TupleValue::Int(30), TupleValue::Float(14.4)]]);
+
+    // a single group without group by even if there is no row
+    let (row_list, _) = query_helper(
+        "select count(*), sum(likes), min(author) from test_aggre_message where id > 100", &manager);
+    assert_row_list(&row_list, vec![vec![TupleValue::Int(0), TupleValue::Null, TupleValue::Null]]);
+    let (row_list, _) = query_helper(
+        "select author, count(*) from test_aggre_message where id > 100 group by author", &manager);
+    assert!(row_list.is_empty());
+}
+
+#[test]
+fn test_sort_aggregate() {
+    let manager = gen_aggre_manager("test_file/test_sort_aggregate");
+    insert_aggre_data(&manager);
+    let (row_list, explain) = query_helper(
+        "select count(*), author, sum(likes) from test_aggre_message \
+        group by author order by author desc", &manager);
+    assert!(explain.contains("SortAggregate"));
+    assert_row_list(&row_list, vec![
+        vec![TupleValue::Int(2), char_value("huang"), TupleValue::Int(7)],
+        vec![TupleValue::Int(3), char_value("doyoubi"), TupleValue::Int(60)],
+        vec![TupleValue::Int(1), TupleValue::Null, TupleValue::Int(5)],
+    ]);
+
+    // sorted after the aggregation if not ordered by the group by attribute
+    let (row_list, explain) = query_helper(
+        "select author, max(score) from test_aggre_message group by author \
+        having count(*) < 3 order by max(score)", &manager);
+    assert!(explain.contains("HashAggregate"));
+    assert_row_list(&row_list, vec![
+        vec![char_value("huang"), TupleValue::Float(3.0)],
+        vec![TupleValue::Null, TupleValue::Float(4.0)],
+    ]);
+}
+
+#[test]
+fn test_aggregate_attr_desc() {
+    let manager = gen_aggre_manager("test_file/test_aggregate_attr_desc");
+    let table = manager.borrow().get_table("test_aggre_message").unwrap();
+    let mut stmt = gen_parse_result!(Statement::parse,
+        "select author, avg(likes), sum(likes), sum(score), count(author), min(author) \
+        from test_aggre_message group by author");
+    let table_set = ::exec::gen_plan::gen_table_set(&stmt, &manager);
+    assert_pattern!(::parser::sem_check::check_sem(&mut stmt, &table_set), Ok(..));
+    let select = extract!(stmt, Statement::Select(select), select);
+    let attr_desc : Vec<String> = gen_select_attr_desc(&select, &vec![table]).iter().map(|t| format!("{:?}", t)).collect();
+    let expected : Vec<String> = vec![AttrType::Char{ len : 16 }, AttrType::Float, AttrType::Int, AttrType::Float,
+        AttrType::Int, AttrType::Char{ len : 16 }].iter().map(|t| format!("{:?}", t)).collect();
+    assert_eq!(attr_desc, expected);
+}
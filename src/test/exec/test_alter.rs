@@ -0,0 +1,107 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::{remove_dir_all, metadata};
+use ::store::table::TableManager;
+use ::utils::config::Config;
+use ::store::tuple::TupleValue;
+use ::exec::error::ExecErrorType;
+use ::exec::query::FileScan;
+use super::test_query::{gen_test_table, insert_data};
+
+
+macro_rules! query_helper {
+    ($input_str:expr, $manager:expr) => ({
+        let mut query = gen_plan_helper!($input_str, $manager);
+        query.open();
+        let mut row_list = Vec::new();
+        while let Some(t) = query.get_next() {
+            row_list.push(t);
+        }
+        row_list
+    })
+}
+
+#[test]
+fn test_alter_table() {
+    let _ = remove_dir_all("test_file/test_alter_table/");
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        table_meta_dir = "test_file/test_alter_table/table_meta/"
+        table_file_dir = "test_file/test_alter_table/table_file/""#.to_string());
+    {
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        assert_pattern!(exec_helper!(
+            "create table test_alter_message(id int not null primary, content char(20) not null)",
+            &manager), None);
+        assert_pattern!(exec_helper!(
+            "create index content_index on test_alter_message (content)", &manager), None);
+        for i in 0..50 {
+            let sql = format!("insert test_alter_message values({}, \"content{}\")", i, i);
+            assert_pattern!(exec_helper!(&sql[..], &manager), None);
+        }
+        manager.borrow_mut().commit().unwrap();
+
+        assert_pattern!(exec_helper!(
+            "alter table test_alter_message add column score float not null default 1", &manager), None);
+        let row_list = query_helper!("select * from test_alter_message where id = 7", &manager);
+        assert_eq!(row_list.len(), 1);
+        assert_eq!(row_list[0].len(), 3);
+        assert_eq!(extract!(&row_list[0][2], &TupleValue::Float(f), f), 1.0);
+        assert_pattern!(exec_helper!("insert test_alter_message values(50, \"content50\", 2.5)", &manager), None);
+
+        assert_pattern!(exec_helper!(
+            "alter table test_alter_message rename column content to message", &manager), None);
+        let row_list = query_helper!(
+            "select * from test_alter_message where message = \"content50\"", &manager);
+        assert_eq!(row_list.len(), 1);
+        assert_eq!(extract!(&row_list[0][2], &TupleValue::Float(f), f), 2.5);
+
+        assert_pattern!(exec_helper!("alter table test_alter_message drop column score", &manager), None);
+        let row_list = query_helper!("select * from test_alter_message", &manager);
+        assert_eq!(row_list.len(), 51);
+        assert!(row_list.iter().all(|t| t.len() == 2));
+
+        assert_pattern!(exec_helper!("alter table test_alter_message rename to test_alter_note", &manager), None);
+        assert!(manager.borrow().get_table("test_alter_message").is_none());
+        assert!(metadata("test_file/test_alter_table/table_file/test_alter_message.table").is_err());
+        assert!(metadata("test_file/test_alter_table/table_file/test_alter_message.content_index.index").is_err());
+        assert!(metadata("test_file/test_alter_table/table_file/test_alter_note.table").is_ok());
+        assert!(metadata("test_file/test_alter_table/table_file/test_alter_note.rewrite.table").is_err());
+        assert_pattern!(exec_helper!("insert test_alter_note values(51, \"content51\")", &manager), None);
+        manager.borrow_mut().commit().unwrap();
+    }
+    // the catalog and the rewritten files should match after restart
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let row_list = query_helper!("select * from test_alter_note where message = \"content3\"", &manager);
+    assert_eq!(row_list.len(), 1);
+    assert_eq!(extract!(&row_list[0][0], &TupleValue::Int(n), n), 3);
+    assert_eq!(query_helper!("select * from test_alter_note", &manager).len(), 52);
+}
+
+#[test]
+fn test_alter_pinned_page() {
+    let _ = remove_dir_all("test_file/test_alter_pinned_page/");
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/test_alter_pinned_page/table_meta/"
+        table_file_dir = "test_file/test_alter_pinned_page/table_file/""#.to_string());
+    let table_name = "test_alter_pinned_message".to_string();
+    let manager = TableManager::make_ref(&config);
+    manager.borrow_mut().add_table(gen_test_table(&table_name));
+    insert_data(&table_name, &manager);
+
+    let mut scan = FileScan::new(&table_name, &manager);
+    scan.open();
+    assert_pattern!(scan.get_next(), Some(..));
+    let err = extract!(exec_helper!(
+        "alter table test_alter_pinned_message drop column score", &manager), Some(err), err);
+    assert_eq!(err.error_type, ExecErrorType::PageInUse);
+    // the schema is not changed
+    assert_eq!(manager.borrow().get_table(&table_name).unwrap().borrow().attr_list.len(), 3);
+    scan.close();
+
+    assert_pattern!(exec_helper!("alter table test_alter_pinned_message drop column score", &manager), None);
+    let row_list = query_helper!("select * from test_alter_pinned_message", &manager);
+    assert_eq!(row_list.len(), 3);
+    assert_eq!(extract!(&row_list[1][1], &TupleValue::Char(ref s), s.clone()), "dyb");
+}
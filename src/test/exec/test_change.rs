@@ -1,4 +1,3 @@
-use std::ptr::read;
 use std::collections::HashMap;
 use ::exec::change::{Insert, Delete, Update};
 use ::exec::query::{FileScan, Filter};
@@ -6,7 +5,6 @@ use ::exec::error::ExecErrorType;
 use ::store::tuple::TupleValue;
 use ::store::table::{TableManager, Table, Attr, AttrType};
 use ::utils::config::Config;
-use ::utils::pointer::read_string;
 use ::parser::condition::ConditionExpr;
 use super::test_query::{gen_test_manager, gen_test_table};
 
@@ -90,9 +88,9 @@ fn test_insert_without_creating_new_page() {
     assert_pattern!(scan.get_next(), Some(..));
     assert_pattern!(scan.get_next(), None);
 
-    assert_eq!(unsafe{read::<i32>(tuple_data[0] as *const i32)}, 1234);
-    assert_eq!(unsafe{read::<f32>(tuple_data[1] as *const f32)}, 2.3333);
-    assert_eq!(unsafe{read_string(tuple_data[2], 16)}, "i am doyoubi");
+    assert_eq!(extract!(&tuple_data[0], &TupleValue::Int(n), n), 1234);
+    assert_eq!(extract!(&tuple_data[1], &TupleValue::Float(f), f), 2.3333);
+    assert_eq!(extract!(&tuple_data[2], &TupleValue::Char(ref s), s.clone()), "i am doyoubi");
 }
 
 #[test]
@@ -137,7 +135,7 @@ fn test_delete() {
         let mut delete = Delete::new(&table_name, data_souce, &manager);
         delete.open();
         let deleted_tuple = extract!(delete.get_next(), Some(tuple_data), tuple_data);
-        assert_eq!(unsafe{ read::<i32>(deleted_tuple[0] as *const i32) }, 777);
+        assert_eq!(extract!(&deleted_tuple[0], &TupleValue::Int(n), n), 777);
         assert_pattern!(delete.get_next(), None);
 
         let mut scan = FileScan::new(&table_name, &manager);
@@ -145,8 +143,8 @@ fn test_delete() {
         let t1 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 1);
+        assert_eq!(extract!(&t1[0], &TupleValue::Int(n), n), 233);
+        assert_eq!(extract!(&t2[0], &TupleValue::Int(n), n), 1);
     }
 }
 
@@ -156,10 +154,9 @@ fn test_update() {
         // update all
         let table_name = "test_change_message".to_string();
         let manager = gen_test_manager(&table_name);
-        let table = gen_test_table(&table_name);
         let mut set_values = HashMap::new();
         set_values.insert(1, TupleValue::Float(233.666));
-        let mut update = Update::new(&table_name, table.gen_tuple_desc(), set_values,
+        let mut update = Update::new(&table_name, set_values,
             FileScan::new(&table_name, &manager), &manager);
         update.open();
         assert_pattern!(update.get_next(), Some(..));
@@ -172,9 +169,9 @@ fn test_update() {
         let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 233.666);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 233.666);
-        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 233.666);
+        assert_eq!(extract!(&t1[1], &TupleValue::Float(f), f), 233.666);
+        assert_eq!(extract!(&t2[1], &TupleValue::Float(f), f), 233.666);
+        assert_eq!(extract!(&t3[1], &TupleValue::Float(f), f), 233.666);
     }
     {
         // update with where clause
@@ -188,9 +185,9 @@ fn test_update() {
         let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 666.666);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 12345.777);
-        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 123.0);
+        assert_eq!(extract!(&t1[1], &TupleValue::Float(f), f), 666.666);
+        assert_eq!(extract!(&t2[1], &TupleValue::Float(f), f), 12345.777);
+        assert_eq!(extract!(&t3[1], &TupleValue::Float(f), f), 123.0);
 
         let mut set_values = HashMap::new();
         set_values.insert(1, TupleValue::Float(233.666));
@@ -198,12 +195,12 @@ fn test_update() {
         let cond = Box::new(gen_parse_result!(ConditionExpr::parse,
             "test_change_message.id = 777"));
         data_souce = Filter::new(cond, table.gen_index_map(), table.gen_tuple_desc(), data_souce);
-        let mut update = Update::new(&table_name, table.gen_tuple_desc(), set_values,
+        let mut update = Update::new(&table_name, set_values,
             data_souce, &manager);
         update.open();
         let updated_tuple = extract!(update.get_next(), Some(tuple_data), tuple_data);
-        assert_eq!(unsafe{ read::<i32>(updated_tuple[0] as *const i32) }, 777);
-        assert_eq!(unsafe{ read::<f32>(updated_tuple[1] as *const f32) }, 233.666);
+        assert_eq!(extract!(&updated_tuple[0], &TupleValue::Int(n), n), 777);
+        assert_eq!(extract!(&updated_tuple[1], &TupleValue::Float(f), f), 233.666);
         assert_pattern!(update.get_next(), None);
 
         let mut scan = FileScan::new(&table_name, &manager);
@@ -212,9 +209,104 @@ fn test_update() {
         let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 666.666);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 233.666);
-        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 123.0);
+        assert_eq!(extract!(&t1[1], &TupleValue::Float(f), f), 666.666);
+        assert_eq!(extract!(&t2[1], &TupleValue::Float(f), f), 233.666);
+        assert_eq!(extract!(&t3[1], &TupleValue::Float(f), f), 123.0);
     }
 }
 
+
+#[test]
+fn test_update_null() {
+    let table_name = "test_change_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    let table = gen_test_table(&table_name);
+
+    let mut plan = gen_plan_helper!(
+        "update test_change_message set score = null where id = 777", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), Some(..));
+    assert_pattern!(plan.get_next(), None);
+    assert_pattern!(plan.get_error(), None);
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 1, 1), TupleValue::Null);
+
+    let filter_id_list = |expr : &str| {
+        let cond = Box::new(gen_parse_result!(ConditionExpr::parse, expr));
+        let mut plan = Filter::new(cond, table.gen_index_map(), table.gen_tuple_desc(),
+            FileScan::new(&table_name, &manager));
+        plan.open();
+        let mut id_list = Vec::new();
+        while let Some(tuple_data) = plan.get_next() {
+            id_list.push(extract!(&tuple_data[0], &TupleValue::Int(n), n));
+        }
+        id_list
+    };
+    assert_eq!(filter_id_list("test_change_message.score is null"), vec![777]);
+    assert_eq!(filter_id_list("test_change_message.score is not null"), vec![233, 1]);
+    // comparison with null is unknown, and so is its negation
+    assert_eq!(filter_id_list("test_change_message.score < 100000"), vec![233, 1]);
+    assert_eq!(filter_id_list("not test_change_message.score < 100000"), Vec::<i32>::new());
+    assert_eq!(filter_id_list("test_change_message.score < 0 or test_change_message.id = 777"), vec![777]);
+
+    let mut set_values = HashMap::new();
+    set_values.insert(1, TupleValue::Float(1.5));
+    let cond = Box::new(gen_parse_result!(ConditionExpr::parse,
+        "test_change_message.score is null"));
+    let data_source = Filter::new(cond, table.gen_index_map(), table.gen_tuple_desc(),
+        FileScan::new(&table_name, &manager));
+    let mut update = Update::new(&table_name, set_values, data_source, &manager);
+    update.open();
+    assert_pattern!(update.get_next(), Some(..));
+    assert_pattern!(update.get_next(), None);
+    assert_pattern!(manager.borrow_mut().get_tuple_value(&table_name, 1, 1), TupleValue::Float(1.5));
+}
+
+#[test]
+fn test_var_len_attr() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let mut plan = gen_plan_helper!(
+        "create table test_var_len_article(id int not null primary, title varchar(32) not null, content text)",
+        &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+
+    let long_content : String = (0..5000).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    let sql_list = vec![
+        "insert test_var_len_article values(1, \"first\", null)".to_string(),
+        format!("insert test_var_len_article values(2, \"second\", \"{}\")", long_content),
+        "insert test_var_len_article values(3, \"third\", \"short\")".to_string(),
+    ];
+    for sql in sql_list.iter() {
+        let mut plan = gen_plan_helper!(&sql[..], &manager);
+        plan.open();
+        assert_pattern!(plan.get_next(), None);
+        assert_pattern!(plan.get_error(), None);
+    }
+
+    let mut plan = gen_plan_helper!(
+        "update test_var_len_article set title = \"a much longer title\" where content = \"short\"", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), Some(..));
+    assert_pattern!(plan.get_next(), None);
+
+    let select_title = |sql : &str| {
+        let mut plan = gen_plan_helper!(sql, &manager);
+        plan.open();
+        let mut title_list = Vec::new();
+        while let Some(tuple_data) = plan.get_next() {
+            title_list.push(extract!(&tuple_data[0], &TupleValue::Char(ref s), s.clone()));
+        }
+        title_list
+    };
+    assert_eq!(select_title("select title from test_var_len_article where title = \"first\""), vec!["first"]);
+    assert_eq!(select_title("select title from test_var_len_article where content is not null"),
+        vec!["second", "a much longer title"]);
+    assert_eq!(select_title(&format!(
+        "select title from test_var_len_article where content = \"{}\"", long_content)), vec!["second"]);
+    let content = manager.borrow_mut().get_tuple_value(&"test_var_len_article".to_string(), 1, 2);
+    assert_eq!(extract!(content, TupleValue::Char(s), s), long_content);
+}
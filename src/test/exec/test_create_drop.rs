@@ -1,3 +1,6 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::{remove_dir_all, metadata};
 use ::parser::common::Statement;
 use ::store::table::{TableManager, Table, Attr, AttrType};
 use ::utils::config::Config;
@@ -37,6 +40,8 @@ fn test_drop_table() {
                 primary : true,
                 nullable : false,
             }],
+        index_list : vec![],
+        compression : None,
     };
     manager.borrow_mut().add_table(table);
     let mut plan = gen_plan_helper!("drop table msg", &manager);
@@ -45,3 +50,46 @@ fn test_drop_table() {
     assert_pattern!(plan.get_next(), None);
     assert_pattern!(manager.borrow().get_table("msg"), None);
 }
+
+#[test]
+fn test_drop_table_storage() {
+    let _ = remove_dir_all("test_file/test_drop_table_storage/");
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/test_drop_table_storage/table_meta/"
+        table_file_dir = "test_file/test_drop_table_storage/table_file/""#.to_string());
+    let table_path = "test_file/test_drop_table_storage/table_file/msg.table";
+    let exec = |sql : &str, manager : &::store::table::TableManagerRef| {
+        let mut plan = gen_plan_helper!(sql, manager);
+        plan.open();
+        while let Some(..) = plan.get_next() {}
+        assert_pattern!(plan.get_error(), None);
+    };
+    {
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        exec("create table msg(id int not null primary, content char(32))", &manager);
+        exec("insert msg values(1, \"stale\")", &manager);
+        manager.borrow_mut().commit().unwrap();
+        let fd = manager.borrow().file_manager.get_file_fd(&"msg".to_string());
+        exec("drop table msg", &manager);
+        assert_pattern!(manager.borrow_mut().file_manager.page_pool.get_page(fd, 0), None);
+        // exit before the drop is committed
+        assert!(metadata(table_path).is_ok());
+    }
+    {
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        assert_pattern!(manager.borrow().get_table("msg"), Some(..));
+        exec("drop table msg", &manager);
+        manager.borrow_mut().commit().unwrap();
+        assert!(metadata(table_path).is_err());
+        assert!(metadata("test_file/test_drop_table_storage/table_file/msg.fsm").is_err());
+        assert!(metadata("test_file/test_drop_table_storage/table_file/msg.primary.index").is_err());
+    }
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    assert_pattern!(manager.borrow().get_table("msg"), None);
+    // the new table should not see the tuples of the dropped one
+    exec("create table msg(id int not null primary, content char(32))", &manager);
+    let mut query = gen_plan_helper!("select * from msg", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), None);
+}
@@ -1,9 +1,9 @@
-use std::ptr::read;
 use ::exec::query::FileScan;
+use ::exec::error::ExecErrorType;
 use ::exec::gen_plan::{
     gen_update_plan,
 };
-use ::utils::pointer::read_string;
+use ::store::tuple::TupleValue;
 use super::test_query::gen_test_manager;
 
 
@@ -26,12 +26,12 @@ fn test_gen_update_plan() {
         let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t1[1] as *const f32) }, 86.86);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 86.86);
-        assert_eq!(unsafe{ read::<f32>(t3[1] as *const f32) }, 86.86);
-        assert_eq!(unsafe{ read_string(t1[2], 16) }, "updated");
-        assert_eq!(unsafe{ read_string(t2[2], 16) }, "updated");
-        assert_eq!(unsafe{ read_string(t3[2], 16) }, "updated");
+        assert_eq!(extract!(&t1[1], &TupleValue::Float(f), f), 86.86);
+        assert_eq!(extract!(&t2[1], &TupleValue::Float(f), f), 86.86);
+        assert_eq!(extract!(&t3[1], &TupleValue::Float(f), f), 86.86);
+        assert_eq!(extract!(&t1[2], &TupleValue::Char(ref s), s.clone()), "updated");
+        assert_eq!(extract!(&t2[2], &TupleValue::Char(ref s), s.clone()), "updated");
+        assert_eq!(extract!(&t3[2], &TupleValue::Char(ref s), s.clone()), "updated");
     }
     {
         let table_name = "test_gen_plan_message".to_string();
@@ -49,7 +49,7 @@ fn test_gen_update_plan() {
         let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(scan.get_next(), Some(..));
         assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t2[1] as *const f32) }, 86.86);
+        assert_eq!(extract!(&t2[1], &TupleValue::Float(f), f), 86.86);
     }
 }
 
@@ -84,8 +84,8 @@ fn test_gen_delete_plan() {
         let t2 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         let t3 = extract!(scan.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(scan.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
-        assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
+        assert_eq!(extract!(&t2[0], &TupleValue::Int(n), n), 777);
+        assert_eq!(extract!(&t3[0], &TupleValue::Int(n), n), 1);
     }
 }
 
@@ -101,9 +101,9 @@ fn test_query() {
         let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
         let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(query.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
-        assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
+        assert_eq!(extract!(&t1[0], &TupleValue::Int(n), n), 233);
+        assert_eq!(extract!(&t2[0], &TupleValue::Int(n), n), 777);
+        assert_eq!(extract!(&t3[0], &TupleValue::Int(n), n), 1);
     }
     {
         let table_name = "test_gen_plan_message".to_string();
@@ -113,7 +113,7 @@ fn test_query() {
         query.open();
         let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(query.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
+        assert_eq!(extract!(&t2[0], &TupleValue::Int(n), n), 777);
     }
     {
         let table_name = "test_gen_plan_message".to_string();
@@ -123,7 +123,145 @@ fn test_query() {
         query.open();
         let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(query.get_next(), None);
-        assert_eq!(unsafe{ read::<f32>(t2[0] as *const f32) }, 12345.777);
-        assert_eq!(unsafe{ read_string(t2[1], 16) }, "dyb");
+        assert_eq!(extract!(&t2[0], &TupleValue::Float(f), f), 12345.777);
+        assert_eq!(extract!(&t2[1], &TupleValue::Char(ref s), s.clone()), "dyb");
     }
 }
+
+#[test]
+fn test_primary_key_index_plan() {
+    let table_name = "test_gen_plan_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    {
+        let query = gen_plan_helper!(
+            "select * from test_gen_plan_message where id = 777", &manager);
+        assert!(query.explain().contains("IndexScan"));
+    }
+    {
+        let query = gen_plan_helper!(
+            "select * from test_gen_plan_message where score > 1.0 and 777 = id", &manager);
+        assert!(query.explain().contains("IndexScan"));
+    }
+    {
+        let query = gen_plan_helper!(
+            "select * from test_gen_plan_message where id = 777 or score > 1.0", &manager);
+        assert!(!query.explain().contains("IndexScan"));
+    }
+    {
+        let query = gen_plan_helper!(
+            "select * from test_gen_plan_message where id < 777", &manager);
+        assert!(query.explain().contains("IndexScan"));
+    }
+    {
+        let query = gen_plan_helper!(
+            "select * from test_gen_plan_message where score < 1.0", &manager);
+        assert!(!query.explain().contains("IndexScan"));
+    }
+    {
+        // condition other than primary key is still checked
+        let mut query = gen_plan_helper!(
+            "select * from test_gen_plan_message where id = 777 and score < 1.0", &manager);
+        query.open();
+        assert_pattern!(query.get_next(), None);
+    }
+    {
+        let mut delete = gen_plan_helper!(
+            "delete from test_gen_plan_message where id = 777", &manager);
+        assert!(delete.explain().contains("IndexScan"));
+        delete.open();
+        assert_pattern!(delete.get_next(), Some(..));
+        assert_pattern!(delete.get_next(), None);
+        let mut query = gen_plan_helper!(
+            "select * from test_gen_plan_message where id = 777", &manager);
+        query.open();
+        assert_pattern!(query.get_next(), None);
+    }
+    {
+        // the primary key can be inserted again after deleted
+        let mut insert = gen_plan_helper!(
+            "insert test_gen_plan_message values(777, 1.5, \"again\")", &manager);
+        insert.open();
+        assert_pattern!(insert.get_next(), None);
+        assert_pattern!(insert.get_error(), None);
+        let mut query = gen_plan_helper!(
+            "select * from test_gen_plan_message where id = 777", &manager);
+        query.open();
+        let t = extract!(query.get_next(), Some(tuple_data), tuple_data);
+        assert_pattern!(query.get_next(), None);
+        assert_eq!(extract!(&t[2], &TupleValue::Char(ref s), s.clone()), "again");
+    }
+}
+
+macro_rules! query_id_helper {
+    ($input_str:expr, $manager:expr) => ({
+        let mut query = gen_plan_helper!($input_str, $manager);
+        assert!(query.explain().contains("IndexScan"));
+        query.open();
+        let mut id_list = Vec::new();
+        while let Some(t) = query.get_next() {
+            id_list.push(extract!(&t[0], &TupleValue::Int(n), n));
+        }
+        id_list
+    })
+}
+
+#[test]
+fn test_index_plan() {
+    let table_name = "test_index_plan_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    assert_pattern!(exec_helper!(
+        "create index score_index on test_index_plan_message (score)", &manager), None);
+    // range scan returns tuples in key order
+    assert_eq!(query_id_helper!(
+        "select * from test_index_plan_message where score > 200.0", &manager), vec![233, 777]);
+    assert_eq!(query_id_helper!(
+        "select * from test_index_plan_message where score >= 123 and 666.666 > score", &manager),
+        vec![1]);
+    assert_eq!(query_id_helper!(
+        "select * from test_index_plan_message where id > 1", &manager), vec![233, 777]);
+    {
+        // the index matching more attributes is used
+        let query = gen_plan_helper!(
+            "select * from test_index_plan_message where score > 200.0 and id = 777", &manager);
+        assert!(query.explain().contains("primary"));
+    }
+
+    // unique index
+    assert_pattern!(exec_helper!(
+        "create unique index content_index on test_index_plan_message (content)", &manager), None);
+    let error = exec_helper!("insert test_index_plan_message values(2, 1.0, \"dyb\")", &manager);
+    assert_eq!(extract!(error, Some(e), e.error_type), ExecErrorType::UniqueKeyExist);
+    assert_pattern!(exec_helper!(
+        "insert test_index_plan_message values(2, 1.0, \"new\")", &manager), None);
+    assert_eq!(query_id_helper!(
+        "select * from test_index_plan_message where content = \"new\"", &manager), vec![2]);
+    let error = exec_helper!(
+        "update test_index_plan_message set content = \"str\" where id = 233", &manager);
+    assert_eq!(extract!(error, Some(e), e.error_type), ExecErrorType::UniqueKeyExist);
+    assert_pattern!(exec_helper!(
+        "update test_index_plan_message set content = \"updated\" where id = 233", &manager), None);
+    assert!(query_id_helper!(
+        "select * from test_index_plan_message where content = \"qweryu\"", &manager).is_empty());
+    assert_eq!(query_id_helper!(
+        "select * from test_index_plan_message where content = \"updated\"", &manager), vec![233]);
+
+    // secondary index is updated
+    assert_pattern!(exec_helper!("update test_index_plan_message set score = 1", &manager), None);
+    let mut id_list = query_id_helper!(
+        "select * from test_index_plan_message where score = 1.0", &manager);
+    id_list.sort();
+    assert_eq!(id_list, vec![1, 2, 233, 777]);
+    let error = exec_helper!(
+        "create unique index score_unique on test_index_plan_message (score)", &manager);
+    assert_eq!(extract!(error, Some(e), e.error_type), ExecErrorType::UniqueKeyExist);
+    {
+        let table = manager.borrow().get_table(&table_name).unwrap();
+        assert_pattern!(table.borrow().get_index_desc("score_unique"), None);
+    }
+
+    assert_pattern!(exec_helper!(
+        "drop index score_index on test_index_plan_message", &manager), None);
+    let query = gen_plan_helper!(
+        "select * from test_index_plan_message where score = 1.0", &manager);
+    assert!(!query.explain().contains("IndexScan"));
+}
@@ -0,0 +1,354 @@
+use ::store::table::TableManagerRef;
+use ::store::tuple::{Row, TupleValue};
+use ::test::utils::{gen_exec_manager, query_helper, assert_row_list, char_value, count_temp_file};
+
+
+fn gen_join_manager(dir : &str, work_memory_size : usize) -> TableManagerRef {
+    gen_exec_manager(dir, work_memory_size, &[
+        "create table test_join_author(id int not null primary, name char(16) not null)",
+        "create table test_join_message(id int not null primary, author_id int null, content char(16) not null)",
+    ])
+}
+
+fn insert_join_data(manager : &TableManagerRef) {
+    for sql in [
+        "insert test_join_author values(1, \"doyoubi\")",
+        "insert test_join_author values(2, \"huang\")",
+        "insert test_join_author values(3, \"nobody\")",
+        "insert test_join_message values(10, 2, \"hello\")",
+        "insert test_join_message values(11, 1, \"world\")",
+        "insert test_join_message values(12, null, \"anonymous\")",
+        "insert test_join_message values(13, 2, \"again\")",
+    ].iter() {
+        assert_pattern!(exec_helper!(*sql, manager), None);
+    }
+}
+
+#[test]
+fn test_nested_loop_join() {
+    let manager = gen_join_manager("test_file/test_nested_loop_join", 1024 * 1024);
+    insert_join_data(&manager);
+    let (row_list, explain) = query_helper(
+        "select test_join_message.content, test_join_author.name from test_join_message, test_join_author \
+        where author_id < test_join_author.id order by test_join_message.id, test_join_author.id", &manager);
+    assert!(explain.contains("NestedLoopJoin"));
+    assert_row_list(&row_list, vec![
+        vec![char_value("hello"), char_value("nobody")],
+        vec![char_value("world"), char_value("huang")],
+        vec![char_value("world"), char_value("nobody")],
+        vec![char_value("again"), char_value("nobody")],
+    ]);
+
+    // the rows of the tables are concatenated
+    let (row_list, _) = query_helper(
+        "select * from test_join_author, test_join_message where test_join_message.id = 11", &manager);
+    assert_eq!(row_list.len(), 3);
+    assert!(row_list.iter().all(|row| row.len() == 5));
+    assert_eq!(extract!(&row_list[0][2], &TupleValue::Int(n), n), 11);
+
+    // the conditions of a single table are checked before the join
+    let (row_list, explain) = query_helper(
+        "select test_join_author.id, test_join_message.id from test_join_author, test_join_message \
+        where test_join_author.id = 2 and content = \"hello\"", &manager);
+    assert!(explain.contains("IndexScan"));
+    assert!(explain.contains("inner: Filter"));
+    assert_row_list(&row_list, vec![vec![TupleValue::Int(2), TupleValue::Int(10)]]);
+
+    let (row_list, _) = query_helper(
+        "select test_join_author.id from test_join_author, test_join_message where test_join_message.id > 100",
+        &manager);
+    assert!(row_list.is_empty());
+}
+
+#[test]
+fn test_nested_loop_join_spill() {
+    let table_file_dir = "test_file/test_nested_loop_join_spill/table_file/";
+    // about 8 rows in a block, the inner rows are kept in a temporary file
+    let manager = gen_join_manager("test_file/test_nested_loop_join_spill", 1024);
+    for i in 0..40 {
+        let sql = format!("insert test_join_author values({}, \"author{}\")", i, i);
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    for i in 0..100 {
+        let sql = format!("insert test_join_message values({}, {}, \"message{}\")", i, i % 50, i);
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    // not an equality, so that a nested loop join is used
+    let mut plan = gen_plan_helper!(
+        "select test_join_message.id, test_join_author.id from test_join_author, test_join_message \
+        where test_join_author.id <= author_id and test_join_author.id >= author_id", &manager);
+    assert!(plan.explain().contains("NestedLoopJoin"));
+    plan.open();
+    let mut pair_list = Vec::new();
+    while let Some(row) = plan.get_next() {
+        if pair_list.is_empty() {
+            assert_eq!(count_temp_file(table_file_dir), 1);
+        }
+        pair_list.push((extract!(&row[0], &TupleValue::Int(n), n), extract!(&row[1], &TupleValue::Int(n), n)));
+    }
+    assert_pattern!(plan.get_error(), None);
+    pair_list.sort();
+    let expected : Vec<(i32, i32)> = (0..100).filter(|i| i % 50 < 40).map(|i| (i, i % 50)).collect();
+    assert_eq!(pair_list, expected);
+    assert_eq!(count_temp_file(table_file_dir), 0);
+}
+
+#[test]
+fn test_hash_join() {
+    let manager = gen_join_manager("test_file/test_hash_join", 1024 * 1024);
+    insert_join_data(&manager);
+    let (row_list, explain) = query_helper(
+        "select test_join_message.content, test_join_author.name from test_join_message, test_join_author \
+        where author_id = test_join_author.id order by test_join_message.id", &manager);
+    assert!(explain.contains("HashJoin"));
+    assert!(!explain.contains("NestedLoopJoin"));
+    assert_row_list(&row_list, vec![
+        vec![char_value("hello"), char_value("huang")],
+        vec![char_value("world"), char_value("doyoubi")],
+        vec![char_value("again"), char_value("huang")],
+    ]);
+
+    // the other conditions of both tables are checked after the keys are matched
+    let (row_list, explain) = query_helper(
+        "select test_join_author.id, test_join_message.id from test_join_author, test_join_message \
+        where test_join_author.id = author_id and test_join_message.id > test_join_author.id + 10", &manager);
+    assert!(explain.contains("HashJoin"));
+    assert!(explain.contains("condition: Some"));
+    assert_row_list(&row_list, vec![vec![TupleValue::Int(2), TupleValue::Int(13)]]);
+
+    let (row_list, _) = query_helper(
+        "select name, count(*) from test_join_author, test_join_message \
+        where test_join_author.id = author_id group by name order by name", &manager);
+    assert_row_list(&row_list, vec![
+        vec![char_value("doyoubi"), TupleValue::Int(1)],
+        vec![char_value("huang"), TupleValue::Int(2)],
+    ]);
+
+    // int and float are not hashed the same way
+    assert_pattern!(exec_helper!("create table test_join_score(id int not null primary, score float not null)", &manager), None);
+    assert_pattern!(exec_helper!("insert test_join_score values(1, 2.0)", &manager), None);
+    let (row_list, explain) = query_helper(
+        "select test_join_message.id from test_join_message, test_join_score where author_id = score", &manager);
+    assert!(explain.contains("NestedLoopJoin"));
+    assert_row_list(&row_list, vec![vec![TupleValue::Int(10)], vec![TupleValue::Int(13)]]);
+}
+
+#[test]
+fn test_hash_join_spill() {
+    let table_file_dir = "test_file/test_hash_join_spill/table_file/";
+    let manager = gen_join_manager("test_file/test_hash_join_spill", 1024);
+    for i in 0..200 {
+        let sql = format!("insert test_join_author values({}, \"author{}\")", i, i);
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    for i in 0..300 {
+        let sql = if i % 10 == 0 {
+            format!("insert test_join_message values({}, null, \"message{}\")", i, i)
+        } else {
+            format!("insert test_join_message values({}, {}, \"message{}\")", i, i % 250, i)
+        };
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    let mut plan = gen_plan_helper!(
+        "select test_join_message.id, test_join_author.id from test_join_author, test_join_message \
+        where test_join_author.id = author_id", &manager);
+    assert!(plan.explain().contains("HashJoin"));
+    plan.open();
+    let mut pair_list = Vec::new();
+    while let Some(row) = plan.get_next() {
+        if pair_list.is_empty() {
+            // the rows of both tables are partitioned
+            assert!(count_temp_file(table_file_dir) > 1);
+        }
+        pair_list.push((extract!(&row[0], &TupleValue::Int(n), n), extract!(&row[1], &TupleValue::Int(n), n)));
+    }
+    assert_pattern!(plan.get_error(), None);
+    pair_list.sort();
+    let expected : Vec<(i32, i32)> = (0..300).filter(|i| i % 10 != 0 && i % 250 < 200).map(|i| (i, i % 250)).collect();
+    assert_eq!(pair_list, expected);
+    assert_eq!(count_temp_file(table_file_dir), 0);
+}
+
+fn int_pair_list(row_list : &Vec<Row>) -> Vec<(Option<i32>, Option<i32>)> {
+    let int_value = |value : &TupleValue| match value {
+        &TupleValue::Int(n) => Some(n),
+        &TupleValue::Null => None,
+        other => panic!("unexpected value {:?}", other),
+    };
+    row_list.iter().map(|row| (int_value(&row[0]), int_value(&row[1]))).collect()
+}
+
+#[test]
+fn test_outer_join() {
+    let manager = gen_join_manager("test_file/test_outer_join", 1024 * 1024);
+    insert_join_data(&manager);
+
+    let (row_list, explain) = query_helper(
+        "select test_join_author.name, test_join_message.id from test_join_author left join test_join_message \
+        on test_join_author.id = author_id order by test_join_author.id, test_join_message.id", &manager);
+    assert!(explain.contains("HashJoin"));
+    assert_row_list(&row_list, vec![
+        vec![char_value("doyoubi"), TupleValue::Int(11)],
+        vec![char_value("huang"), TupleValue::Int(10)],
+        vec![char_value("huang"), TupleValue::Int(13)],
+        vec![char_value("nobody"), TupleValue::Null],
+    ]);
+
+    let (row_list, _) = query_helper(
+        "select test_join_message.id, test_join_author.name from test_join_author right outer join test_join_message \
+        on test_join_author.id = author_id order by test_join_message.id", &manager);
+    assert_row_list(&row_list, vec![
+        vec![TupleValue::Int(10), char_value("huang")],
+        vec![TupleValue::Int(11), char_value("doyoubi")],
+        vec![TupleValue::Int(12), TupleValue::Null],
+        vec![TupleValue::Int(13), char_value("huang")],
+    ]);
+
+    let (row_list, _) = query_helper(
+        "select test_join_author.id, test_join_message.id from test_join_author full join test_join_message \
+        on test_join_author.id = author_id order by test_join_message.id, test_join_author.id", &manager);
+    assert_eq!(int_pair_list(&row_list), vec![
+        (Some(3), None), (Some(2), Some(10)), (Some(1), Some(11)), (None, Some(12)), (Some(2), Some(13))]);
+
+    // the where condition is checked after the rows are padded
+    let (row_list, _) = query_helper(
+        "select name from test_join_author left join test_join_message \
+        on test_join_author.id = author_id where test_join_message.id is null", &manager);
+    assert_row_list(&row_list, vec![vec![char_value("nobody")]]);
+
+    // the join condition doesn't remove the rows of the padded side
+    let (row_list, _) = query_helper(
+        "select test_join_author.id, test_join_message.id from test_join_author left join test_join_message \
+        on test_join_author.id = author_id and test_join_author.id > 1 \
+        order by test_join_author.id, test_join_message.id", &manager);
+    assert_eq!(int_pair_list(&row_list), vec![
+        (Some(1), None), (Some(2), Some(10)), (Some(2), Some(13)), (Some(3), None)]);
+
+    let (row_list, explain) = query_helper(
+        "select test_join_author.id, test_join_message.id from test_join_author left join test_join_message \
+        on author_id > test_join_author.id order by test_join_author.id, test_join_message.id", &manager);
+    assert!(explain.contains("NestedLoopJoin"));
+    assert_eq!(int_pair_list(&row_list), vec![
+        (Some(1), Some(10)), (Some(1), Some(13)), (Some(2), None), (Some(3), None)]);
+
+    let (row_list, _) = query_helper(
+        "select test_join_author.id, test_join_message.id from test_join_author right join test_join_message \
+        on author_id > test_join_author.id order by test_join_message.id", &manager);
+    assert_eq!(int_pair_list(&row_list), vec![
+        (Some(1), Some(10)), (None, Some(11)), (None, Some(12)), (Some(1), Some(13))]);
+
+    assert_pattern!(exec_helper!(
+        "create table test_join_profile(id int not null primary, age int null)", &manager), None);
+    assert_pattern!(exec_helper!("insert test_join_profile values(1, 30)", &manager), None);
+    assert_pattern!(exec_helper!("insert test_join_profile values(3, 40)", &manager), None);
+    let (row_list, _) = query_helper(
+        "select test_join_author.id, age from test_join_author left join test_join_profile using (id) \
+        order by test_join_author.id", &manager);
+    assert_eq!(int_pair_list(&row_list), vec![(Some(1), Some(30)), (Some(2), None), (Some(3), Some(40))]);
+
+    // USING merges the columns of the attribute
+    assert_pattern!(exec_helper!("insert test_join_profile values(4, 50)", &manager), None);
+    let (row_list, _) = query_helper(
+        "select id, age from test_join_author left join test_join_profile using (id) order by id", &manager);
+    assert_eq!(int_pair_list(&row_list), vec![(Some(1), Some(30)), (Some(2), None), (Some(3), Some(40))]);
+    let (row_list, _) = query_helper(
+        "select * from test_join_author right join test_join_profile using (id) order by age", &manager);
+    assert_row_list(&row_list, vec![
+        vec![TupleValue::Int(1), char_value("doyoubi"), TupleValue::Int(30)],
+        vec![TupleValue::Int(3), char_value("nobody"), TupleValue::Int(40)],
+        vec![TupleValue::Int(4), TupleValue::Null, TupleValue::Int(50)],
+    ]);
+    let (row_list, _) = query_helper(
+        "select id, age from test_join_author full join test_join_profile using (id)", &manager);
+    let mut pair_list = int_pair_list(&row_list);
+    pair_list.sort();
+    assert_eq!(pair_list, vec![(Some(1), Some(30)), (Some(2), None), (Some(3), Some(40)), (Some(4), Some(50))]);
+
+    let (row_list, _) = query_helper(
+        "select test_join_message.id, age from test_join_message join test_join_author \
+        on author_id = test_join_author.id left join test_join_profile on test_join_author.id = test_join_profile.id \
+        order by test_join_message.id", &manager);
+    assert_eq!(int_pair_list(&row_list), vec![
+        (Some(10), None), (Some(11), Some(30)), (Some(13), None)]);
+}
+
+#[test]
+fn test_full_join_spill() {
+    let table_file_dir = "test_file/test_full_join_spill/table_file/";
+    let manager = gen_join_manager("test_file/test_full_join_spill", 1024);
+    for i in 0..200 {
+        let sql = format!("insert test_join_author values({}, \"author{}\")", i, i);
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    for i in 0..300 {
+        let sql = if i % 10 == 0 {
+            format!("insert test_join_message values({}, null, \"message{}\")", i, i)
+        } else {
+            format!("insert test_join_message values({}, {}, \"message{}\")", i, i % 250, i)
+        };
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    let mut expected : Vec<(Option<i32>, Option<i32>)> = (0..300).map(|i| {
+        match i % 250 {
+            _ if i % 10 == 0 => (Some(i), None),
+            author_id if author_id < 200 => (Some(i), Some(author_id)),
+            _ => (Some(i), None),
+        }
+    }).collect();
+    expected.extend((0..200).filter(|i| i % 10 == 0).map(|i| (None, Some(i))));
+    expected.sort();
+
+    for sql in [
+        // partitioned by hash
+        "select test_join_message.id, test_join_author.id from test_join_message full join test_join_author \
+        on author_id = test_join_author.id",
+        // the inner rows are kept in a temporary file
+        "select test_join_message.id, test_join_author.id from test_join_message full join test_join_author \
+        on author_id <= test_join_author.id and author_id >= test_join_author.id",
+    ].iter() {
+        let (row_list, _) = query_helper(*sql, &manager);
+        let mut pair_list = int_pair_list(&row_list);
+        pair_list.sort();
+        assert_eq!(pair_list, expected);
+    }
+    assert_eq!(count_temp_file(table_file_dir), 0);
+}
+
+#[test]
+fn test_skewed_hash_join() {
+    // the partition of the same key doesn't fit in memory however it's partitioned
+    let table_file_dir = "test_file/test_skewed_hash_join/table_file/";
+    let manager = gen_join_manager("test_file/test_skewed_hash_join", 1024);
+    assert_pattern!(exec_helper!(
+        "create table test_join_fan(id int not null primary, author_id int null)", &manager), None);
+    let message_author = |i : i32| if i % 4 == 0 { None } else if i % 5 == 0 { Some(i) } else { Some(1) };
+    let fan_author = |i : i32| if i % 3 == 0 { i + 1000 } else { 1 };
+    for i in 0..120 {
+        let author_id = message_author(i).map(|id| id.to_string()).unwrap_or("null".to_string());
+        let sql = format!("insert test_join_message values({}, {}, \"message{}\")", i, author_id, i);
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    for i in 0..60 {
+        let sql = format!("insert test_join_fan values({}, {})", i, fan_author(i));
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    let mut expected = Vec::new();
+    for i in 0..120 {
+        let fan_list : Vec<i32> = (0..60).filter(|&j| message_author(i) == Some(fan_author(j))).collect();
+        if fan_list.is_empty() {
+            expected.push((Some(i), None));
+        }
+        expected.extend(fan_list.into_iter().map(|j| (Some(i), Some(j))));
+    }
+    expected.extend((0..60).filter(|&j| fan_author(j) != 1).map(|j| (None, Some(j))));
+    expected.sort();
+
+    let (row_list, explain) = query_helper(
+        "select test_join_message.id, test_join_fan.id from test_join_message full join test_join_fan \
+        on test_join_message.author_id = test_join_fan.author_id", &manager);
+    assert!(explain.contains("hash"));
+    let mut pair_list = int_pair_list(&row_list);
+    pair_list.sort();
+    assert_eq!(pair_list, expected);
+    assert_eq!(count_temp_file(table_file_dir), 0);
+}
@@ -1,15 +1,18 @@
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::ptr::read;
-use ::utils::pointer::read_string;
 use ::store::table::{TableManagerRef, TableManager, Table, Attr, AttrType};
-use ::store::file::TableFileManager;
+use ::store::file::{TableFileManager, RecordId};
+use ::store::tuple::TupleValue;
+use ::store::index::PRIMARY_INDEX;
+use ::store::btree::KeyRange;
 use ::parser::common::{ValueExpr, ValueType};
 use ::parser::condition::ConditionExpr;
 use ::utils::config::Config;
-use ::exec::query::{FileScan, Filter, Projection};
+use ::exec::query::{FileScan, IndexScan, Filter, Projection};
 use ::exec::iter::ExecIterRef;
+use ::exec::error::ExecErrorType;
 use ::exec::gen_plan::gen_proj_info;
+use ::test::utils::gen_exec_manager;
 
 
 pub fn gen_test_table(table_name : &String) -> Table {
@@ -35,6 +38,8 @@ pub fn gen_test_table(table_name : &String) -> Table {
                 nullable : false,
             },
         ],
+        index_list : vec![],
+        compression : None,
     }
 }
 
@@ -44,11 +49,11 @@ pub fn insert_data(table_name : &String, manager : &TableManagerRef) {
         ValueExpr{ value : "666.666".to_string(), value_type : ValueType::Float },
         ValueExpr{ value : "qweryu".to_string(), value_type : ValueType::String },
     ];
-    manager.borrow_mut().insert(&table_name, &value_list);
+    manager.borrow_mut().insert(&table_name, &value_list).unwrap();
     value_list[0].value = "777".to_string();
     value_list[1].value = "12345.777".to_string();
     value_list[2].value = "dyb".to_string();
-    manager.borrow_mut().insert(&table_name, &value_list);
+    manager.borrow_mut().insert(&table_name, &value_list).unwrap();
 
     value_list[0].value = "1".to_string();
     value_list[1].value = "123.0".to_string();
@@ -57,9 +62,12 @@ pub fn insert_data(table_name : &String, manager : &TableManagerRef) {
     manager.borrow_mut().file_manager.insert_in_page(&table_name, 1, &value_list);
 
     let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    // index pages share the page pool and may swap out table pages
+    manager.borrow_mut().file_manager.ensure_page_loaded(&file, 0).unwrap();
     assert!(file.borrow().is_inuse(0, 0));
     assert!(file.borrow().is_inuse(0, 1));
     assert!(!file.borrow().is_inuse(0, 2));
+    manager.borrow_mut().file_manager.ensure_page_loaded(&file, 1).unwrap();
     assert!(file.borrow().is_inuse(1, 0));
 }
 
@@ -76,21 +84,21 @@ pub fn gen_test_manager(table_name : &String) -> TableManagerRef {
 
 macro_rules! assert_int {
     ($p:expr, $n:expr) => ({
-        let i = unsafe{ read::<i32>($p as *const i32) };
+        let i = extract!(&$p, &TupleValue::Int(n), n);
         assert_eq!(i, $n);
     })
 }
 
 macro_rules! assert_float {
     ($p:expr, $n:expr) => ({
-        let i = unsafe{ read::<f32>($p as *const f32) };
+        let i = extract!(&$p, &TupleValue::Float(f), f);
         assert_eq!(i, $n);
     })
 }
 
 macro_rules! assert_str {
     ($p:expr, $s:expr) => ({
-        let i = unsafe{ read_string($p, 16) };
+        let i = extract!(&$p, &TupleValue::Char(ref s), s.clone());
         assert_eq!(i, $s);
     })
 }
@@ -116,6 +124,180 @@ fn test_file_scan() {
     assert_pattern!(plan.get_next(), None);
 }
 
+#[test]
+fn test_record_id() {
+    let table_name = "test_record_id_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    let mut plan = FileScan::new(&table_name, &manager);
+    plan.open();
+    assert_pattern!(plan.get_record_id(), None);
+    let mut record_id_list = Vec::new();
+    while let Some(..) = plan.get_next() {
+        record_id_list.push(plan.get_record_id().unwrap());
+    }
+    assert_eq!(record_id_list, vec![
+        RecordId{ page_index : 0, slot : 0 },
+        RecordId{ page_index : 0, slot : 1 },
+        RecordId{ page_index : 1, slot : 0 },
+    ]);
+
+    let scan = IndexScan::new(&table_name, PRIMARY_INDEX,
+        KeyRange::equal(vec![TupleValue::Int(1)]), &manager);
+    let projs = vec![("test_record_id_message".to_string(), "id".to_string())];
+    let mut plan = Projection::new(vec![0], projs, scan);
+    plan.open();
+    assert_pattern!(plan.get_next(), Some(..));
+    assert_eq!(plan.get_record_id(), Some(RecordId{ page_index : 1, slot : 0 }));
+    assert_pattern!(plan.get_next(), None);
+}
+
+#[test]
+fn test_row_after_eviction() {
+    let table_name = "test_row_after_eviction_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    let mut plan = FileScan::new(&table_name, &manager);
+    plan.open();
+    let mut row_list = Vec::new();
+    while let Some(row) = plan.get_next() {
+        row_list.push(row);
+    }
+    assert_eq!(row_list.len(), 3);
+    // swap out every page the rows were read from
+    assert!(manager.borrow_mut().file_manager.resize_pool(1).is_ok());
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert!(file.borrow().loaded_pages.len() <= 1);
+    assert_int!(row_list[0][0], 233);
+    assert_float!(row_list[0][1], 666.666);
+    assert_str!(row_list[0][2], "qweryu");
+    assert_int!(row_list[1][0], 777);
+    assert_str!(row_list[1][2], "dyb");
+    assert_int!(row_list[2][0], 1);
+    assert_str!(row_list[2][2], "str");
+}
+
+#[test]
+fn test_index_scan() {
+    let table_name = "test_query_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    {
+        let mut plan = IndexScan::new(&table_name, PRIMARY_INDEX,
+            KeyRange::equal(vec![TupleValue::Int(777)]), &manager);
+        plan.open();
+        let t = plan.get_next().unwrap();
+        assert_int!(t[0], 777);
+        assert_float!(t[1], 12345.777);
+        assert_str!(t[2], "dyb");
+        assert_pattern!(plan.get_next(), None);
+        plan.close();
+    }
+    {
+        // tuple inserted in the second page
+        let mut plan = IndexScan::new(&table_name, PRIMARY_INDEX,
+            KeyRange::equal(vec![TupleValue::Int(1)]), &manager);
+        plan.open();
+        let t = plan.get_next().unwrap();
+        assert_int!(t[0], 1);
+        assert_str!(t[2], "str");
+        assert_pattern!(plan.get_next(), None);
+        plan.close();
+    }
+    {
+        let mut plan = IndexScan::new(&table_name, PRIMARY_INDEX,
+            KeyRange::equal(vec![TupleValue::Int(666)]), &manager);
+        plan.open();
+        assert_pattern!(plan.get_next(), None);
+        plan.close();
+    }
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 2);
+}
+
+#[test]
+fn test_pool_exhausted() {
+    let table_name = "test_pool_exhausted_message".to_string();
+    let manager = gen_test_manager(&table_name);
+    let mut scan1 = FileScan::new(&table_name, &manager);
+    scan1.open();
+    assert_int!(scan1.get_next().unwrap()[0], 233);
+    // the last frame of the pool is never pinned
+    let mut scan2 = IndexScan::new(&table_name, PRIMARY_INDEX,
+        KeyRange::equal(vec![TupleValue::Int(1)]), &manager);
+    scan2.open();
+    assert_pattern!(scan2.get_next(), None);
+    assert_eq!(scan2.get_error().unwrap().error_type, ExecErrorType::PoolExhausted);
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 1);
+
+    assert!(manager.borrow_mut().file_manager.resize_pool(1).is_err());
+    assert!(manager.borrow_mut().file_manager.resize_pool(4).is_ok());
+    assert_eq!(manager.borrow().file_manager.page_pool.get_capacity(), 4);
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 3);
+    // the page pinned by scan1 can be pinned again
+    let mut scan3 = FileScan::new(&table_name, &manager);
+    scan3.open();
+    let mut count = 0;
+    while let Some(..) = scan3.get_next() {
+        count += 1;
+    }
+    assert_eq!(count, 3);
+    assert_pattern!(scan3.get_error(), None);
+    assert_int!(scan1.get_next().unwrap()[0], 777);
+    assert_int!(scan1.get_next().unwrap()[0], 1);
+    assert_pattern!(scan1.get_next(), None);
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 4);
+
+    assert!(manager.borrow_mut().file_manager.resize_pool(2).is_ok());
+    assert_eq!(manager.borrow().file_manager.page_pool.get_capacity(), 2);
+    assert!(manager.borrow().file_manager.page_pool.get_load() <= 2);
+    let mut scan4 = FileScan::new(&table_name, &manager);
+    scan4.open();
+    assert_int!(scan4.get_next().unwrap()[0], 233);
+    assert_int!(scan4.get_next().unwrap()[0], 777);
+    assert_int!(scan4.get_next().unwrap()[0], 1);
+    assert_pattern!(scan4.get_next(), None);
+    assert_pattern!(scan4.get_error(), None);
+}
+
+#[test]
+fn test_pool_exhausted_by_pins() {
+    // the pages changed by a statement are swapped out, only the pins held by the scans exhaust the pool
+    let manager = gen_exec_manager("test_file/test_pool_exhausted_by_pins", 4096,
+        &["create table t(id int not null primary, content char(200))"]);
+    for i in 0..100 {
+        assert_pattern!(exec_helper!(&format!("insert t values({}, 'a')", i), &manager), None);
+        manager.borrow_mut().commit().unwrap();
+    }
+    assert!(manager.borrow_mut().file_manager.resize_pool(5).is_ok());
+    let table_name = "t".to_string();
+    let mut scan_list = Vec::new();
+    for skipped in vec![0, 40] {
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        for _ in 0..skipped + 1 {
+            assert_pattern!(scan.get_next(), Some(..));
+        }
+        scan_list.push(scan);
+    }
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 3);
+    assert_pattern!(exec_helper!("update t set content = 'b'", &manager), None);
+    manager.borrow_mut().commit().unwrap();
+
+    for skipped in vec![20, 60] {
+        let mut scan = FileScan::new(&table_name, &manager);
+        scan.open();
+        for _ in 0..skipped + 1 {
+            assert_pattern!(scan.get_next(), Some(..));
+        }
+        scan_list.push(scan);
+    }
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 1);
+    let mut scan = IndexScan::new(&table_name, PRIMARY_INDEX,
+        KeyRange::equal(vec![TupleValue::Int(99)]), &manager);
+    scan.open();
+    assert_pattern!(scan.get_next(), None);
+    let err = scan.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::PoolExhausted);
+    assert_eq!(err.error_msg, "buffer pool exhausted, 4 of 5 pages are pinned");
+}
+
 fn gen_filter_plan(expr : &str) -> ExecIterRef {
     let table_name = "test_query_message".to_string();
     let manager = gen_test_manager(&table_name);
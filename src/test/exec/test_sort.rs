@@ -0,0 +1,60 @@
+use ::store::table::TableManagerRef;
+use ::store::tuple::TupleValue;
+use ::test::utils::{gen_exec_manager, query_helper, count_temp_file};
+
+
+fn gen_sort_manager(dir : &str, work_memory_size : usize) -> TableManagerRef {
+    gen_exec_manager(dir, work_memory_size, &[
+        "create table test_sort_message(id int not null primary, score float null, content char(16) not null)",
+    ])
+}
+
+#[test]
+fn test_sort_in_memory() {
+    let manager = gen_sort_manager("test_file/test_sort_in_memory", 1024 * 1024);
+    for &(id, score, content) in [(1, "2.5", "b"), (2, "null", "a"), (3, "1.5", "b"), (4, "2.5", "a")].iter() {
+        let sql = format!("insert test_sort_message values({}, {}, \"{}\")", id, score, content);
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    let query_id_list = |sql : &str| -> Vec<i32> {
+        query_helper(sql, &manager).0.iter().map(|t| extract!(&t[0], &TupleValue::Int(n), n)).collect()
+    };
+    // null comes first in ascending order
+    assert_eq!(query_id_list("select id from test_sort_message order by score"), vec![2, 3, 1, 4]);
+    assert_eq!(query_id_list("select id from test_sort_message order by score desc, id desc"), vec![4, 1, 3, 2]);
+    assert_eq!(query_id_list("select id from test_sort_message order by content, id desc"), vec![4, 2, 3, 1]);
+    assert_eq!(query_id_list(
+        "select id from test_sort_message where id > 1 order by content desc, score asc"), vec![3, 2, 4]);
+}
+
+#[test]
+fn test_sort_spill() {
+    let dir = "test_file/test_sort_spill";
+    let table_file_dir = "test_file/test_sort_spill/table_file/";
+    // about 8 rows in a run, more runs than the fan in are merged in passes
+    let manager = gen_sort_manager(dir, 1024);
+    let row_num = 500;
+    for i in 0..row_num {
+        // ids in a shuffled order
+        let id = (i * 7919) % row_num;
+        let sql = format!("insert test_sort_message values({}, {}, \"content{}\")", id, id % 10, id);
+        assert_pattern!(exec_helper!(&sql[..], &manager), None);
+    }
+    {
+        let mut plan = gen_plan_helper!(
+            "select id, score from test_sort_message order by score desc, id", &manager);
+        plan.open();
+        let mut row_list = Vec::new();
+        while let Some(t) = plan.get_next() {
+            if row_list.is_empty() {
+                assert!(count_temp_file(table_file_dir) > 0);
+            }
+            row_list.push((extract!(&t[0], &TupleValue::Int(n), n), extract!(&t[1], &TupleValue::Float(f), f)));
+        }
+        assert_pattern!(plan.get_error(), None);
+        let mut expected : Vec<(i32, f32)> = (0..row_num).map(|id| (id, (id % 10) as f32)).collect();
+        expected.sort_by(|lhs, rhs| rhs.1.partial_cmp(&lhs.1).unwrap().then(lhs.0.cmp(&rhs.0)));
+        assert_eq!(row_list, expected);
+        assert_eq!(count_temp_file(table_file_dir), 0);
+    }
+}
@@ -0,0 +1,125 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::{remove_dir_all, metadata};
+use ::store::table::TableManager;
+use ::utils::config::Config;
+use ::utils::libwrapper::get_page_size;
+use ::store::tuple::TupleValue;
+use ::exec::error::ExecErrorType;
+use ::exec::query::FileScan;
+use super::test_query::{gen_test_table, insert_data};
+
+
+macro_rules! exec_helper {
+    ($input_str:expr, $manager:expr) => ({
+        let mut plan = gen_plan_helper!($input_str, $manager);
+        plan.open();
+        while let Some(..) = plan.get_next() {}
+        let error = plan.get_error();
+        if error.is_none() {
+            $manager.borrow_mut().commit().unwrap();
+        }
+        error
+    })
+}
+
+macro_rules! query_id_helper {
+    ($input_str:expr, $manager:expr) => ({
+        let mut query = gen_plan_helper!($input_str, $manager);
+        query.open();
+        let mut id_list = Vec::new();
+        while let Some(t) = query.get_next() {
+            id_list.push(extract!(&t[0], &TupleValue::Int(n), n));
+        }
+        id_list
+    })
+}
+
+#[test]
+fn test_vacuum() {
+    let _ = remove_dir_all("test_file/test_vacuum/");
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 64
+        table_meta_dir = "test_file/test_vacuum/table_meta/"
+        table_file_dir = "test_file/test_vacuum/table_file/""#.to_string());
+    let table_name = "test_vacuum_message".to_string();
+    {
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        assert_pattern!(exec_helper!(
+            "create table test_vacuum_message(id int not null primary, content char(200) not null)",
+            &manager), None);
+        assert_pattern!(exec_helper!(
+            "create index content_index on test_vacuum_message (content)", &manager), None);
+        for i in 0..100 {
+            let sql = format!("insert test_vacuum_message values({}, \"content{}\")", i, i);
+            assert_pattern!(exec_helper!(&sql[..], &manager), None);
+        }
+        assert_pattern!(exec_helper!("delete from test_vacuum_message where id >= 10", &manager), None);
+        let file = manager.borrow_mut().file_manager.get_file(&table_name);
+        let old_page_sum = file.borrow().page_sum;
+
+        let mut plan = gen_plan_helper!("vacuum test_vacuum_message", &manager);
+        plan.open();
+        let t = extract!(plan.get_next(), Some(tuple_data), tuple_data);
+        let reclaimed = extract!(&t[0], &TupleValue::Int(n), n) as usize;
+        assert_pattern!(plan.get_next(), None);
+        assert_pattern!(plan.get_error(), None);
+
+        let file = manager.borrow_mut().file_manager.get_file(&table_name);
+        let new_page_sum = file.borrow().page_sum;
+        assert!(reclaimed > 0);
+        assert_eq!(old_page_sum - new_page_sum, reclaimed);
+        let file_len = metadata("test_file/test_vacuum/table_file/test_vacuum_message.table").unwrap().len();
+        assert_eq!(file_len as usize, (new_page_sum + 1) * get_page_size());
+        assert!(metadata("test_file/test_vacuum/table_file/test_vacuum_message.rewrite.table").is_err());
+
+        let id_list = query_id_helper!("select * from test_vacuum_message", &manager);
+        assert_eq!(id_list, (0..10).collect::<Vec<i32>>());
+        assert_eq!(query_id_helper!("select * from test_vacuum_message where id = 7", &manager), vec![7]);
+        assert_eq!(query_id_helper!(
+            "select * from test_vacuum_message where content = \"content3\"", &manager), vec![3]);
+        assert_pattern!(exec_helper!(
+            "insert test_vacuum_message values(100, \"content100\")", &manager), None);
+        manager.borrow_mut().commit().unwrap();
+    }
+    // the files replaced by vacuum should be loaded after restart
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let mut query = gen_plan_helper!("select * from test_vacuum_message where content = \"content100\"", &manager);
+    query.open();
+    let t = extract!(query.get_next(), Some(tuple_data), tuple_data);
+    assert_eq!(extract!(&t[1], &TupleValue::Char(ref s), s.clone()), "content100");
+    assert_pattern!(query.get_next(), None);
+    assert_eq!(query_id_helper!("select * from test_vacuum_message", &manager).len(), 11);
+}
+
+#[test]
+fn test_vacuum_pinned_page() {
+    let _ = remove_dir_all("test_file/test_vacuum_pinned_page/");
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/test_vacuum_pinned_page/table_meta/"
+        table_file_dir = "test_file/test_vacuum_pinned_page/table_file/""#.to_string());
+    let table_name = "test_vacuum_pinned_message".to_string();
+    let manager = TableManager::make_ref(&config);
+    manager.borrow_mut().add_table(gen_test_table(&table_name));
+    insert_data(&table_name, &manager);
+
+    let mut scan = FileScan::new(&table_name, &manager);
+    scan.open();
+    assert_pattern!(scan.get_next(), Some(..));
+    let mut plan = gen_plan_helper!("vacuum", &manager);
+    plan.open();
+    assert_pattern!(plan.get_next(), None);
+    let err = extract!(plan.get_error(), Some(err), err);
+    assert_eq!(err.error_type, ExecErrorType::PageInUse);
+    scan.close();
+
+    // the tuple inserted in the second page is moved to the first one
+    let mut plan = gen_plan_helper!("vacuum", &manager);
+    plan.open();
+    let t = extract!(plan.get_next(), Some(tuple_data), tuple_data);
+    assert_eq!(extract!(&t[0], &TupleValue::Int(n), n), 1);
+    assert_pattern!(plan.get_error(), None);
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 2);
+    assert_eq!(query_id_helper!("select * from test_vacuum_pinned_message", &manager), vec![233, 777, 1]);
+}
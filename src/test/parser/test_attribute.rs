@@ -87,10 +87,27 @@ fn test_aggre_func_with_single_attr(parse_func : ParseFun) {
     assert_pattern!(it.next(), None);
 }
 
+fn test_aggre_func_with_star(parse_func : ParseFun) {
+    let tokens = gen_token!("count(*)");
+    assert_eq!(tokens.len(), 4);
+    let mut it = tokens.iter();
+    let func_exp = parse_func(&mut it);
+    assert_pattern!(func_exp, Ok(..));
+    let func_exp = func_exp.unwrap();
+    assert_eq!(func_exp.to_string(), "count(*)");
+    let (func, table, attr) = extract!(
+        func_exp, AttributeExpr::AggreFuncCall{ func, table, attr }, (func, table, attr));
+    assert_eq!(func, "count".to_string());
+    assert_eq!(table, None);
+    assert_eq!(attr, "*".to_string());
+    assert_pattern!(it.next(), None);
+}
+
 #[test]
 fn test_parse_aggre_func() {
     test_aggre_func_with_table_name(AttributeExpr::parse_aggre_func);
     test_aggre_func_with_single_attr(AttributeExpr::parse_aggre_func);
+    test_aggre_func_with_star(AttributeExpr::parse_aggre_func);
     test_invalid_tokens(AttributeExpr::parse_aggre_func);
 }
 
@@ -119,6 +119,16 @@ fn test_string_token() {
     assert_error_eq!(h, 1, CompileErrorType::LexerInCompleteString);
 }
 
+#[test]
+fn test_single_quoted_string_token() {
+    let mut h = TokenTestHelper::new("'lz4' 'a\"b' 'incomplete\"");
+    assert_token_len!(h, 2);
+    assert_error_len!(h, 1);
+    assert_token_eq!(h, 1, "lz4", TokenType::StringLiteral);
+    assert_token_eq!(h, 7, "a\"b", TokenType::StringLiteral);
+    assert_error_eq!(h, 0, CompileErrorType::LexerInCompleteString);
+}
+
 #[test]
 fn test_identifier_token() {
     let mut h = TokenTestHelper::new("ident ident2 _233");
@@ -135,9 +145,9 @@ fn test_keyword_token() {
         "select fROM Where order by group having \
          insert values update set delete \
          create table drop null and or not is \
-         int float char"
+         int float char varchar text"
         );
-    assert_token_len!(h, 23);
+    assert_token_len!(h, 25);
     assert_error_len!(h, 0);
     assert_token_eq!(h, 1, "select", TokenType::Select);
     assert_token_eq!(h, 8, "fROM", TokenType::From);
@@ -162,6 +172,22 @@ fn test_keyword_token() {
     assert_token_eq!(h, 40 + 32 + 37 + 1, "int", TokenType::Int);
     assert_token_eq!(h, 40 + 32 + 37 + 5, "float", TokenType::Float);
     assert_token_eq!(h, 40 + 32 + 37 + 11, "char", TokenType::Char);
+    assert_token_eq!(h, 40 + 32 + 37 + 16, "varchar", TokenType::Varchar);
+    assert_token_eq!(h, 40 + 32 + 37 + 24, "text", TokenType::Text);
+}
+
+#[test]
+fn test_join_keyword_token() {
+    let mut h = TokenTestHelper::new("join Inner left right full outer using");
+    assert_token_len!(h, 7);
+    assert_error_len!(h, 0);
+    assert_token_eq!(h, 1, "join", TokenType::Join);
+    assert_token_eq!(h, 6, "Inner", TokenType::Inner);
+    assert_token_eq!(h, 12, "left", TokenType::Left);
+    assert_token_eq!(h, 17, "right", TokenType::Right);
+    assert_token_eq!(h, 23, "full", TokenType::Full);
+    assert_token_eq!(h, 28, "outer", TokenType::Outer);
+    assert_token_eq!(h, 34, "using", TokenType::Using);
 }
 
 #[test]
@@ -1,18 +1,24 @@
-use ::store::table::{TableSet, Table, Attr, AttrType};
+use ::store::table::{TableSet, Table, Attr, AttrType, IndexDesc};
 use ::parser::condition::ConditionExpr;
 use ::parser::compile_error::CompileErrorType;
 use ::parser::select::SelectStatement;
 use ::parser::update::UpdateStatement;
 use ::parser::insert::InsertStatement;
 use ::parser::delete::DeleteStatement;
-use ::parser::create_drop::{CreateStatement, DropStatement};
+use ::parser::create_drop::{CreateStatement, DropStatement, CreateIndexStatement, DropIndexStatement};
+use ::parser::vacuum::VacuumStatement;
+use ::parser::alter::AlterStatement;
 use ::parser::sem_check::{
+    check_create_index,
+    check_drop_index,
     check_drop,
     check_create,
     check_condition,
     check_insert,
     check_update,
     check_select,
+    check_vacuum,
+    check_alter,
 };
 
 
@@ -45,6 +51,8 @@ fn add_table(table_set : &mut TableSet) {
                 nullable : false,
             }
         ],
+        index_list : vec![],
+        compression : None,
     };
     let t2 = Table{
         name : "book".to_string(),
@@ -67,10 +75,38 @@ fn add_table(table_set : &mut TableSet) {
                 primary : false,
                 nullable : true,
             }
-        ]
+        ],
+        index_list : vec![],
+        compression : None,
+    };
+    let t3 = Table{
+        name : "article".to_string(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+            },
+            Attr{
+                name : "title".to_string(),
+                attr_type : AttrType::Varchar{ len : 20 },
+                primary : false,
+                nullable : false,
+            },
+            Attr{
+                name : "content".to_string(),
+                attr_type : AttrType::Text,
+                primary : false,
+                nullable : true,
+            }
+        ],
+        index_list : vec![],
+        compression : None,
     };
     table_set.add_table(t1);
     table_set.add_table(t2);
+    table_set.add_table(t3);
 }
 
 #[test]
@@ -122,6 +158,132 @@ fn test_check_create() {
             "create table author(id int not null primary, id char(10))");
         assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemDuplicateAttr);
     }
+    {// primary type
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id varchar(10) not null primary, bio text)");
+        let table_set = TableSet::new();
+        assert_ok!(check_create(&create_stmt, &table_set));
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id text not null primary)");
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemInvalidIndexType);
+    }
+    {// table option
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary) with (compression = 'lz4')");
+        let table_set = TableSet::new();
+        assert_ok!(check_create(&create_stmt, &table_set));
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary) with (compression = zip)");
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemInvalidTableOption);
+        let create_stmt = gen_parse_result!(CreateStatement::parse,
+            "create table author(id int not null primary) with (fillfactor = 70)");
+        assert_err!(check_create(&create_stmt, &table_set), CompileErrorType::SemInvalidTableOption);
+    }
+}
+
+#[test]
+fn test_check_create_index() {
+    let mut table_set = TableSet::new();
+    let create_stmt = gen_parse_result!(CreateIndexStatement::parse,
+        "create index name_index on book (name, author_id)");
+    assert_err!(check_create_index(&create_stmt, &table_set), CompileErrorType::SemTableNotExist);
+    add_table(&mut table_set);
+    assert_ok!(check_create_index(&create_stmt, &table_set));
+    let create_stmt = gen_parse_result!(CreateIndexStatement::parse,
+        "create index name_index on book (name, content)");
+    assert_err!(check_create_index(&create_stmt, &table_set), CompileErrorType::SemInvalidAttribute);
+    let create_stmt = gen_parse_result!(CreateIndexStatement::parse,
+        "create index name_index on book (name, name)");
+    assert_err!(check_create_index(&create_stmt, &table_set), CompileErrorType::SemDuplicateAttr);
+    let create_stmt = gen_parse_result!(CreateIndexStatement::parse,
+        "create index title_index on article (title)");
+    assert_ok!(check_create_index(&create_stmt, &table_set));
+    let create_stmt = gen_parse_result!(CreateIndexStatement::parse,
+        "create index content_index on article (content)");
+    assert_err!(check_create_index(&create_stmt, &table_set), CompileErrorType::SemInvalidIndexType);
+    table_set.tables.get_mut("book").unwrap().index_list.push(IndexDesc{
+        name : "name_index".to_string(),
+        attr_list : vec!["name".to_string()],
+        unique : false,
+    });
+    let create_stmt = gen_parse_result!(CreateIndexStatement::parse,
+        "create unique index name_index on book (author_id)");
+    assert_err!(check_create_index(&create_stmt, &table_set), CompileErrorType::SemIndexExist);
+}
+
+#[test]
+fn test_check_drop_index() {
+    let mut table_set = TableSet::new();
+    let drop_stmt = gen_parse_result!(DropIndexStatement::parse, "drop index name_index on book");
+    assert_err!(check_drop_index(&drop_stmt, &table_set), CompileErrorType::SemTableNotExist);
+    add_table(&mut table_set);
+    assert_err!(check_drop_index(&drop_stmt, &table_set), CompileErrorType::SemIndexNotExist);
+    table_set.tables.get_mut("book").unwrap().index_list.push(IndexDesc{
+        name : "name_index".to_string(),
+        attr_list : vec!["name".to_string()],
+        unique : false,
+    });
+    assert_ok!(check_drop_index(&drop_stmt, &table_set));
+}
+
+#[test]
+fn test_check_vacuum() {
+    let mut table_set = TableSet::new();
+    let vacuum_stmt = gen_parse_result!(VacuumStatement::parse, "vacuum book");
+    assert_err!(check_vacuum(&vacuum_stmt, &table_set), CompileErrorType::SemTableNotExist);
+    let vacuum_all_stmt = gen_parse_result!(VacuumStatement::parse, "vacuum");
+    assert_ok!(check_vacuum(&vacuum_all_stmt, &table_set));
+    add_table(&mut table_set);
+    assert_ok!(check_vacuum(&vacuum_stmt, &table_set));
+}
+
+#[test]
+fn test_check_alter() {
+    let mut table_set = TableSet::new();
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book add column price float");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemTableNotExist);
+    add_table(&mut table_set);
+    assert_ok!(check_alter(&alter_stmt, &table_set));
+    let alter_stmt = gen_parse_result!(AlterStatement::parse,
+        "alter table book add column price float not null default 1");
+    assert_ok!(check_alter(&alter_stmt, &table_set));
+    let alter_stmt = gen_parse_result!(AlterStatement::parse,
+        "alter table book add column price float not null");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemInvalidAlter);
+    let alter_stmt = gen_parse_result!(AlterStatement::parse,
+        "alter table book add column price float default \"free\"");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemInvalidInsertValueType);
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book add column name int");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemDuplicateAttr);
+    let alter_stmt = gen_parse_result!(AlterStatement::parse,
+        "alter table book add column isbn int not null primary default 0");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemMultiplePrimary);
+
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book drop column author_id");
+    assert_ok!(check_alter(&alter_stmt, &table_set));
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book drop column price");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemInvalidAttribute);
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book drop column id");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemChangePrimaryAttr);
+    table_set.tables.get_mut("book").unwrap().index_list.push(IndexDesc{
+        name : "name_index".to_string(),
+        attr_list : vec!["name".to_string()],
+        unique : false,
+    });
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book drop column name");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemInvalidAlter);
+
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book rename column name to title");
+    assert_ok!(check_alter(&alter_stmt, &table_set));
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book rename column name to id");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemDuplicateAttr);
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book rename column title to name");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book rename to novel");
+    assert_ok!(check_alter(&alter_stmt, &table_set));
+    let alter_stmt = gen_parse_result!(AlterStatement::parse, "alter table book rename to author");
+    assert_err!(check_alter(&alter_stmt, &table_set), CompileErrorType::SemTableExist);
 }
 
 #[test]
@@ -220,6 +382,13 @@ fn test_check_insert() {
 
     let mut insert = gen_parse_result!(InsertStatement::parse, "insert book values(1, null, \"book name\")");
     assert_ok!(check_insert(&mut insert, &table_set));
+
+    let mut insert = gen_parse_result!(InsertStatement::parse,
+        "insert article values(1, \"title\", \"it is not difficult to come up with a long content\")");
+    assert_ok!(check_insert(&mut insert, &table_set));
+    let mut insert = gen_parse_result!(InsertStatement::parse,
+        "insert article values(1, \"it is difficult to come up with a long title\", null)");
+    assert_err!(check_insert(&mut insert, &table_set), CompileErrorType::SemInvalidInsertCharLen);
 }
 
 #[test]
@@ -283,6 +452,14 @@ fn test_check_select() {
     let mut select = gen_parse_result!(SelectStatement::parse, "select book.name from book order by book.id");
     assert_ok!(check_select(&mut select, &table_set));
 
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.name from book order by book.id desc, author_id");
+    assert_ok!(check_select(&mut select, &table_set));
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.name from book order by book.id, num");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
     let mut select = gen_parse_result!(SelectStatement::parse, "select book.name from book order by num");
     assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
 
@@ -293,6 +470,109 @@ fn test_check_select() {
     let mut select = gen_parse_result!(SelectStatement::parse,
         "select book.id from book group by book.name order by book.name");
     assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select author_id, count(*), avg(book.id), max(book.name) from book \
+        group by author_id having count(book.name) > 1 order by max(book.name) desc");
+    assert_ok!(check_select(&mut select, &table_set));
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select count(*), min(book.name) from book");
+    assert_ok!(check_select(&mut select, &table_set));
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select book.name, count(*) from book");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemShouldUseGroupByAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select sum(*) from book");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAggregateFunctionUse);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select author_id, avg(book.name) from book group by author_id");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidValueType);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select author_id from book group by author_id having max(book.name) > 1");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidValueType);
+}
+
+#[test]
+fn test_check_multiple_table_select() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.name, author.name from book, author where author_id = author.id");
+    assert_ok!(check_select(&mut select, &table_set));
+    assert_eq!(format!("{}", select), "select (book.name), (author.name) from book, author \
+        where ((book.author_id) = (author.id))");
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select name from book, author");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.id from book, author where id > 1");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select * from book, book");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemDuplicateTable);
+
+    let mut select = gen_parse_result!(SelectStatement::parse, "select * from book, message");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemTableNotExist);
+}
+
+#[test]
+fn test_check_join_select() {
+    let mut table_set = TableSet::new();
+    add_table(&mut table_set);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select book.name from book left join author on author_id = author.id where author.id is null");
+    assert_ok!(check_select(&mut select, &table_set));
+    assert_eq!(format!("{}", select), "select (book.name) from book left join author \
+        on ((book.author_id) = (author.id)) where ((author.id) is Null(null))");
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select * from book full join author using (id)");
+    assert_ok!(check_select(&mut select, &table_set));
+
+    // the attributes of the tables which are not padded are still not nullable
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select * from book right join author using (id) where author.id is null");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemAttributeNotNullable);
+
+    // an unqualified attribute of USING refers to the side not padded by the join
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select id from book right join author using (id) where id > 1 order by id");
+    assert_ok!(check_select(&mut select, &table_set));
+    assert_eq!(format!("{}", select), "select (author.id) from book right join author using (id) \
+        where ((author.id) > Integer(1)) order by (author.id)");
+
+    // the attribute merged by a full join can only be selected
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select id from book full join author using (id)");
+    assert_ok!(check_select(&mut select, &table_set));
+    assert_eq!(format!("{}", select), "select id from book full join author using (id)");
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select id from book full join author using (id) where id > 1");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select count(id) from book full join author using (id)");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select * from book join author on name = author.name");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select * from book join author on book.id = author.id, book");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemDuplicateTable);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select * from book join author using (author_id)");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemInvalidAttribute);
+
+    let mut select = gen_parse_result!(SelectStatement::parse,
+        "select * from book join author using (name) join author using (id)");
+    assert_err!(check_select(&mut select, &table_set), CompileErrorType::SemDuplicateTable);
 }
 
 #[test]
@@ -5,6 +5,9 @@ use ::parser::update::{AssignExpr, UpdateStatement};
 use ::parser::insert::InsertStatement;
 use ::parser::delete::DeleteStatement;
 use ::parser::create_drop::{DropStatement, AttributeDeclaration, CreateStatement, AttrType};
+use ::parser::create_drop::{CreateIndexStatement, DropIndexStatement};
+use ::parser::vacuum::VacuumStatement;
+use ::parser::alter::AlterStatement;
 use super::super::utils::{test_by_display_str, test_by_list_to_str};
 
 #[test]
@@ -50,6 +53,28 @@ fn test_parse_relation() {
     );
 }
 
+#[test]
+fn test_parse_join() {
+    test_by_list_to_str(
+        "from tb1 join tb2 on tb1.a = tb2.b", 12,
+        Relation::parse,
+        "tb1 join tb2 on ((tb1.a) = (tb2.b))"
+    );
+    test_by_list_to_str(
+        "from tb1 inner join tb2 using (a, b), tb3", 13,
+        Relation::parse,
+        "tb1 join tb2 using (a, b), tb3"
+    );
+    test_by_list_to_str(
+        "from tb1 left outer join tb2 using (a) right join tb3 on a > 1 full join tb4 using (b)", 24,
+        Relation::parse,
+        "tb1 left join tb2 using (a) right join tb3 on (a > Integer(1)) full join tb4 using (b)"
+    );
+    assert_pattern!(Relation::parse(&mut gen_token!("from tb1 join tb2").iter()), Err(..));
+    assert_pattern!(Relation::parse(&mut gen_token!("from tb1 left tb2 using (a)").iter()), Err(..));
+    assert_pattern!(Relation::parse(&mut gen_token!("from tb1 join tb2 using ()").iter()), Err(..));
+}
+
 #[test]
 fn test_parse_group_by_having() {
     test_by_display_str(
@@ -94,6 +119,11 @@ fn test_parse_select_statement() {
         SelectStatement::parse,
         "select attr from huang group by attr order by doyoubi"
     );
+    test_by_display_str(
+        "select attr from huang order by doyoubi desc, huang.attr asc, score", 15,
+        SelectStatement::parse,
+        "select attr from huang order by doyoubi desc, (huang.attr), score"
+    );
 }
 
 #[test]
@@ -201,6 +231,16 @@ fn test_attr_type_parse() {
         AttrType::parse,
         "Char(10)"
     );
+    test_by_display_str(
+        "varchar(100)", 4,
+        AttrType::parse,
+        "Varchar(100)"
+    );
+    test_by_display_str(
+        "text", 1,
+        AttrType::parse,
+        "Text"
+    );
 }
 
 #[test]
@@ -221,5 +261,76 @@ fn test_create_statement_parse() {
         )", 16,
         CreateStatement::parse,
         "create table dept ((id Int null primary), (name Char(666) not null))"
+    );
+    test_by_display_str(
+        "create table dept (id int primary) with (compression = 'lz4')", 14,
+        CreateStatement::parse,
+        "create table dept ((id Int null primary)) with (compression = lz4)"
     )
 }
+
+#[test]
+fn test_create_index_statement_parse() {
+    test_by_display_str(
+        "create index name_index on dept (name)", 8,
+        CreateIndexStatement::parse,
+        "create index name_index on dept (name)"
+    );
+    test_by_display_str(
+        "create unique index dept_index on dept (name, id)", 11,
+        CreateIndexStatement::parse,
+        "create unique index dept_index on dept (name, id)"
+    );
+}
+
+#[test]
+fn test_drop_index_statement_parse() {
+    test_by_display_str(
+        "drop index name_index on dept", 5,
+        DropIndexStatement::parse,
+        "drop index name_index on dept"
+    );
+}
+
+#[test]
+fn test_vacuum_statement_parse() {
+    test_by_display_str(
+        "vacuum", 1,
+        VacuumStatement::parse,
+        "vacuum"
+    );
+    test_by_display_str(
+        "vacuum dept", 2,
+        VacuumStatement::parse,
+        "vacuum dept"
+    );
+}
+
+#[test]
+fn test_alter_statement_parse() {
+    test_by_display_str(
+        "alter table dept add column score float", 7,
+        AlterStatement::parse,
+        "alter table dept add column (score Float null)"
+    );
+    test_by_display_str(
+        "alter table dept add column name char(10) not null default \"none\"", 14,
+        AlterStatement::parse,
+        "alter table dept add column (name Char(10) not null) default String(none)"
+    );
+    test_by_display_str(
+        "alter table dept drop column score", 6,
+        AlterStatement::parse,
+        "alter table dept drop column score"
+    );
+    test_by_display_str(
+        "alter table dept rename column score to grade", 8,
+        AlterStatement::parse,
+        "alter table dept rename column score to grade"
+    );
+    test_by_display_str(
+        "alter table dept rename to department", 6,
+        AlterStatement::parse,
+        "alter table dept rename to department"
+    );
+}
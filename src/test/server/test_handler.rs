@@ -1,7 +1,10 @@
-use ::server::handler::{sql_handler, ResultHandler};
-use ::store::tuple::TupleData;
+use std::rc::Rc;
+use ::server::handler::{sql_handler, ResultHandler, process_table_command};
+use ::store::tuple::{Row, TupleValue};
 use ::store::table::{TableManager, AttrType};
+use ::store::vfs::{MemVfs, IoFault};
 use ::utils::config::Config;
+use ::test::utils::open_manager;
 
 
 #[derive(Debug)]
@@ -20,8 +23,8 @@ impl ResultHandler for MockHandler {
     fn handle_error(&mut self, err_msg : String) {
         self.helper_data = err_msg
     }
-    fn handle_tuple_data(&mut self, tuple_data : Option<TupleData>) {
-        match tuple_data {
+    fn handle_tuple_data(&mut self, row : Option<Row>) {
+        match row {
             Some(..) => self.helper_data.push('1'),
             None => self.helper_data.push('0'),
         }
@@ -43,3 +46,90 @@ fn test_handler() {
     sql_handler(&sql, &mut handler, &manager);
     assert_eq!(handler.helper_data, "");
 }
+
+#[test]
+fn test_rollback_failed_statement() {
+    // the rows changed before the statement fails are not committed with the next statement
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 16
+        table_meta_dir = "test_file/test_rollback_failed_statement/table_meta/"
+        table_file_dir = "test_file/test_rollback_failed_statement/table_file/""#.to_string());
+    let vfs = Rc::new(MemVfs::new());
+    let manager = open_manager(&config, &vfs);
+    let mut handler = MockHandler::new();
+    for sql in vec!["create table a(id int not null primary, u int not null)",
+            "insert a values(1, 1)", "insert a values(2, 2)", "insert a values(3, 3)",
+            "create unique index ux on a (u)"] {
+        sql_handler(&sql.to_string(), &mut handler, &manager);
+        assert_eq!(handler.helper_data, "");
+    }
+    sql_handler(&"update a set u = 10 where id < 3".to_string(), &mut handler, &manager);
+    assert!(handler.helper_data.starts_with("UniqueKeyExist"));
+    handler.helper_data.clear();
+    sql_handler(&"insert a values(4, 4)".to_string(), &mut handler, &manager);
+    assert_eq!(handler.helper_data, "");
+
+    let manager = open_manager(&config, &vfs);
+    let mut query = gen_plan_helper!("select * from a", &manager);
+    query.open();
+    let mut u_list = Vec::new();
+    while let Some(row) = query.get_next() {
+        u_list.push(extract!(&row[1], &TupleValue::Int(n), n));
+    }
+    assert_eq!(u_list, vec![1, 2, 3, 4]);
+    let mut query = gen_plan_helper!("select * from a where u = 10", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), None);
+}
+
+#[test]
+fn test_failed_rollback() {
+    // the error of the rollback is reported and the later statements are refused
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 16
+        table_meta_dir = "test_file/test_failed_rollback/table_meta/"
+        table_file_dir = "test_file/test_failed_rollback/table_file/""#.to_string());
+    let vfs = Rc::new(MemVfs::new());
+    let manager = open_manager(&config, &vfs);
+    let mut handler = MockHandler::new();
+    for sql in vec!["create table a(id int not null primary, u int not null)",
+            "insert a values(1, 1)", "insert a values(2, 2)", "create unique index ux on a (u)"] {
+        sql_handler(&sql.to_string(), &mut handler, &manager);
+        assert_eq!(handler.helper_data, "");
+    }
+    vfs.inject_fault(&"test_file/test_failed_rollback/table_file/a.table".to_string(), IoFault::WriteError);
+    sql_handler(&"update a set u = 10".to_string(), &mut handler, &manager);
+    assert!(handler.helper_data.starts_with("UniqueKeyExist"));
+    assert!(handler.helper_data.contains("can't roll back the statement"));
+    assert!(manager.borrow_mut().checkpoint().is_err());
+    handler.helper_data.clear();
+    sql_handler(&"insert a values(3, 3)".to_string(), &mut handler, &manager);
+    assert!(handler.helper_data.starts_with("IoError: can't roll back the statement"));
+    drop(manager);
+
+    let manager = open_manager(&config, &vfs);
+    let mut query = gen_plan_helper!("select * from a", &manager);
+    query.open();
+    let mut u_list = Vec::new();
+    while let Some(row) = query.get_next() {
+        u_list.push(extract!(&row[1], &TupleValue::Int(n), n));
+    }
+    assert_eq!(u_list, vec![1, 2]);
+}
+
+#[test]
+fn test_resize_pool_command() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_resize_pool_command/"
+        table_file_dir = "test_file/table_file/test_resize_pool_command/""#.to_string());
+    let manager = TableManager::make_ref(&config);
+    let out = process_table_command(&"resize pool 8".to_string(), &manager).unwrap();
+    assert_eq!(out, "pool size: 8, loaded: 0, pinned: 0, dirty: 0");
+    assert_eq!(manager.borrow().file_manager.page_pool.get_capacity(), 8);
+    let out = process_table_command(&"resize pool eight".to_string(), &manager).unwrap();
+    assert_eq!(out, "invalid pool size: eight");
+    assert!(process_table_command(&"resize pool 0".to_string(), &manager).unwrap().contains("pinned"));
+    assert_eq!(process_table_command(&"show pool".to_string(), &manager).unwrap(),
+        "pool size: 8, loaded: 0, pinned: 0, dirty: 0");
+}
@@ -10,3 +10,30 @@ mod test_table;
 #[allow(dead_code)]
 #[allow(unused_imports)]  // lint bug
 mod test_file;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_btree;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_wal;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_fsm;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_header;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_cache;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_vfs;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_compress;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_crypto;
+#[allow(dead_code)]
+#[allow(unused_imports)]  // lint bug
+mod test_checksum;
@@ -0,0 +1,238 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::remove_file;
+use ::utils::config::Config;
+use ::store::btree::{self, Node, NodeStore, IndexEntry, KeyRange};
+use ::store::file::TableFileManager;
+use ::store::table::{TableManager, Table, Attr, AttrType};
+use ::store::tuple::TupleValue;
+use ::store::index::PRIMARY_INDEX;
+use ::store::error::StorageResult;
+use ::parser::common::{ValueExpr, ValueType};
+
+
+struct MemStore {
+    nodes : Vec<Node>,
+    root : Option<usize>,
+    max_entry_num : usize,
+}
+
+impl NodeStore for MemStore {
+    fn get_root(&self) -> Option<usize> { self.root }
+    fn set_root(&mut self, root : usize) { self.root = Some(root); }
+    fn read_node(&mut self, page_index : usize) -> StorageResult<Node> { Ok(self.nodes[page_index].clone()) }
+    fn write_node(&mut self, page_index : usize, node : &Node) -> StorageResult<()> {
+        self.nodes[page_index] = node.clone();
+        Ok(())
+    }
+    fn alloc_node(&mut self) -> StorageResult<usize> {
+        self.nodes.push(Node::new_leaf());
+        Ok(self.nodes.len() - 1)
+    }
+    fn max_entry_num(&self) -> usize { self.max_entry_num }
+}
+
+fn gen_entry(key : i32, position : usize) -> IndexEntry {
+    IndexEntry{ key : vec![TupleValue::Int(key)], position : position }
+}
+
+#[test]
+fn test_btree_insert_and_lookup() {
+    let mut store = MemStore{ nodes : Vec::new(), root : None, max_entry_num : 3 };
+    assert_eq!(btree::lookup(&mut store, &vec![TupleValue::Int(1)]).unwrap(), Vec::<usize>::new());
+    // insert in an order which is neither ascending nor descending
+    for i in 0..100 {
+        let key = (i * 37) % 100;
+        btree::insert(&mut store, gen_entry(key, key as usize + 1000)).unwrap();
+    }
+    assert!(store.nodes.len() > 1);
+    assert!(!store.nodes[store.root.unwrap()].is_leaf);
+    for key in 0..100 {
+        assert_eq!(btree::lookup(&mut store, &vec![TupleValue::Int(key)]).unwrap(), vec![key as usize + 1000]);
+    }
+    assert_eq!(btree::lookup(&mut store, &vec![TupleValue::Int(100)]).unwrap(), Vec::<usize>::new());
+    assert_eq!(btree::lookup(&mut store, &vec![TupleValue::Int(-1)]).unwrap(), Vec::<usize>::new());
+}
+
+#[test]
+fn test_btree_duplicate_key() {
+    let mut store = MemStore{ nodes : Vec::new(), root : None, max_entry_num : 3 };
+    for position in 0..20 {
+        btree::insert(&mut store, gen_entry(position as i32 % 2, position)).unwrap();
+    }
+    let positions = btree::lookup(&mut store, &vec![TupleValue::Int(1)]).unwrap();
+    assert_eq!(positions, (0..20).filter(|p| p % 2 == 1).collect::<Vec<usize>>());
+}
+
+#[test]
+fn test_btree_delete() {
+    let mut store = MemStore{ nodes : Vec::new(), root : None, max_entry_num : 4 };
+    assert!(!btree::delete(&mut store, &gen_entry(1, 1)).unwrap());
+    for key in 0..50 {
+        btree::insert(&mut store, gen_entry(key, key as usize)).unwrap();
+    }
+    for key in (0..50).filter(|k| k % 3 == 0) {
+        assert!(btree::delete(&mut store, &gen_entry(key, key as usize)).unwrap());
+    }
+    assert!(!btree::delete(&mut store, &gen_entry(3, 3)).unwrap());
+    assert!(!btree::delete(&mut store, &gen_entry(4, 5)).unwrap());  // position not match
+    for key in 0..50 {
+        let result = btree::lookup(&mut store, &vec![TupleValue::Int(key)]).unwrap();
+        if key % 3 == 0 {
+            assert!(result.is_empty());
+        } else {
+            assert_eq!(result, vec![key as usize]);
+        }
+    }
+}
+
+#[test]
+fn test_btree_range_scan() {
+    let mut store = MemStore{ nodes : Vec::new(), root : None, max_entry_num : 3 };
+    for i in 0..50 {
+        let key = (i * 7) % 50;
+        btree::insert(&mut store, IndexEntry{
+            key : vec![TupleValue::Int(key % 5), TupleValue::Int(key)],
+            position : key as usize,
+        }).unwrap();
+    }
+    let mut range = KeyRange::equal(vec![]);
+    range.lower = Some((TupleValue::Int(3), true));
+    let expected : Vec<usize> = (0..50).filter(|k| k % 5 == 3).chain((0..50).filter(|k| k % 5 == 4)).collect();
+    assert_eq!(btree::scan(&mut store, &range).unwrap(), expected);
+    range.lower = Some((TupleValue::Int(3), false));
+    range.upper = Some((TupleValue::Int(4), true));
+    assert_eq!(btree::scan(&mut store, &range).unwrap(), (0..50).filter(|k| k % 5 == 4).collect::<Vec<usize>>());
+    range.upper = Some((TupleValue::Int(4), false));
+    assert!(btree::scan(&mut store, &range).unwrap().is_empty());
+
+    // range on the second attribute after an equal prefix
+    let mut range = KeyRange::equal(vec![TupleValue::Int(2)]);
+    range.lower = Some((TupleValue::Int(12), false));
+    range.upper = Some((TupleValue::Int(37), true));
+    assert_eq!(btree::scan(&mut store, &range).unwrap(), vec![17, 22, 27, 32, 37]);
+    range.lower = None;
+    assert_eq!(btree::scan(&mut store, &range).unwrap(), vec![2, 7, 12, 17, 22, 27, 32, 37]);
+}
+
+#[test]
+fn test_node_page_format() {
+    let key_desc = vec![AttrType::Int, AttrType::Char{ len : 5 }];
+    let node = Node{
+        is_leaf : false,
+        entries : vec![
+            IndexEntry{ key : vec![TupleValue::Int(7), TupleValue::Char("abc".to_string())], position : 3 },
+            IndexEntry{ key : vec![TupleValue::Int(9), TupleValue::Char("de".to_string())], position : 4 },
+            IndexEntry{ key : vec![TupleValue::Int(9), TupleValue::Null], position : 10 },
+        ],
+        children : vec![5, 6, 8, 11],
+        next : None,
+    };
+    let data = ::utils::libwrapper::alloc_page();
+    node.write_to_page(data, &key_desc);
+    let read_node = Node::read_from_page(data, &key_desc);
+    unsafe{ ::libc::free(data) };
+    assert!(!read_node.is_leaf);
+    assert_eq!(read_node.children, vec![5, 6, 8, 11]);
+    assert_pattern!(read_node.next, None);
+    assert_eq!(read_node.entries.len(), 3);
+    assert_pattern!(read_node.entries[1].key[0].clone(), TupleValue::Int(9));
+    assert_eq!(extract!(read_node.entries[1].key[1], TupleValue::Char(ref s), s.clone()), "de");
+    assert_eq!(read_node.entries[1].position, 4);
+    assert_pattern!(read_node.entries[2].key[0].clone(), TupleValue::Int(9));
+    assert_pattern!(read_node.entries[2].key[1].clone(), TupleValue::Null);
+}
+
+pub fn gen_index_test_table(table_name : &str) -> Table {
+    Table{
+        name : table_name.to_string(),
+        attr_list : vec![
+            Attr{
+                name : "content".to_string(),
+                attr_type : AttrType::Char{ len : 8 },
+                primary : false,
+                nullable : false,
+            },
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+            },
+        ],
+        index_list : vec![],
+        compression : None,
+    }
+}
+
+pub fn gen_value_list(id : i32) -> Vec<ValueExpr> {
+    vec![
+        ValueExpr{ value : format!("c{}", id), value_type : ValueType::String },
+        ValueExpr{ value : id.to_string(), value_type : ValueType::Integer },
+    ]
+}
+
+#[test]
+fn test_file_manager_index() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 3
+        table_meta_dir = "test_file/table_meta/test_file_manager_index/"
+        table_file_dir = "test_file/table_file/test_file_manager_index/""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let table_name = "test_index_message".to_string();
+    let table = Rc::new(RefCell::new(gen_index_test_table(&table_name)));
+    manager.create_file(table_name.clone(), table);
+    // enough tuples to split index pages and table pages
+    for id in 0..2000 {
+        manager.insert(&table_name, &gen_value_list(id * 7 % 2000)).unwrap();
+    }
+    for id in (0..2000).filter(|id| id % 100 == 0) {
+        let positions = manager.index_lookup(&table_name, PRIMARY_INDEX, &vec![TupleValue::Int(id)]).unwrap();
+        assert_eq!(positions.len(), 1);
+        assert_pattern!(manager.get_tuple_data(&table_name, positions[0]), Ok(Some(..)));
+        assert_eq!(extract!(manager.get_tuple_value(&table_name, positions[0], 1), TupleValue::Int(n), n), id);
+    }
+    assert!(manager.get_index(&table_name, PRIMARY_INDEX).borrow().page_sum > 1);
+
+    let positions = manager.index_lookup(&table_name, PRIMARY_INDEX, &vec![TupleValue::Int(1234)]).unwrap();
+    let record_id = manager.get_file(&table_name).borrow().get_record_id(positions[0]);
+    manager.delete(&table_name, &record_id).unwrap();
+    assert!(manager.index_lookup(&table_name, PRIMARY_INDEX, &vec![TupleValue::Int(1234)]).unwrap().is_empty());
+    assert_eq!(manager.index_lookup(&table_name, PRIMARY_INDEX, &vec![TupleValue::Int(1235)]).unwrap().len(), 1);
+}
+
+#[test]
+fn test_index_persistence() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_index_persistence/"
+        table_file_dir = "test_file/table_file/test_index_persistence/""#.to_string());
+    let table_name = "test_index_persistence_message".to_string();
+    {
+        let mut manager = TableManager::new(&config);
+        manager.add_table(gen_index_test_table(&table_name));
+        for id in 0..1000 {
+            manager.insert(&table_name, &gen_value_list(id)).unwrap();
+        }
+        manager.save_to_file().unwrap();
+    }
+    {
+        let mut manager = TableManager::from_json_file(&config);
+        for id in 0..1000 {
+            let positions = manager.file_manager.index_lookup(
+                &table_name, PRIMARY_INDEX, &vec![TupleValue::Int(id)]).unwrap();
+            assert_eq!(positions, vec![id as usize]);
+        }
+    }
+    // index will be rebuilt from table file if the index file not exist
+    assert!(remove_file(
+        "test_file/table_file/test_index_persistence/test_index_persistence_message.primary.index").is_ok());
+    {
+        let mut manager = TableManager::from_json_file(&config);
+        for id in 0..1000 {
+            let positions = manager.file_manager.index_lookup(
+                &table_name, PRIMARY_INDEX, &vec![TupleValue::Int(id)]).unwrap();
+            assert_eq!(positions, vec![id as usize]);
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::utils::config::Config;
+use ::store::lru::CacheValue;
+use ::store::cache::{ReplaceCache, new_cache};
+use ::store::clock::ClockCache;
+use ::store::lru_k::LruKCache;
+use ::store::two_q::TwoQueueCache;
+use ::store::file::TableFileManager;
+use ::store::tuple::TupleValue;
+use ::store::index::PRIMARY_INDEX;
+use super::test_btree::{gen_index_test_table, gen_value_list};
+
+
+const POLICY_LIST : [&'static str; 4] = ["lru", "clock", "lru-k", "2q"];
+
+#[derive(Debug, Clone)]
+struct MockValue {
+    pub key : u64,
+    pub pinned : bool,
+}
+
+impl CacheValue for MockValue {
+    type KeyType = u64;
+    fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+}
+
+impl MockValue {
+    fn new(k : u64) -> Self {
+        MockValue{
+            key : k,
+            pinned : false,
+        }
+    }
+    fn new_pinned(k : u64) -> Self {
+        MockValue{
+            key : k,
+            pinned : true,
+        }
+    }
+}
+
+fn evict<C : ReplaceCache<MockValue>>(c : &mut C) -> u64 {
+    let key = extract!(c.prepare_page(), Some(v), v.key);
+    c.remove_tail();
+    key
+}
+
+#[test]
+fn test_policy() {
+    for policy in POLICY_LIST.iter() {
+        let mut c = new_cache::<MockValue>(policy, 3);
+        assert_eq!(c.capacity(), 3);
+        assert_pattern!(c.prepare_page(), None);
+        c.put(&1, MockValue::new(1));
+        c.put(&2, MockValue::new(2));
+        assert_pattern!(c.prepare_page(), None);
+        c.put(&3, MockValue::new(3));
+        assert_eq!(c.get_load(), 3);
+        assert_eq!(extract!(c.get(&2), Some(v), v.key), 2);
+        assert_pattern!(c.get(&4), None);
+
+        let key = extract!(c.prepare_page(), Some(v), v.key);
+        c.remove_tail();
+        assert_eq!(c.get_load(), 2);
+        assert_pattern!(c.get(&key), None);
+        c.put(&4, MockValue::new(4));
+        assert_eq!(extract!(c.get(&4), Some(v), v.key), 4);
+
+        assert_eq!(extract!(c.remove(&4), Some(v), v.key), 4);
+        assert_pattern!(c.remove(&4), None);
+        assert_eq!(c.get_load(), 2);
+        assert_pattern!(c.prepare_page(), None);
+    }
+}
+
+#[test]
+fn test_policy_pinned() {
+    for policy in POLICY_LIST.iter() {
+        let mut c = new_cache::<MockValue>(policy, 3);
+        c.put(&1, MockValue::new_pinned(1));
+        c.put(&2, MockValue::new_pinned(2));
+        c.put(&3, MockValue::new(3));
+        assert_eq!(extract!(c.prepare_page(), Some(v), v.key), 3);
+        c.remove_tail();
+        c.put(&4, MockValue::new(4));
+        assert_pattern!(c.get(&4), Some(..));
+        assert_pattern!(c.get(&1), Some(..));
+        assert_pattern!(c.get(&2), Some(..));
+        assert_pattern!(c.get(&3), None);
+    }
+}
+
+#[test]
+#[should_panic]
+fn test_all_pinned() {
+    let mut c = ClockCache::new(2);
+    c.put(&1, MockValue::new_pinned(1));
+    c.put(&2, MockValue::new_pinned(2));
+    c.prepare_page();  // panic here
+}
+
+#[test]
+fn test_clock() {
+    let mut c = ClockCache::new(3);
+    c.put(&1, MockValue::new(1));
+    c.put(&2, MockValue::new(2));
+    c.put(&3, MockValue::new(3));
+    // all the values are referenced, the first round only clears the bits
+    assert_eq!(evict(&mut c), 1);
+    c.put(&4, MockValue::new(4));
+    // 2 gets a second chance
+    c.get(&2);
+    assert_eq!(evict(&mut c), 3);
+}
+
+#[test]
+fn test_lru_k() {
+    let mut c = LruKCache::new(3, 2);
+    c.put(&1, MockValue::new(1));
+    c.get(&1);  // correlated to the put
+    c.put(&2, MockValue::new(2));
+    c.get(&1);
+    c.put(&3, MockValue::new(3));
+    c.get(&2);
+    // 3 has only one reference
+    assert_eq!(evict(&mut c), 3);
+    // values scanned once go first
+    for k in 4..10 {
+        c.put(&k, MockValue::new(k));
+        assert_eq!(evict(&mut c), k);
+    }
+    // the 2nd most recent reference of 1 is older than 2
+    c.put(&10, MockValue::new(10));
+    c.get(&10);
+    c.get(&2);
+    c.get(&10);
+    assert_eq!(evict(&mut c), 1);
+}
+
+#[test]
+fn test_two_queue() {
+    let mut c = TwoQueueCache::new(4);
+    for k in 1..5 {
+        c.put(&k, MockValue::new(k));
+    }
+    assert_eq!(evict(&mut c), 1);
+    // 1 is remembered and goes to am
+    c.put(&1, MockValue::new(1));
+    for k in 5..20 {
+        assert!(evict(&mut c) != 1);
+        c.put(&k, MockValue::new(k));
+        c.get(&k);
+    }
+    assert_pattern!(c.get(&1), Some(..));
+
+    // lru can't keep 1 during the scan
+    let mut c = new_cache::<MockValue>("lru", 4);
+    for k in 1..5 {
+        c.put(&k, MockValue::new(k));
+    }
+    c.get(&1);
+    for k in 5..20 {
+        c.prepare_page();
+        c.remove_tail();
+        c.put(&k, MockValue::new(k));
+        c.get(&k);
+    }
+    assert_pattern!(c.get(&1), None);
+}
+
+#[test]
+fn test_page_pool_policy() {
+    for policy in POLICY_LIST.iter() {
+        let config = Config::new(&format!(r#"
+            max_memory_pool_page_num = 3
+            buffer_policy = "{}"
+            table_meta_dir = "test_file/table_meta/test_page_pool_policy/"
+            table_file_dir = "test_file/table_file/test_page_pool_policy/{}/""#, policy, policy));
+        let mut manager = TableFileManager::new(&config);
+        assert_eq!(manager.page_pool.get_capacity(), 3);
+        let table_name = "test_page_pool_policy_message".to_string();
+        let table = Rc::new(RefCell::new(gen_index_test_table(&table_name)));
+        manager.create_file(table_name.clone(), table);
+        manager.clear_files(&table_name);
+        for id in 0..500 {
+            manager.insert(&table_name, &gen_value_list(id * 7 % 500)).unwrap();
+        }
+        for id in (0..500).filter(|id| id % 10 == 0) {
+            let positions = manager.index_lookup(&table_name, PRIMARY_INDEX, &vec![TupleValue::Int(id)]).unwrap();
+            assert_eq!(positions.len(), 1);
+            assert_eq!(extract!(manager.get_tuple_value(&table_name, positions[0], 1), TupleValue::Int(n), n), id);
+        }
+        assert_eq!(manager.get_unpinned_num(), 3);
+    }
+}
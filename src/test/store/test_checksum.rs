@@ -0,0 +1,11 @@
+use ::store::checksum::crc32;
+
+
+#[test]
+fn test_crc32() {
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xcbf43926);
+    assert_eq!(crc32(b"The quick brown fox jumps over the lazy dog"), 0x414fa339);
+    // a flipped bit changes the checksum
+    assert!(crc32(b"123456788") != 0xcbf43926);
+}
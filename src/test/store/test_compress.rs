@@ -0,0 +1,90 @@
+use std::rc::Rc;
+use ::utils::config::Config;
+use ::utils::libwrapper::get_page_size;
+use ::store::compress::{PageCodec, Lz4Codec};
+use ::store::wal::LogRecord;
+use ::store::vfs::{Vfs, MemVfs};
+use ::parser::common::{ValueExpr, ValueType};
+use ::test::exec::test_query;
+use ::test::utils::open_manager;
+
+
+#[test]
+fn test_lz4_codec() {
+    let codec = Lz4Codec;
+    let mut seed : u32 = 233;
+    let random : Vec<u8> = (0..4096).map(|_| {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+        (seed >> 16) as u8
+    }).collect();
+    let repeated : Vec<u8> = (0..4096).map(|i| if i % 100 < 16 { b"zero padded char"[i % 100] } else { 0 }).collect();
+    for page in [vec![0; 4096], random, repeated, vec![1, 2, 3], vec![]].iter() {
+        let data = codec.compress(page);
+        let mut decompressed = vec![7; page.len()];
+        assert!(codec.decompress(&data, &mut decompressed).is_ok());
+        assert_eq!(decompressed, *page);
+    }
+    let data = codec.compress(&vec![0; 4096]);
+    assert!(data.len() < 64);
+    let mut page = vec![0; 4096];
+    assert!(codec.decompress(&data[..data.len() - 1], &mut page).is_err());
+    let mut page = vec![0; 4095];
+    assert!(codec.decompress(&data, &mut page).is_err());
+}
+
+#[test]
+fn test_compressed_table() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 16
+        table_meta_dir = "test_file/test_compressed_table/table_meta/"
+        table_file_dir = "test_file/test_compressed_table/table_file/""#.to_string());
+    let table_name = "test_compressed_table_message".to_string();
+    let tuple_sum = 600;
+    let vfs = Rc::new(MemVfs::new());
+    {
+        let manager = open_manager(&config, &vfs);
+        let mut table = test_query::gen_test_table(&table_name);
+        table.compression = Some("lz4".to_string());
+        manager.borrow_mut().add_table(table);
+        for i in 0..tuple_sum {
+            let value_list = vec![
+                ValueExpr{ value : i.to_string(), value_type : ValueType::Integer },
+                ValueExpr{ value : "1.0".to_string(), value_type : ValueType::Float },
+                ValueExpr{ value : "a".to_string(), value_type : ValueType::String },
+            ];
+            manager.borrow_mut().insert(&table_name, &value_list).unwrap();
+        }
+        manager.borrow_mut().commit().unwrap();
+        manager.borrow_mut().checkpoint().unwrap();
+        // the page map is only logged again after it's changed
+        manager.borrow_mut().commit().unwrap();
+        let commit_size = LogRecord::Commit.encode().len() as u64;
+        assert_eq!(manager.borrow().file_manager.get_wal_size(), commit_size);
+        let file = manager.borrow_mut().file_manager.get_file(&table_name);
+        let page_sum = file.borrow().page_sum;
+        assert!(page_sum > 3);
+        let len = vfs.open(&file.borrow().saved_name).unwrap().get_len() as usize;
+        assert!(len < get_page_size() * (page_sum + 1) / 2);
+
+        // the pages grow and are moved
+        let mut update = gen_plan_helper!(
+            "update test_compressed_table_message set content = \"not compressed\"", &manager);
+        update.open();
+        while let Some(..) = update.get_next() {}
+        assert_pattern!(update.get_error(), None);
+        manager.borrow_mut().commit().unwrap();
+        // exit without flushing pages and catalog
+    }
+    let manager = open_manager(&config, &vfs);
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert!(file.borrow().page_map.is_some());
+    let mut query = gen_plan_helper!(
+        "select * from test_compressed_table_message where content = \"not compressed\"", &manager);
+    query.open();
+    let mut count = 0;
+    while let Some(..) = query.get_next() {
+        count += 1;
+    }
+    assert_pattern!(query.get_error(), None);
+    assert_eq!(count, tuple_sum);
+}
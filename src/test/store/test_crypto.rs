@@ -0,0 +1,182 @@
+use std::rc::Rc;
+use std::fs::File;
+use std::io::Write;
+use ::utils::config::Config;
+use ::utils::file::ensure_dir_exist;
+use ::store::table::IndexDesc;
+use ::store::tuple::TupleValue;
+use ::store::crypto::{Cipher, poly1305, chacha20_poly1305_seal, chacha20_poly1305_open, load_key};
+use ::store::vfs::{Vfs, MemVfs};
+use ::exec::spill::SpillFile;
+use ::exec::error::ExecErrorType;
+use ::test::exec::test_query;
+use ::test::utils::open_manager;
+
+
+fn from_hex(s : &str) -> Vec<u8> {
+    (0..s.len() / 2).map(|i| u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).unwrap()).collect()
+}
+
+#[test]
+fn test_chacha20_poly1305() {
+    // test vectors of RFC 8439
+    let key = from_hex("85d6be7857556d337f4452fe42d506a80103808afb0db2fd4abff6af4149f51b");
+    assert_eq!(poly1305(&key, b"Cryptographic Forum Research Group").to_vec(),
+        from_hex("a8061dc1305136c6c22b8baf0c0127a9"));
+
+    let mut key = [0; 32];
+    for i in 0..32 {
+        key[i] = 0x80 + i as u8;
+    }
+    let nonce = [7, 0, 0, 0, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47];
+    let aad = from_hex("50515253c0c1c2c3c4c5c6c7");
+    let plaintext = b"Ladies and Gentlemen of the class of '99: \
+        If I could offer you only one tip for the future, sunscreen would be it.";
+    let data = chacha20_poly1305_seal(&key, &nonce, &aad, plaintext);
+    assert_eq!(data.len(), plaintext.len() + 16);
+    assert_eq!(&data[..16], &from_hex("d31a8d34648e60db7b86afbc53ef7ec2")[..]);
+    assert_eq!(&data[data.len() - 16..], &from_hex("1ae10b594f09e26a7e902ecbd0600691")[..]);
+    assert_eq!(chacha20_poly1305_open(&key, &nonce, &aad, &data).unwrap(), plaintext.to_vec());
+    assert!(chacha20_poly1305_open(&key, &nonce, &aad[1..], &data).is_err());
+    let mut tampered = data.clone();
+    tampered[0] ^= 1;
+    assert!(chacha20_poly1305_open(&key, &nonce, &aad, &tampered).is_err());
+
+    // fresh nonce for every seal
+    let cipher = Cipher::new(key);
+    let (a, b) = (cipher.seal(&aad, plaintext), cipher.seal(&aad, plaintext));
+    assert!(a != b);
+    assert_eq!(cipher.open(&aad, &a).unwrap(), plaintext.to_vec());
+    assert!(Cipher::new([0; 32]).open(&aad, &a).is_err());
+}
+
+fn gen_config(name : &str, key : &str) -> Config {
+    let dir = format!("test_file/{}/", name);
+    ensure_dir_exist(&dir);
+    let key_file = format!("{}{}.key", dir, key);
+    File::create(&key_file).unwrap().write_all(key.repeat(64).as_bytes()).unwrap();
+    assert!(load_key(&key_file).is_ok());
+    Config::new(&format!(r#"
+        max_memory_pool_page_num = 4
+        encryption_key_file = "{0}"
+        table_meta_dir = "test_file/{1}/table_meta/"
+        table_file_dir = "test_file/{1}/table_file/""#, key_file, name))
+}
+
+fn contains(data : &[u8], s : &str) -> bool {
+    data.windows(s.len()).any(|w| w == s.as_bytes())
+}
+
+#[test]
+fn test_encrypted_database() {
+    let config = gen_config("test_encrypted_database", "a");
+    let table_name = "test_encrypted_database_message".to_string();
+    let vfs = Rc::new(MemVfs::new());
+    {
+        let manager = open_manager(&config, &vfs);
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        let desc = IndexDesc{ name : "content_index".to_string(), attr_list : vec!["content".to_string()],
+            unique : false };
+        assert!(manager.borrow_mut().create_index(&table_name, desc).unwrap());
+        test_query::insert_data(&table_name, &manager);
+        manager.borrow_mut().commit().unwrap();
+        let file = manager.borrow_mut().file_manager.get_file(&table_name);
+        let data = vfs.open(&file.borrow().saved_name).unwrap().read_all().unwrap();
+        assert!(!contains(&data, "qweryu"));
+        // the log has the same pages, only the file names are plain
+        let data = vfs.open(&"test_file/test_encrypted_database/table_meta/table_meta.wal".to_string())
+            .unwrap().read_all().unwrap();
+        assert!(data.len() > 0 && !contains(&data, "qweryu"));
+        manager.borrow_mut().checkpoint().unwrap();
+        let data = vfs.open(&"test_file/test_encrypted_database/table_meta/table_meta.json".to_string())
+            .unwrap().read_all().unwrap();
+        assert!(data.len() > 0 && !contains(&data, &table_name));
+        let index = manager.borrow().file_manager.get_index(&table_name, "content_index");
+        let data = vfs.open(&index.borrow().saved_name).unwrap().read_all().unwrap();
+        assert!(data.len() > 0 && !contains(&data, "qweryu"));
+    }
+    let manager = open_manager(&config, &vfs);
+    let mut query = gen_plan_helper!(
+        "select * from test_encrypted_database_message where content = \"qweryu\"", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), Some(..));
+    assert_pattern!(query.get_next(), None);
+    assert_pattern!(query.get_error(), None);
+    drop(query);
+    // the index pages are sealed for the name of the renamed files
+    let mut alter = gen_plan_helper!(
+        "alter table test_encrypted_database_message rename to test_encrypted_database_note", &manager);
+    alter.open();
+    while let Some(..) = alter.get_next() {}
+    assert_pattern!(alter.get_error(), None);
+    drop(alter);
+    let manager = open_manager(&config, &vfs);
+    let mut query = gen_plan_helper!(
+        "select * from test_encrypted_database_note where content = \"qweryu\"", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), Some(..));
+    assert_pattern!(query.get_error(), None);
+    drop(query);
+
+    // the rows of the executors spilled to temporary files
+    let mut spill = SpillFile::create(&manager, "sort").unwrap();
+    let row = vec![TupleValue::Int(1), TupleValue::Char("qweryu".to_string())];
+    spill.write_row(&row).unwrap();
+    spill.finish_write().unwrap();
+    let path = "test_file/test_encrypted_database/table_file/sort.1.tmp".to_string();
+    let data = vfs.open(&path).unwrap().read_all().unwrap();
+    assert!(data.len() > 0 && !contains(&data, "qweryu"));
+    let read = extract!(spill.read_row().unwrap(), Some(row), row);
+    assert_eq!(extract!(&read[1], &TupleValue::Char(ref s), s.clone()), "qweryu");
+    assert_pattern!(spill.read_row().unwrap(), None);
+}
+
+#[test]
+fn test_page_of_other_table() {
+    // a page copied from another table of the same layout fails to open
+    let config = gen_config("test_page_of_other_table", "a");
+    let vfs = Rc::new(MemVfs::new());
+    let name_list = vec!["test_page_of_other_table_a".to_string(), "test_page_of_other_table_b".to_string()];
+    {
+        let manager = open_manager(&config, &vfs);
+        for table_name in name_list.iter() {
+            manager.borrow_mut().add_table(test_query::gen_test_table(table_name));
+            test_query::insert_data(table_name, &manager);
+        }
+        manager.borrow_mut().commit().unwrap();
+        manager.borrow_mut().checkpoint().unwrap();
+        let file_list : Vec<_> = name_list.iter()
+            .map(|table_name| manager.borrow_mut().file_manager.get_file(table_name)).collect();
+        let (a_offset, a_len) = file_list[0].borrow().get_page_location(0);
+        let (b_offset, b_len) = file_list[1].borrow().get_page_location(0);
+        assert_eq!(a_len, b_len);
+        let mut page = vec![0; a_len];
+        let mut a_file = vfs.open(&file_list[0].borrow().saved_name).unwrap();
+        assert_eq!(a_file.read_at(a_offset, &mut page).unwrap(), a_len);
+        vfs.open(&file_list[1].borrow().saved_name).unwrap().write_at(b_offset, &page).unwrap();
+    }
+    let manager = open_manager(&config, &vfs);
+    let mut query = gen_plan_helper!("select * from test_page_of_other_table_a", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), Some(..));
+    drop(query);
+    let mut query = gen_plan_helper!("select * from test_page_of_other_table_b", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), None);
+    let err = query.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::PageCorrupted);
+    assert!(err.error_msg.starts_with("can't decrypt page 0"));
+}
+
+#[test]
+#[should_panic(expected = "wrong encryption key")]
+fn test_wrong_key() {
+    let table_name = "test_wrong_key_message".to_string();
+    let vfs = Rc::new(MemVfs::new());
+    {
+        let manager = open_manager(&gen_config("test_wrong_key", "a"), &vfs);
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        manager.borrow_mut().commit().unwrap();
+    }
+    open_manager(&gen_config("test_wrong_key", "b"), &vfs);
+}
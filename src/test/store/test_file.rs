@@ -1,15 +1,22 @@
 use std::ptr::{read, write};
+use std::collections::HashMap;
 use std::rc::Rc;
 use std::cell::RefCell;
 use std::sync::{Arc, RwLock};
+use std::fs::OpenOptions;
+use std::io::{Read, Write, Seek, SeekFrom};
 use libc::malloc;
 use ::utils::pointer::{read_string, write_string, pointer_offset};
 use ::utils::config::Config;
-use ::store::file::{TableFile, FilePage, BitMap, PageHeader, TableFileManager};
+use ::utils::libwrapper::get_page_size;
+use ::store::file::{TableFile, FilePage, PageHeader, TableFileManager, RecordId};
+use ::store::fsm::EMPTY_PAGE;
 use ::store::buffer::{DataPtr, Page};
 use ::store::table::{Table, Attr, AttrType, TableManager};
 use ::parser::common::{ValueExpr, ValueType};
-use ::store::tuple::TupleValue;
+use ::store::tuple::{TupleValue, gen_value_list, encode_tuple, read_var_string};
+use ::store::error::StorageErrorType;
+use ::exec::error::ExecErrorType;
 use ::test::exec::test_query;
 use ::test::exec::test_query::gen_test_manager;
 
@@ -19,81 +26,115 @@ fn test_page_header() {
     {
         let data;
         unsafe{
-            data = malloc(8);
-            write::<u32>(data as *mut u32, 233);
-            write::<u32>((data as *mut u32).offset(1), 666);
+            data = malloc(20);
+            write::<u32>(data as *mut u32, 0);
+            write::<u32>((data as *mut u32).offset(1), 233);
+            write::<u32>((data as *mut u32).offset(2), 666);
+            write::<u32>((data as *mut u32).offset(3), 4000);
+            write::<u32>((data as *mut u32).offset(4), 2333);
         }
         let mut header = PageHeader{
-            slot_sum : 233,
+            page_type : 1,
+            slot_num : 0,
             first_free_slot : 0,
+            data_start : 0,
+            checksum : 0,
             data : data,
         };
         header.init_from_page_data();
-        assert_eq!(header.slot_sum, 233);
+        assert_eq!(header.page_type, 0);
+        assert_eq!(header.slot_num, 233);
         assert_eq!(header.first_free_slot, 666);
+        assert_eq!(header.data_start, 4000);
+        assert_eq!(header.checksum, 2333);
     }
     {
-        let data;
-        unsafe{
-            data = malloc(8);
-            write::<u32>(data as *mut u32, 111);
-            write::<u32>((data as *mut u32).offset(1), 222);
-        }
+        let data = unsafe{ malloc(20) };
         let mut header = PageHeader{
-            slot_sum : 233,
+            page_type : 0,
+            slot_num : 233,
             first_free_slot : 666,
+            data_start : 4000,
+            checksum : 2333,
             data : data,
         };
         header.save_to_page_data();
-        assert_eq!(unsafe{read::<u32>(data as *const u32)}, 233);
-        assert_eq!(unsafe{read::<u32>((data as *const u32).offset(1))}, 666);
+        assert_eq!(unsafe{read::<u32>(data as *const u32)}, 0);
+        assert_eq!(unsafe{read::<u32>((data as *const u32).offset(1))}, 233);
+        assert_eq!(unsafe{read::<u32>((data as *const u32).offset(2))}, 666);
+        assert_eq!(unsafe{read::<u32>((data as *const u32).offset(3))}, 4000);
+        assert_eq!(unsafe{read::<u32>((data as *const u32).offset(4))}, 2333);
     }
 }
 
 #[test]
-fn test_bitmap()
-{
-    let data = unsafe{ malloc(3) };
-    let mut bitmap = BitMap{
-        data : data,
-        slot_sum : 24,
-    };
-    bitmap.clean();
-    for i in 0..24 {
-        assert!(!bitmap.is_inuse(i));
-    }
-    bitmap.set_inuse(8, true);
-    assert!(!bitmap.is_inuse(7));
-    assert!(bitmap.is_inuse(8));
-    assert!(!bitmap.is_inuse(9));
-    assert_eq!(bitmap.next_tuple_index(0), 8);
-    bitmap.set_inuse(8 + 7, true);
-    assert!(!bitmap.is_inuse(8 + 6));
-    assert!(bitmap.is_inuse(8 + 7));
-    assert!(!bitmap.is_inuse(8 + 8));
-    assert_eq!(bitmap.next_tuple_index(0), 8);
-    assert_eq!(bitmap.next_tuple_index(8), 8);
-    assert_eq!(bitmap.next_tuple_index(8 + 1), 8 + 7);
-    bitmap.set_inuse(8 + 3, true);
-    assert!(!bitmap.is_inuse(8 + 2));
-    assert!(bitmap.is_inuse(8 + 3));
-    assert!(!bitmap.is_inuse(8 + 4));
-    unsafe{
-        assert_eq!(read::<u8>(data as *const u8), 0);
-        assert_eq!(read::<u8>((data as *const u8).offset(1)), 137);
-        assert_eq!(read::<u8>((data as *const u8).offset(2)), 0);
-    }
-    assert_eq!(bitmap.get_first_free_slot(), 0);
-    bitmap.set_inuse(0, true);
-    assert_eq!(bitmap.get_first_free_slot(), 1);
-    for i in 1..8 {
-        bitmap.set_inuse(i, true);
-    }
-    assert_eq!(bitmap.next_tuple_index(8), 8);
-    assert_eq!(bitmap.get_first_free_slot(), 8 + 1);
-    bitmap.set_inuse(3, false);
-    assert_eq!(bitmap.get_first_free_slot(), 3);
-    assert_eq!(bitmap.next_tuple_index(50), bitmap.slot_sum);
+fn test_slot_directory() {
+    let mut mem_page = Page::new(1, 2);
+    mem_page.alloc();
+    let page = Rc::new(RefCell::new(mem_page));
+    let mut file_page = FilePage::new(page, 8);
+    file_page.init_empty_page();
+    assert_eq!(file_page.slot_sum, (4096 - 20) / (8 + 4));
+    assert_eq!(file_page.next_tuple_index(0), None);
+    assert_eq!(file_page.insert(&[1; 8]), 0);
+    assert_eq!(file_page.insert(&[2; 100]), 1);
+    assert_eq!(file_page.insert(&[3; 8]), 2);
+    assert_eq!(file_page.header.slot_num, 3);
+    assert_eq!(file_page.header.first_free_slot, 3);
+    assert_eq!(file_page.header.data_start, 4096 - 116);
+    assert_eq!(file_page.get_free_space(), 4096 - 20 - 3 * 4 - 116);
+
+    file_page.delete(1);
+    assert!(!file_page.is_inuse(1));
+    assert_eq!(file_page.header.first_free_slot, 1);
+    assert_eq!(file_page.next_tuple_index(1), Some(2));
+    assert_eq!(file_page.insert(&[4; 50]), 1);
+    assert_eq!(file_page.header.first_free_slot, 3);
+    assert_eq!(file_page.get_tuple_bytes(1), vec![4; 50]);
+
+    // grow in place, the old space is reclaimed by compaction
+    assert!(file_page.update(0, &[5; 20]));
+    assert_eq!(file_page.get_tuple_bytes(0), vec![5; 20]);
+    let free_space = file_page.get_free_space();
+    assert!(!file_page.update(2, &vec![6; free_space + 9]));
+    assert!(file_page.update(2, &vec![6; free_space + 8]));
+    assert_eq!(file_page.get_free_space(), 0);
+    assert!(file_page.is_full());
+    assert_eq!(file_page.get_tuple_bytes(0), vec![5; 20]);
+    assert_eq!(file_page.get_tuple_bytes(1), vec![4; 50]);
+    assert_eq!(file_page.get_tuple_bytes(2), vec![6; free_space + 8]);
+
+    // trailing free slots are removed from the directory
+    file_page.delete(2);
+    assert_eq!(file_page.header.slot_num, 2);
+    file_page.delete(0);
+    assert_eq!(file_page.header.slot_num, 2);
+    assert_eq!(file_page.header.first_free_slot, 0);
+    file_page.delete(1);
+    assert_eq!(file_page.header.slot_num, 0);
+    assert_eq!(file_page.get_free_space(), 4096 - 20);
+}
+
+#[test]
+fn test_overflow_page() {
+    let mut mem_page = Page::new(1, 2);
+    mem_page.alloc();
+    let page = Rc::new(RefCell::new(mem_page));
+    let mut file_page = FilePage::new(page, 8);
+    file_page.init_empty_page();
+    file_page.insert(&[1; 8]);
+    file_page.init_overflow_page(Some(3), b"overflow");
+    assert!(file_page.is_overflow());
+    assert!(file_page.is_full());
+    assert_eq!(file_page.next_tuple_index(0), None);
+    file_page.header.init_from_page_data();
+    assert!(file_page.is_overflow());
+    assert_eq!(file_page.read_overflow_page(), (Some(3), b"overflow".to_vec()));
+    file_page.init_overflow_page(None, &[]);
+    assert_eq!(file_page.read_overflow_page(), (None, vec![]));
+    file_page.init_empty_page();
+    assert!(!file_page.is_overflow());
+    assert_eq!(file_page.next_tuple_index(0), None);
 }
 
 fn gen_test_table() -> Table {
@@ -119,6 +160,8 @@ fn gen_test_table() -> Table {
                 nullable : true,
             },
         ],
+        index_list : vec![],
+        compression : None,
     }
 }
 
@@ -126,51 +169,64 @@ fn gen_test_table() -> Table {
 fn test_file_page_insert() {
     let table = gen_test_table();
     let tuple_desc = table.gen_tuple_desc();
-    assert_eq!(tuple_desc.tuple_len, 16);
+    assert_eq!(tuple_desc.min_tuple_len, 20);
     let mut mem_page = Page::new(1, 2);
     mem_page.alloc();
     let page = Rc::new(RefCell::new(mem_page));
-    let mut file_page = FilePage::new(page, tuple_desc.tuple_len);
+    let mut file_page = FilePage::new(page, tuple_desc.min_tuple_len);
     file_page.init_empty_page();
     let mut value_list = vec![
         ValueExpr{ value : "233".to_string(), value_type : ValueType::Integer },
         ValueExpr{ value : "abcdef".to_string(), value_type : ValueType::String },
         ValueExpr{ value : "666.666".to_string(), value_type : ValueType::Float },
     ];
+    let no_overflow = HashMap::new();
     assert_eq!(file_page.header.first_free_slot, 0);
     assert_eq!(file_page.is_inuse(0), false);
-    file_page.insert(&value_list, &tuple_desc);
+    let values = gen_value_list(&value_list, &tuple_desc.attr_desc);
+    file_page.insert(&encode_tuple(&tuple_desc.attr_desc, &values, &no_overflow));
     assert_eq!(file_page.header.first_free_slot, 1);
     assert_eq!(file_page.is_inuse(0), true);
     value_list[0].value = "777".to_string();
     value_list[1].value = "dyb".to_string();
-    value_list[2].value = "12345.777".to_string();
-    file_page.insert(&value_list, &tuple_desc);
+    value_list[2] = ValueExpr{ value : "null".to_string(), value_type : ValueType::Null };
+    let values = gen_value_list(&value_list, &tuple_desc.attr_desc);
+    file_page.insert(&encode_tuple(&tuple_desc.attr_desc, &values, &no_overflow));
     assert_eq!(file_page.header.first_free_slot, 2);
     assert_eq!(file_page.is_inuse(1), true);
 
-    file_page.save_to_page();
     let mut p = file_page.mem_page.borrow().data;
-    assert_eq!(unsafe{ read(p as *const u32) }, 253);  // slot_sum
+    assert_eq!(unsafe{ read(p as *const u32) }, 0);  // page type
+    p = pointer_offset(p, 4);
+    assert_eq!(unsafe{ read(p as *const u32) }, 2);  // slot_num
     p = pointer_offset(p, 4);
     assert_eq!(unsafe{ read(p as *const u32) }, 2);  // first_free_slot
     p = pointer_offset(p, 4);
-    assert_eq!(unsafe{ read(p as *const u8) }, 3);  // bitmap
-    assert_eq!(unsafe{ read(pointer_offset(p, 4) as *const u8) }, 0);  // bitmap
-    p = pointer_offset(p, (253 + 7) / 8);
+    assert_eq!(unsafe{ read(p as *const u32) }, 4096 - 40);  // data_start
+    p = pointer_offset(p, 8);  // skip checksum
+    assert_eq!(unsafe{ read(p as *const u16) }, 4096 - 20);  // slot 0: offset
+    assert_eq!(unsafe{ read(pointer_offset(p, 2) as *const u16) }, 20);  // slot 0: len
+    assert_eq!(unsafe{ read(pointer_offset(p, 4) as *const u16) }, 4096 - 40);  // slot 1: offset
     // first tuple
+    p = pointer_offset(file_page.mem_page.borrow().data, 4096 - 20);
+    assert_eq!(unsafe{ read(p as *const u32) }, 0);  // null bitmap
+    p = pointer_offset(p, 4);
     assert_eq!(unsafe{ read(p as *const u32) }, 233);  // tuple data: id
     p = pointer_offset(p, 4);
     assert_eq!(unsafe{ read_string(p, 6) }, "abcdef");  // tuple data: content
     p = pointer_offset(p, 8);
     assert_eq!(unsafe{ read(p as *const f32) }, 666.666);  // tuple data: score
-    p = pointer_offset(p, 4);
     // second tuple
+    p = pointer_offset(file_page.mem_page.borrow().data, 4096 - 40);
+    assert_eq!(unsafe{ read(p as *const u32) }, 4);  // null bitmap, score is null
+    p = pointer_offset(p, 4);
     assert_eq!(unsafe{ read(p as *const u32) }, 777);  // tuple data: id
     p = pointer_offset(p, 4);
     assert_eq!(unsafe{ read_string(p, 6) }, "dyb");  // tuple data: content
-    p = pointer_offset(p, 8);
-    assert_eq!(unsafe{ read(p as *const f32) }, 12345.777);  // tuple data: score
+    assert_pattern!(file_page.get_tuple_value(1, 2, &tuple_desc), TupleValue::Null);
+    let tuple_data = file_page.get_tuple_data(1, &tuple_desc).unwrap();
+    assert!(!tuple_data[1].is_null());
+    assert!(tuple_data[2].is_null());
 }
 
 #[test]
@@ -188,7 +244,7 @@ fn test_file_insert() {
         ValueExpr{ value : "abcdef".to_string(), value_type : ValueType::String },
         ValueExpr{ value : "666.666".to_string(), value_type : ValueType::Float },
     ];
-    manager.insert(&table_name, &value_list);
+    manager.insert(&table_name, &value_list).unwrap();
     assert_pattern!(manager.get_tuple_value(&table_name, 0, 0), TupleValue::Int(233));
     assert_pattern!(manager.get_tuple_value(&table_name, 0, 2), TupleValue::Float(666.666));
     assert_eq!(extract!(
@@ -197,7 +253,7 @@ fn test_file_insert() {
     value_list[0].value = "777".to_string();
     value_list[1].value = "dyb".to_string();
     value_list[2].value = "12345.777".to_string();
-    manager.insert(&table_name, &value_list);
+    manager.insert(&table_name, &value_list).unwrap();
     assert_pattern!(manager.get_tuple_value(&table_name, 1, 0), TupleValue::Int(777));
     assert_pattern!(manager.get_tuple_value(&table_name, 1, 2), TupleValue::Float(12345.777));
     assert_eq!(extract!(
@@ -219,8 +275,8 @@ fn test_get_tuple_data() {
         ValueExpr{ value : "abcdef".to_string(), value_type : ValueType::String },
         ValueExpr{ value : "666.666".to_string(), value_type : ValueType::Float },
     ];
-    manager.insert(&table_name, &value_list);
-    let tuple_data = manager.get_tuple_data(&table_name, 0).unwrap();
+    manager.insert(&table_name, &value_list).unwrap();
+    let tuple_data = manager.get_tuple_data(&table_name, 0).unwrap().unwrap();
     let p1 = tuple_data[0];
     let p2 = tuple_data[1];
     let p3 = tuple_data[2];
@@ -229,6 +285,86 @@ fn test_get_tuple_data() {
     assert_eq!(unsafe{ read::<f32>(p3 as *const f32) }, 666.666);
 }
 
+#[test]
+fn test_var_len_attr() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    let mut manager = TableFileManager::new(&config);
+    let table = Rc::new(RefCell::new(Table{
+        name : "test_file_article".to_string(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+            },
+            Attr{
+                name : "title".to_string(),
+                attr_type : AttrType::Varchar{ len : 100 },
+                primary : false,
+                nullable : true,
+            },
+            Attr{
+                name : "content".to_string(),
+                attr_type : AttrType::Text,
+                primary : false,
+                nullable : true,
+            },
+        ],
+        index_list : vec![],
+        compression : None,
+    }));
+    let table_name = "test_file_article".to_string();
+    manager.create_file(table_name.clone(), table);
+    let file = manager.get_file(&table_name);
+    let long_content : String = (0..10000).map(|i| (b'a' + (i % 26) as u8) as char).collect();
+    let value_list = vec![
+        ValueExpr{ value : "1".to_string(), value_type : ValueType::Integer },
+        ValueExpr{ value : "short".to_string(), value_type : ValueType::String },
+        ValueExpr{ value : "hi".to_string(), value_type : ValueType::String },
+    ];
+    manager.insert(&table_name, &value_list).unwrap();
+    let value_list = vec![
+        ValueExpr{ value : "2".to_string(), value_type : ValueType::Integer },
+        ValueExpr{ value : "null".to_string(), value_type : ValueType::Null },
+        ValueExpr{ value : long_content.clone(), value_type : ValueType::String },
+    ];
+    manager.insert(&table_name, &value_list).unwrap();
+    // one data page and 3 overflow pages
+    assert_eq!(file.borrow().page_sum, 4);
+    assert_eq!(extract!(manager.get_tuple_value(&table_name, 0, 1), TupleValue::Char(s), s), "short");
+    assert_eq!(extract!(manager.get_tuple_value(&table_name, 0, 2), TupleValue::Char(s), s), "hi");
+    assert_pattern!(manager.get_tuple_value(&table_name, 1, 1), TupleValue::Null);
+    assert_eq!(extract!(manager.get_tuple_value(&table_name, 1, 2), TupleValue::Char(s), s), long_content);
+
+    let tuple_data = manager.get_tuple_data(&table_name, 1).unwrap().unwrap();
+    assert!(tuple_data[1].is_null());
+    assert_eq!(read_var_string(tuple_data[2]), long_content);
+    let record_id = file.borrow().get_record_id(1);
+    assert_eq!(record_id, RecordId{ page_index : 0, slot : 1 });
+    assert_eq!(file.borrow().get_position(&record_id), 1);
+
+    // the long value is moved back into the tuple
+    let mut set_values = HashMap::new();
+    set_values.insert(2, TupleValue::Char("bye".to_string()));
+    assert_pattern!(manager.update(&table_name, &record_id, &set_values), Ok(None));
+    assert_eq!(extract!(manager.get_tuple_value(&table_name, 1, 2), TupleValue::Char(s), s), "bye");
+    // the freed overflow pages are reused
+    assert_eq!(file.borrow().fsm.find(EMPTY_PAGE), Some(1));
+    set_values.insert(2, TupleValue::Char(long_content.clone()));
+    let record_id = RecordId{ page_index : 0, slot : 0 };
+    assert_pattern!(manager.update(&table_name, &record_id, &set_values), Ok(None));
+    assert_eq!(extract!(manager.get_tuple_value(&table_name, 0, 2), TupleValue::Char(s), s), long_content);
+    assert_eq!(file.borrow().page_sum, 4);
+    assert_eq!(extract!(manager.get_tuple_value(&table_name, 0, 1), TupleValue::Char(s), s), "short");
+    manager.delete(&table_name, &record_id).unwrap();
+    assert!(manager.get_tuple_data(&table_name, 0).unwrap().is_none());
+    assert_eq!(extract!(manager.get_tuple_value(&table_name, 1, 2), TupleValue::Char(s), s), "bye");
+}
+
 #[test]
 fn test_file_persistence() {
     {
@@ -241,12 +377,12 @@ fn test_file_persistence() {
             let manager = Rc::new(RefCell::new(TableManager::new(&config)));
             manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
             test_query::insert_data(&table_name, &manager);
-            manager.borrow_mut().save_to_file();
+            manager.borrow_mut().save_to_file().unwrap();
         }
         let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
         let file = manager.borrow_mut().file_manager.get_file(&table_name);
         assert_eq!(file.borrow().page_sum, 2);
-        assert_eq!(file.borrow().first_free_page, 0);
+        assert_eq!(file.borrow().fsm.get_page_num(), 2);
         let mut query = gen_plan_helper!(
             "select * from test_file_persistence_message", &manager);
         query.open();
@@ -254,9 +390,9 @@ fn test_file_persistence() {
         let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
         let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
         assert_pattern!(query.get_next(), None);
-        assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
-        assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
-        assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
+        assert_eq!(extract!(&t1[0], &TupleValue::Int(n), n), 233);
+        assert_eq!(extract!(&t2[0], &TupleValue::Int(n), n), 777);
+        assert_eq!(extract!(&t3[0], &TupleValue::Int(n), n), 1);
     }
     {
         // test page switch
@@ -287,13 +423,13 @@ fn test_file_persistence() {
             assert_pattern!(scan.get_next(), Some(..));
             assert_pattern!(insert.get_next(), None);
 
-            manager.borrow_mut().save_to_file();
+            manager.borrow_mut().save_to_file().unwrap();
         }
         let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
         {
             let file = manager.borrow_mut().file_manager.get_file(&table_name);
             assert_eq!(file.borrow().page_sum, 2);
-            assert_eq!(file.borrow().first_free_page, 0);
+            assert_eq!(file.borrow().fsm.get_page_num(), 2);
             let mut query = gen_plan_helper!(
                 "select * from test_file_persistence_message", &manager);
             query.open();
@@ -301,20 +437,147 @@ fn test_file_persistence() {
             let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
             let t3 = extract!(query.get_next(), Some(tuple_data), tuple_data);
             assert_pattern!(query.get_next(), None);
-            assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 233);
-            assert_eq!(unsafe{ read::<i32>(t2[0] as *const i32) }, 777);
-            assert_eq!(unsafe{ read::<i32>(t3[0] as *const i32) }, 1);
+            assert_eq!(extract!(&t1[0], &TupleValue::Int(n), n), 233);
+            assert_eq!(extract!(&t2[0], &TupleValue::Int(n), n), 777);
+            assert_eq!(extract!(&t3[0], &TupleValue::Int(n), n), 1);
         }
         {
             let file = manager.borrow_mut().file_manager.get_file(&add_table_name);
             assert_eq!(file.borrow().page_sum, 1);
-            assert_eq!(file.borrow().first_free_page, 0);
+            assert_eq!(file.borrow().fsm.get_page_num(), 1);
             let mut query = gen_plan_helper!(
                 "select * from test_file_message", &manager);
             query.open();
             let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
             assert_pattern!(query.get_next(), None);
-            assert_eq!(unsafe{ read::<i32>(t1[0] as *const i32) }, 766);
+            assert_eq!(extract!(&t1[0], &TupleValue::Int(n), n), 766);
+        }
+    }
+}
+
+#[test]
+fn test_page_checksum() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        table_meta_dir = "test_file/table_meta/test_page_checksum/"
+        table_file_dir = "test_file/table_file/test_page_checksum/""#.to_string());
+    let table_name = "test_page_checksum_message".to_string();
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        test_query::insert_data(&table_name, &manager);
+        manager.borrow_mut().save_to_file().unwrap();
+    }
+    {
+        // flip the last byte of the second page, which is in its only tuple
+        let mut file = OpenOptions::new().read(true).write(true).open(
+            "test_file/table_file/test_page_checksum/test_page_checksum_message.table").unwrap();
+        let offset = (get_page_size() * 3 - 1) as u64;
+        let mut buf = [0; 1];
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.read_exact(&mut buf).unwrap();
+        buf[0] = !buf[0];
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(&buf).unwrap();
+    }
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    let slot_sum = file.borrow().get_page_slot_sum();
+    let err = extract!(manager.borrow_mut().file_manager.get_next_position(&table_name, slot_sum),
+        Err(err), err);
+    assert_eq!(err.error_type, StorageErrorType::PageCorrupted);
+    assert_eq!(err.table, table_name);
+    assert_eq!(err.page_index, 1);
+    assert!(file.borrow().loaded_pages.get(&1).is_none());
+
+    let mut query = gen_plan_helper!("select * from test_page_checksum_message", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), Some(..));
+    assert_pattern!(query.get_next(), Some(..));
+    assert_pattern!(query.get_next(), None);
+    let err = query.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::PageCorrupted);
+    assert!(err.error_msg.contains(&table_name));
+    assert_eq!(manager.borrow().file_manager.get_unpinned_num(), 2);
+}
+
+#[test]
+fn test_dirty_page() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 2
+        checkpoint_dirty_ratio = 50
+        table_meta_dir = "test_file/table_meta/test_dirty_page/"
+        table_file_dir = "test_file/table_file/test_dirty_page/""#.to_string());
+    let table_name = "test_dirty_page_message".to_string();
+    let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+    manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+    test_query::insert_data(&table_name, &manager);
+    manager.borrow_mut().checkpoint().unwrap();
+    assert_eq!(manager.borrow().file_manager.get_dirty_page_num(), 0);
+    assert!(!manager.borrow().need_checkpoint());
+    assert!(!manager.borrow_mut().checkpoint_if_needed(true).unwrap());
+
+    // a clean page is not written back when it's swapped out
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    let tuple_data = manager.borrow_mut().file_manager.get_tuple_data(&table_name, 0).unwrap().unwrap();
+    assert_eq!(unsafe{ read::<i32>(tuple_data[0] as *const i32) }, 233);
+    unsafe{ write::<i32>(tuple_data[0] as *mut i32, 666) };  // not marked dirty
+    let slot_sum = file.borrow().get_page_slot_sum();
+    manager.borrow_mut().file_manager.get_tuple_data(&table_name, slot_sum).unwrap();
+    manager.borrow_mut().file_manager.index_lookup(&table_name, "primary", &vec![TupleValue::Int(1)]).unwrap();
+    assert!(file.borrow().loaded_pages.get(&0).is_none());
+    assert_eq!(extract!(manager.borrow_mut().file_manager.get_tuple_value(&table_name, 0, 0),
+        TupleValue::Int(n), n), 233);
+
+    // only the changed table page and index page are dirty
+    let value_list = vec![
+        ValueExpr{ value : "2".to_string(), value_type : ValueType::Integer },
+        ValueExpr{ value : "1.0".to_string(), value_type : ValueType::Float },
+        ValueExpr{ value : "dirty".to_string(), value_type : ValueType::String },
+    ];
+    manager.borrow_mut().file_manager.insert_in_page(&table_name, 1, &value_list);
+    assert_eq!(manager.borrow().file_manager.get_dirty_page_num(), 2);
+    assert!(manager.borrow().need_checkpoint());
+    assert!(manager.borrow_mut().checkpoint_if_needed(false).unwrap());
+    assert_eq!(manager.borrow().file_manager.get_dirty_page_num(), 0);
+}
+
+#[test]
+fn test_read_ahead() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 3
+        read_ahead_pages = 2
+        table_meta_dir = "test_file/table_meta/test_read_ahead/"
+        table_file_dir = "test_file/table_file/test_read_ahead/""#.to_string());
+    let table_name = "test_read_ahead_message".to_string();
+    let tuple_sum = 600;
+    {
+        let manager = Rc::new(RefCell::new(TableManager::new(&config)));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        for i in 0..tuple_sum {
+            let value_list = vec![
+                ValueExpr{ value : i.to_string(), value_type : ValueType::Integer },
+                ValueExpr{ value : "1.0".to_string(), value_type : ValueType::Float },
+                ValueExpr{ value : "read ahead".to_string(), value_type : ValueType::String },
+            ];
+            manager.borrow_mut().insert(&table_name, &value_list).unwrap();
         }
+        manager.borrow_mut().save_to_file().unwrap();
+    }
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    assert!(file.borrow().page_sum > 3);
+
+    let mut query = gen_plan_helper!("select * from test_read_ahead_message", &manager);
+    query.open();
+    // the next pages wait outside the page pool
+    assert_eq!(file.borrow().loaded_pages.len(), 1);
+    assert_eq!(manager.borrow().file_manager.get_read_ahead_num(), 2);
+    let mut count = 0;
+    while let Some(..) = query.get_next() {
+        count += 1;
     }
+    assert_pattern!(query.get_error(), None);
+    assert_eq!(count, tuple_sum);
+    assert_eq!(manager.borrow().file_manager.get_read_ahead_num(), 0);
 }
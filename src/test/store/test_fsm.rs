@@ -0,0 +1,128 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::utils::config::Config;
+use ::utils::file::ensure_dir_exist;
+use ::store::fsm::{FreeSpaceMap, EMPTY_PAGE, get_category, get_required_category};
+use ::store::file::TableFileManager;
+use ::store::vfs::new_vfs;
+use ::store::table::{Table, Attr, AttrType};
+use ::parser::common::{ValueExpr, ValueType};
+
+
+#[test]
+fn test_category() {
+    assert_eq!(get_category(0), 0);
+    assert_eq!(get_category(15), 0);
+    assert_eq!(get_category(16), 1);
+    assert_eq!(get_category(4076), 254);
+    assert_eq!(get_required_category(0), 1);
+    assert_eq!(get_required_category(16), 1);
+    assert_eq!(get_required_category(17), 2);
+    assert_eq!(get_required_category(4076), EMPTY_PAGE);
+}
+
+#[test]
+fn test_free_space_map() {
+    let dir = "test_file/table_file".to_string();
+    ensure_dir_exist(&dir);
+    let vfs = new_vfs("disk");
+    let mut fsm = FreeSpaceMap::new(&"test_fsm".to_string(), &dir, &vfs);
+    assert_eq!(fsm.find(1), None);
+    for i in 0..100 {
+        fsm.set(i, 0);
+    }
+    assert_eq!(fsm.get_page_num(), 100);
+    assert_eq!(fsm.find(1), None);
+    fsm.set(77, 10);
+    fsm.set(33, 5);
+    assert_eq!(fsm.find(1), Some(33));
+    assert_eq!(fsm.find(6), Some(77));
+    assert_eq!(fsm.find(11), None);
+    fsm.update(88, 4096, true);
+    assert_eq!(fsm.get(88), EMPTY_PAGE);
+    assert_eq!(fsm.find(EMPTY_PAGE), Some(88));
+    assert_eq!(fsm.find(11), Some(88));
+    fsm.set(33, 0);
+    assert_eq!(fsm.find(1), Some(77));
+
+    fsm.save_to_file().unwrap();
+    let mut loaded = FreeSpaceMap::new(&"test_fsm".to_string(), &dir, &vfs);
+    // pages not in the file are considered empty
+    loaded.init_from_file(120);
+    assert_eq!(loaded.get_page_num(), 120);
+    assert_eq!(loaded.get(77), 10);
+    assert_eq!(loaded.get(33), 0);
+    assert_eq!(loaded.find(1), Some(77));
+    assert_eq!(loaded.find(11), Some(88));
+    assert_eq!(loaded.find(EMPTY_PAGE), Some(88));
+    loaded.set(88, 0);
+    assert_eq!(loaded.find(EMPTY_PAGE), Some(100));
+}
+
+fn gen_test_table(table_name : &String) -> Table {
+    Table{
+        name : table_name.clone(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+            },
+            Attr{
+                name : "content".to_string(),
+                attr_type : AttrType::Char{ len : 100 },
+                primary : false,
+                nullable : false,
+            },
+        ],
+        index_list : vec![],
+        compression : None,
+    }
+}
+
+fn gen_value_list(id : i32) -> Vec<ValueExpr> {
+    vec![
+        ValueExpr{ value : id.to_string(), value_type : ValueType::Integer },
+        ValueExpr{ value : "content".to_string(), value_type : ValueType::String },
+    ]
+}
+
+#[test]
+fn test_reuse_free_slot() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 3
+        table_meta_dir = "test_file/table_meta/test_reuse_free_slot/"
+        table_file_dir = "test_file/table_file/test_reuse_free_slot/""#.to_string());
+    let table_name = "test_fsm_message".to_string();
+    {
+        let mut manager = TableFileManager::new(&config);
+        manager.create_file(table_name.clone(), Rc::new(RefCell::new(gen_test_table(&table_name))));
+        for id in 0..200 {
+            manager.insert(&table_name, &gen_value_list(id)).unwrap();
+        }
+        let file = manager.get_file(&table_name);
+        let page_sum = file.borrow().page_sum;
+        assert!(page_sum > 3);
+        // delete some tuples in the first page
+        for position in vec![3, 5] {
+            let record_id = file.borrow().get_record_id(position);
+            manager.delete(&table_name, &record_id).unwrap();
+        }
+        manager.insert(&table_name, &gen_value_list(1000)).unwrap();
+        assert_eq!(file.borrow().page_sum, page_sum);
+        assert_pattern!(manager.get_tuple_data(&table_name, 3), Ok(Some(..)));
+        manager.save_all().unwrap();
+    }
+    {
+        // the free space map is loaded after restart
+        let mut manager = TableFileManager::new(&config);
+        manager.init_from_file(vec![Rc::new(RefCell::new(gen_test_table(&table_name)))]);
+        let file = manager.get_file(&table_name);
+        let page_sum = file.borrow().page_sum;
+        assert_eq!(file.borrow().fsm.find_page(108), Some(0));
+        manager.insert(&table_name, &gen_value_list(1001)).unwrap();
+        assert_eq!(file.borrow().page_sum, page_sum);
+        assert_pattern!(manager.get_tuple_data(&table_name, 5), Ok(Some(..)));
+    }
+}
@@ -0,0 +1,73 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use ::utils::file::ensure_dir_exist;
+use ::utils::libwrapper::get_page_size;
+use ::store::header::{FileHeader, FORMAT_VERSION, HEADER_SIZE, gen_table_fingerprint};
+use ::store::file::TableFile;
+use ::store::vfs::new_vfs;
+use ::store::table::{Table, Attr, AttrType};
+
+
+fn gen_table(content_type : AttrType) -> Table {
+    Table{
+        name : "test_header".to_string(),
+        attr_list : vec![
+            Attr{
+                name : "id".to_string(),
+                attr_type : AttrType::Int,
+                primary : true,
+                nullable : false,
+            },
+            Attr{
+                name : "content".to_string(),
+                attr_type : content_type,
+                primary : false,
+                nullable : true,
+            },
+        ],
+        index_list : vec![],
+        compression : None,
+    }
+}
+
+#[test]
+fn test_header_encode() {
+    let header = FileHeader::new(233, 7, 3);
+    let buf = header.encode();
+    assert_eq!(buf.len(), HEADER_SIZE);
+    assert_eq!(&buf[..4], b"BLST");
+    assert_eq!(&buf[20..24], &[7, 0, 0, 0]);  // little-endian page_sum
+    assert_eq!(FileHeader::decode(&buf), Ok(header.clone()));
+    assert_eq!(header.page_size as usize, get_page_size());
+    assert!(header.check(233).is_ok());
+    assert!(header.check(666).is_err());
+
+    // file of the old format starting with page_sum
+    assert!(FileHeader::decode(&[7, 0, 0, 0, 0, 0, 0, 0]).is_err());
+    let mut buf = header.encode();
+    buf[4] = FORMAT_VERSION as u8 + 1;
+    let err = extract!(FileHeader::decode(&buf), Err(err), err);
+    assert!(err.contains("version"));
+    let mut other_page_size = header.clone();
+    other_page_size.page_size *= 4;
+    let err = extract!(other_page_size.check(233), Err(err), err);
+    assert!(err.contains("page size"));
+}
+
+#[test]
+fn test_schema_fingerprint() {
+    let dir = "test_file/table_file".to_string();
+    ensure_dir_exist(&dir);
+    let vfs = new_vfs("disk");
+    let table = Rc::new(RefCell::new(gen_table(AttrType::Char{ len : 16 })));
+    assert_eq!(gen_table_fingerprint(&table.borrow()), gen_table_fingerprint(&table.borrow().clone()));
+    {
+        let mut file = TableFile::new("test_header".to_string(), table.clone(), &dir, &vfs, None);
+        file.save_to_file().unwrap();
+    }
+    let mut file = TableFile::new("test_header".to_string(), table.clone(), &dir, &vfs, None);
+    assert_eq!(file.init_from_file(), Ok(()));
+    let other_table = Rc::new(RefCell::new(gen_table(AttrType::Char{ len : 32 })));
+    let mut file = TableFile::new("test_header".to_string(), other_table, &dir, &vfs, None);
+    assert!(file.init_from_file().is_err());
+}
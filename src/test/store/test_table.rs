@@ -44,7 +44,8 @@ const JSON_DATA : &'static str = r#"
                     "primary": false,
                     "nullable": false
                 }
-            ]
+            ],
+            "index_list": []
         },
         "book": {
             "name": "book",
@@ -61,7 +62,8 @@ const JSON_DATA : &'static str = r#"
                     "primary": true,
                     "nullable": true
                 }
-            ]
+            ],
+            "index_list": []
         }
     }
     "#;
@@ -84,6 +86,8 @@ fn test_json_translate() {
                 nullable : false,
             }
         ],
+        index_list : vec![],
+        compression : None,
     };
     let t2 = Table{
         name : "book".to_string(),
@@ -100,7 +104,9 @@ fn test_json_translate() {
                 primary : true,
                 nullable : true,
             }
-        ]
+        ],
+        index_list : vec![],
+        compression : None,
     };
     let config = Config::new(&r#"
         max_memory_pool_page_num = 5
@@ -141,3 +147,27 @@ fn test_gen_table_set() {
     let set = manager.gen_table_set(&used_table);
     assert_eq!(set.tables.len(), 2);
 }
+
+#[test]
+fn test_index_list_json() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 5
+        table_meta_dir = "test_file/table_meta/"
+        table_file_dir = "test_file/table_file""#.to_string());
+    {
+        // table meta saved before index supported
+        let json = r#"{"author":{"name":"author","attr_list":[]}}"#.to_string();
+        let manager = TableManager::from_json(&config, &json, false);
+        let table = manager.get_table("author").unwrap();
+        assert!(table.borrow().index_list.is_empty());
+    }
+    {
+        let json = r#"{"author":{"name":"author","attr_list":[],"index_list":[{"name":"name_index","attr_list":["name","id"],"unique":true}]}}"#.to_string();
+        let manager = TableManager::from_json(&config, &json, false);
+        let table = manager.get_table("author").unwrap();
+        let index = extract!(table.borrow().get_index_desc("name_index"), Some(index), index);
+        assert_eq!(index.attr_list, vec!["name".to_string(), "id".to_string()]);
+        assert!(index.unique);
+        assert_eq!(manager.to_json(), json);
+    }
+}
@@ -0,0 +1,233 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::metadata;
+use ::utils::config::Config;
+use ::utils::libwrapper::get_page_size;
+use ::utils::file::path_join;
+use ::store::table::TableManager;
+use ::store::tuple::TupleValue;
+use ::store::vfs::{Vfs, MemVfs, IoFault};
+use ::store::error::StorageErrorType;
+use ::exec::error::ExecErrorType;
+use ::test::exec::test_query;
+use ::test::utils::open_manager;
+
+
+fn gen_config(name : &str) -> Config {
+    Config::new(&format!(r#"
+        max_memory_pool_page_num = 4
+        table_meta_dir = "test_file/{0}/table_meta/"
+        table_file_dir = "test_file/{0}/table_file/""#, name))
+}
+
+#[test]
+fn test_mem_vfs() {
+    let vfs = MemVfs::new();
+    let path = "a.table".to_string();
+    assert!(!vfs.exists(&path));
+    let mut file = vfs.open(&path).unwrap();
+    assert!(vfs.exists(&path));
+    assert!(file.write_at(4, &[1, 2]).is_ok());
+    assert_eq!(file.get_len(), 6);
+    let mut buf = [7; 8];
+    assert_eq!(file.read_at(2, &mut buf).unwrap(), 4);
+    assert_eq!(&buf[..4], &[0, 0, 1, 2]);
+    assert!(file.truncate(5).is_ok());
+    assert_eq!(file.read_all().unwrap(), vec![0, 0, 0, 0, 1]);
+
+    let other = vfs.open(&path).unwrap();
+    assert!(other.get_id() != file.get_id());
+    assert_eq!(other.get_len(), 5);
+
+    let new_path = "b.table".to_string();
+    assert!(vfs.rename(&path, &new_path).is_ok());
+    assert!(!vfs.exists(&path));
+    assert_eq!(vfs.open(&new_path).unwrap().get_len(), 5);
+    assert!(vfs.delete(&new_path).is_ok());
+    assert!(vfs.delete(&new_path).is_err());
+    // still usable after deleted
+    assert_eq!(file.get_len(), 5);
+}
+
+#[test]
+fn test_mem_vfs_fault() {
+    let vfs = MemVfs::new();
+    let path = "a.table".to_string();
+    let mut file = vfs.open(&path).unwrap();
+    let mut buf = [0; 4];
+    vfs.inject_fault(&path, IoFault::ReadError);
+    assert!(file.read_at(0, &mut buf).is_err());
+    assert!(file.read_at(0, &mut buf).is_ok());
+    vfs.inject_fault(&path, IoFault::WriteError);
+    assert!(file.write_at(0, &[1, 2, 3, 4]).is_err());
+    assert_eq!(file.get_len(), 0);
+    vfs.inject_fault(&path, IoFault::TornWrite(2));
+    assert!(file.write_at(0, &[1, 2, 3, 4]).is_ok());
+    assert_eq!(file.read_all().unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn test_memory_database() {
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        vfs = "memory"
+        table_meta_dir = "test_file/test_memory_database/table_meta/"
+        table_file_dir = "test_file/test_memory_database/table_file/""#.to_string());
+    let table_name = "test_memory_database_message".to_string();
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+    test_query::insert_data(&table_name, &manager);
+    manager.borrow_mut().commit().unwrap();
+    manager.borrow_mut().checkpoint().unwrap();
+    assert!(metadata("test_file/test_memory_database/").is_err());
+
+    let mut query = gen_plan_helper!("select * from test_memory_database_message", &manager);
+    query.open();
+    let mut count = 0;
+    while let Some(..) = query.get_next() {
+        count += 1;
+    }
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn test_reopen_memory_database() {
+    let config = gen_config("test_reopen_memory_database");
+    let table_name = "test_reopen_memory_database_message".to_string();
+    let vfs = Rc::new(MemVfs::new());
+    {
+        let manager = open_manager(&config, &vfs);
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        test_query::insert_data(&table_name, &manager);
+        manager.borrow_mut().commit().unwrap();
+        let mut delete = gen_plan_helper!(
+            "delete from test_reopen_memory_database_message where id = 777", &manager);
+        delete.open();
+        while let Some(..) = delete.get_next() {}
+        manager.borrow_mut().commit().unwrap();
+        // exit without flushing pages and catalog
+    }
+    let manager = open_manager(&config, &vfs);
+    let mut query = gen_plan_helper!("select * from test_reopen_memory_database_message", &manager);
+    query.open();
+    let mut count = 0;
+    while let Some(..) = query.get_next() {
+        count += 1;
+    }
+    assert_eq!(count, 2);
+    assert_pattern!(query.get_error(), None);
+}
+
+#[test]
+fn test_io_fault() {
+    let config = gen_config("test_io_fault");
+    let table_name = "test_io_fault_message".to_string();
+    let vfs = Rc::new(MemVfs::new());
+    {
+        let manager = Rc::new(RefCell::new(TableManager::with_vfs(&config, vfs.clone())));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        test_query::insert_data(&table_name, &manager);
+        manager.borrow_mut().save_to_file().unwrap();
+    }
+    let manager = open_manager(&config, &vfs);
+    let path = manager.borrow_mut().file_manager.get_file(&table_name).borrow().saved_name.clone();
+
+    vfs.inject_fault(&path, IoFault::ReadError);
+    let mut query = gen_plan_helper!("select * from test_io_fault_message", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), None);
+    let err = query.get_error().unwrap();
+    assert_eq!(err.error_type, ExecErrorType::IoError);
+    assert!(err.error_msg.contains(&table_name));
+    // the fault is consumed
+    let mut query = gen_plan_helper!("select * from test_io_fault_message", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), Some(..));
+    query.close();
+
+    // the tuple is at the end of the second page, only the new header reaches the file
+    let mut update = gen_plan_helper!(
+        "update test_io_fault_message set content = \"torn\" where id = 1", &manager);
+    update.open();
+    while let Some(..) = update.get_next() {}
+    assert_pattern!(update.get_error(), None);
+    vfs.inject_fault(&path, IoFault::TornWrite(get_page_size() / 2));
+    manager.borrow_mut().file_manager.get_file(&table_name).borrow_mut().save_page(1).unwrap();
+    let manager = open_manager(&config, &vfs);
+    let file = manager.borrow_mut().file_manager.get_file(&table_name);
+    let slot_sum = file.borrow().get_page_slot_sum();
+    let err = extract!(manager.borrow_mut().file_manager.get_next_position(&table_name, slot_sum),
+        Err(err), err);
+    assert_eq!(err.error_type, StorageErrorType::PageCorrupted);
+    assert_eq!(err.page_index, 1);
+}
+
+#[test]
+fn test_write_fault() {
+    // a failed write fails the statement and never loses the acknowledged ones
+    let config = gen_config("test_write_fault");
+    let table_name = "test_write_fault_message".to_string();
+    let vfs = Rc::new(MemVfs::new());
+    let manager = open_manager(&config, &vfs);
+    manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+    test_query::insert_data(&table_name, &manager);
+    manager.borrow_mut().commit().unwrap();
+    let wal_path = path_join(&config.get_str("table_meta_dir"), &"table_meta.wal".to_string());
+    let table_path = manager.borrow_mut().file_manager.get_file(&table_name).borrow().saved_name.clone();
+
+    // the delete can't be logged, so it's not acknowledged and is rolled back
+    let mut delete = gen_plan_helper!("delete from test_write_fault_message where id = 777", &manager);
+    delete.open();
+    while let Some(..) = delete.get_next() {}
+    assert_pattern!(delete.get_error(), None);
+    drop(delete);
+    vfs.inject_fault(&wal_path, IoFault::WriteError);
+    assert!(manager.borrow_mut().commit().is_err());
+    let mut query = gen_plan_helper!("select * from test_write_fault_message", &manager);
+    query.open();
+    let mut count = 0;
+    while let Some(..) = query.get_next() {
+        count += 1;
+    }
+    assert_eq!(count, 3);
+    drop(query);
+
+    let mut delete = gen_plan_helper!("delete from test_write_fault_message where id = 1", &manager);
+    delete.open();
+    while let Some(..) = delete.get_next() {}
+    drop(delete);
+    manager.borrow_mut().commit().unwrap();
+    // the pages can't be flushed, the log is kept for the next start
+    vfs.inject_fault(&table_path, IoFault::WriteError);
+    assert!(manager.borrow_mut().checkpoint().is_err());
+    assert!(manager.borrow().file_manager.get_wal_size() > 0);
+
+    let manager = open_manager(&config, &vfs);
+    let mut query = gen_plan_helper!("select * from test_write_fault_message", &manager);
+    query.open();
+    let mut id_list = Vec::new();
+    while let Some(row) = query.get_next() {
+        id_list.push(extract!(&row[0], &TupleValue::Int(n), n));
+    }
+    assert_pattern!(query.get_error(), None);
+    assert_eq!(id_list, vec![233, 777]);
+}
+
+#[test]
+#[should_panic(expected = "can't read the header")]
+fn test_header_read_fault() {
+    // a table file that can't be read is not taken as a broken one
+    let config = gen_config("test_header_read_fault");
+    let table_name = "test_header_read_fault_message".to_string();
+    let vfs = Rc::new(MemVfs::new());
+    let table_path = {
+        let manager = open_manager(&config, &vfs);
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        manager.borrow_mut().commit().unwrap();
+        manager.borrow_mut().checkpoint().unwrap();
+        let table_path = manager.borrow_mut().file_manager.get_file(&table_name).borrow().saved_name.clone();
+        table_path
+    };
+    vfs.inject_fault(&table_path, IoFault::ReadError);
+    open_manager(&config, &vfs);
+}
@@ -0,0 +1,217 @@
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::{OpenOptions, remove_dir_all, metadata};
+use std::io::{Read, Write};
+use ::utils::config::Config;
+use ::utils::file::ensure_dir_exist;
+use ::utils::libwrapper::get_page_size;
+use ::store::table::{TableManager, TableManagerRef};
+use ::store::tuple::TupleValue;
+use ::store::wal::{Wal, LogRecord};
+use ::store::vfs::{Vfs, VfsRef, MemVfs, IoFault, new_vfs};
+use ::store::error::StorageErrorType;
+use ::parser::common::{ValueExpr, ValueType};
+use ::test::exec::test_query;
+use ::test::utils::open_manager;
+
+
+#[test]
+fn test_log_record_encode() {
+    let record_list = vec![
+        LogRecord::Write{ path : "a.table".to_string(), offset : 4096, data : vec![1, 2, 3] },
+        LogRecord::Replace{ path : "table_meta.json".to_string(), data : b"{}".to_vec() },
+        LogRecord::Remove{ path : "a.i.index".to_string() },
+        LogRecord::Rename{ from : "a.vacuum.table".to_string(), to : "a.table".to_string() },
+        LogRecord::Undo{ path : "a.table".to_string(), offset : 4096, data : vec![4, 5], len : 8192 },
+        LogRecord::Commit,
+    ];
+    for record in record_list.iter() {
+        let buf = record.encode();
+        let (decoded, len) = extract!(LogRecord::decode(&buf), Some(result), result);
+        assert_eq!(decoded, *record);
+        assert_eq!(len, buf.len());
+        // torn record
+        assert_pattern!(LogRecord::decode(&buf[..buf.len() - 1]), None);
+        // broken record
+        let mut broken = buf.clone();
+        broken[5] ^= 1;
+        assert_pattern!(LogRecord::decode(&broken), None);
+    }
+}
+
+#[test]
+fn test_read_committed() {
+    let dir = "test_file/test_read_committed/".to_string();
+    let _ = remove_dir_all(&dir);
+    ensure_dir_exist(&dir);
+    let path = format!("{}test.wal", dir);
+    let vfs = new_vfs("disk");
+    let w1 = LogRecord::Write{ path : "a".to_string(), offset : 0, data : vec![1] };
+    let w2 = LogRecord::Write{ path : "b".to_string(), offset : 8, data : vec![2] };
+    {
+        let mut wal = Wal::open(&path, &vfs);
+        wal.append(&w1).unwrap();
+        wal.commit().unwrap();
+        wal.append(&w2).unwrap();
+    }
+    assert_eq!(Wal::read_committed(&path, &vfs), vec![w1.clone()]);
+    {
+        let mut wal = Wal::open(&path, &vfs);
+        wal.commit().unwrap();
+        wal.append(&w1).unwrap();
+        wal.commit().unwrap();
+    }
+    assert_eq!(Wal::read_committed(&path, &vfs), vec![w1.clone(), w2.clone(), w1.clone()]);
+    {
+        // broken tail
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        let buf = w2.encode();
+        assert!(file.write_all(&buf[..buf.len() - 2]).is_ok());
+    }
+    assert_eq!(Wal::read_committed(&path, &vfs).len(), 3);
+    let mut wal = Wal::open(&path, &vfs);
+    wal.truncate().unwrap();
+    assert_eq!(wal.get_size(), 0);
+    assert!(Wal::read_committed(&path, &vfs).is_empty());
+}
+
+#[test]
+fn test_wal_write_error() {
+    let mem_vfs = Rc::new(MemVfs::new());
+    let vfs : VfsRef = mem_vfs.clone();
+    let path = "test.wal".to_string();
+    let w1 = LogRecord::Write{ path : "a".to_string(), offset : 0, data : vec![1] };
+    let w2 = LogRecord::Write{ path : "b".to_string(), offset : 8, data : vec![2] };
+    let mut wal = Wal::open(&path, &vfs);
+    wal.append(&w1).unwrap();
+    wal.commit().unwrap();
+    let size = wal.get_size();
+    mem_vfs.inject_fault(&path, IoFault::WriteError);
+    assert!(wal.append(&w2).is_err());
+    assert_eq!(wal.get_size(), size);
+    // the records of a failed commit are not committed by the next one
+    wal.append(&w2).unwrap();
+    mem_vfs.inject_fault(&path, IoFault::WriteError);
+    assert!(wal.commit().is_err());
+    wal.discard().unwrap();
+    assert_eq!(wal.get_size(), size);
+    wal.append(&w1).unwrap();
+    wal.commit().unwrap();
+    assert_eq!(Wal::read_committed(&path, &vfs), vec![w1.clone(), w1.clone()]);
+}
+
+#[test]
+fn test_wal_recovery() {
+    let _ = remove_dir_all("test_file/test_wal_recovery/");
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        table_meta_dir = "test_file/test_wal_recovery/table_meta/"
+        table_file_dir = "test_file/test_wal_recovery/table_file/""#.to_string());
+    let table_name = "test_wal_recovery_message".to_string();
+    {
+        let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+        manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+        test_query::insert_data(&table_name, &manager);
+        manager.borrow_mut().commit().unwrap();
+        let mut delete = gen_plan_helper!(
+            "delete from test_wal_recovery_message where id = 777", &manager);
+        delete.open();
+        while let Some(..) = delete.get_next() {}
+        manager.borrow_mut().commit().unwrap();
+        // exit without flushing pages and catalog
+    }
+    {
+        let mut json_str = String::new();
+        let mut file = OpenOptions::new().read(true).open(
+            "test_file/test_wal_recovery/table_meta/table_meta.json").unwrap();
+        assert!(file.read_to_string(&mut json_str).is_ok());
+        assert!(!json_str.contains(&table_name));
+    }
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    let wal_size = metadata("test_file/test_wal_recovery/table_meta/table_meta.wal").unwrap().len();
+    assert_eq!(wal_size, 0);
+    let mut query = gen_plan_helper!(
+        "select * from test_wal_recovery_message", &manager);
+    query.open();
+    let t1 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+    let t2 = extract!(query.get_next(), Some(tuple_data), tuple_data);
+    assert_pattern!(query.get_next(), None);
+    assert_eq!(extract!(&t1[0], &TupleValue::Int(n), n), 233);
+    assert_eq!(extract!(&t2[0], &TupleValue::Int(n), n), 1);
+    let mut query = gen_plan_helper!(
+        "select * from test_wal_recovery_message where id = 1", &manager);
+    query.open();
+    assert_pattern!(query.get_next(), Some(..));
+}
+
+#[test]
+fn test_log_changed_headers_only() {
+    // a commit without changes writes nothing but the commit record
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 4
+        table_meta_dir = "test_file/test_log_changed_headers_only/table_meta/"
+        table_file_dir = "test_file/test_log_changed_headers_only/table_file/""#.to_string());
+    let table_name = "test_log_changed_headers_only_message".to_string();
+    let vfs = Rc::new(MemVfs::new());
+    let manager = open_manager(&config, &vfs);
+    manager.borrow_mut().add_table(test_query::gen_test_table(&table_name));
+    test_query::insert_data(&table_name, &manager);
+    manager.borrow_mut().commit().unwrap();
+    let wal_size = manager.borrow().file_manager.get_wal_size();
+    manager.borrow_mut().commit().unwrap();
+    let commit_size = LogRecord::Commit.encode().len() as u64;
+    assert_eq!(manager.borrow().file_manager.get_wal_size(), wal_size + commit_size);
+}
+
+fn count_rows(sql : &str, manager : &TableManagerRef) -> usize {
+    let mut query = gen_plan_helper!(sql, manager);
+    query.open();
+    let mut n = 0;
+    while let Some(..) = query.get_next() {
+        n += 1;
+    }
+    assert_pattern!(query.get_error(), None);
+    n
+}
+
+#[test]
+fn test_steal() {
+    // the statements changing more pages than the pool are written before they commit,
+    // the pages written by a failed or interrupted statement are reverted
+    let config = Config::new(&r#"
+        max_memory_pool_page_num = 5
+        table_meta_dir = "test_file/test_steal/table_meta/"
+        table_file_dir = "test_file/test_steal/table_file/""#.to_string());
+    let vfs = Rc::new(MemVfs::new());
+    let manager = open_manager(&config, &vfs);
+    assert_pattern!(exec_helper!("create table t(id int not null primary, v int not null, s char(200))",
+        &manager), None);
+    manager.borrow_mut().commit().unwrap();
+    for i in 0..200 {
+        let sql = format!("insert t values({}, 0, 'a')", i);
+        assert_pattern!(exec_helper!(&sql, &manager), None);
+        manager.borrow_mut().commit().unwrap();
+    }
+    let table_path = "test_file/test_steal/table_file/t.table".to_string();
+    assert!(vfs.open(&table_path).unwrap().get_len() as usize > 5 * get_page_size());
+    assert_pattern!(exec_helper!("update t set v = 1", &manager), None);
+    manager.borrow_mut().commit().unwrap();
+    assert_eq!(count_rows("select * from t where v = 1", &manager), 200);
+
+    // failed statement
+    assert_pattern!(exec_helper!("update t set v = 2", &manager), None);
+    assert!(manager.borrow().file_manager.has_uncommitted_changes());
+    manager.borrow_mut().rollback().unwrap();
+    assert_eq!(count_rows("select * from t where v = 1", &manager), 200);
+    // interrupted statement
+    assert_pattern!(exec_helper!("update t set v = 3", &manager), None);
+    drop(manager);
+    let manager = open_manager(&config, &vfs);
+    assert_eq!(count_rows("select * from t where v = 1", &manager), 200);
+
+    assert_pattern!(exec_helper!("delete from t", &manager), None);
+    manager.borrow_mut().commit().unwrap();
+    drop(manager);
+    let manager = open_manager(&config, &vfs);
+    assert_eq!(count_rows("select * from t", &manager), 0);
+}
@@ -2,12 +2,22 @@ use std::fmt::{Display, Debug};
 use std::option::Option::None;
 use std::result::Result::Ok;
 use std::ptr::{write, read};
+use std::slice::from_raw_parts;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fs::{remove_dir_all, read_dir};
 use libc::malloc;
 use ::parser::lexer::TokenIter;
 use ::parser::compile_error::ErrorList;
 use ::parser::common::exp_list_to_string;
 use ::utils::pointer::{write_string, read_string};
+use ::utils::pointer::{read_u16_le, write_u16_le, read_u32_le, write_u32_le};
+use ::utils::pointer::{read_i32_le, write_i32_le, read_f32_le, write_f32_le};
 use ::store::buffer::DataPtr;
+use ::store::table::{TableManager, TableManagerRef};
+use ::store::vfs::MemVfs;
+use ::store::tuple::{Row, TupleValue};
+use ::utils::config::Config;
 
 
 macro_rules! gen_token {
@@ -82,6 +92,63 @@ macro_rules! gen_plan_helper {
     })
 }
 
+macro_rules! exec_helper {
+    ($input_str:expr, $manager:expr) => ({
+        let mut plan = gen_plan_helper!($input_str, $manager);
+        plan.open();
+        while let Some(..) = plan.get_next() {}
+        plan.get_error()
+    })
+}
+
+pub fn gen_exec_manager(dir : &str, work_memory_size : usize, create_list : &[&str]) -> TableManagerRef {
+    // a new database in dir with the tables created
+    let _ = remove_dir_all(dir);
+    let config = Config::new(&format!(r#"
+        max_memory_pool_page_num = 16
+        work_memory_size = {}
+        table_meta_dir = "{}/table_meta/"
+        table_file_dir = "{}/table_file/""#, work_memory_size, dir, dir));
+    let manager = Rc::new(RefCell::new(TableManager::from_json_file(&config)));
+    for sql in create_list.iter() {
+        assert_pattern!(exec_helper!(*sql, &manager), None);
+    }
+    manager
+}
+
+pub fn open_manager(config : &Config, vfs : &Rc<MemVfs>) -> TableManagerRef {
+    // the files stay in vfs, so the database can be opened again after the manager is dropped
+    Rc::new(RefCell::new(TableManager::from_json_file_with_vfs(config, vfs.clone())))
+}
+
+pub fn query_helper(sql : &str, manager : &TableManagerRef) -> (Vec<Row>, String) {
+    // the rows and the explanation of the plan
+    let mut plan = gen_plan_helper!(sql, manager);
+    let explain = plan.explain();
+    plan.open();
+    let mut row_list = Vec::new();
+    while let Some(row) = plan.get_next() {
+        row_list.push(row);
+    }
+    assert_pattern!(plan.get_error(), None);
+    (row_list, explain)
+}
+
+pub fn assert_row_list(row_list : &Vec<Row>, expected : Vec<Row>) {
+    // TupleValue is not PartialEq
+    assert_eq!(format!("{:?}", row_list), format!("{:?}", expected));
+}
+
+pub fn char_value(s : &str) -> TupleValue {
+    TupleValue::Char(s.to_string())
+}
+
+pub fn count_temp_file(dir : &str) -> usize {
+    read_dir(dir).unwrap().filter(|entry| {
+        entry.as_ref().unwrap().file_name().to_string_lossy().ends_with(".tmp")
+    }).count()
+}
+
 macro_rules! gen_parse_result {
     ($class:ident :: $parse_func:ident, $input_str:expr) => ({
         let tokens = gen_token!($input_str);
@@ -111,3 +178,22 @@ fn test_raw_str_convert() {
         assert_eq!(read_string(p, 3), "abc");
     }
 }
+
+#[test]
+fn test_little_endian_convert() {
+    unsafe{
+        let p : DataPtr = malloc(4);
+        write_u32_le(p, 0x01020304);
+        assert_eq!(from_raw_parts(p as *const u8, 4), &[4, 3, 2, 1]);
+        assert_eq!(read_u32_le(p), 0x01020304);
+        write_u16_le(p, 0x0102);
+        assert_eq!(from_raw_parts(p as *const u8, 2), &[2, 1]);
+        assert_eq!(read_u16_le(p), 0x0102);
+        write_i32_le(p, -2);
+        assert_eq!(from_raw_parts(p as *const u8, 4), &[254, 255, 255, 255]);
+        assert_eq!(read_i32_le(p), -2);
+        write_f32_le(p, 1.0);
+        assert_eq!(from_raw_parts(p as *const u8, 4), &[0, 0, 128, 63]);
+        assert_eq!(read_f32_le(p), 1.0);
+    }
+}
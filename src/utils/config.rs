@@ -32,7 +32,13 @@ impl Config {
     pub fn get_int(&self, path : &str) -> i64 {
         extract!(self.config.get(path), Some(&Value::Integer(n)), n)
     }
+    pub fn get_opt_int(&self, path : &str) -> Option<i64> {
+        self.config.get(path).map(|v| extract!(v, &Value::Integer(n), n))
+    }
     pub fn get_str(&self, path : &str) -> String {
         extract!(self.config.get(path), Some(&Value::String(ref s)), s.clone())
     }
+    pub fn get_opt_str(&self, path : &str) -> Option<String> {
+        self.config.get(path).map(|v| extract!(v, &Value::String(ref s), s.clone()))
+    }
 }
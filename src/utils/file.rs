@@ -3,9 +3,9 @@ use std::path::Path;
 
 
 pub fn path_join(path : &String, file : &String) -> String {
-    match metadata(path) {
-        Ok(m) => assert!(m.is_dir()),
-        Err(e) => panic!("path_join fail {:?}, {:?}", path, e),
+    // the directory of a memory file system does not exist on disk
+    if let Ok(m) = metadata(path) {
+        assert!(m.is_dir(), "path_join fail {:?} is not a directory", path);
     }
     Path::new(path).join(file).to_str().unwrap().to_string()
 }
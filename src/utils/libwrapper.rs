@@ -1,9 +1,18 @@
-use libc::{_SC_PAGESIZE, sysconf, posix_memalign, c_void};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use libc::{posix_memalign, c_void};
 
 
+// the page size is a property of the data files instead of the OS,
+// so that files can be moved between hosts with different page sizes
+pub const DEFAULT_PAGE_SIZE : usize = 4096;
+const MIN_PAGE_SIZE : usize = 1024;
+const MAX_PAGE_SIZE : usize = 1 << 16;  // slot offsets are u16
+
+static PAGE_SIZE : AtomicUsize = AtomicUsize::new(DEFAULT_PAGE_SIZE);
+
 pub fn alloc_page() -> *mut c_void {
     unsafe {
-        let size = sysconf(_SC_PAGESIZE) as usize;
+        let size = get_page_size();
         let mut p = std::ptr::null_mut();
         posix_memalign(&mut p, size, size);
         p
@@ -11,5 +20,12 @@ pub fn alloc_page() -> *mut c_void {
 }
 
 pub fn get_page_size() -> usize {
-    unsafe { sysconf(_SC_PAGESIZE) as usize }
+    PAGE_SIZE.load(Ordering::Relaxed)
+}
+
+pub fn set_page_size(size : usize) {
+    // should be called before any page is allocated
+    assert!(size.is_power_of_two() && size >= MIN_PAGE_SIZE && size <= MAX_PAGE_SIZE,
+        "invalid page size {}", size);
+    PAGE_SIZE.store(size, Ordering::Relaxed);
 }
@@ -16,6 +16,15 @@ macro_rules! is_match {
     );
 }
 
+macro_rules! try_opt {
+    ($expression:expr) => (
+        match $expression {
+            Some(v) => v,
+            None => return None,
+        }
+    );
+}
+
 macro_rules! impl_debug_from_display {
     ($type_name:ident) => (
         impl $type_name {
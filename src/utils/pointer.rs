@@ -1,4 +1,5 @@
 use std::ptr::{write, read, write_bytes};
+use std::mem::transmute;
 use std::vec::Vec;
 use std::ffi::CString;
 use ::store::buffer::DataPtr;
@@ -32,6 +33,39 @@ pub unsafe fn read_string(ptr : DataPtr, len : usize) -> String {
     s
 }
 
+// numbers in pages are always little-endian so that files can be read on any host
+pub unsafe fn read_u16_le(ptr : DataPtr) -> u16 {
+    u16::from_le(read::<u16>(ptr as *const u16))
+}
+
+pub unsafe fn write_u16_le(ptr : DataPtr, n : u16) {
+    write::<u16>(ptr as *mut u16, n.to_le());
+}
+
+pub unsafe fn read_u32_le(ptr : DataPtr) -> u32 {
+    u32::from_le(read::<u32>(ptr as *const u32))
+}
+
+pub unsafe fn write_u32_le(ptr : DataPtr, n : u32) {
+    write::<u32>(ptr as *mut u32, n.to_le());
+}
+
+pub unsafe fn read_i32_le(ptr : DataPtr) -> i32 {
+    i32::from_le(read::<i32>(ptr as *const i32))
+}
+
+pub unsafe fn write_i32_le(ptr : DataPtr, n : i32) {
+    write::<i32>(ptr as *mut i32, n.to_le());
+}
+
+pub unsafe fn read_f32_le(ptr : DataPtr) -> f32 {
+    transmute::<u32, f32>(read_u32_le(ptr))
+}
+
+pub unsafe fn write_f32_le(ptr : DataPtr, n : f32) {
+    write_u32_le(ptr, transmute::<f32, u32>(n));
+}
+
 pub fn pointer_offset(ptr : DataPtr, byte_offset : usize) -> DataPtr {
     unsafe{
         (ptr as *mut u8).offset(byte_offset as isize) as DataPtr